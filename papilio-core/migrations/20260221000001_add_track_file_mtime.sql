@@ -0,0 +1,4 @@
+-- 增量扫描所需的文件修改时间 (Unix 秒)
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS file_mtime BIGINT;
+
+ALTER TABLE scan_status ADD COLUMN IF NOT EXISTS skipped_count INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- 流派、分轨总数
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS genre TEXT;
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS track_total INTEGER;
+ALTER TABLE albums ADD COLUMN IF NOT EXISTS genre TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_tracks_genre ON tracks(genre);
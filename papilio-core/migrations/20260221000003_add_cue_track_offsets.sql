@@ -0,0 +1,6 @@
+-- CUE 整轨分割：同一音频文件可对应多条音轨，以起始偏移区分
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS start_ms INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS end_ms INTEGER;
+
+ALTER TABLE tracks DROP CONSTRAINT IF EXISTS tracks_path_key;
+ALTER TABLE tracks ADD CONSTRAINT tracks_path_start_ms_key UNIQUE (path, start_ms);
@@ -0,0 +1,3 @@
+-- ReplayGain 响度信息 (dB，基准 -18 LUFS)
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS replaygain_track_gain REAL;
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS replaygain_album_gain REAL;
@@ -0,0 +1,4 @@
+-- 区分扫描与整理，避免整理时前端误显示为"扫描中"
+ALTER TABLE scan_status ADD COLUMN IF NOT EXISTS operation TEXT NOT NULL DEFAULT 'idle';
+ALTER TABLE scan_status ADD CONSTRAINT scan_status_operation_check
+    CHECK (operation IN ('idle', 'scanning', 'organizing'));
@@ -0,0 +1,2 @@
+-- 在线歌词改由 lrclib.net 提供，单独记录来源
+ALTER TYPE lyrics_source_type ADD VALUE IF NOT EXISTS 'lrclib';
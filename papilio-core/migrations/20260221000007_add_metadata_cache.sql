@@ -0,0 +1,11 @@
+-- 外部元数据查询缓存 (MusicBrainz / Wikidata / Cover Art Archive)
+-- payload 为 JSON 文本，'null' 表示上游确认无结果 (同样缓存，避免重复打满限流)
+CREATE TABLE IF NOT EXISTS metadata_cache (
+    entity_type TEXT NOT NULL,
+    query_hash TEXT NOT NULL,
+    payload TEXT NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (entity_type, query_hash)
+);
+
+CREATE INDEX IF NOT EXISTS idx_metadata_cache_fetched_at ON metadata_cache (fetched_at);
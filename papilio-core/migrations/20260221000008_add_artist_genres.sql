@@ -0,0 +1,2 @@
+-- 歌手流派 (来自 MusicBrainz genres / tags)，bio 字段在初始表结构中已存在
+ALTER TABLE artists ADD COLUMN IF NOT EXISTS genres TEXT[] NOT NULL DEFAULT '{}';
@@ -0,0 +1,8 @@
+-- 播放记录带上实际收听时长：只有达到阈值的记录才算一次"播放"
+ALTER TABLE play_history ADD COLUMN IF NOT EXISTS played_ms INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE play_history ADD COLUMN IF NOT EXISTS completed BOOLEAN NOT NULL DEFAULT FALSE;
+-- 历史数据无法区分完整播放与误触，保留为已计数
+ALTER TABLE play_history ADD COLUMN IF NOT EXISTS counted BOOLEAN NOT NULL DEFAULT FALSE;
+UPDATE play_history SET counted = TRUE;
+
+CREATE INDEX IF NOT EXISTS idx_play_history_user_counted ON play_history (user_id, track_id) WHERE counted;
@@ -0,0 +1,12 @@
+-- 搜索：pg_trgm 模糊匹配 + unaccent 去除重音，曲名/歌手/专辑均建立三元组索引
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- unaccent() 不是 IMMUTABLE，包一层固定词典的函数才能用于表达式索引
+CREATE OR REPLACE FUNCTION papilio_search_norm(text) RETURNS text AS $$
+    SELECT lower(public.unaccent('public.unaccent'::regdictionary, $1))
+$$ LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT;
+
+CREATE INDEX IF NOT EXISTS idx_tracks_title_trgm ON tracks USING gin (papilio_search_norm(title) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_artists_name_trgm ON artists USING gin (papilio_search_norm(name) gin_trgm_ops);
+CREATE INDEX IF NOT EXISTS idx_albums_title_trgm ON albums USING gin (papilio_search_norm(title) gin_trgm_ops);
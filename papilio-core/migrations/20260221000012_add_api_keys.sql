@@ -0,0 +1,15 @@
+-- API Key：供脚本与第三方工具调用，只保存 SHA-256 摘要，明文仅在创建时返回一次
+CREATE TABLE IF NOT EXISTS api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    -- 明文前缀，便于用户在列表中辨认
+    prefix TEXT NOT NULL,
+    key_hash TEXT NOT NULL UNIQUE,
+    -- 'read' 只允许 GET/HEAD，'full' 与登录会话等价
+    scopes TEXT[] NOT NULL DEFAULT '{full}',
+    last_used_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_keys_user ON api_keys (user_id);
@@ -0,0 +1,2 @@
+-- 定时扫描：记录上次由调度器触发的时间，用于计算下一次运行
+ALTER TABLE scan_status ADD COLUMN IF NOT EXISTS last_scheduled_scan_at TIMESTAMPTZ;
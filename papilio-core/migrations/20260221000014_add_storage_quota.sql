@@ -0,0 +1,3 @@
+-- 用户存储配额：NULL 表示不限制，storage_used_bytes 累计该用户上传文件占用的字节数
+ALTER TABLE users ADD COLUMN IF NOT EXISTS storage_quota_bytes BIGINT;
+ALTER TABLE users ADD COLUMN IF NOT EXISTS storage_used_bytes BIGINT NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+-- 批量歌手同步的取消标志与断点：cursor_artist_id 为最后处理完的歌手，按 id 顺序续传
+ALTER TABLE artist_sync_status ADD COLUMN IF NOT EXISTS cancel_requested BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE artist_sync_status ADD COLUMN IF NOT EXISTS cursor_artist_id UUID;
@@ -0,0 +1,14 @@
+-- 收藏专辑与关注歌手，与 user_favorites (曲目) 并列
+CREATE TABLE IF NOT EXISTS user_favorite_albums (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    album_id UUID NOT NULL REFERENCES albums(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, album_id)
+);
+
+CREATE TABLE IF NOT EXISTS user_favorite_artists (
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    artist_id UUID NOT NULL REFERENCES artists(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (user_id, artist_id)
+);
@@ -0,0 +1,9 @@
+-- 批量歌手同步的逐条结果，每位歌手只保留最近一次
+CREATE TABLE IF NOT EXISTS sync_results (
+    artist_id UUID PRIMARY KEY REFERENCES artists(id) ON DELETE CASCADE,
+    status TEXT NOT NULL, -- success / failed / timeout
+    error TEXT,
+    synced_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_sync_results_status ON sync_results(status, synced_at DESC);
@@ -0,0 +1,2 @@
+-- 是否向同一服务器的其他用户公开"正在收听"
+ALTER TABLE users ADD COLUMN IF NOT EXISTS share_now_playing BOOLEAN NOT NULL DEFAULT TRUE;
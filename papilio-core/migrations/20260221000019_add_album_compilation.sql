@@ -0,0 +1,2 @@
+-- 合辑 (Various Artists)：专辑的 artist_id 为专辑歌手，曲目各自保留演唱者
+ALTER TABLE albums ADD COLUMN IF NOT EXISTS is_compilation BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,3 @@
+-- 扫描时文件缺失的音轨先软删除，宽限期后再物理删除，避免挂载点暂时不可用时丢失歌单与收藏
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS deleted_at TIMESTAMPTZ;
+CREATE INDEX IF NOT EXISTS idx_tracks_deleted_at ON tracks (deleted_at) WHERE deleted_at IS NOT NULL;
@@ -0,0 +1,2 @@
+-- 完整发行日期 (标签或 MusicBrainz)，release_year 保留以兼容旧客户端与年份筛选
+ALTER TABLE albums ADD COLUMN IF NOT EXISTS release_date DATE;
@@ -0,0 +1,2 @@
+-- 曲目级 MusicBrainz 同步写入的 ISRC (国际标准录音制品编码)
+ALTER TABLE tracks ADD COLUMN IF NOT EXISTS isrc TEXT;
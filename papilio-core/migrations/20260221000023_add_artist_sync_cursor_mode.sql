@@ -0,0 +1,2 @@
+-- 断点所属的同步模式：force 与普通同步的候选集合不同，断点只能在同一模式下续传
+ALTER TABLE artist_sync_status ADD COLUMN IF NOT EXISTS cursor_force BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,6 @@
+-- 整理时移动文件前写入的意图记录：移动完成到改写 tracks.path 之间进程退出时，启动时据此对账
+CREATE TABLE IF NOT EXISTS organize_pending_moves (
+    source_path TEXT PRIMARY KEY,
+    dest_path TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
@@ -0,0 +1,3 @@
+-- 管理员上传的歌手图片计入上传者配额：记录上传者与文件大小，替换时退还给原上传者
+ALTER TABLE artists ADD COLUMN IF NOT EXISTS image_uploaded_by UUID REFERENCES users(id) ON DELETE SET NULL;
+ALTER TABLE artists ADD COLUMN IF NOT EXISTS image_size BIGINT;
@@ -0,0 +1,42 @@
+use rand::{distributions::Alphanumeric, Rng};
+use sha2::{Digest, Sha256};
+
+/// 明文 Key 的固定前缀，方便在日志或代码仓库中识别泄露的 Key
+pub const API_KEY_PREFIX: &str = "pap_";
+
+/// 只读 scope：仅允许 GET/HEAD 请求
+pub const API_KEY_SCOPE_READ: &str = "read";
+/// 完整 scope：与登录会话权限相同
+pub const API_KEY_SCOPE_FULL: &str = "full";
+
+pub fn generate_api_key() -> String {
+    let random: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect();
+    format!("{}{}", API_KEY_PREFIX, random)
+}
+
+/// Key 本身是高熵随机串，直接取 SHA-256 即可，无需 Argon2 这类慢哈希
+pub fn hash_api_key(key: &str) -> String {
+    Sha256::digest(key.trim().as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_api_key_hash_is_stable() {
+        let key = generate_api_key();
+        assert!(key.starts_with(API_KEY_PREFIX));
+        assert_eq!(key.len(), API_KEY_PREFIX.len() + 40);
+        assert_eq!(hash_api_key(&key), hash_api_key(&key));
+        assert_ne!(hash_api_key(&key), hash_api_key(&generate_api_key()));
+        assert_eq!(hash_api_key(&key).len(), 64);
+    }
+}
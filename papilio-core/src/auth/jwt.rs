@@ -1,15 +1,107 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use chrono::{Duration, Utc};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use uuid::Uuid;
 
+/// 本服务登录时签发的 token 的 `iss`
+pub const LOCAL_ISSUER: &str = "papilio";
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: Uuid, // User ID
     pub username: String,
     pub exp: usize, // Expiration time
     pub iat: usize, // Issued at
+    /// 签发方。缺省视为本服务签发 (兼容早期 token)，其他取值为外部身份提供方
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+}
+
+impl Claims {
+    /// 由外部身份提供方签发：不经过本服务登录，Valkey 中没有对应的会话
+    pub fn is_external(&self) -> bool {
+        self.iss.as_deref().is_some_and(|iss| iss != LOCAL_ISSUER)
+    }
+}
+
+/// 签名算法由 JWT_ALG 决定，默认 HS256 (使用 `jwt_secret`)。
+/// RS256/RS384/RS512 从 JWT_PRIVATE_KEY_PATH / JWT_PUBLIC_KEY_PATH 读取 PEM，
+/// 私钥签发、公钥校验；公钥也可以是外部身份提供方的，其签发的 token 见 `Claims::is_external`。
+/// 取值无法识别时由 `check_jwt_config` 在启动阶段报错。
+pub fn jwt_algorithm() -> Algorithm {
+    static ALG: OnceLock<Algorithm> = OnceLock::new();
+    *ALG.get_or_init(|| configured_algorithm().unwrap_or(Algorithm::HS256))
+}
+
+fn configured_algorithm() -> Result<Algorithm> {
+    match std::env::var("JWT_ALG") {
+        Ok(value) if !value.trim().is_empty() => parse_algorithm(&value).ok_or_else(|| {
+            anyhow!(
+                "Unsupported JWT_ALG: {} (expected HS256, HS384, HS512, RS256, RS384 or RS512)",
+                value
+            )
+        }),
+        _ => Ok(Algorithm::HS256),
+    }
+}
+
+/// 启动时校验 JWT 配置：JWT_ALG 必须可识别，RSA 模式下私钥与公钥都必须能读取
+pub fn check_jwt_config(secret: &str) -> Result<Algorithm> {
+    let alg = configured_algorithm()?;
+    if !is_hmac(alg) {
+        encoding_key(secret)?;
+        decoding_key(secret)?;
+    }
+    Ok(alg)
+}
+
+fn parse_algorithm(value: &str) -> Option<Algorithm> {
+    match value.trim().to_ascii_uppercase().as_str() {
+        "HS256" => Some(Algorithm::HS256),
+        "HS384" => Some(Algorithm::HS384),
+        "HS512" => Some(Algorithm::HS512),
+        "RS256" => Some(Algorithm::RS256),
+        "RS384" => Some(Algorithm::RS384),
+        "RS512" => Some(Algorithm::RS512),
+        _ => None,
+    }
+}
+
+fn is_hmac(alg: Algorithm) -> bool {
+    matches!(alg, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512)
+}
+
+fn read_pem(var: &str) -> Result<Vec<u8>, String> {
+    let path = std::env::var(var).map_err(|_| format!("{} is not set", var))?;
+    std::fs::read(&path).map_err(|e| format!("Failed to read {} ({}): {}", var, path, e))
+}
+
+fn encoding_key(secret: &str) -> Result<EncodingKey> {
+    if is_hmac(jwt_algorithm()) {
+        return Ok(EncodingKey::from_secret(secret.as_ref()));
+    }
+    static KEY: OnceLock<Result<EncodingKey, String>> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let pem = read_pem("JWT_PRIVATE_KEY_PATH")?;
+        EncodingKey::from_rsa_pem(&pem).map_err(|e| format!("Invalid RSA private key: {}", e))
+    })
+    .clone()
+    .map_err(|e| anyhow!(e))
+}
+
+fn decoding_key(secret: &str) -> Result<DecodingKey> {
+    if is_hmac(jwt_algorithm()) {
+        return Ok(DecodingKey::from_secret(secret.as_ref()));
+    }
+    static KEY: OnceLock<Result<DecodingKey, String>> = OnceLock::new();
+    KEY.get_or_init(|| {
+        let pem = read_pem("JWT_PUBLIC_KEY_PATH")?;
+        DecodingKey::from_rsa_pem(&pem).map_err(|e| format!("Invalid RSA public key: {}", e))
+    })
+    .clone()
+    .map_err(|e| anyhow!(e))
 }
 
 pub fn create_token(user_id: Uuid, username: String, secret: &str) -> Result<String> {
@@ -23,27 +115,34 @@ pub fn create_token(user_id: Uuid, username: String, secret: &str) -> Result<Str
         username,
         exp: expiration as usize,
         iat: Utc::now().timestamp() as usize,
+        iss: Some(LOCAL_ISSUER.to_string()),
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )?;
+    sign_claims(&claims, secret)
+}
 
-    Ok(token)
+/// 按当前配置的算法与密钥签名任意 claims
+pub fn sign_claims(claims: &Claims, secret: &str) -> Result<String> {
+    Ok(encode(&Header::new(jwt_algorithm()), claims, &encoding_key(secret)?)?)
 }
 
 pub fn verify_token(token: &str, secret: &str) -> Result<Claims> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::new(Algorithm::HS256),
-    )?;
+    let token_data = decode::<Claims>(token, &decoding_key(secret)?, &Validation::new(jwt_algorithm()))?;
 
     Ok(token_data.claims)
 }
 
+/// `verify_token` 的错误是否因 token 过期 (签名本身有效)
+pub fn is_expired(err: &anyhow::Error) -> bool {
+    err.downcast_ref::<jsonwebtoken::errors::Error>()
+        .is_some_and(|e| matches!(e.kind(), jsonwebtoken::errors::ErrorKind::ExpiredSignature))
+}
+
+/// 会话的对外标识：不直接暴露 token 本身，取其 MD5 前 16 位
+pub fn token_id(token: &str) -> String {
+    format!("{:x}", md5::compute(token.as_bytes()))[..16].to_string()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -59,6 +158,14 @@ mod tests {
         
         assert_eq!(claims.sub, user_id);
         assert_eq!(claims.username, username);
+        assert!(!claims.is_external());
+    }
+
+    #[test]
+    fn test_parse_algorithm() {
+        assert_eq!(parse_algorithm("rs256"), Some(Algorithm::RS256));
+        assert_eq!(parse_algorithm(" HS512 "), Some(Algorithm::HS512));
+        assert_eq!(parse_algorithm("none"), None);
     }
 
     #[test]
@@ -68,5 +175,21 @@ mod tests {
         
         let result = verify_token(&token, "wrong_secret");
         assert!(result.is_err());
+        assert!(!is_expired(&result.unwrap_err()));
+    }
+
+    #[test]
+    fn test_expired_token_is_distinguished() {
+        let claims = Claims {
+            sub: Uuid::new_v4(),
+            username: "user".into(),
+            exp: (Utc::now().timestamp() - 3600) as usize,
+            iat: (Utc::now().timestamp() - 7200) as usize,
+            iss: None,
+        };
+        let token = encode(&Header::new(jwt_algorithm()), &claims, &encoding_key("secret").unwrap()).unwrap();
+
+        let err = verify_token(&token, "secret").unwrap_err();
+        assert!(is_expired(&err));
     }
 }
@@ -1,12 +1,71 @@
 use anyhow::{anyhow, Result};
 use argon2::{
     password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
-    Argon2,
+    Algorithm, Argon2, Params, Version,
 };
+use crate::error::AppError;
+use std::sync::OnceLock;
 
+/// Argon2id 的代价参数，只影响新生成的哈希；已有哈希的参数记录在哈希串里，校验时自动沿用
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Argon2Config {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Config {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl Argon2Config {
+    /// 读取 ARGON2_MEMORY_KIB / ARGON2_ITERATIONS / ARGON2_PARALLELISM，未设置时使用 argon2 库的默认值
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        // 无法解析的取值退回默认，并记录变量名，避免配置错误被静默忽略
+        let read = |key: &str, fallback: u32| match std::env::var(key) {
+            Ok(raw) => raw.trim().parse().unwrap_or_else(|_| {
+                tracing::warn!("Invalid {}={:?}, using default {}", key, raw, fallback);
+                fallback
+            }),
+            Err(_) => fallback,
+        };
+        Self {
+            memory_kib: read("ARGON2_MEMORY_KIB", default.memory_kib),
+            iterations: read("ARGON2_ITERATIONS", default.iterations),
+            parallelism: read("ARGON2_PARALLELISM", default.parallelism),
+        }
+    }
+
+    pub fn build(&self) -> Result<Argon2<'static>> {
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|e| anyhow!("Invalid Argon2 parameters: {}", e))?;
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+}
+
+/// 按环境变量配置的参数生成哈希；参数非法 (如内存小于 8 × 并行度) 时退回默认值，避免配置错误导致无法注册
 pub fn hash_password(password: &str) -> Result<String> {
+    let config = Argon2Config::from_env();
+    let config = match config.build() {
+        Ok(_) => config,
+        Err(e) => {
+            tracing::warn!("{:?}: {}, falling back to defaults", config, e);
+            Argon2Config::default()
+        }
+    };
+    hash_password_with(password, &config)
+}
+
+pub fn hash_password_with(password: &str, config: &Argon2Config) -> Result<String> {
     let salt = SaltString::generate(&mut OsRng);
-    let argon2 = Argon2::default();
+    let argon2 = config.build()?;
     let password_hash = argon2
         .hash_password(password.as_bytes(), &salt)
         .map_err(|e| anyhow!("Failed to hash password: {}", e))?
@@ -22,6 +81,78 @@ pub fn verify_password(password: &str, hash: &str) -> Result<bool> {
         .is_ok())
 }
 
+/// 密码强度要求：最小长度与至少包含的字符类别数 (小写、大写、数字、符号)
+#[derive(Debug, Clone, Copy)]
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub min_classes: usize,
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self {
+            min_length: 8,
+            min_classes: 2,
+        }
+    }
+}
+
+impl PasswordPolicy {
+    /// 读取 PASSWORD_MIN_LENGTH / PASSWORD_MIN_CLASSES，未设置时使用默认值
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        let read = |key: &str, fallback: usize| {
+            std::env::var(key)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(fallback)
+        };
+        Self {
+            min_length: read("PASSWORD_MIN_LENGTH", default.min_length),
+            min_classes: read("PASSWORD_MIN_CLASSES", default.min_classes).min(4),
+        }
+    }
+
+    pub fn validate(&self, password: &str) -> Result<(), AppError> {
+        if password.chars().count() < self.min_length {
+            return Err(AppError::BadRequest(format!(
+                "Password must be at least {} characters long",
+                self.min_length
+            )));
+        }
+
+        let classes = [
+            password.chars().any(|c| c.is_lowercase()),
+            password.chars().any(|c| c.is_uppercase()),
+            password.chars().any(|c| c.is_ascii_digit()),
+            password.chars().any(|c| !c.is_alphanumeric()),
+        ]
+        .iter()
+        .filter(|present| **present)
+        .count();
+
+        if classes < self.min_classes {
+            return Err(AppError::BadRequest(format!(
+                "Password must contain at least {} of: lowercase letters, uppercase letters, digits, symbols",
+                self.min_classes
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// 按环境变量配置的策略校验密码强度
+pub fn validate_password_strength(password: &str) -> Result<(), AppError> {
+    PasswordPolicy::from_env().validate(password)
+}
+
+/// 用户不存在时也跑一次完整的 Argon2 校验，使响应耗时与密码错误一致，避免借此枚举用户名
+pub fn dummy_verify(password: &str) {
+    static DUMMY_HASH: OnceLock<String> = OnceLock::new();
+    let hash = DUMMY_HASH.get_or_init(|| hash_password("papilio-dummy-password").unwrap_or_default());
+    let _ = verify_password(password, hash);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -35,6 +166,28 @@ mod tests {
         assert!(!verify_password("wrong_password", &hash).unwrap());
     }
 
+    #[test]
+    fn test_password_policy_accepts_strong_passwords() {
+        let policy = PasswordPolicy::default();
+        assert!(policy.validate("correct horse 42").is_ok());
+        assert!(policy.validate("Abcdefgh").is_ok());
+        assert!(policy.validate("音乐库的密码-2024").is_ok());
+    }
+
+    #[test]
+    fn test_password_policy_rejects_weak_passwords() {
+        let policy = PasswordPolicy::default();
+        assert!(matches!(policy.validate("a"), Err(AppError::BadRequest(_))));
+        assert!(matches!(policy.validate("Ab1!"), Err(AppError::BadRequest(_))));
+        // 长度足够但只有一类字符
+        assert!(matches!(policy.validate("abcdefghij"), Err(AppError::BadRequest(_))));
+        assert!(matches!(policy.validate("1234567890"), Err(AppError::BadRequest(_))));
+
+        let strict = PasswordPolicy { min_length: 12, min_classes: 3 };
+        assert!(strict.validate("abcdefgh1234").is_err());
+        assert!(strict.validate("Abcdefgh1234").is_ok());
+    }
+
     #[test]
     fn test_different_hashes() {
         let password = "same_password";
@@ -43,4 +196,27 @@ mod tests {
         
         assert_ne!(hash1, hash2, "Hashes should be different due to random salt");
     }
+
+    #[test]
+    fn test_custom_params_hash_still_verifies() {
+        let config = Argon2Config {
+            memory_kib: 8192,
+            iterations: 3,
+            parallelism: 2,
+        };
+        let hash = hash_password_with("tuned_password", &config).unwrap();
+        assert!(hash.contains("m=8192,t=3,p=2"));
+        // 校验使用哈希串里记录的参数，与当前配置无关
+        assert!(verify_password("tuned_password", &hash).unwrap());
+        assert!(!verify_password("wrong_password", &hash).unwrap());
+    }
+
+    #[test]
+    fn test_invalid_params_are_rejected() {
+        let config = Argon2Config {
+            memory_kib: 1,
+            ..Argon2Config::default()
+        };
+        assert!(config.build().is_err());
+    }
 }
@@ -0,0 +1,291 @@
+//! 运维命令行：不经过 HTTP 服务直接对数据库与曲库执行维护任务，便于 cron / 脚本调用。
+//! 连接信息沿用服务端的 `DATABASE_URL` / `MUSIC_DIR` 环境变量。
+
+use anyhow::{anyhow, bail, Context};
+use clap::{Parser, Subcommand};
+use papilio_core::scanner::organizer::{OrganizeOutcome, Organizer};
+use papilio_core::scanner::{ScanOperation, Scanner};
+use papilio_core::MetadataService;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Row};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[derive(Parser)]
+#[command(name = "papilio-admin", about = "Papilio library maintenance CLI")]
+struct Cli {
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    database_url: String,
+
+    /// 曲库根目录，scan / organize / cleanup-orphans 需要
+    #[arg(long, env = "MUSIC_DIR")]
+    music_dir: Option<PathBuf>,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Incrementally scan the library (--force re-reads every file)
+    Scan {
+        #[arg(long)]
+        force: bool,
+    },
+    /// Move files into the configured naming layout
+    Organize {
+        /// Only print the planned moves, touch neither files nor the database
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Fetch artist metadata and images from MusicBrainz
+    SyncArtists {
+        /// Only artists without an image
+        #[arg(long)]
+        missing: bool,
+    },
+    /// Soft-delete tracks whose files are gone, purge expired ones and drop empty albums/artists
+    CleanupOrphans,
+    /// Print library totals
+    Stats,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "papilio_core=info".into()),
+        )
+        .with_writer(std::io::stderr)
+        .init();
+
+    let cli = Cli::parse();
+    let pool = PgPoolOptions::new()
+        .connect(&cli.database_url)
+        .await
+        .context("Failed to connect to DATABASE_URL")?;
+
+    // stats 只读，其余命令在执行期间占用与服务端共用的运行标记
+    let flag = match &cli.command {
+        Command::Scan { .. } | Command::CleanupOrphans => {
+            Some(RunFlag::Scan(ScanOperation::Scanning))
+        }
+        Command::Organize { .. } => Some(RunFlag::Scan(ScanOperation::Organizing)),
+        Command::SyncArtists { .. } => Some(RunFlag::ArtistSync),
+        Command::Stats => None,
+    };
+    if let Some(flag) = &flag {
+        flag.claim(&pool).await?;
+    }
+
+    // Ctrl-C 时同样先清除标记再退出，避免服务端一直认为任务在进行
+    let result = tokio::select! {
+        result = run(cli.command, cli.music_dir.as_ref(), &pool) => result,
+        _ = tokio::signal::ctrl_c() => Err(anyhow!("Interrupted")),
+    };
+    if let Some(flag) = &flag {
+        flag.release(&pool).await;
+    }
+    result
+}
+
+async fn run(command: Command, music_dir: Option<&PathBuf>, pool: &PgPool) -> anyhow::Result<()> {
+    match command {
+        Command::Scan { force } => {
+            let music_root = music_root(music_dir)?;
+            let scanner = Scanner::new(pool.clone())
+                .with_metadata_service(Arc::new(MetadataService::new(pool.clone())));
+            let path = music_root.to_str().context("MUSIC_DIR is not valid UTF-8")?;
+            scanner.scan_directory(path, force).await?;
+            println!("Scan of {} finished", music_root.display());
+        }
+        Command::Organize { dry_run } => {
+            let music_root = music_root(music_dir)?;
+            let organizer = Organizer::new(pool.clone(), music_root.to_path_buf());
+            let report = if dry_run {
+                organizer.preview().await?
+            } else {
+                organizer.organize().await?
+            };
+            for entry in &report.entries {
+                // 预览时逐条列出计划移动的文件，正式整理只列出跳过与失败的
+                if entry.outcome == OrganizeOutcome::Moved && !dry_run {
+                    continue;
+                }
+                println!(
+                    "[{:?}] {} -> {}",
+                    entry.outcome,
+                    entry.source,
+                    entry
+                        .reason
+                        .as_deref()
+                        .or(entry.destination.as_deref())
+                        .unwrap_or_default()
+                );
+            }
+            println!(
+                "{}: {} {}, {} skipped, {} failed{}",
+                if dry_run { "Dry run" } else { "Reorganization finished" },
+                report.moved,
+                if dry_run { "to move" } else { "moved" },
+                report.skipped,
+                report.failed,
+                if report.truncated { " (entries truncated)" } else { "" }
+            );
+        }
+        Command::SyncArtists { missing } => {
+            sync_artists(pool, missing).await?;
+        }
+        Command::CleanupOrphans => {
+            let music_root = music_root(music_dir)?;
+            // 与管理接口一致：根目录不可达时所有音轨都会被当作孤儿，直接拒绝
+            if !music_root.is_dir() {
+                bail!("Music library root is unreachable: {}", music_root.display());
+            }
+            let scanner = Scanner::new(pool.clone());
+            let orphans = scanner.cleanup_orphans().await?;
+            if orphans.mount_down {
+                bail!("Too many tracks are missing on disk, the library mount may be down; nothing was deleted");
+            }
+            let vacuum = scanner.vacuum().await?;
+            println!(
+                "Orphan cleanup finished: {} soft-deleted, {} purged, {} empty albums and {} empty artists removed",
+                orphans.soft_deleted, orphans.purged, vacuum.deleted_albums, vacuum.deleted_artists
+            );
+        }
+        Command::Stats => {
+            print_stats(pool).await?;
+        }
+    }
+
+    Ok(())
+}
+
+fn music_root(music_dir: Option<&PathBuf>) -> anyhow::Result<&Path> {
+    music_dir
+        .map(PathBuf::as_path)
+        .context("MUSIC_DIR must be set (or pass --music-dir)")
+}
+
+/// 服务端与 CLI 共用的数据库运行标记。SCAN_LOCK 只在进程内生效，跨进程的互斥以这里为准
+enum RunFlag {
+    /// `scan_status.is_scanning`，扫描、整理与孤儿清理共用
+    Scan(ScanOperation),
+    /// `artist_sync_status.is_syncing`
+    ArtistSync,
+}
+
+impl RunFlag {
+    /// 条件更新在标记为假时才置真，与服务端或另一个 CLI 进程同时启动时只有一方成功
+    async fn claim(&self, pool: &PgPool) -> anyhow::Result<()> {
+        let claimed = match self {
+            Self::Scan(operation) => sqlx::query(
+                "UPDATE scan_status SET is_scanning = TRUE, operation = $1 WHERE id = 1 AND NOT is_scanning RETURNING id",
+            )
+            .bind(operation.as_str())
+            .fetch_optional(pool)
+            .await?,
+            Self::ArtistSync => sqlx::query(
+                "UPDATE artist_sync_status SET is_syncing = TRUE, cancel_requested = FALSE WHERE id = 1 AND NOT is_syncing RETURNING id",
+            )
+            .fetch_optional(pool)
+            .await?,
+        };
+        if claimed.is_none() {
+            match self {
+                Self::Scan(_) => bail!("A scan or reorganization is already in progress (scan_status.is_scanning)"),
+                Self::ArtistSync => bail!("An artist sync is already in progress (artist_sync_status.is_syncing)"),
+            }
+        }
+        Ok(())
+    }
+
+    /// 命令成功、失败或被中断后都会调用；清除失败只记录日志，不覆盖命令本身的结果
+    async fn release(&self, pool: &PgPool) {
+        let sql = match self {
+            Self::Scan(_) => "UPDATE scan_status SET is_scanning = FALSE, operation = 'idle' WHERE id = 1",
+            Self::ArtistSync => {
+                "UPDATE artist_sync_status SET is_syncing = FALSE, cancel_requested = FALSE WHERE id = 1"
+            }
+        };
+        if let Err(e) = sqlx::query(sql).execute(pool).await {
+            tracing::error!("Failed to clear the run flag: {}", e);
+        }
+    }
+}
+
+async fn sync_artists(pool: &PgPool, missing: bool) -> anyhow::Result<()> {
+    let sql = if missing {
+        "SELECT id, name FROM artists WHERE image_url IS NULL OR image_url = '' ORDER BY name"
+    } else {
+        "SELECT id, name FROM artists ORDER BY name"
+    };
+    let artists = sqlx::query(sql).fetch_all(pool).await?;
+    let total = artists.len();
+
+    // 请求频率由 MetadataService 内部的 MusicBrainz 限流控制，这里逐个处理即可
+    let service = MetadataService::new(pool.clone());
+    let mut failed = 0;
+    for (i, row) in artists.iter().enumerate() {
+        let id: Uuid = row.get("id");
+        let name: String = row.get("name");
+        match service.fetch_and_update_artist(id, false).await {
+            Ok(()) => println!("[{}/{}] {}", i + 1, total, name),
+            Err(e) => {
+                failed += 1;
+                println!("[{}/{}] {} failed: {}", i + 1, total, name, e);
+            }
+        }
+    }
+    println!("Artist sync finished: {} synced, {} failed", total - failed, failed);
+    Ok(())
+}
+
+async fn print_stats(pool: &PgPool) -> anyhow::Result<()> {
+    let totals = sqlx::query(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM tracks WHERE deleted_at IS NULL) AS tracks,
+            (SELECT COUNT(*) FROM tracks WHERE deleted_at IS NOT NULL) AS deleted_tracks,
+            (SELECT COUNT(*) FROM albums) AS albums,
+            (SELECT COUNT(*) FROM artists) AS artists,
+            (SELECT COALESCE(SUM(size), 0)::BIGINT FROM tracks WHERE deleted_at IS NULL) AS total_size,
+            (SELECT COALESCE(SUM(duration), 0)::BIGINT FROM tracks WHERE deleted_at IS NULL) AS total_duration,
+            (SELECT COUNT(*) FROM artists WHERE image_url IS NULL OR image_url = '') AS artists_missing_image
+        "#,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let formats = sqlx::query(
+        r#"
+        SELECT COALESCE(NULLIF(lower(format), ''), 'unknown') AS format, COUNT(*) AS count
+        FROM tracks
+        WHERE deleted_at IS NULL
+        GROUP BY 1
+        ORDER BY count DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let total_size: i64 = totals.get("total_size");
+    let total_duration: i64 = totals.get("total_duration");
+    println!("Tracks:          {}", totals.get::<i64, _>("tracks"));
+    println!("Deleted tracks:  {}", totals.get::<i64, _>("deleted_tracks"));
+    println!("Albums:          {}", totals.get::<i64, _>("albums"));
+    println!("Artists:         {}", totals.get::<i64, _>("artists"));
+    println!("Missing images:  {}", totals.get::<i64, _>("artists_missing_image"));
+    println!("Total size:      {:.2} GiB", total_size as f64 / (1u64 << 30) as f64);
+    println!("Total duration:  {:.1} h", total_duration as f64 / 3600.0);
+    for row in &formats {
+        println!(
+            "  {:<10} {}",
+            row.get::<String, _>("format"),
+            row.get::<i64, _>("count")
+        );
+    }
+    Ok(())
+}
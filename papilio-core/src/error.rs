@@ -9,6 +9,10 @@ pub enum AppError {
     #[error("Auth error: {0}")]
     Auth(String),
 
+    /// 凭据曾经有效但已过期或会话已被注销，客户端应清除本地 token 重新登录
+    #[error("Auth expired: {0}")]
+    AuthExpired(String),
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -23,9 +27,34 @@ pub enum AppError {
 
     #[error("Metadata error: {0}")]
     Metadata(String),
+
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+
+    #[error("Not implemented: {0}")]
+    NotImplemented(String),
+}
+
+impl AppError {
+    /// 供客户端判断错误类型的机器可读错误码，与 HTTP 状态码一一对应
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Auth(_) => "UNAUTHORIZED",
+            AppError::AuthExpired(_) => "AUTH_EXPIRED",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::BadRequest(_) => "BAD_REQUEST",
+            AppError::Metadata(_) => "METADATA_ERROR",
+            AppError::TooManyRequests(_) => "RATE_LIMITED",
+            AppError::NotImplemented(_) => "NOT_IMPLEMENTED",
+            AppError::Database(_) | AppError::Io(_) | AppError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
 }
 
 #[derive(Serialize)]
 pub struct ErrorResponse {
+    /// 错误码，见 [`AppError::code`]
+    pub code: &'static str,
+    /// 人类可读的错误信息，保留以兼容旧客户端
     pub error: String,
 }
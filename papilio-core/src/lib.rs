@@ -2,10 +2,14 @@ pub mod auth;
 pub mod error;
 pub mod metadata;
 pub mod models;
+pub mod playlist_io;
 pub mod scanner;
+pub mod storage;
 
 pub use auth::*;
 pub use error::*;
 pub use metadata::*;
 pub use models::*;
+pub use playlist_io::*;
 pub use scanner::*;
+pub use storage::*;
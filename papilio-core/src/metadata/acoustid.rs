@@ -0,0 +1,173 @@
+//! 无标签文件的声纹识别：`fpcalc` (Chromaprint) 计算指纹，AcoustID 反查 MusicBrainz 录音。
+//! 仅在配置了 ACOUSTID_API_KEY 且标签缺少标题或歌手时使用。
+
+use crate::error::AppError;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::Path;
+use tokio::process::Command;
+use uuid::Uuid;
+
+pub const ACOUSTID_LOOKUP_URL: &str = "https://api.acoustid.org/v2/lookup";
+
+/// 低于该得分的匹配视为不可信，宁可沿用文件名也不要张冠李戴
+pub const MIN_MATCH_SCORE: f64 = 0.8;
+
+/// Chromaprint 指纹及参与计算的音频时长 (秒)
+#[derive(Debug, Clone)]
+pub struct Fingerprint {
+    pub duration: u32,
+    pub fingerprint: String,
+}
+
+/// AcoustID 识别出的录音信息
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordingMatch {
+    pub recording_id: Uuid,
+    pub title: String,
+    pub artist: String,
+    pub album: Option<String>,
+    pub score: f64,
+}
+
+/// 调用 `fpcalc -json` 计算指纹，未安装 fpcalc 时返回错误
+pub async fn fingerprint_file(path: &Path) -> Result<Fingerprint, AppError> {
+    let binary = std::env::var("FPCALC_PATH").unwrap_or_else(|_| "fpcalc".to_string());
+    let output = Command::new(&binary)
+        .arg("-json")
+        .arg(path)
+        .output()
+        .await
+        .map_err(|e| AppError::Metadata(format!("Failed to run {}: {}", binary, e)))?;
+    if !output.status.success() {
+        return Err(AppError::Metadata(format!(
+            "fpcalc failed for {}: {}",
+            path.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let json: Value = serde_json::from_slice(&output.stdout)
+        .map_err(|e| AppError::Metadata(format!("Invalid fpcalc output: {}", e)))?;
+    match (json["duration"].as_f64(), json["fingerprint"].as_str()) {
+        (Some(duration), Some(fingerprint)) if !fingerprint.is_empty() => Ok(Fingerprint {
+            duration: duration.round() as u32,
+            fingerprint: fingerprint.to_string(),
+        }),
+        _ => Err(AppError::Metadata("fpcalc returned no fingerprint".to_string())),
+    }
+}
+
+/// 从 `meta=recordings releasegroups` 的查询结果中选出得分最高且信息完整的录音。
+/// 专辑优先取类型为 Album 的发行组
+pub fn parse_lookup_response(json: &Value) -> Option<RecordingMatch> {
+    if json["status"].as_str() != Some("ok") {
+        return None;
+    }
+
+    let mut results: Vec<&Value> = json["results"].as_array()?.iter().collect();
+    results.sort_by(|a, b| {
+        let score = |v: &Value| v["score"].as_f64().unwrap_or(0.0);
+        score(b).total_cmp(&score(a))
+    });
+
+    results
+        .into_iter()
+        .filter(|r| r["score"].as_f64().unwrap_or(0.0) >= MIN_MATCH_SCORE)
+        .find_map(|result| {
+            let score = result["score"].as_f64()?;
+            result["recordings"].as_array()?.iter().find_map(|rec| {
+                let recording_id = Uuid::parse_str(rec["id"].as_str()?).ok()?;
+                let title = rec["title"].as_str()?.trim();
+                let artist = rec["artists"]
+                    .as_array()?
+                    .iter()
+                    .filter_map(|a| a["name"].as_str())
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                if title.is_empty() || artist.is_empty() {
+                    return None;
+                }
+                let groups = rec["releasegroups"].as_array();
+                let album = groups
+                    .and_then(|g| g.iter().find(|g| g["type"].as_str() == Some("Album")))
+                    .or_else(|| groups.and_then(|g| g.first()))
+                    .and_then(|g| g["title"].as_str())
+                    .map(str::to_string);
+                Some(RecordingMatch {
+                    recording_id,
+                    title: title.to_string(),
+                    artist,
+                    album,
+                    score,
+                })
+            })
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_lookup_response_picks_best_recording() {
+        let response = json!({
+            "status": "ok",
+            "results": [
+                {
+                    "id": "low",
+                    "score": 0.42,
+                    "recordings": [{
+                        "id": "11111111-1111-1111-1111-111111111111",
+                        "title": "Wrong Song",
+                        "artists": [{ "name": "Someone Else" }]
+                    }]
+                },
+                {
+                    "id": "high",
+                    "score": 0.97,
+                    "recordings": [
+                        { "id": "22222222-2222-2222-2222-222222222222" },
+                        {
+                            "id": "33333333-3333-3333-3333-333333333333",
+                            "title": "Sunny Day",
+                            "artists": [{ "name": "Guest Singer" }, { "name": "Friend" }],
+                            "releasegroups": [
+                                { "title": "Sunny Day (Single)", "type": "Single" },
+                                { "title": "Summer Hits", "type": "Album" }
+                            ]
+                        }
+                    ]
+                }
+            ]
+        });
+
+        let found = parse_lookup_response(&response).unwrap();
+        assert_eq!(found.title, "Sunny Day");
+        assert_eq!(found.artist, "Guest Singer, Friend");
+        assert_eq!(found.album.as_deref(), Some("Summer Hits"));
+        assert_eq!(
+            found.recording_id,
+            Uuid::parse_str("33333333-3333-3333-3333-333333333333").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_lookup_response_rejects_weak_or_failed_lookups() {
+        let weak = json!({
+            "status": "ok",
+            "results": [{
+                "score": 0.5,
+                "recordings": [{
+                    "id": "11111111-1111-1111-1111-111111111111",
+                    "title": "Maybe",
+                    "artists": [{ "name": "Someone" }]
+                }]
+            }]
+        });
+        assert!(parse_lookup_response(&weak).is_none());
+        assert!(parse_lookup_response(&json!({ "status": "error", "error": { "message": "invalid API key" } })).is_none());
+        assert!(parse_lookup_response(&json!({ "status": "ok", "results": [] })).is_none());
+    }
+}
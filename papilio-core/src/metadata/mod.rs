@@ -1,43 +1,105 @@
+pub mod acoustid;
+pub mod rate_limit;
+
 use crate::error::AppError;
+use acoustid::RecordingMatch;
+use rate_limit::RateLimiter;
 use musicbrainz_rs::client::MusicBrainzClient;
 use musicbrainz_rs::entity::artist::{Artist as MBArtist, ArtistSearchQuery};
+use musicbrainz_rs::entity::recording::{Recording as MBRecording, RecordingSearchQuery};
 use musicbrainz_rs::entity::relations::RelationContent;
 use musicbrainz_rs::entity::release::{Release as MBRelease, ReleaseSearchQuery};
 use musicbrainz_rs::Fetch;
 use musicbrainz_rs::Search;
-use serde_json::Value;
-use sqlx::PgPool;
+use serde_json::{json, Value};
+use sqlx::{PgPool, Row};
 use std::path::Path;
 use std::time::Duration;
 use tokio_retry::strategy::{jitter, ExponentialBackoff};
 use tokio_retry::Retry;
 use uuid::Uuid;
 
+/// 元数据请求使用的代理：优先 METADATA_PROXY，其次 HTTP_PROXY，均未设置时直连
+fn resolve_proxy() -> Option<String> {
+    ["METADATA_PROXY", "HTTP_PROXY"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .map(|v| v.trim().to_string())
+        .find(|v| !v.is_empty())
+}
+
+/// 缓存键：规范化后的查询串取 MD5
+fn cache_key(query: &str) -> String {
+    format!("{:x}", md5::compute(query.trim().to_lowercase()))
+}
+
+/// lrclib.net 开放歌词库
+const LRCLIB_BASE_URL: &str = "https://lrclib.net";
+
+const LASTFM_API_URL: &str = "https://ws.audioscrobbler.com/2.0/";
+/// Last.fm 对没有上传图片的歌手统一返回这张星星占位图
+const LASTFM_PLACEHOLDER_IMAGE: &str = "2a96cbd8b46e442fc41c2b86b821562f";
+
+const ITUNES_SEARCH_URL: &str = "https://itunes.apple.com/search";
+const DEEZER_ALBUM_SEARCH_URL: &str = "https://api.deezer.com/search/album";
+
+/// CAA 没有封面时依次尝试的备用来源，可通过 COVER_FALLBACK_PROVIDERS 调整 (逗号分隔，`none` 关闭)
+const DEFAULT_COVER_FALLBACK_PROVIDERS: &[&str] = &["itunes", "deezer"];
+
+/// 每位歌手最多保留的流派数
+const MAX_ARTIST_GENRES: usize = 5;
+
+/// 录音时长与本地曲目相差超过该秒数时不视为同一录音
+const RECORDING_DURATION_TOLERANCE_SECS: i64 = 5;
+
+/// AcoustID 限制每秒 3 次请求
+const ACOUSTID_MIN_INTERVAL: Duration = Duration::from_millis(350);
+
+/// MusicBrainz 要求每个客户端平均不超过 1 次/秒，可通过 MUSICBRAINZ_MIN_INTERVAL_MS 调整
+const DEFAULT_MB_MIN_INTERVAL_MS: u64 = 1000;
+
+/// 元数据缓存默认有效期 (天)，可通过 METADATA_CACHE_TTL_DAYS 覆盖
+const DEFAULT_CACHE_TTL_DAYS: i32 = 30;
+
 pub struct MetadataService {
     db: PgPool,
     client: reqwest::Client,
     mb_client: MusicBrainzClient,
+    cache_ttl_days: i32,
+    lastfm_api_key: Option<String>,
+    cover_fallback_providers: Vec<String>,
+    acoustid_api_key: Option<String>,
+    /// 所有 MusicBrainz 请求共用的令牌桶，批量同步与单个同步同时进行时也不会超限
+    mb_limiter: RateLimiter,
+    acoustid_limiter: RateLimiter,
 }
 
 impl MetadataService {
     pub fn new(db: PgPool) -> Self {
         let user_agent = "PapilioMusic/1.0.0 ( contact: admin@papilio.music )";
 
-        // 配置全局代理环境，确保所有依赖库（如 musicbrainz_rs）均能正常联网
-        let proxy_url = "http://192.168.10.31:7890";
-        std::env::set_var("HTTP_PROXY", proxy_url);
-        std::env::set_var("HTTPS_PROXY", proxy_url);
-        std::env::set_var("http_proxy", proxy_url);
-        std::env::set_var("https_proxy", proxy_url);
-
         let mut builder = reqwest::Client::builder()
             .user_agent(user_agent)
             .timeout(Duration::from_secs(60))
             .danger_accept_invalid_certs(true);
 
-        if let Ok(proxy) = reqwest::Proxy::all(proxy_url) {
-            builder = builder.proxy(proxy);
-            tracing::info!("MetadataService: Network proxy configured -> {}", proxy_url);
+        match resolve_proxy() {
+            Some(proxy_url) => match reqwest::Proxy::all(&proxy_url) {
+                Ok(proxy) => {
+                    builder = builder.proxy(proxy);
+                    // musicbrainz_rs 内部自建 reqwest 客户端，只能通过环境变量让它走同一个代理
+                    for key in ["HTTP_PROXY", "HTTPS_PROXY"] {
+                        if std::env::var(key).map(|v| v.trim().is_empty()).unwrap_or(true) {
+                            std::env::set_var(key, &proxy_url);
+                        }
+                    }
+                    tracing::info!("MetadataService: Network proxy configured -> {}", proxy_url);
+                }
+                Err(e) => {
+                    tracing::error!("MetadataService: Invalid proxy '{}': {}, connecting directly", proxy_url, e);
+                }
+            },
+            None => tracing::info!("MetadataService: No proxy configured, connecting directly"),
         }
 
         let client = builder.build().unwrap_or_else(|e| {
@@ -51,13 +113,171 @@ impl MetadataService {
             tracing::error!("MetadataService: Failed to set MB User-Agent: {}", e);
         }
 
+        let cache_ttl_days = std::env::var("METADATA_CACHE_TTL_DAYS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_CACHE_TTL_DAYS);
+
+        let lastfm_api_key = std::env::var("LASTFM_API_KEY")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        if lastfm_api_key.is_some() {
+            tracing::info!("MetadataService: Last.fm API key configured");
+        }
+
+        let acoustid_api_key = std::env::var("ACOUSTID_API_KEY")
+            .ok()
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty());
+        if acoustid_api_key.is_some() {
+            tracing::info!("MetadataService: AcoustID API key configured, fingerprinting untagged files");
+        }
+
+        let mb_interval = std::env::var("MUSICBRAINZ_MIN_INTERVAL_MS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_MB_MIN_INTERVAL_MS);
+        let mb_burst = std::env::var("MUSICBRAINZ_BURST")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(1);
+
+        let cover_fallback_providers = match std::env::var("COVER_FALLBACK_PROVIDERS") {
+            Ok(v) => v
+                .split(',')
+                .map(|p| p.trim().to_lowercase())
+                .filter(|p| DEFAULT_COVER_FALLBACK_PROVIDERS.contains(&p.as_str()))
+                .collect(),
+            Err(_) => DEFAULT_COVER_FALLBACK_PROVIDERS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+        };
+
         Self {
             db,
             client,
             mb_client,
+            cache_ttl_days,
+            lastfm_api_key,
+            cover_fallback_providers,
+            acoustid_api_key,
+            mb_limiter: RateLimiter::new(Duration::from_millis(mb_interval), mb_burst),
+            acoustid_limiter: RateLimiter::new(ACOUSTID_MIN_INTERVAL, 1),
+        }
+    }
+
+    pub fn fingerprinting_enabled(&self) -> bool {
+        self.acoustid_api_key.is_some()
+    }
+
+    /// 通过声纹识别无标签文件。未配置 ACOUSTID_API_KEY、fpcalc 不可用或没有可信匹配时返回 None，
+    /// 调用方继续使用标签 / 文件名
+    pub async fn identify_recording(&self, path: &Path) -> Option<RecordingMatch> {
+        let api_key = self.acoustid_api_key.as_deref()?;
+        let fp = acoustid::fingerprint_file(path)
+            .await
+            .map_err(|e| tracing::warn!(path = %path.display(), error = ?e, "Fingerprinting failed"))
+            .ok()?;
+
+        let found = self
+            .cached("acoustid_lookup", &fp.fingerprint, false, || async {
+                self.acoustid_limiter.acquire().await;
+                let duration = fp.duration.to_string();
+                let json: Value = self
+                    .client
+                    .post(acoustid::ACOUSTID_LOOKUP_URL)
+                    .form(&[
+                        ("client", api_key),
+                        ("meta", "recordings releasegroups"),
+                        ("duration", duration.as_str()),
+                        ("fingerprint", fp.fingerprint.as_str()),
+                    ])
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Metadata(format!("AcoustID request failed: {}", e)))?
+                    .json()
+                    .await
+                    .map_err(|e| AppError::Metadata(format!("AcoustID JSON parse failed: {}", e)))?;
+                if json["status"].as_str() != Some("ok") {
+                    // 密钥无效等错误不写入缓存
+                    return Err(AppError::Metadata(format!("AcoustID error: {}", json["error"]["message"])));
+                }
+                Ok(acoustid::parse_lookup_response(&json)
+                    .and_then(|m| serde_json::to_value(m).ok())
+                    .unwrap_or(Value::Null))
+            })
+            .await
+            .map_err(|e| tracing::warn!(path = %path.display(), error = ?e, "AcoustID lookup failed"))
+            .ok()?;
+
+        serde_json::from_value(found).ok()
+    }
+
+    /// 读取未过期的缓存条目，未命中或读取失败都返回 None
+    async fn cache_get(&self, entity_type: &str, key: &str) -> Option<Value> {
+        let row = sqlx::query(
+            "SELECT payload FROM metadata_cache \
+             WHERE entity_type = $1 AND query_hash = $2 \
+             AND fetched_at > NOW() - make_interval(days => $3)",
+        )
+        .bind(entity_type)
+        .bind(cache_key(key))
+        .bind(self.cache_ttl_days)
+        .fetch_optional(&self.db)
+        .await
+        .map_err(|e| tracing::warn!("Metadata cache read failed: {}", e))
+        .ok()??;
+
+        serde_json::from_str(&row.get::<String, _>("payload")).ok()
+    }
+
+    async fn cache_put(&self, entity_type: &str, key: &str, value: &Value) {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO metadata_cache (entity_type, query_hash, payload, fetched_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (entity_type, query_hash)
+            DO UPDATE SET payload = EXCLUDED.payload, fetched_at = NOW()
+            "#,
+        )
+        .bind(entity_type)
+        .bind(cache_key(key))
+        .bind(value.to_string())
+        .execute(&self.db)
+        .await;
+
+        if let Err(e) = result {
+            tracing::warn!("Metadata cache write failed: {}", e);
         }
     }
 
+    /// 先查缓存，未命中 (或 bypass) 时执行 `fetch` 并写回。
+    /// `Value::Null` 表示上游确认无结果，同样缓存以免反复请求。
+    async fn cached<F, Fut>(
+        &self,
+        entity_type: &str,
+        key: &str,
+        bypass: bool,
+        fetch: F,
+    ) -> Result<Value, AppError>
+    where
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = Result<Value, AppError>>,
+    {
+        if !bypass {
+            if let Some(value) = self.cache_get(entity_type, key).await {
+                tracing::debug!(entity_type = %entity_type, key = %key, "Metadata cache hit");
+                return Ok(value);
+            }
+        }
+
+        let value = fetch().await?;
+        self.cache_put(entity_type, key, &value).await;
+        Ok(value)
+    }
+
     async fn mb_retry<F, Fut, T>(&self, action: F) -> Result<T, AppError>
     where
         F: Fn() -> Fut,
@@ -65,33 +285,49 @@ impl MetadataService {
     {
         let retry_strategy = ExponentialBackoff::from_millis(1000).map(jitter).take(3);
 
-        Retry::spawn(retry_strategy, action)
-            .await
-            .map_err(|e| AppError::Metadata(format!("MusicBrainz API error: {:?}", e)))
+        // 每次尝试 (包括重试) 都要先取得令牌
+        Retry::spawn(retry_strategy, || async {
+            self.mb_limiter.acquire().await;
+            action().await
+        })
+        .await
+        .map_err(|e| AppError::Metadata(format!("MusicBrainz API error: {:?}", e)))
     }
 
-    pub async fn fetch_and_update_artist(&self, artist_id: Uuid) -> Result<(), AppError> {
+    /// 同步歌手元数据。`bypass_cache` 为 true 时忽略已缓存的上游响应并强制刷新。
+    pub async fn fetch_and_update_artist(
+        &self,
+        artist_id: Uuid,
+        bypass_cache: bool,
+    ) -> Result<(), AppError> {
         let artist = sqlx::query!("SELECT name FROM artists WHERE id = $1", artist_id)
             .fetch_one(&self.db)
             .await?;
 
         tracing::info!(artist = %artist.name, "Syncing artist metadata...");
 
-        let query = ArtistSearchQuery::query_builder()
-            .artist(&artist.name)
-            .build();
-
-        let results = self
-            .mb_retry(|| async {
-                MBArtist::search(query.clone())
-                    .execute_with_client(&self.mb_client)
-                    .await
+        let found = self
+            .cached("mb_artist_search", &artist.name, bypass_cache, || async {
+                let query = ArtistSearchQuery::query_builder()
+                    .artist(&artist.name)
+                    .build();
+                let results = self
+                    .mb_retry(|| async {
+                        MBArtist::search(query.clone())
+                            .execute_with_client(&self.mb_client)
+                            .await
+                    })
+                    .await?;
+                Ok(results
+                    .entities
+                    .first()
+                    .map(|a| json!({ "id": a.id }))
+                    .unwrap_or(Value::Null))
             })
             .await?;
 
-        if let Some(mb_artist) = results.entities.first() {
-            let mb_id_str = mb_artist.id.clone();
-            let mb_id = Uuid::parse_str(&mb_id_str).ok();
+        if let Some(mb_id_str) = found["id"].as_str() {
+            let mb_id = Uuid::parse_str(mb_id_str).ok();
 
             sqlx::query!(
                 "UPDATE artists SET musicbrainz_artist_id = $1 WHERE id = $2",
@@ -102,7 +338,24 @@ impl MetadataService {
             .await?;
 
             if let Some(id) = mb_id {
-                if let Err(e) = self.fetch_artist_image(id, artist_id).await {
+                // 简介/流派拉取失败不影响 MBID 与图片
+                let details = match self.fetch_artist_details(id, bypass_cache).await {
+                    Ok(details) => {
+                        if let Err(e) = self.update_artist_profile(artist_id, &details).await {
+                            tracing::warn!(artist = %artist.name, error = ?e, "Failed to store artist profile");
+                        }
+                        Some(details)
+                    }
+                    Err(e) => {
+                        tracing::warn!(artist = %artist.name, error = ?e, "Failed to fetch artist details");
+                        None
+                    }
+                };
+
+                if let Err(e) = self
+                    .fetch_artist_image(artist_id, details.as_ref(), bypass_cache)
+                    .await
+                {
                     tracing::error!(artist = %artist.name, error = ?e, "Failed to fetch artist image");
                 }
             }
@@ -113,7 +366,84 @@ impl MetadataService {
         Ok(())
     }
 
-    async fn fetch_artist_image(&self, mb_id: Uuid, artist_id: Uuid) -> Result<(), AppError> {
+    /// 拉取 MusicBrainz 歌手详情 (URL 关系、流派、标签、消歧义说明)，整理成精简 JSON 缓存
+    async fn fetch_artist_details(&self, mb_id: Uuid, bypass_cache: bool) -> Result<Value, AppError> {
+        let mb_key = mb_id.to_string();
+        self.cached("mb_artist_details", &mb_key, bypass_cache, || async {
+            let artist_full = self
+                .mb_retry(|| async {
+                    MBArtist::fetch()
+                        .id(&mb_key)
+                        .with_url_relations()
+                        .with_genres()
+                        .with_tags()
+                        .execute_with_client(&self.mb_client)
+                        .await
+                })
+                .await?;
+
+            let mut image = None;
+            let mut wikidata = None;
+            for rel in artist_full.relations.unwrap_or_default() {
+                if let RelationContent::Url(url) = rel.content {
+                    if rel.relation_type == "image" && image.is_none() {
+                        image = Some(url.resource);
+                    } else if rel.relation_type == "wikidata" && wikidata.is_none() {
+                        wikidata = url.resource.split('/').next_back().map(|s| s.to_string());
+                    }
+                }
+            }
+
+            // 优先使用官方流派，没有时退回投票数最高的用户标签
+            let mut genres = artist_full.genres.unwrap_or_default();
+            genres.sort_by_key(|g| std::cmp::Reverse(g.count));
+            let mut genre_names: Vec<String> = genres.into_iter().map(|g| g.name).collect();
+            if genre_names.is_empty() {
+                let mut tags = artist_full.tags.unwrap_or_default();
+                tags.sort_by_key(|t| std::cmp::Reverse(t.count));
+                genre_names = tags.into_iter().map(|t| t.name).collect();
+            }
+            genre_names.truncate(MAX_ARTIST_GENRES);
+
+            let bio = Some(artist_full.disambiguation.trim().to_string()).filter(|d| !d.is_empty());
+
+            Ok(json!({
+                "image": image,
+                "wikidata": wikidata,
+                "bio": bio,
+                "genres": genre_names,
+            }))
+        })
+        .await
+    }
+
+    /// 写入简介与流派；已有简介 (可能由管理员手工维护) 时不覆盖
+    async fn update_artist_profile(&self, artist_id: Uuid, details: &Value) -> Result<(), AppError> {
+        let bio = details["bio"].as_str();
+        let genres: Vec<String> = details["genres"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|g| g.as_str().map(|s| s.to_string()))
+            .collect();
+
+        sqlx::query(
+            "UPDATE artists SET bio = COALESCE(bio, $1), genres = $2 WHERE id = $3",
+        )
+        .bind(bio)
+        .bind(&genres)
+        .bind(artist_id)
+        .execute(&self.db)
+        .await?;
+        Ok(())
+    }
+
+    async fn fetch_artist_image(
+        &self,
+        artist_id: Uuid,
+        details: Option<&Value>,
+        bypass_cache: bool,
+    ) -> Result<(), AppError> {
         let artist_name = sqlx::query_scalar!("SELECT name FROM artists WHERE id = $1", artist_id)
             .fetch_one(&self.db)
             .await
@@ -121,47 +451,35 @@ impl MetadataService {
 
         tracing::info!(artist = %artist_name, "Fetching artist image...");
         
-        let mut image_url = None;
+        // 策略 1: 优先尝试 Last.fm (覆盖率较高)。配置了 API Key 时走官方接口，
+        // 否则沿用页面抓取
+        let mut image_url = if self.lastfm_api_key.is_some() {
+            self.fetch_image_from_lastfm_api(&artist_name, bypass_cache).await.ok()
+        } else {
+            self.fetch_image_from_lastfm(&artist_name).await.ok()
+        };
 
-        // 策略 1: 优先尝试 Last.fm (覆盖率较高)
-        image_url = self.fetch_image_from_lastfm(&artist_name).await.ok();
-        
         if image_url.is_some() {
             tracing::info!(artist = %artist_name, "Found image on Last.fm");
         }
 
         // 策略 2: 回退至 MusicBrainz/Wikidata
         if image_url.is_none() {
-            let artist_full = self
-                .mb_retry(|| async {
-                    MBArtist::fetch()
-                        .id(&mb_id.to_string())
-                        .with_url_relations()
-                        .execute_with_client(&self.mb_client)
-                        .await
-                })
-                .await.ok();
-
-            if let Some(artist_full) = artist_full {
-                if let Some(rels) = artist_full.relations {
-                    for rel in rels {
-                        if let RelationContent::Url(url) = rel.content {
-                            if rel.relation_type == "image" {
-                                image_url = Some(url.resource);
-                                break;
-                            }
-                            if rel.relation_type == "wikidata" {
-                                let qid = url.resource.split('/').next_back().map(|s| s.to_string());
-                                if let Some(q) = qid {
-                                    image_url = self.fetch_image_from_wikidata(&q).await.ok();
-                                }
-                            }
-                        }
+            if let Some(links) = details {
+                image_url = links["image"].as_str().map(|s| s.to_string());
+                if image_url.is_none() {
+                    if let Some(qid) = links["wikidata"].as_str() {
+                        image_url = self.fetch_image_from_wikidata(qid, bypass_cache).await.ok();
                     }
                 }
             }
         }
 
+        // 策略 3: 官方接口没有结果时，页面抓取作为最后手段
+        if image_url.is_none() && self.lastfm_api_key.is_some() {
+            image_url = self.fetch_image_from_lastfm(&artist_name).await.ok();
+        }
+
         if let Some(url) = image_url {
             if let Err(e) = self.download_and_save_artist_image(&url, artist_id).await {
                 tracing::warn!(artist = %artist_name, error = ?e, "Download failed, storing remote URL as fallback");
@@ -176,25 +494,30 @@ impl MetadataService {
         Ok(())
     }
 
-    async fn fetch_image_from_wikidata(&self, qid: &str) -> Result<String, AppError> {
-        let url = format!(
-            "https://www.wikidata.org/wiki/Special:EntityData/{}.json",
-            qid
-        );
-        let resp = self
-            .client
-            .get(url)
-            .send()
-            .await
-            .map_err(|e| AppError::Metadata(format!("Wikidata request failed: {}", e)))?;
+    async fn fetch_image_from_wikidata(&self, qid: &str, bypass_cache: bool) -> Result<String, AppError> {
+        // 完整实体 JSON 动辄数百 KB，缓存里只保留 P18 (图片) 声明
+        let p18 = self
+            .cached("wikidata_entity", qid, bypass_cache, || async {
+                let url = format!(
+                    "https://www.wikidata.org/wiki/Special:EntityData/{}.json",
+                    qid
+                );
+                let resp = self
+                    .client
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Metadata(format!("Wikidata request failed: {}", e)))?;
 
-        let json: Value = resp
-            .json()
-            .await
-            .map_err(|e| AppError::Metadata(format!("Wikidata JSON parse failed: {}", e)))?;
+                let json: Value = resp
+                    .json()
+                    .await
+                    .map_err(|e| AppError::Metadata(format!("Wikidata JSON parse failed: {}", e)))?;
+                Ok(json["entities"][qid]["claims"]["P18"].clone())
+            })
+            .await?;
 
-        let image_name = json["entities"][qid]["claims"]["P18"][0]["mainsnak"]["datavalue"]
-            ["value"]
+        let image_name = p18[0]["mainsnak"]["datavalue"]["value"]
             .as_str()
             .ok_or_else(|| AppError::Metadata("No image (P18) found in Wikidata".to_string()))?;
 
@@ -212,6 +535,63 @@ impl MetadataService {
         Ok(final_url)
     }
 
+    /// Last.fm 官方 `artist.getInfo` 接口，返回最大尺寸的图片地址
+    async fn fetch_image_from_lastfm_api(
+        &self,
+        artist_name: &str,
+        bypass_cache: bool,
+    ) -> Result<String, AppError> {
+        let api_key = self
+            .lastfm_api_key
+            .as_deref()
+            .ok_or_else(|| AppError::Metadata("LASTFM_API_KEY is not configured".to_string()))?;
+
+        let images = self
+            .cached("lastfm_artist_info", artist_name, bypass_cache, || async {
+                let resp = self
+                    .client
+                    .get(LASTFM_API_URL)
+                    .query(&[
+                        ("method", "artist.getinfo"),
+                        ("artist", artist_name),
+                        ("api_key", api_key),
+                        ("autocorrect", "1"),
+                        ("format", "json"),
+                    ])
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Metadata(format!("Last.fm API request failed: {}", e)))?;
+
+                let json: Value = resp
+                    .json()
+                    .await
+                    .map_err(|e| AppError::Metadata(format!("Last.fm API JSON parse failed: {}", e)))?;
+
+                // error 6 = 歌手不存在，缓存为空结果；其它错误 (Key 无效、限流) 不缓存
+                match json["error"].as_i64() {
+                    Some(6) => Ok(Value::Null),
+                    Some(code) => Err(AppError::Metadata(format!(
+                        "Last.fm API error {}: {}",
+                        code,
+                        json["message"].as_str().unwrap_or_default()
+                    ))),
+                    None => Ok(json["artist"]["image"].clone()),
+                }
+            })
+            .await?;
+
+        // 图片按尺寸从小到大排列，取最后一个非空且不是占位星星图的地址
+        images
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|img| img["#text"].as_str())
+            .filter(|url| !url.is_empty() && !url.contains(LASTFM_PLACEHOLDER_IMAGE))
+            .next_back()
+            .map(|url| url.to_string())
+            .ok_or_else(|| AppError::Metadata("No image returned by Last.fm API".to_string()))
+    }
+
     async fn fetch_image_from_lastfm(&self, artist_name: &str) -> Result<String, AppError> {
         let url = format!(
             "https://www.last.fm/music/{}/+images",
@@ -327,9 +707,8 @@ impl MetadataService {
 
             let filename = format!("artist_{}.{}", artist_id, extension);
 
-            let base_dir =
-                std::env::var("AVATAR_DIR").unwrap_or_else(|_| "/app/data/avatars".to_string());
-            let full_path = Path::new(&base_dir).join(&filename);
+            let base_dir = crate::storage::avatar_dir();
+            let full_path = crate::storage::resolve_within(&base_dir, &filename)?;
 
             tokio::fs::create_dir_all(&base_dir).await?;
             tokio::fs::write(full_path, img_data).await?;
@@ -371,61 +750,301 @@ impl MetadataService {
 
         tracing::info!(album = %album.title, artist = %album.artist_name, "Fetching album metadata from MusicBrainz");
 
-        let query = ReleaseSearchQuery::query_builder()
-            .release(&album.title)
-            .artist(&album.artist_name)
-            .build();
-
-        let results = self
-            .mb_retry(|| async {
-                MBRelease::search(query.clone())
-                    .execute_with_client(&self.mb_client)
-                    .await
+        let release_key = format!("{}\u{1f}{}", album.title, album.artist_name);
+        let found = self
+            .cached("mb_release_search", &release_key, false, || async {
+                let query = ReleaseSearchQuery::query_builder()
+                    .release(&album.title)
+                    .artist(&album.artist_name)
+                    .build();
+                let results = self
+                    .mb_retry(|| async {
+                        MBRelease::search(query.clone())
+                            .execute_with_client(&self.mb_client)
+                            .await
+                    })
+                    .await?;
+                Ok(results
+                    .entities
+                    .first()
+                    .map(|r| json!({ "id": r.id, "date": r.date.as_ref().map(|d| d.0.clone()) }))
+                    .unwrap_or(Value::Null))
             })
             .await?;
 
-        if let Some(mb_release) = results.entities.first() {
-            let mb_id = Uuid::parse_str(&mb_release.id).ok();
-            let year = mb_release
-                .date
-                .as_ref()
-                .and_then(|d| d.0.split('-').next()?.parse::<i32>().ok());
+        if let Some(release_id) = found["id"].as_str() {
+            let mb_id = Uuid::parse_str(release_id).ok();
+            let date = found["date"].as_str();
+            let year = date.and_then(|d| d.split('-').next()?.parse::<i32>().ok());
+            // MusicBrainz 的日期可能只有年份或年月，此时只更新年份
+            let release_date = date.and_then(crate::scanner::parse_release_date);
 
             sqlx::query!(
-                "UPDATE albums SET musicbrainz_album_id = $1, release_year = COALESCE(release_year, $2) WHERE id = $3",
-                mb_id, year, album_id
+                "UPDATE albums SET musicbrainz_album_id = $1,
+                    release_year = COALESCE(release_year, $2),
+                    release_date = COALESCE(release_date, $3)
+                 WHERE id = $4",
+                mb_id, year, release_date, album_id
             ).execute(&self.db).await?;
 
-            tracing::info!(album = %album.title, year = ?year, "Matched MusicBrainz album info");
+            tracing::info!(album = %album.title, year = ?year, date = ?release_date, "Matched MusicBrainz album info");
 
             if album.cover_path.is_none() {
                 if let Some(id) = mb_id {
-                    let _ = self.fetch_cover_from_caa(id, album_id).await;
+                    match self.fetch_cover_from_caa(id, album_id).await {
+                        Ok(true) => return Ok(()),
+                        Ok(false) => {}
+                        Err(e) => tracing::debug!(album = %album.title, error = ?e, "CAA cover lookup failed"),
+                    }
                 }
             }
         }
 
+        // CAA 未收录 (常见于亚洲与独立发行) 时按 "歌手 专辑" 搜索备用来源
+        if album.cover_path.is_none() {
+            self.fetch_cover_fallback(album_id, &album.artist_name, &album.title)
+                .await;
+        }
+
         Ok(())
     }
 
-    async fn fetch_cover_from_caa(&self, mb_id: Uuid, album_id: Uuid) -> Result<(), AppError> {
-        let url = format!("https://coverartarchive.org/release/{}", mb_id);
+    /// 按 标题 + 歌手 + 时长 匹配 MusicBrainz 录音，写入录音 MBID 与 ISRC。
+    /// `fix_title` 为 true 时用 MusicBrainz 的规范标题覆盖本地标题 (仅大小写、标点等差异)，
+    /// 并写回音频文件的标签，否则下次扫描会按旧标签改回去。
+    /// 返回是否匹配成功
+    pub async fn fetch_and_update_track(
+        &self,
+        track_id: Uuid,
+        fix_title: bool,
+        bypass_cache: bool,
+    ) -> Result<bool, AppError> {
+        let track = sqlx::query(
+            "SELECT t.title, t.duration, t.path, t.end_ms, a.name AS artist_name
+             FROM tracks t LEFT JOIN artists a ON t.artist_id = a.id
+             WHERE t.id = $1",
+        )
+        .bind(track_id)
+        .fetch_optional(&self.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Track not found".to_string()))?;
+        let title: String = track.get("title");
+        let duration: i32 = track.get("duration");
+        let artist: String = track
+            .get::<Option<String>, _>("artist_name")
+            .unwrap_or_default();
+
+        tracing::info!(track = %title, artist = %artist, "Fetching recording metadata from MusicBrainz");
+
+        let key = format!("{}\u{1f}{}", title, artist);
+        let candidates = self
+            .cached("mb_recording_search", &key, bypass_cache, || async {
+                let query = RecordingSearchQuery::query_builder()
+                    .recording(&title)
+                    .artist(&artist)
+                    .build();
+                let results = self
+                    .mb_retry(|| async {
+                        MBRecording::search(query.clone())
+                            .execute_with_client(&self.mb_client)
+                            .await
+                    })
+                    .await?;
+                Ok(Value::Array(
+                    results
+                        .entities
+                        .iter()
+                        .map(|r| {
+                            json!({
+                                "id": r.id,
+                                "title": r.title,
+                                "length": r.length,
+                                "isrcs": r.isrcs.clone().unwrap_or_default(),
+                            })
+                        })
+                        .collect(),
+                ))
+            })
+            .await?;
+
+        let Some(found) = pick_recording(&title, duration, &candidates) else {
+            tracing::warn!(track = %title, artist = %artist, "No matching MusicBrainz recording");
+            return Ok(false);
+        };
+        let mb_id = found["id"].as_str().and_then(|id| Uuid::parse_str(id).ok());
+        let isrc = found["isrcs"][0].as_str();
+        let canonical_title = found["title"]
+            .as_str()
+            .filter(|canonical| fix_title && *canonical != title);
+
+        // MBID 解析失败时保留原值；已被其他曲目 (同一录音的另一个文件) 占用时
+        // 同样保留原值，只更新 ISRC/标题，避免唯一约束把整个同步变成 500
+        let stored = sqlx::query_scalar::<_, Option<Uuid>>(
+            "UPDATE tracks SET musicbrainz_track_id = CASE
+                    WHEN EXISTS (SELECT 1 FROM tracks o WHERE o.musicbrainz_track_id = $1 AND o.id <> $4)
+                        THEN musicbrainz_track_id
+                    ELSE COALESCE($1, musicbrainz_track_id)
+                END,
+                isrc = COALESCE($2, isrc),
+                title = COALESCE($3, title),
+                updated_at = NOW()
+             WHERE id = $4
+             RETURNING musicbrainz_track_id",
+        )
+        .bind(mb_id)
+        .bind(isrc)
+        .bind(canonical_title)
+        .bind(track_id)
+        .fetch_optional(&self.db)
+        .await?
+        .flatten();
+
+        if mb_id.is_some() && stored != mb_id {
+            tracing::warn!(track = %title, mbid = ?mb_id, "MusicBrainz recording already linked to another track, MBID not updated");
+        }
+
+        // CUE 分轨共用一个音频文件，无法逐轨写标签
+        if let Some(canonical) = canonical_title.filter(|_| track.get::<Option<i32>, _>("end_ms").is_none()) {
+            let path = std::path::PathBuf::from(track.get::<String, _>("path"));
+            let edits = crate::scanner::TagEdits {
+                title: Some(canonical.to_string()),
+                ..Default::default()
+            };
+            let written = tokio::task::spawn_blocking(move || crate::scanner::write_tags(&path, &edits))
+                .await
+                .map_err(|e| AppError::Internal(e.to_string()))?;
+            if let Err(e) = written {
+                tracing::warn!(track = %title, error = %e, "Failed to write corrected title to tags, a rescan will restore the old title");
+            }
+        }
+
+        tracing::info!(track = %title, mbid = ?mb_id, isrc = ?isrc, "Matched MusicBrainz recording");
+        Ok(true)
+    }
+
+    async fn fetch_cover_fallback(&self, album_id: Uuid, artist: &str, title: &str) {
+        let key = format!("{}\u{1f}{}", title, artist);
+        for provider in &self.cover_fallback_providers {
+            let found = match provider.as_str() {
+                "itunes" => {
+                    self.cached("itunes_album_cover", &key, false, || async {
+                        self.search_itunes_cover(artist, title).await
+                    })
+                    .await
+                }
+                "deezer" => {
+                    self.cached("deezer_album_cover", &key, false, || async {
+                        self.search_deezer_cover(artist, title).await
+                    })
+                    .await
+                }
+                _ => continue,
+            };
 
-        let resp = self
+            let url = match found {
+                Ok(Value::String(url)) => url,
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!(provider = %provider, album = %title, error = ?e, "Cover fallback lookup failed");
+                    continue;
+                }
+            };
+
+            match self.save_album_cover(&url, album_id).await {
+                Ok(true) => {
+                    tracing::info!(provider = %provider, album = %title, "Saved cover from fallback provider");
+                    return;
+                }
+                Ok(false) => {}
+                Err(e) => {
+                    tracing::warn!(provider = %provider, url = %url, error = ?e, "Cover fallback download failed")
+                }
+            }
+        }
+    }
+
+    /// iTunes Search API：返回匹配专辑的高清封面地址，找不到时为 `Value::Null`
+    async fn search_itunes_cover(&self, artist: &str, title: &str) -> Result<Value, AppError> {
+        let term = format!("{} {}", artist, title);
+        let json: Value = self
             .client
-            .get(url)
+            .get(ITUNES_SEARCH_URL)
+            .query(&[("term", term.as_str()), ("entity", "album"), ("limit", "10")])
             .send()
             .await
-            .map_err(|e| AppError::Metadata(e.to_string()))?;
-
-        if !resp.status().is_success() {
-            return Err(AppError::Metadata("No cover found in CAA".to_string()));
-        }
+            .map_err(|e| AppError::Metadata(format!("iTunes request failed: {}", e)))?
+            .json()
+            .await
+            .map_err(|e| AppError::Metadata(format!("iTunes JSON parse failed: {}", e)))?;
+
+        let candidates = json["results"].as_array().into_iter().flatten().filter_map(|r| {
+            Some((
+                r["artistName"].as_str()?,
+                r["collectionName"].as_str()?,
+                r["artworkUrl100"].as_str()?,
+            ))
+        });
+        // artworkUrl100 只是缩略图，改写尺寸段即可拿到大图
+        Ok(pick_cover_candidate(artist, title, candidates)
+            .map(|url| Value::String(url.replace("100x100bb", "1000x1000bb")))
+            .unwrap_or(Value::Null))
+    }
 
-        let json: Value = resp
+    /// Deezer 公开搜索接口，取 cover_xl (1000px)
+    async fn search_deezer_cover(&self, artist: &str, title: &str) -> Result<Value, AppError> {
+        let q = format!("artist:\"{}\" album:\"{}\"", artist, title);
+        let json: Value = self
+            .client
+            .get(DEEZER_ALBUM_SEARCH_URL)
+            .query(&[("q", q.as_str()), ("limit", "10")])
+            .send()
+            .await
+            .map_err(|e| AppError::Metadata(format!("Deezer request failed: {}", e)))?
             .json()
             .await
-            .map_err(|e| AppError::Metadata(e.to_string()))?;
+            .map_err(|e| AppError::Metadata(format!("Deezer JSON parse failed: {}", e)))?;
+
+        let candidates = json["data"].as_array().into_iter().flatten().filter_map(|r| {
+            Some((
+                r["artist"]["name"].as_str()?,
+                r["title"].as_str()?,
+                r["cover_xl"].as_str().or_else(|| r["cover_big"].as_str())?,
+            ))
+        });
+        Ok(pick_cover_candidate(artist, title, candidates)
+            .map(|url| Value::String(url.to_string()))
+            .unwrap_or(Value::Null))
+    }
+
+    /// 返回是否成功保存了封面
+    async fn fetch_cover_from_caa(&self, mb_id: Uuid, album_id: Uuid) -> Result<bool, AppError> {
+        let json = self
+            .cached("caa_release", &mb_id.to_string(), false, || async {
+                let url = format!("https://coverartarchive.org/release/{}", mb_id);
+                let resp = self
+                    .client
+                    .get(url)
+                    .send()
+                    .await
+                    .map_err(|e| AppError::Metadata(e.to_string()))?;
+
+                // 404 表示该发行确实没有封面，缓存为空结果
+                if resp.status() == reqwest::StatusCode::NOT_FOUND {
+                    return Ok(Value::Null);
+                }
+                if !resp.status().is_success() {
+                    return Err(AppError::Metadata(format!("CAA returned {}", resp.status())));
+                }
+
+                resp.json::<Value>()
+                    .await
+                    .map_err(|e| AppError::Metadata(e.to_string()))
+            })
+            .await?;
+
+        if json.is_null() {
+            return Ok(false);
+        }
 
         let cover_url = json["images"]
             .as_array()
@@ -434,80 +1053,289 @@ impl MetadataService {
             })
             .and_then(|i| i["image"].as_str());
 
-        if let Some(img_url) = cover_url {
-            let resp = self
-                .client
-                .get(img_url)
-                .timeout(Duration::from_secs(15))
-                .send()
-                .await
-                .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+        match cover_url {
+            Some(img_url) => {
+                let saved = self.save_album_cover(img_url, album_id).await?;
+                if saved {
+                    tracing::info!(album_id = %album_id, "Successfully downloaded CAA cover");
+                }
+                Ok(saved)
+            }
+            None => Ok(false),
+        }
+    }
 
-            if resp.status().is_success() {
-                let extension = match resp
-                    .headers()
-                    .get(reqwest::header::CONTENT_TYPE)
-                    .and_then(|h| h.to_str().ok())
-                {
-                    Some("image/png") => "png",
-                    Some("image/webp") => "webp",
-                    _ => "jpg",
-                };
+    /// 下载封面写入 COVER_DIR 并更新 `albums.cover_path`；上游返回非 2xx 时返回 `Ok(false)`
+    async fn save_album_cover(&self, img_url: &str, album_id: Uuid) -> Result<bool, AppError> {
+        let resp = self
+            .client
+            .get(img_url)
+            .timeout(Duration::from_secs(15))
+            .send()
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
 
-                let img_data = resp
-                    .bytes()
-                    .await
-                    .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
+        if !resp.status().is_success() {
+            return Ok(false);
+        }
 
-                let filename = format!("{}.{}", album_id, extension);
-                let save_relative = format!("data/covers/{}", filename);
+        let extension = match resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|h| h.to_str().ok())
+        {
+            Some("image/png") => "png",
+            Some("image/webp") => "webp",
+            _ => "jpg",
+        };
+
+        let img_data = resp
+            .bytes()
+            .await
+            .map_err(|e| AppError::Io(std::io::Error::other(e)))?;
 
-                let base_dir = std::env::var("COVER_DIR")
-                    .unwrap_or_else(|_| "/mnt/data1/rust/papilio/data/covers".to_string());
-                let full_path = Path::new(&base_dir).join(filename);
+        let filename = format!("{}.{}", album_id, extension);
+        let save_relative = crate::storage::internal_cover_ref(&filename);
 
-                tokio::fs::create_dir_all(&base_dir).await?;
-                tokio::fs::write(full_path, img_data).await?;
+        let base_dir = crate::storage::cover_dir();
+        let full_path = crate::storage::resolve_within(&base_dir, &filename)?;
 
-                sqlx::query!(
-                    "UPDATE albums SET cover_path = $1 WHERE id = $2",
-                    save_relative,
-                    album_id
-                )
-                .execute(&self.db)
-                .await?;
+        tokio::fs::create_dir_all(&base_dir).await?;
+        tokio::fs::write(full_path, img_data).await?;
 
-                tracing::info!(album_id = %album_id, "Successfully downloaded CAA cover");
-            }
-        }
+        sqlx::query!(
+            "UPDATE albums SET cover_path = $1 WHERE id = $2",
+            save_relative,
+            album_id
+        )
+        .execute(&self.db)
+        .await?;
 
-        Ok(())
+        Ok(true)
     }
 
+    /// 从 lrclib.net 拉取歌词：先按曲名/歌手/专辑/时长精确匹配，未命中再走搜索。
+    /// 拿到逐行时间轴歌词时直接标记为 completed，仅有纯文本时进入 pending 等待对齐。
     pub async fn fetch_lyrics_online(
         &self,
         track_id: Uuid,
         title: &str,
         artist: &str,
+        album: Option<&str>,
+        duration: i32,
     ) -> Result<(), AppError> {
-        tracing::info!(title = %title, artist = %artist, "Searching cloud lyrics");
-
-        // Simulating a cloud fetch delay
-        tokio::time::sleep(std::time::Duration::from_secs(1)).await;
-
-        let simulated_lrc = format!(
-            "[00:00.00] {}\n[00:02.00] {}\n[00:04.00] (自动同步云端歌词)",
-            title, artist
-        );
+        tracing::info!(title = %title, artist = %artist, "Searching lrclib lyrics");
+
+        let record = match self.lrclib_get(title, artist, album, duration).await? {
+            Some(record) => Some(record),
+            None => self.lrclib_search(title, artist, duration).await?,
+        };
+
+        let (lyrics, sync_status) = record
+            .as_ref()
+            .and_then(|r| {
+                let synced = r["syncedLyrics"].as_str().filter(|s| !s.trim().is_empty());
+                let plain = r["plainLyrics"].as_str().filter(|s| !s.trim().is_empty());
+                synced
+                    .map(|s| (s.to_string(), "completed"))
+                    .or_else(|| plain.map(|s| (s.to_string(), "pending")))
+            })
+            .ok_or_else(|| {
+                AppError::NotFound(format!("No lyrics found on lrclib for '{} - {}'", artist, title))
+            })?;
 
-        sqlx::query!(
-            "UPDATE tracks SET lyrics = $1 WHERE id = $2",
-            simulated_lrc,
-            track_id
+        sqlx::query(
+            "UPDATE tracks SET lyrics = $1, lyrics_source = 'lrclib'::lyrics_source_type, \
+             sync_status = $2::lyric_sync_status, updated_at = NOW() WHERE id = $3",
         )
+        .bind(&lyrics)
+        .bind(sync_status)
+        .bind(track_id)
         .execute(&self.db)
         .await?;
 
+        tracing::info!(title = %title, sync_status = %sync_status, "Stored lrclib lyrics");
         Ok(())
     }
+
+    /// lrclib `/api/get`：精确匹配，404 视为未命中
+    async fn lrclib_get(
+        &self,
+        title: &str,
+        artist: &str,
+        album: Option<&str>,
+        duration: i32,
+    ) -> Result<Option<Value>, AppError> {
+        let mut query = vec![
+            ("track_name", title.to_string()),
+            ("artist_name", artist.to_string()),
+        ];
+        if let Some(album) = album {
+            query.push(("album_name", album.to_string()));
+        }
+        if duration > 0 {
+            query.push(("duration", duration.to_string()));
+        }
+
+        let resp = self
+            .client
+            .get(format!("{}/api/get", LRCLIB_BASE_URL))
+            .query(&query)
+            .send()
+            .await
+            .map_err(|e| AppError::Metadata(format!("lrclib request failed: {}", e)))?;
+
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let resp = resp
+            .error_for_status()
+            .map_err(|e| AppError::Metadata(format!("lrclib request failed: {}", e)))?;
+        let json: Value = resp
+            .json()
+            .await
+            .map_err(|e| AppError::Metadata(format!("lrclib JSON parse failed: {}", e)))?;
+        Ok(Some(json))
+    }
+
+    /// lrclib `/api/search`：取时长最接近且带歌词的结果
+    async fn lrclib_search(
+        &self,
+        title: &str,
+        artist: &str,
+        duration: i32,
+    ) -> Result<Option<Value>, AppError> {
+        let resp = self
+            .client
+            .get(format!("{}/api/search", LRCLIB_BASE_URL))
+            .query(&[("track_name", title), ("artist_name", artist)])
+            .send()
+            .await
+            .map_err(|e| AppError::Metadata(format!("lrclib search failed: {}", e)))?
+            .error_for_status()
+            .map_err(|e| AppError::Metadata(format!("lrclib search failed: {}", e)))?;
+
+        let json: Value = resp
+            .json()
+            .await
+            .map_err(|e| AppError::Metadata(format!("lrclib JSON parse failed: {}", e)))?;
+
+        let best = json
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|r| r["syncedLyrics"].is_string() || r["plainLyrics"].is_string())
+            .min_by_key(|r| {
+                // 优先带时间轴的结果，其次时长差
+                let missing_sync = !r["syncedLyrics"].is_string();
+                let diff = r["duration"]
+                    .as_f64()
+                    .map(|d| (d - duration as f64).abs() as i64)
+                    .unwrap_or(i64::MAX);
+                (missing_sync, diff)
+            })
+            .cloned();
+        Ok(best)
+    }
+}
+
+/// 只保留字母与数字并转小写，用于宽松比较歌手 / 专辑名
+fn normalize_for_match(value: &str) -> String {
+    value
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(|c| c.to_lowercase())
+        .collect()
+}
+
+/// 从搜索结果 `(歌手, 专辑, 封面地址)` 中挑选匹配项：歌手与专辑名规范化后须相互包含，
+/// 专辑名完全相同的优先。宁可没有封面也不要张冠李戴
+fn pick_cover_candidate<'a>(
+    artist: &str,
+    title: &str,
+    candidates: impl Iterator<Item = (&'a str, &'a str, &'a str)>,
+) -> Option<&'a str> {
+    let artist = normalize_for_match(artist);
+    let title = normalize_for_match(title);
+    if artist.is_empty() || title.is_empty() {
+        return None;
+    }
+    let loosely_eq = |a: &str, b: &str| !a.is_empty() && (a.contains(b) || b.contains(a));
+
+    let mut fallback = None;
+    for (cand_artist, cand_title, url) in candidates {
+        let cand_artist = normalize_for_match(cand_artist);
+        let cand_title = normalize_for_match(cand_title);
+        if !loosely_eq(&cand_artist, &artist) || !loosely_eq(&cand_title, &title) {
+            continue;
+        }
+        if cand_title == title {
+            return Some(url);
+        }
+        fallback.get_or_insert(url);
+    }
+    fallback
+}
+
+/// 从录音搜索结果中挑选：标题规范化后须一致，已知时长时相差不超过容差，时长最接近者优先
+fn pick_recording<'a>(title: &str, duration_secs: i32, candidates: &'a Value) -> Option<&'a Value> {
+    let title = normalize_for_match(title);
+    if title.is_empty() {
+        return None;
+    }
+    candidates
+        .as_array()?
+        .iter()
+        .filter(|c| c["title"].as_str().is_some_and(|t| normalize_for_match(t) == title))
+        .filter_map(|c| {
+            // 没有时长的录音排在有时长的之后
+            let diff = match c["length"].as_i64() {
+                Some(ms) => (ms / 1000 - duration_secs as i64).abs(),
+                None => RECORDING_DURATION_TOLERANCE_SECS,
+            };
+            (diff <= RECORDING_DURATION_TOLERANCE_SECS).then_some((diff, c))
+        })
+        .min_by_key(|(diff, _)| *diff)
+        .map(|(_, c)| c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pick_recording() {
+        let candidates = json!([
+            { "id": "live", "title": "Creep", "length": 289000, "isrcs": [] },
+            { "id": "other", "title": "Creep (Acoustic)", "length": 238000, "isrcs": [] },
+            { "id": "album", "title": "Creep", "length": 238640, "isrcs": ["GBAYE9200070"] },
+            { "id": "unknown", "title": "creep", "length": null, "isrcs": [] }
+        ]);
+        assert_eq!(pick_recording("Creep", 238, &candidates).unwrap()["id"], "album");
+        // 时长都对不上时只剩没有时长的候选
+        assert_eq!(pick_recording("CREEP", 120, &candidates).unwrap()["id"], "unknown");
+        assert!(pick_recording("Karma Police", 238, &candidates).is_none());
+    }
+
+    #[test]
+    fn test_pick_cover_candidate() {
+        let results = [
+            ("Other Artist", "Fantasy", "wrong"),
+            ("周杰倫", "范特西 (Deluxe Edition)", "deluxe"),
+            ("周杰倫", "范特西", "exact"),
+        ];
+        assert_eq!(
+            pick_cover_candidate("周杰倫", "范特西", results.iter().copied()),
+            Some("exact")
+        );
+        // 没有完全相同的专辑名时退而求其次
+        assert_eq!(
+            pick_cover_candidate("周杰倫", "范特西", results[..2].iter().copied()),
+            Some("deluxe")
+        );
+        assert_eq!(
+            pick_cover_candidate("Radiohead", "OK Computer", results.iter().copied()),
+            None
+        );
+    }
 }
@@ -0,0 +1,88 @@
+//! 令牌桶限流器。同一个 `MetadataService` 内的所有请求 (单个歌手同步、批量同步、曲目同步) 共用一个桶，
+//! 不再在调用方各自 sleep。
+
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+#[derive(Debug)]
+pub struct RateLimiter {
+    /// 每补充一个令牌所需的时间
+    interval: Duration,
+    burst: u32,
+    state: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl RateLimiter {
+    /// 每 `interval` 补充一个令牌，最多积攒 `burst` 个 (至少为 1)
+    pub fn new(interval: Duration, burst: u32) -> Self {
+        let burst = burst.max(1);
+        Self {
+            interval,
+            burst,
+            state: Mutex::new(Bucket {
+                tokens: burst as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// 取得一个令牌，桶空时等待。等待期间持有锁，排队的调用方按先来后到依次放行
+    pub async fn acquire(&self) {
+        let mut bucket = self.state.lock().await;
+        if let Some(wait) = self.refill(&mut bucket, Instant::now()) {
+            tokio::time::sleep(wait).await;
+            self.refill(&mut bucket, Instant::now());
+        }
+        bucket.tokens = (bucket.tokens - 1.0).max(0.0);
+    }
+
+    /// 按经过的时间补充令牌，令牌不足一个时返回还需等待的时长
+    fn refill(&self, bucket: &mut Bucket, now: Instant) -> Option<Duration> {
+        if self.interval.is_zero() {
+            bucket.tokens = self.burst as f64;
+            return None;
+        }
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() / self.interval.as_secs_f64())
+            .min(self.burst as f64);
+        bucket.refilled_at = now;
+        (bucket.tokens < 1.0).then(|| self.interval.mul_f64(1.0 - bucket.tokens))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_refill_waits_for_next_token() {
+        let limiter = RateLimiter::new(Duration::from_millis(1000), 2);
+        let start = Instant::now();
+        let mut bucket = Bucket { tokens: 0.0, refilled_at: start };
+
+        let wait = limiter.refill(&mut bucket, start + Duration::from_millis(250)).unwrap();
+        assert_eq!(wait.as_millis(), 750);
+
+        assert!(limiter.refill(&mut bucket, start + Duration::from_millis(1000)).is_none());
+        // 闲置再久也只积攒 burst 个令牌
+        limiter.refill(&mut bucket, start + Duration::from_secs(60));
+        assert_eq!(bucket.tokens, 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_spaces_requests() {
+        let limiter = RateLimiter::new(Duration::from_millis(50), 1);
+        let start = Instant::now();
+        for _ in 0..3 {
+            limiter.acquire().await;
+        }
+        // 第一个令牌立即可用，之后每个间隔 50ms
+        assert!(start.elapsed() >= Duration::from_millis(100));
+    }
+}
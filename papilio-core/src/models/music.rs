@@ -0,0 +1,88 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Artist {
+    pub id: Uuid,
+    pub name: String,
+    pub bio: Option<String>,
+    pub image_url: Option<String>,
+    pub musicbrainz_artist_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub genres: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Album {
+    pub id: Uuid,
+    pub title: String,
+    pub artist_id: Uuid,
+    pub release_year: Option<i32>,
+    /// 完整发行日期，来源为标签或 MusicBrainz；只知道年份时为 null
+    pub release_date: Option<NaiveDate>,
+    pub cover_path: Option<String>,
+    pub musicbrainz_album_id: Option<Uuid>,
+    pub musicbrainz_release_group_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// 由曲目流派聚合而来 (取首个出现的流派)
+    pub genre: Option<String>,
+    /// 合辑：artist_id 指向专辑歌手 (如 Various Artists)，曲目歌手各不相同
+    pub is_compilation: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Track {
+    pub id: Uuid,
+    pub title: String,
+    pub album_id: Option<Uuid>,
+    pub artist_id: Option<Uuid>,
+    pub artist_name: Option<String>,
+    pub album_title: Option<String>,
+    pub artist_image_url: Option<String>,
+    pub duration: i32,
+    pub track_number: Option<i32>,
+    pub track_total: Option<i32>,
+    pub disc_number: i32,
+    pub genre: Option<String>,
+    /// ReplayGain 曲目/专辑增益 (dB)，无标签时为 null
+    pub replaygain_track_gain: Option<f32>,
+    pub replaygain_album_gain: Option<f32>,
+    pub path: String,
+    pub bitrate: Option<i32>,
+    pub format: Option<String>,
+    pub size: Option<i64>,
+    pub bpm: Option<i32>,
+    pub musicbrainz_track_id: Option<Uuid>,
+    /// 由曲目级 MusicBrainz 同步写入
+    pub isrc: Option<String>,
+    pub lyrics: Option<String>,
+    pub lyric_offset_ms: i32,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Playlist {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreatePlaylist {
+    pub name: String,
+    pub description: Option<String>,
+    pub is_public: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateLyricOffset {
+    pub offset_ms: i32,
+}
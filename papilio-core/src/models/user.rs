@@ -0,0 +1,132 @@
+use crate::error::AppError;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct User {
+    pub id: Uuid,
+    pub username: String,
+    pub password_hash: String,
+    pub email: Option<String>,
+    pub is_admin: bool,
+    pub nickname: Option<String>,
+    pub avatar: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateUser {
+    pub username: String,
+    pub password: String,
+    pub nickname: Option<String>,
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateUser {
+    pub nickname: Option<String>,
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    pub password: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct UserResponse {
+    pub id: Uuid,
+    pub username: String,
+    pub nickname: Option<String>,
+    pub avatar: Option<String>,
+    pub email: Option<String>,
+    pub is_admin: bool,
+}
+
+impl From<User> for UserResponse {
+    fn from(user: User) -> Self {
+        Self {
+            id: user.id,
+            username: user.username,
+            nickname: user.nickname,
+            avatar: user.avatar,
+            email: user.email,
+            is_admin: user.is_admin,
+        }
+    }
+}
+
+impl User {
+    pub async fn find_by_id(db: &PgPool, id: Uuid) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as!(
+            User,
+            r#"SELECT id, username, password_hash, email, is_admin as "is_admin!", nickname, avatar, created_at, updated_at
+               FROM users WHERE id = $1"#,
+            id
+        )
+        .fetch_optional(db)
+        .await?;
+        Ok(user)
+    }
+
+    pub async fn find_by_username(db: &PgPool, username: &str) -> Result<Option<User>, AppError> {
+        let user = sqlx::query_as!(
+            User,
+            r#"SELECT id, username, password_hash, email, is_admin as "is_admin!", nickname, avatar, created_at, updated_at
+               FROM users WHERE username = $1"#,
+            username
+        )
+        .fetch_optional(db)
+        .await?;
+        Ok(user)
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        payload: CreateUser,
+        password_hash: String,
+    ) -> Result<User, AppError> {
+        let user = sqlx::query_as!(
+            User,
+            r#"INSERT INTO users (username, password_hash, nickname, email)
+               VALUES ($1, $2, $3, $4)
+               RETURNING id, username, password_hash, email, is_admin as "is_admin!", nickname, avatar, created_at, updated_at"#,
+            payload.username,
+            password_hash,
+            payload.nickname,
+            payload.email
+        )
+        .fetch_one(db)
+        .await?;
+        Ok(user)
+    }
+
+    pub async fn update(
+        db: &PgPool,
+        id: Uuid,
+        nickname: Option<String>,
+        avatar: Option<String>,
+        email: Option<String>,
+        password_hash: Option<String>,
+    ) -> Result<User, AppError> {
+        let user = sqlx::query_as!(
+            User,
+            r#"UPDATE users SET
+                   nickname = COALESCE($2, nickname),
+                   avatar = COALESCE($3, avatar),
+                   email = COALESCE($4, email),
+                   password_hash = COALESCE($5, password_hash)
+               WHERE id = $1
+               RETURNING id, username, password_hash, email, is_admin as "is_admin!", nickname, avatar, created_at, updated_at"#,
+            id,
+            nickname,
+            avatar,
+            email,
+            password_hash
+        )
+        .fetch_optional(db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+        Ok(user)
+    }
+}
@@ -0,0 +1,259 @@
+//! 歌单文件的导入导出：扩展 M3U (M3U8) 与 XSPF
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+/// 从外部歌单文件解析出的一条记录，字段均可能缺失
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PlaylistEntry {
+    /// 文件路径或 URL
+    pub location: Option<String>,
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub duration_secs: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParsedPlaylist {
+    pub title: Option<String>,
+    pub entries: Vec<PlaylistEntry>,
+}
+
+/// 导出时的一条曲目
+pub struct ExportTrack<'a> {
+    pub url: String,
+    pub title: &'a str,
+    pub artist: Option<&'a str>,
+    pub album: Option<&'a str>,
+    pub duration_secs: i32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    M3u8,
+    Xspf,
+}
+
+impl PlaylistFormat {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "m3u" | "m3u8" => Some(Self::M3u8),
+            "xspf" => Some(Self::Xspf),
+            _ => None,
+        }
+    }
+
+    pub fn mime(&self) -> &'static str {
+        match self {
+            Self::M3u8 => "audio/x-mpegurl; charset=utf-8",
+            Self::Xspf => "application/xspf+xml; charset=utf-8",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::M3u8 => "m3u8",
+            Self::Xspf => "xspf",
+        }
+    }
+}
+
+/// 按文件名或内容判断格式后解析
+pub fn parse_playlist(content: &str, filename: Option<&str>) -> ParsedPlaylist {
+    let content = content.trim_start_matches('\u{feff}');
+    let by_name = filename
+        .and_then(|f| f.rsplit('.').next())
+        .and_then(PlaylistFormat::from_name);
+    let looks_like_xml = content.trim_start().starts_with('<');
+
+    match by_name {
+        Some(PlaylistFormat::Xspf) => parse_xspf(content),
+        Some(PlaylistFormat::M3u8) => parse_m3u(content),
+        None if looks_like_xml => parse_xspf(content),
+        None => parse_m3u(content),
+    }
+}
+
+/// 解析 M3U / 扩展 M3U。`#EXTINF:秒数,歌手 - 标题` 作用于紧随其后的路径行
+pub fn parse_m3u(content: &str) -> ParsedPlaylist {
+    let mut playlist = ParsedPlaylist::default();
+    let mut pending = PlaylistEntry::default();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let (duration, label) = info.split_once(',').unwrap_or((info, ""));
+            pending.duration_secs = duration
+                .split_whitespace()
+                .next()
+                .and_then(|d| d.parse::<f64>().ok())
+                .filter(|d| *d > 0.0)
+                .map(|d| d.round() as i32);
+            let label = label.trim();
+            match label.split_once(" - ") {
+                Some((artist, title)) => {
+                    pending.artist = Some(artist.trim().to_string());
+                    pending.title = Some(title.trim().to_string());
+                }
+                None if !label.is_empty() => pending.title = Some(label.to_string()),
+                None => {}
+            }
+        } else if let Some(title) = line.strip_prefix("#PLAYLIST:") {
+            playlist.title = Some(title.trim().to_string());
+        } else if line.starts_with('#') {
+            continue;
+        } else {
+            pending.location = Some(line.to_string());
+            playlist.entries.push(std::mem::take(&mut pending));
+        }
+    }
+
+    playlist
+}
+
+static XSPF_TRACK: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<track\b[^>]*>(.*?)</track>").unwrap());
+static XSPF_TRACKLIST: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?s)<trackList\b").unwrap());
+
+/// 解析 XSPF。只读取 location / title / creator / duration，忽略扩展元素
+pub fn parse_xspf(content: &str) -> ParsedPlaylist {
+    // 歌单标题只取 trackList 之前的 <title>，避免误用第一首歌的标题
+    let header = XSPF_TRACKLIST
+        .find(content)
+        .map(|m| &content[..m.start()])
+        .unwrap_or(content);
+
+    let entries = XSPF_TRACK
+        .captures_iter(content)
+        .map(|cap| {
+            let body = cap.get(1).map(|m| m.as_str()).unwrap_or_default();
+            PlaylistEntry {
+                location: xml_element(body, "location"),
+                title: xml_element(body, "title"),
+                artist: xml_element(body, "creator"),
+                duration_secs: xml_element(body, "duration")
+                    .and_then(|d| d.parse::<i64>().ok())
+                    .map(|ms| (ms / 1000) as i32),
+            }
+        })
+        .collect();
+
+    ParsedPlaylist {
+        title: xml_element(header, "title"),
+        entries,
+    }
+}
+
+pub fn write_m3u8(title: &str, tracks: &[ExportTrack]) -> String {
+    let mut out = String::from("#EXTM3U\n");
+    out.push_str(&format!("#PLAYLIST:{}\n", single_line(title)));
+    for track in tracks {
+        let label = match track.artist {
+            Some(artist) => format!("{} - {}", artist, track.title),
+            None => track.title.to_string(),
+        };
+        out.push_str(&format!("#EXTINF:{},{}\n", track.duration_secs, single_line(&label)));
+        out.push_str(&track.url);
+        out.push('\n');
+    }
+    out
+}
+
+pub fn write_xspf(title: &str, tracks: &[ExportTrack]) -> String {
+    let mut out = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    out.push_str("<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n");
+    out.push_str(&format!("  <title>{}</title>\n  <trackList>\n", xml_escape(title)));
+    for track in tracks {
+        out.push_str("    <track>\n");
+        out.push_str(&format!("      <location>{}</location>\n", xml_escape(&track.url)));
+        out.push_str(&format!("      <title>{}</title>\n", xml_escape(track.title)));
+        if let Some(artist) = track.artist {
+            out.push_str(&format!("      <creator>{}</creator>\n", xml_escape(artist)));
+        }
+        if let Some(album) = track.album {
+            out.push_str(&format!("      <album>{}</album>\n", xml_escape(album)));
+        }
+        out.push_str(&format!(
+            "      <duration>{}</duration>\n",
+            track.duration_secs as i64 * 1000
+        ));
+        out.push_str("    </track>\n");
+    }
+    out.push_str("  </trackList>\n</playlist>\n");
+    out
+}
+
+fn single_line(value: &str) -> String {
+    value.replace(['\r', '\n'], " ")
+}
+
+fn xml_element(body: &str, name: &str) -> Option<String> {
+    let open = format!("<{}>", name);
+    let close = format!("</{}>", name);
+    let start = body.find(&open)? + open.len();
+    let end = body[start..].find(&close)? + start;
+    let value = xml_unescape(body[start..end].trim());
+    (!value.is_empty()).then_some(value)
+}
+
+fn xml_escape(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn xml_unescape(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_extended_m3u() {
+        let content = "#EXTM3U\n#PLAYLIST:Road Trip\n#EXTINF:215,Queen - Don't Stop Me Now\n/music/Queen/Jazz/12.flac\n\nrelative/song.mp3\n";
+        let playlist = parse_playlist(content, Some("trip.m3u8"));
+        assert_eq!(playlist.title.as_deref(), Some("Road Trip"));
+        assert_eq!(playlist.entries.len(), 2);
+
+        let first = &playlist.entries[0];
+        assert_eq!(first.artist.as_deref(), Some("Queen"));
+        assert_eq!(first.title.as_deref(), Some("Don't Stop Me Now"));
+        assert_eq!(first.duration_secs, Some(215));
+        assert_eq!(first.location.as_deref(), Some("/music/Queen/Jazz/12.flac"));
+
+        // 没有 EXTINF 的行只有路径
+        assert_eq!(playlist.entries[1].title, None);
+        assert_eq!(playlist.entries[1].location.as_deref(), Some("relative/song.mp3"));
+    }
+
+    #[test]
+    fn test_xspf_round_trip() {
+        let tracks = [ExportTrack {
+            url: "http://host/api/music/stream/1".to_string(),
+            title: "Rock & Roll",
+            artist: Some("Led Zeppelin"),
+            album: Some("IV"),
+            duration_secs: 220,
+        }];
+        let xml = write_xspf("<Mix>", &tracks);
+        let playlist = parse_playlist(&xml, None);
+
+        assert_eq!(playlist.title.as_deref(), Some("<Mix>"));
+        assert_eq!(playlist.entries.len(), 1);
+        assert_eq!(playlist.entries[0].title.as_deref(), Some("Rock & Roll"));
+        assert_eq!(playlist.entries[0].artist.as_deref(), Some("Led Zeppelin"));
+        assert_eq!(playlist.entries[0].duration_secs, Some(220));
+    }
+}
@@ -0,0 +1,164 @@
+//! CUE 整轨分割：解析内嵌 CUESHEET 标签或同名 `.cue` 文件
+
+use std::path::{Path, PathBuf};
+
+/// CUE 中的时间单位为帧，每秒 75 帧
+const FRAMES_PER_SECOND: u64 = 75;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CueSheet {
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    pub tracks: Vec<CueTrack>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CueTrack {
+    pub number: u32,
+    pub title: Option<String>,
+    pub performer: Option<String>,
+    /// INDEX 01 对应的起始位置 (毫秒)
+    pub start_ms: i32,
+}
+
+impl CueSheet {
+    /// 计算每条分轨的结束位置：下一轨的起点，最后一轨为 None (播放到文件末尾)
+    pub fn end_ms(&self, index: usize) -> Option<i32> {
+        self.tracks.get(index + 1).map(|t| t.start_ms)
+    }
+}
+
+/// 解析 CUE 文本。只识别单文件整轨常用的 TITLE / PERFORMER / TRACK / INDEX 01 指令，
+/// 少于两条分轨时返回 None (没有分割的意义)。
+pub fn parse_cue(content: &str) -> Option<CueSheet> {
+    let mut sheet = CueSheet::default();
+    let mut current: Option<CueTrack> = None;
+
+    for line in content.lines() {
+        let line = line.trim().trim_start_matches('\u{feff}');
+        let (command, rest) = match line.split_once(char::is_whitespace) {
+            Some((c, r)) => (c.to_ascii_uppercase(), r.trim()),
+            None => continue,
+        };
+
+        match command.as_str() {
+            "TRACK" => {
+                if let Some(track) = current.take() {
+                    sheet.tracks.push(track);
+                }
+                let number = rest.split_whitespace().next()?.parse().ok()?;
+                current = Some(CueTrack {
+                    number,
+                    title: None,
+                    performer: None,
+                    start_ms: -1,
+                });
+            }
+            "TITLE" => match current.as_mut() {
+                Some(track) => track.title = Some(unquote(rest)),
+                None => sheet.title = Some(unquote(rest)),
+            },
+            "PERFORMER" => match current.as_mut() {
+                Some(track) => track.performer = Some(unquote(rest)),
+                None => sheet.performer = Some(unquote(rest)),
+            },
+            "INDEX" => {
+                let mut parts = rest.split_whitespace();
+                if parts.next() == Some("01") {
+                    if let (Some(track), Some(ms)) = (current.as_mut(), parts.next().and_then(parse_timestamp)) {
+                        track.start_ms = ms;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(track) = current.take() {
+        sheet.tracks.push(track);
+    }
+
+    // 丢弃缺少 INDEX 01 的分轨，并保证按起点有序
+    sheet.tracks.retain(|t| t.start_ms >= 0);
+    sheet.tracks.sort_by_key(|t| t.start_ms);
+
+    (sheet.tracks.len() >= 2).then_some(sheet)
+}
+
+/// 查找与音频文件同名的 `.cue` 文件
+pub fn find_cue_file(audio_path: &Path) -> Option<PathBuf> {
+    let candidate = audio_path.with_extension("cue");
+    if candidate.is_file() {
+        return Some(candidate);
+    }
+    // 常见命名：`album.flac.cue`
+    let file_name = audio_path.file_name()?.to_str()?;
+    let candidate = audio_path.with_file_name(format!("{}.cue", file_name));
+    candidate.is_file().then_some(candidate)
+}
+
+/// 读取外部 `.cue` 文件，非 UTF-8 时按 GBK 解码 (国内整轨资源常见)
+pub async fn read_cue_file(path: &Path) -> Option<String> {
+    let bytes = tokio::fs::read(path).await.ok()?;
+    let (content, _, has_errors) = encoding_rs::UTF_8.decode(&bytes);
+    if !has_errors {
+        return Some(content.into_owned());
+    }
+    let (content, _, _) = encoding_rs::GBK.decode(&bytes);
+    Some(content.into_owned())
+}
+
+fn unquote(value: &str) -> String {
+    value.trim().trim_matches('"').trim().to_string()
+}
+
+/// `mm:ss:ff` -> 毫秒
+fn parse_timestamp(value: &str) -> Option<i32> {
+    let mut parts = value.split(':').map(|p| p.parse::<u64>().ok());
+    let minutes = parts.next()??;
+    let seconds = parts.next()??;
+    let frames = parts.next()??;
+    let ms = (minutes * 60 + seconds) * 1000 + frames * 1000 / FRAMES_PER_SECOND;
+    i32::try_from(ms).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"REM GENRE Classical
+PERFORMER "Berliner Philharmoniker"
+TITLE "Symphony No. 9"
+FILE "album.flac" WAVE
+  TRACK 01 AUDIO
+    TITLE "I. Allegro ma non troppo"
+    INDEX 01 00:00:00
+  TRACK 02 AUDIO
+    TITLE "II. Molto vivace"
+    PERFORMER "Karajan"
+    INDEX 00 15:40:50
+    INDEX 01 15:42:37
+"#;
+
+    #[test]
+    fn test_parse_cue() {
+        let sheet = parse_cue(SAMPLE).expect("sheet should parse");
+        assert_eq!(sheet.title.as_deref(), Some("Symphony No. 9"));
+        assert_eq!(sheet.performer.as_deref(), Some("Berliner Philharmoniker"));
+        assert_eq!(sheet.tracks.len(), 2);
+
+        assert_eq!(sheet.tracks[0].start_ms, 0);
+        assert_eq!(sheet.tracks[1].number, 2);
+        assert_eq!(sheet.tracks[1].performer.as_deref(), Some("Karajan"));
+        // 15:42:37 => 942 秒 + 37/75 秒
+        assert_eq!(sheet.tracks[1].start_ms, 942_493);
+        assert_eq!(sheet.end_ms(0), Some(942_493));
+        assert_eq!(sheet.end_ms(1), None);
+    }
+
+    #[test]
+    fn test_parse_cue_single_track_is_ignored() {
+        let content = "FILE \"a.flac\" WAVE\n  TRACK 01 AUDIO\n    INDEX 01 00:00:00\n";
+        assert!(parse_cue(content).is_none());
+    }
+}
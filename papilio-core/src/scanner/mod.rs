@@ -1,10 +1,13 @@
 use crate::error::AppError;
+use crate::metadata::MetadataService;
 use futures::stream::FuturesUnordered;
 use futures::StreamExt;
 use lofty::{prelude::*, probe::Probe, tag::Accessor};
 use sqlx::{PgPool, Row};
+use std::collections::HashMap;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::fs;
 use tokio::sync::Semaphore;
 use uuid::Uuid;
@@ -17,16 +20,338 @@ use once_cell::sync::Lazy;
 use tokio::sync::Mutex;
 use sanitize_filename;
 
+pub mod cue;
+pub mod naming;
 pub mod organizer;
+pub mod schedule;
+pub mod watcher;
 
 static SCAN_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
 
+/// 歌手/专辑 ID 缓存在进程内共享：vacuum 删除行后清空一次，监听器长期持有的 Scanner 同样失效
+static ARTIST_CACHE: Lazy<DashMap<String, Uuid>> = Lazy::new(DashMap::new);
+static ALBUM_CACHE: Lazy<DashMap<(String, Uuid), Uuid>> = Lazy::new(DashMap::new);
+
+/// 内置支持的音频扩展名，可通过 SCAN_EXTENSIONS (逗号分隔) 覆盖
+const DEFAULT_AUDIO_EXTENSIONS: &[&str] = &[
+    "flac", "mp3", "m4a", "ogg", "wav", "opus", "aac", "aif", "aiff", "wv", "ape",
+];
+
+static AUDIO_EXTENSIONS: Lazy<Vec<String>> = Lazy::new(|| {
+    let configured: Vec<String> = std::env::var("SCAN_EXTENSIONS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().trim_start_matches('.').to_lowercase())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if configured.is_empty() {
+        DEFAULT_AUDIO_EXTENSIONS.iter().map(|s| s.to_string()).collect()
+    } else {
+        tracing::info!("Using custom SCAN_EXTENSIONS: {:?}", configured);
+        configured
+    }
+});
+
+/// 文件指纹 (大小, 修改时间秒)，用于增量扫描判断文件是否变化
+fn file_signature(meta: &std::fs::Metadata) -> (i64, Option<i64>) {
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs() as i64);
+    (meta.len() as i64, mtime)
+}
+
+/// 内嵌封面的落盘位置 (COVER_STORAGE)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CoverStorage {
+    /// 曲库目录 `歌手/专辑/cover.ext` (默认)
+    Library,
+    /// 内部封面目录 `COVER_DIR/{album_id}.ext`，适用于只读挂载的曲库
+    Internal,
+}
+
+static COVER_STORAGE: Lazy<CoverStorage> = Lazy::new(|| {
+    match std::env::var("COVER_STORAGE")
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+        .as_str()
+    {
+        "internal" => CoverStorage::Internal,
+        _ => CoverStorage::Library,
+    }
+});
+
+/// 写入 `COVER_DIR/{album_id}.ext`，返回与在线封面一致的内部封面标识 (见 `storage::internal_cover_ref`)
+async fn write_internal_cover(
+    data: &[u8],
+    album_id: Uuid,
+    extension: &str,
+) -> Result<String, AppError> {
+    let base_dir = crate::storage::cover_dir();
+    let filename = format!("{}.{}", album_id, extension);
+    let full_path = crate::storage::resolve_within(&base_dir, &filename)?;
+
+    tfs::create_dir_all(&base_dir).await?;
+    tfs::write(&full_path, data).await?;
+    tracing::info!(album_id = %album_id, "Saved cover to {}", full_path.display());
+
+    Ok(crate::storage::internal_cover_ref(&filename))
+}
+
+/// `albums.cover_path` 对应的磁盘路径：内部封面在 COVER_DIR 下，其余相对 MUSIC_DIR
+fn cover_file_path(cover_path: &str) -> Option<PathBuf> {
+    if let Some(internal) = crate::storage::internal_cover_file(cover_path) {
+        internal.ok()
+    } else {
+        let music_root = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
+        Some(Path::new(&music_root).join(cover_path))
+    }
+}
+
+/// 带 COMPILATION 标签但没有专辑歌手时使用的统一歌手名
+pub const VARIOUS_ARTISTS: &str = "Various Artists";
+
+/// COMPILATION / TCMP 标签的取值通常为 "1"，也兼容 "true" / "yes"
+fn is_compilation_flag(value: &str) -> bool {
+    matches!(value.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes")
+}
+
+/// 常见的"群星"专辑歌手写法
+fn is_various_artists(name: &str) -> bool {
+    matches!(
+        name.trim().to_lowercase().as_str(),
+        "various artists" | "various" | "va" | "v.a." | "群星" | "羣星"
+    )
+}
+
+/// 新入库曲目的初始对齐状态：只有真正拿到歌词才进入 pending 队列，否则为 none
+fn initial_sync_status(lyrics: Option<&str>) -> &'static str {
+    match lyrics {
+        Some(text) if !text.trim().is_empty() => "pending",
+        _ => "none",
+    }
+}
+
+/// 解析完整的发行日期 (`2001-05-14`，允许带时间部分)，只有年份或年月时返回 None，
+/// 这类情况仍只记录 release_year
+pub fn parse_release_date(value: &str) -> Option<chrono::NaiveDate> {
+    let date = value.trim().get(..10)?;
+    chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+/// 解析 ReplayGain 取值，如 "-6.54 dB"
+fn parse_gain_db(value: &str) -> Option<f32> {
+    value
+        .trim()
+        .trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|v| v.is_finite())
+}
+
+/// R128 (Opus) 增益为 Q7.8 定点数，参考电平 -23 LUFS；换算为 ReplayGain 的 -18 LUFS 基准需 +5 dB
+fn parse_r128_gain(value: &str) -> Option<f32> {
+    let q78 = value.trim().parse::<i32>().ok()?;
+    Some(q78 as f32 / 256.0 + 5.0)
+}
+
+/// 从标签中读取 (曲目增益, 专辑增益)，单位 dB，缺失时为 None
+fn read_replay_gain(tags: &[lofty::tag::Tag]) -> (Option<f32>, Option<f32>) {
+    use lofty::tag::ItemKey;
+
+    let mut track_gain = None;
+    let mut album_gain = None;
+    for tag in tags {
+        if track_gain.is_none() {
+            track_gain = tag
+                .get_string(&ItemKey::ReplayGainTrackGain)
+                .and_then(parse_gain_db)
+                .or_else(|| {
+                    tag.get_string(&ItemKey::Unknown("R128_TRACK_GAIN".to_string()))
+                        .and_then(parse_r128_gain)
+                });
+        }
+        if album_gain.is_none() {
+            album_gain = tag
+                .get_string(&ItemKey::ReplayGainAlbumGain)
+                .and_then(parse_gain_db)
+                .or_else(|| {
+                    tag.get_string(&ItemKey::Unknown("R128_ALBUM_GAIN".to_string()))
+                        .and_then(parse_r128_gain)
+                });
+        }
+    }
+    (track_gain, album_gain)
+}
+
+/// 整轨文件中各分轨共享的属性
+struct CueFileInfo<'a> {
+    path: &'a str,
+    album_id: Uuid,
+    artist_id: Uuid,
+    duration_ms: i32,
+    bitrate: Option<i32>,
+    format: Option<&'a str>,
+    file_sig: Option<(i64, Option<i64>)>,
+    disc_number: i32,
+    genre: Option<&'a str>,
+    album_gain: Option<f32>,
+}
+
+/// 判断路径是否为可扫描的音频文件 (扫描器与整理引擎共用)
+pub fn is_audio_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|s| s.to_str())
+        .map(|s| {
+            let ext = s.to_lowercase();
+            AUDIO_EXTENSIONS.iter().any(|e| *e == ext)
+        })
+        .unwrap_or(false)
+}
+
+/// 进度刷新的最长时间间隔，保证低吞吐时 UI 依然实时
+const PROGRESS_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct OrphanTrack {
+    pub id: Uuid,
+    pub title: String,
+    pub path: String,
+}
+
+/// 未配置 ORPHAN_MAX_MISSING_PERCENT 时的阈值：超过该比例的音轨同时缺失时视为挂载点不可用
+const DEFAULT_MAX_MISSING_PERCENT: f64 = 25.0;
+
+/// 未配置 TRACK_DELETE_GRACE_DAYS 时软删除音轨的保留天数
+const DEFAULT_DELETE_GRACE_DAYS: i32 = 30;
+
+fn max_missing_percent() -> f64 {
+    std::env::var("ORPHAN_MAX_MISSING_PERCENT")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| (0.0..=100.0).contains(v))
+        .unwrap_or(DEFAULT_MAX_MISSING_PERCENT)
+}
+
+fn delete_grace_days() -> i32 {
+    std::env::var("TRACK_DELETE_GRACE_DAYS")
+        .ok()
+        .and_then(|v| v.trim().parse::<i32>().ok())
+        .filter(|v| *v >= 0)
+        .unwrap_or(DEFAULT_DELETE_GRACE_DAYS)
+}
+
+/// 未配置 SCAN_MIN_FOUND_RATIO 时的下限：磁盘上找到的文件数低于库中已有文件数的该比例时拒绝扫描
+const DEFAULT_MIN_FOUND_RATIO: f64 = 0.1;
+
+/// 库中已有文件数低于该值时不做空库判断，避免新建曲库或小曲库误报
+const EMPTY_LIBRARY_MIN_KNOWN: i64 = 10;
+
+fn min_found_ratio() -> f64 {
+    std::env::var("SCAN_MIN_FOUND_RATIO")
+        .ok()
+        .and_then(|v| v.trim().parse::<f64>().ok())
+        .filter(|v| (0.0..=1.0).contains(v))
+        .unwrap_or(DEFAULT_MIN_FOUND_RATIO)
+}
+
+/// 扫描根目录下找到的音频文件远少于库中已有的文件时，多半是网络共享未挂载 (只剩空的挂载点目录)
+fn library_looks_unmounted(found: usize, known: i64, min_ratio: f64) -> bool {
+    known >= EMPTY_LIBRARY_MIN_KNOWN && (found as f64) < known as f64 * min_ratio
+}
+
+fn library_unmounted_error(found: usize, path: &str, known: i64) -> AppError {
+    tracing::error!(
+        "Found {} audio files under {} but the library has {}; the share may be unmounted, aborting scan",
+        found,
+        path,
+        known
+    );
+    AppError::BadRequest(format!(
+        "Library appears empty/unmounted: found {} audio files under {} but {} are indexed",
+        found, path, known
+    ))
+}
+
+/// 缺失比例是否超过阈值。曲库很小时一两首缺失也可能超过比例，因此至少缺失 10 首才判定
+fn looks_like_mount_down(missing: usize, total: usize, max_percent: f64) -> bool {
+    total > 0 && missing >= 10 && (missing as f64 / total as f64) * 100.0 > max_percent
+}
+
+/// scan_status.operation 的取值：区分扫描与整理，便于前端准确展示
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScanOperation {
+    #[default]
+    Idle,
+    Scanning,
+    Organizing,
+}
+
+impl ScanOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Idle => "idle",
+            Self::Scanning => "scanning",
+            Self::Organizing => "organizing",
+        }
+    }
+}
+
+/// 推送给 SSE 订阅者的扫描/整理进度快照
+#[derive(Debug, Clone, Copy, Default, PartialEq, serde::Serialize)]
+pub struct ScanProgress {
+    pub is_scanning: bool,
+    pub operation: ScanOperation,
+    pub current_count: i32,
+    pub total_count: i32,
+}
+
+pub type ScanProgressSender = Arc<tokio::sync::watch::Sender<ScanProgress>>;
+
+/// 持有 SCAN_LOCK 的任务结束时 (包括出错提前返回) 通知订阅者
+struct ProgressDoneGuard(Option<ScanProgressSender>);
+
+impl Drop for ProgressDoneGuard {
+    fn drop(&mut self) {
+        if let Some(tx) = &self.0 {
+            tx.send_modify(|p| {
+                p.is_scanning = false;
+                p.operation = ScanOperation::Idle;
+            });
+        }
+    }
+}
+
+/// 孤儿音轨清理结果。`mount_down` 为 true 时判定挂载点不可用，本次没有做任何删除
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct OrphanCleanupReport {
+    pub soft_deleted: u64,
+    pub purged: u64,
+    pub mount_down: bool,
+}
+
+/// 曲库清理结果
+#[derive(Debug, Default, Clone, serde::Serialize)]
+pub struct VacuumReport {
+    pub repaired_tracks: u64,
+    pub deleted_albums: u64,
+    pub deleted_artists: u64,
+}
+
 pub struct Scanner {
     db: PgPool,
     concurrency_limit: Arc<Semaphore>,
     progress_counter: Arc<AtomicI32>,
-    artist_cache: Arc<DashMap<String, Uuid>>,
-    album_cache: Arc<DashMap<(String, Uuid), Uuid>>,
+    progress_flush_every: i32,
+    /// 上次写入数据库的 (进度, 时间)
+    last_progress_flush: Arc<std::sync::Mutex<(i32, Instant)>>,
+    progress_tx: Option<ScanProgressSender>,
+    /// 配置了 ACOUSTID_API_KEY 时用于识别缺少标题或歌手标签的文件
+    metadata: Option<Arc<MetadataService>>,
 }
 
 impl Scanner {
@@ -35,12 +360,39 @@ impl Scanner {
             .unwrap_or_else(|_| "8".to_string())
             .parse()
             .unwrap_or(8);
+        let flush_every = std::env::var("SCAN_PROGRESS_FLUSH_EVERY")
+            .ok()
+            .and_then(|v| v.parse::<i32>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(5);
         Self {
             db,
             concurrency_limit: Arc::new(Semaphore::new(limit)),
             progress_counter: Arc::new(AtomicI32::new(0)),
-            artist_cache: Arc::new(DashMap::new()),
-            album_cache: Arc::new(DashMap::new()),
+            progress_flush_every: flush_every,
+            last_progress_flush: Arc::new(std::sync::Mutex::new((0, Instant::now()))),
+            progress_tx: None,
+            metadata: None,
+        }
+    }
+
+    /// 在写入 scan_status 的同时把进度推送到 watch 通道
+    pub fn with_progress_channel(mut self, tx: ScanProgressSender) -> Self {
+        self.progress_tx = Some(tx);
+        self
+    }
+
+    /// 启用声纹识别补全缺失的标签，未配置 AcoustID 时不产生任何请求
+    pub fn with_metadata_service(mut self, metadata: Arc<MetadataService>) -> Self {
+        if metadata.fingerprinting_enabled() {
+            self.metadata = Some(metadata);
+        }
+        self
+    }
+
+    fn publish_progress(&self, update: impl FnOnce(&mut ScanProgress)) {
+        if let Some(tx) = &self.progress_tx {
+            tx.send_modify(update);
         }
     }
 
@@ -48,10 +400,52 @@ impl Scanner {
         SCAN_LOCK.try_lock().is_err()
     }
 
-    pub async fn scan_directory(&self, path: &str) -> Result<(), AppError> {
+    /// 根目录下已入库 (未软删除) 的文件数
+    async fn indexed_file_count(&self, path: &str) -> Result<i64, AppError> {
+        let root_prefix = format!("{}/", path.trim_end_matches('/'));
+        let known: i64 = sqlx::query_scalar(
+            "SELECT COUNT(DISTINCT path) FROM tracks WHERE deleted_at IS NULL AND left(path, length($1)) = $1",
+        )
+        .bind(&root_prefix)
+        .fetch_one(&self.db)
+        .await?;
+        Ok(known)
+    }
+
+    /// 空库保护的预检，供触发扫描的接口在后台任务启动前同步调用，把错误直接返回给请求方。
+    /// 找到的文件数达到阈值即停止遍历，正常挂载的大曲库不必走完整个目录；
+    /// 目录遍历是同步 IO，放在 `spawn_blocking` 中执行，不占用异步工作线程
+    pub async fn check_library_mounted(&self, path: &str) -> Result<(), AppError> {
+        let known_files = self.indexed_file_count(path).await?;
+        let min_ratio = min_found_ratio();
+        if known_files < EMPTY_LIBRARY_MIN_KNOWN {
+            return Ok(());
+        }
+        let enough = (known_files as f64 * min_ratio).ceil() as usize;
+        let root = path.to_string();
+        let found = tokio::task::spawn_blocking(move || {
+            WalkDir::new(root)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file() && is_audio_path(e.path()))
+                .take(enough)
+                .count()
+        })
+        .await
+        .map_err(|e| AppError::Internal(format!("Library check task failed: {}", e)))?;
+        if library_looks_unmounted(found, known_files, min_ratio) {
+            return Err(library_unmounted_error(found, path, known_files));
+        }
+        Ok(())
+    }
+
+    /// 扫描目录。默认增量模式：大小与修改时间均未变化的文件直接跳过；
+    /// `force` 为 true 时重新处理所有文件。
+    pub async fn scan_directory(&self, path: &str, force: bool) -> Result<(), AppError> {
         let _lock = SCAN_LOCK.try_lock().map_err(|_| {
             AppError::BadRequest("A scan is already in progress".to_string())
         })?;
+        let _done = ProgressDoneGuard(self.progress_tx.clone());
 
         let scan_path = Path::new(path);
         if !scan_path.exists() || !scan_path.is_dir() {
@@ -64,19 +458,73 @@ impl Scanner {
 
         tracing::info!("Starting scan of directory: {}", path);
         self.progress_counter.store(0, Ordering::SeqCst);
+        if let Ok(mut last) = self.last_progress_flush.lock() {
+            *last = (0, Instant::now());
+        }
 
-        let entries: Vec<_> = WalkDir::new(path)
+        let all_entries: Vec<_> = WalkDir::new(path)
             .into_iter()
             .filter_map(|e| e.ok())
             .filter(|e| e.file_type().is_file() && self.is_audio_file(e.path()))
             .collect();
 
-        let total = entries.len() as i32;
-        tracing::info!("Found {} audio files to process", total);
+        // 空库保护：在写入任何状态之前中止，后续的孤儿清理不会执行
+        let known_files = self.indexed_file_count(path).await?;
+        if library_looks_unmounted(all_entries.len(), known_files, min_found_ratio()) {
+            return Err(library_unmounted_error(all_entries.len(), path, known_files));
+        }
+
+        // 增量扫描：一次性载入已入库文件的指纹，只派发新增或变化的文件
+        let known: HashMap<String, (Option<i64>, Option<i64>)> = if force {
+            HashMap::new()
+        } else {
+            // 软删除的音轨不参与比对，文件重新出现时会被重新处理并恢复
+            sqlx::query("SELECT path, size, file_mtime FROM tracks WHERE deleted_at IS NULL")
+                .fetch_all(&self.db)
+                .await?
+                .into_iter()
+                .map(|row| (row.get("path"), (row.get("size"), row.get("file_mtime"))))
+                .collect()
+        };
 
-        sqlx::query("UPDATE scan_status SET is_scanning = TRUE, current_count = 0, total_count = $1 WHERE id = 1")
+        let found = all_entries.len();
+        let entries: Vec<_> = all_entries
+            .into_iter()
+            .filter(|e| {
+                let stored = e.path().to_str().and_then(|p| known.get(p));
+                match (stored, e.metadata()) {
+                    (Some((size, mtime)), Ok(meta)) => {
+                        let (cur_size, cur_mtime) = file_signature(&meta);
+                        *size != Some(cur_size) || mtime.is_none() || *mtime != cur_mtime
+                    }
+                    _ => true,
+                }
+            })
+            .collect();
+
+        let total = entries.len() as i32;
+        let skipped = (found - entries.len()) as i32;
+        tracing::info!(
+            "Found {} audio files: {} to process, {} unchanged (force: {})",
+            found,
+            total,
+            skipped,
+            force
+        );
+
+        sqlx::query("UPDATE scan_status SET is_scanning = TRUE, operation = $3, current_count = 0, total_count = $1, skipped_count = $2 WHERE id = 1")
             .bind(total)
+            .bind(skipped)
+            .bind(ScanOperation::Scanning.as_str())
             .execute(&self.db).await?;
+        self.publish_progress(|p| {
+            *p = ScanProgress {
+                is_scanning: true,
+                operation: ScanOperation::Scanning,
+                current_count: 0,
+                total_count: total,
+            }
+        });
 
         let mut futures = FuturesUnordered::new();
         let mut failure_count = 0;
@@ -135,6 +583,7 @@ impl Scanner {
             if self.handle_task_result(res).is_err() {
                 // failure_count += 1; // Don't care about trailing failures for threshold, but could log
             }
+            self.update_scan_progress_inc().await?;
         }
 
         // 最终强制校准一次
@@ -142,7 +591,7 @@ impl Scanner {
         self.cleanup_orphan_tracks().await?;
 
         sqlx::query(
-            "UPDATE scan_status SET is_scanning = FALSE, last_scan_at = NOW() WHERE id = 1",
+            "UPDATE scan_status SET is_scanning = FALSE, operation = 'idle', last_scan_at = NOW() WHERE id = 1",
         )
         .execute(&self.db)
         .await?;
@@ -172,17 +621,31 @@ impl Scanner {
             db: self.db.clone(),
             concurrency_limit: self.concurrency_limit.clone(),
             progress_counter: self.progress_counter.clone(),
-            artist_cache: self.artist_cache.clone(),
-            album_cache: self.album_cache.clone(),
+            progress_flush_every: self.progress_flush_every,
+            last_progress_flush: self.last_progress_flush.clone(),
+            progress_tx: self.progress_tx.clone(),
+            metadata: self.metadata.clone(),
         })
     }
 
     async fn update_scan_progress_inc(&self) -> Result<(), AppError> {
         let current = self.progress_counter.load(Ordering::SeqCst);
-        // 每完成 5 个任务才更新一次数据库状态，减少 IO 压力
-        if current % 5 != 0 {
-            return Ok(());
+        // 按完成数量 (SCAN_PROGRESS_FLUSH_EVERY) 或时间间隔批量刷新，减少 IO 压力
+        {
+            let mut last = self
+                .last_progress_flush
+                .lock()
+                .map_err(|_| AppError::Internal("Progress flush state poisoned".to_string()))?;
+            let (last_count, last_at) = *last;
+            if current == last_count
+                || (current - last_count < self.progress_flush_every
+                    && last_at.elapsed() < PROGRESS_FLUSH_INTERVAL)
+            {
+                return Ok(());
+            }
+            *last = (current, Instant::now());
         }
+        self.publish_progress(|p| p.current_count = current);
         sqlx::query("UPDATE scan_status SET current_count = $1 WHERE id = 1")
             .bind(current)
             .execute(&self.db)
@@ -192,45 +655,222 @@ impl Scanner {
     }
 
     async fn update_scan_progress_final(&self) -> Result<(), AppError> {
-        sqlx::query(
-            "UPDATE scan_status SET current_count = (SELECT COUNT(*) FROM tracks) WHERE id = 1",
-        )
+        // 增量模式下 total_count 只统计本次派发的文件，直接对齐到总数
+        self.publish_progress(|p| p.current_count = p.total_count);
+        sqlx::query("UPDATE scan_status SET current_count = total_count WHERE id = 1")
         .execute(&self.db)
         .await
         .map(|_| ())
         .map_err(AppError::Database)
     }
 
-    async fn cleanup_orphan_tracks(&self) -> Result<(), AppError> {
-        tracing::info!("Cleaning up orphan tracks...");
-        let rows = sqlx::query("SELECT id, path FROM tracks")
+    /// 目录监听触发的增量入库：处理新增/修改的音频文件，并移除已删除路径 (文件或整个目录) 对应的音轨。
+    /// 与全量扫描共用 SCAN_LOCK，扫描进行中时等待其结束。
+    pub async fn apply_fs_changes(
+        &self,
+        changed: &[PathBuf],
+        removed: &[PathBuf],
+    ) -> Result<(), AppError> {
+        let _lock = SCAN_LOCK.lock().await;
+
+        for path in changed {
+            tracing::info!("Watcher: indexing {}", path.display());
+            if let Err(e) = self.process_file(path).await {
+                tracing::warn!("Watcher: failed to index {}: {}", path.display(), e);
+            }
+        }
+
+        for path in removed {
+            // 事件与处理之间文件可能又被放回
+            if path.exists() {
+                continue;
+            }
+            let Some(path_str) = path.to_str() else {
+                continue;
+            };
+            let dir_prefix = format!("{}/", path_str.trim_end_matches('/'));
+            let deleted = sqlx::query(
+                "UPDATE tracks SET deleted_at = NOW()
+                 WHERE deleted_at IS NULL AND (path = $1 OR left(path, length($2)) = $2)",
+            )
+            .bind(path_str)
+            .bind(&dir_prefix)
+            .execute(&self.db)
+            .await?
+            .rows_affected();
+            if deleted > 0 {
+                tracing::warn!("Watcher: soft-deleted {} track(s) under {}", deleted, path_str);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 列出数据库中文件已不存在于磁盘的音轨 (不含已软删除的)，只读，不做任何删除
+    pub async fn find_orphan_tracks(&self) -> Result<Vec<OrphanTrack>, AppError> {
+        let rows = sqlx::query("SELECT id, title, path FROM tracks WHERE deleted_at IS NULL")
             .fetch_all(&self.db)
             .await?;
 
-        for row in rows {
-            let id: Uuid = row.get("id");
-            let path: String = row.get("path");
-            if !Path::new(&path).exists() {
-                tracing::warn!("Removing orphan track from DB: {}", path);
-                sqlx::query("DELETE FROM tracks WHERE id = $1")
-                    .bind(id)
-                    .execute(&self.db)
-                    .await?;
+        Ok(rows
+            .into_iter()
+            .filter_map(|row| {
+                let path: String = row.get("path");
+                if Path::new(&path).exists() {
+                    return None;
+                }
+                Some(OrphanTrack {
+                    id: row.get("id"),
+                    title: row.get("title"),
+                    path,
+                })
+            })
+            .collect())
+    }
+
+    /// 单独执行孤儿清理 (不做扫描)，与扫描共用 SCAN_LOCK
+    pub async fn cleanup_orphans(&self) -> Result<OrphanCleanupReport, AppError> {
+        let _lock = SCAN_LOCK.try_lock().map_err(|_| {
+            AppError::BadRequest("A scan or reorganization is in progress".to_string())
+        })?;
+        self.cleanup_orphan_tracks().await
+    }
+
+    /// 文件缺失的音轨只做软删除 (歌单、收藏保持关联)，超过宽限期的再物理删除。
+    /// 缺失比例过高时多半是 NAS 挂载点暂时不可用，本次整体跳过
+    async fn cleanup_orphan_tracks(&self) -> Result<OrphanCleanupReport, AppError> {
+        tracing::info!("Cleaning up orphan tracks...");
+        let orphans = self.find_orphan_tracks().await?;
+        let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM tracks WHERE deleted_at IS NULL")
+            .fetch_one(&self.db)
+            .await?;
+
+        let max_percent = max_missing_percent();
+        if looks_like_mount_down(orphans.len(), total as usize, max_percent) {
+            tracing::error!(
+                "{} of {} tracks are missing on disk (more than {}%), the library mount may be down; skipping orphan cleanup",
+                orphans.len(),
+                total,
+                max_percent
+            );
+            return Ok(OrphanCleanupReport {
+                mount_down: true,
+                ..Default::default()
+            });
+        }
+
+        let mut soft_deleted = 0;
+        if !orphans.is_empty() {
+            for orphan in &orphans {
+                tracing::warn!("Soft-deleting orphan track: {}", orphan.path);
             }
+            let ids: Vec<Uuid> = orphans.iter().map(|o| o.id).collect();
+            soft_deleted = sqlx::query("UPDATE tracks SET deleted_at = NOW() WHERE id = ANY($1) AND deleted_at IS NULL")
+                .bind(&ids)
+                .execute(&self.db)
+                .await?
+                .rows_affected();
         }
-        Ok(())
+
+        let purged = self.purge_deleted_tracks(None, delete_grace_days()).await?;
+        if purged > 0 {
+            tracing::info!("Purged {} track(s) soft-deleted more than {} days ago", purged, delete_grace_days());
+        }
+        Ok(OrphanCleanupReport {
+            soft_deleted,
+            purged,
+            mount_down: false,
+        })
+    }
+
+    /// 取消软删除。`ids` 为 None 时恢复全部，返回恢复的条数
+    pub async fn restore_tracks(&self, ids: Option<&[Uuid]>) -> Result<u64, AppError> {
+        let restored = sqlx::query(
+            "UPDATE tracks SET deleted_at = NULL, updated_at = NOW()
+             WHERE deleted_at IS NOT NULL AND ($1::uuid[] IS NULL OR id = ANY($1))",
+        )
+        .bind(ids)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        Ok(restored)
+    }
+
+    /// 物理删除软删除超过 `grace_days` 天的音轨 (`ids` 限定范围，None 为全部)，返回删除的条数
+    pub async fn purge_deleted_tracks(
+        &self,
+        ids: Option<&[Uuid]>,
+        grace_days: i32,
+    ) -> Result<u64, AppError> {
+        let purged = sqlx::query(
+            "DELETE FROM tracks
+             WHERE deleted_at IS NOT NULL
+               AND deleted_at <= NOW() - make_interval(days => $2)
+               AND ($1::uuid[] IS NULL OR id = ANY($1))",
+        )
+        .bind(ids)
+        .bind(grace_days)
+        .execute(&self.db)
+        .await?
+        .rows_affected();
+        Ok(purged)
+    }
+
+    /// 清理没有任何音轨的专辑/歌手，并修复缺失 artist_id 的音轨 (只修复这一项：
+    /// album_id / artist_id 的外键由数据库约束保证不会悬空)。
+    /// 与扫描共用 SCAN_LOCK，在单个事务中执行，可重复调用。
+    pub async fn vacuum(&self) -> Result<VacuumReport, AppError> {
+        let _lock = SCAN_LOCK.try_lock().map_err(|_| {
+            AppError::BadRequest("A scan or reorganization is in progress".to_string())
+        })?;
+
+        let mut tx = self.db.begin().await?;
+
+        // 1. 音轨缺失 artist_id 时沿用其专辑的歌手
+        let repaired_tracks = sqlx::query(
+            "UPDATE tracks t SET artist_id = a.artist_id
+             FROM albums a
+             WHERE t.album_id = a.id AND t.artist_id IS NULL",
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // 2. 删除空专辑
+        let deleted_albums = sqlx::query(
+            "DELETE FROM albums a WHERE NOT EXISTS (SELECT 1 FROM tracks t WHERE t.album_id = a.id)",
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        // 3. 删除既无音轨也无专辑的歌手
+        let deleted_artists = sqlx::query(
+            "DELETE FROM artists ar
+             WHERE NOT EXISTS (SELECT 1 FROM tracks t WHERE t.artist_id = ar.id)
+               AND NOT EXISTS (SELECT 1 FROM albums a WHERE a.artist_id = ar.id)",
+        )
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+
+        tx.commit().await?;
+
+        // 缓存里可能还留着刚被删除的 ID
+        ARTIST_CACHE.clear();
+        ALBUM_CACHE.clear();
+
+        let report = VacuumReport {
+            repaired_tracks,
+            deleted_albums,
+            deleted_artists,
+        };
+        tracing::info!("Library vacuum finished: {:?}", report);
+        Ok(report)
     }
 
     fn is_audio_file(&self, path: &Path) -> bool {
-        path.extension()
-            .and_then(|s| s.to_str())
-            .map(|s| {
-                matches!(
-                    s.to_lowercase().as_str(),
-                    "flac" | "mp3" | "m4a" | "ogg" | "wav"
-                )
-            })
-            .unwrap_or(false)
+        is_audio_path(path)
     }
 
     #[tracing::instrument(skip(self, path), fields(path = %path.display()))]
@@ -240,8 +880,11 @@ impl Scanner {
             .ok_or_else(|| AppError::Internal("Invalid path encoding".to_string()))?;
         tracing::debug!("Processing metadata for file");
 
+        // 先按文件头探测真实格式，扩展名不准确 (如 .aac 实为 ADTS/MP4) 时也能正确解析
         let tagged_file = Probe::open(path)
             .map_err(|e| AppError::Metadata(format!("Failed to open {}: {}", path_str, e)))?
+            .guess_file_type()
+            .map_err(|e| AppError::Metadata(format!("Failed to probe {}: {}", path_str, e)))?
             .read()
             .map_err(|e| {
                 AppError::Metadata(format!("Failed to read tags from {}: {}", path_str, e))
@@ -249,13 +892,24 @@ impl Scanner {
 
         let properties = tagged_file.properties();
         let duration = properties.duration().as_secs() as i32;
-        let bitrate = properties.audio_bitrate();
+        // 部分格式 (如 APE/WavPack 的某些编码器) 不提供码率，按文件大小估算
+        let bitrate = properties.audio_bitrate().or_else(|| {
+            let secs = properties.duration().as_secs();
+            let size = path.metadata().ok()?.len();
+            (secs > 0).then(|| (size * 8 / secs / 1000) as u32)
+        });
 
         let mut title_opt = None;
         let mut artist_opt = None;
+        let mut album_artist_opt = None;
+        let mut compilation_flag = false;
         let mut album_opt = None;
         let mut track_num = None;
+        let mut track_total = None;
+        let mut disc_num = None;
+        let mut genres: Vec<String> = Vec::new();
         let mut year = None;
+        let mut release_date = None;
 
         // 优先级 1: 遍历所有可用的 Tag (ID3v2, Vorbis, etc.) 以获取基础元数据
         for tag in tagged_file.tags() {
@@ -265,15 +919,61 @@ impl Scanner {
             if artist_opt.is_none() {
                 artist_opt = tag.artist().map(|s| s.to_string());
             }
+            if album_artist_opt.is_none() {
+                album_artist_opt = tag
+                    .get_string(&lofty::tag::ItemKey::AlbumArtist)
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .map(|s| s.to_string());
+            }
+            compilation_flag |= tag
+                .get_string(&lofty::tag::ItemKey::FlagCompilation)
+                .is_some_and(is_compilation_flag);
             if album_opt.is_none() {
                 album_opt = tag.album().map(|s| s.to_string());
             }
             if track_num.is_none() {
                 track_num = tag.track();
             }
+            if track_total.is_none() {
+                track_total = tag.track_total();
+            }
+            if disc_num.is_none() {
+                disc_num = tag.disk();
+            }
             if year.is_none() {
                 year = tag.year();
             }
+            if release_date.is_none() {
+                release_date = [lofty::tag::ItemKey::ReleaseDate, lofty::tag::ItemKey::RecordingDate]
+                    .iter()
+                    .find_map(|key| tag.get_string(key).and_then(parse_release_date));
+            }
+            // Vorbis Comment 允许重复的 GENRE 字段，全部收集后去重
+            for genre in tag.get_strings(&lofty::tag::ItemKey::Genre) {
+                let genre = genre.trim();
+                if !genre.is_empty() && !genres.iter().any(|g| g.eq_ignore_ascii_case(genre)) {
+                    genres.push(genre.to_string());
+                }
+            }
+        }
+        let genre = (!genres.is_empty()).then(|| genres.join("; "));
+        let (track_gain, album_gain) = read_replay_gain(tagged_file.tags());
+
+        // 标题或歌手缺失时尝试声纹识别，只补空缺，不覆盖已有标签
+        let mut recording_id = None;
+        if title_opt.is_none() || artist_opt.is_none() {
+            if let Some(metadata) = &self.metadata {
+                if let Some(found) = metadata.identify_recording(path).await {
+                    tracing::info!(title = %found.title, artist = %found.artist, score = found.score, "Identified untagged file by fingerprint");
+                    title_opt.get_or_insert(found.title);
+                    artist_opt.get_or_insert(found.artist);
+                    if album_opt.is_none() {
+                        album_opt = found.album;
+                    }
+                    recording_id = Some(found.recording_id);
+                }
+            }
         }
 
         let final_title = title_opt.unwrap_or_else(|| {
@@ -287,50 +987,67 @@ impl Scanner {
 
         tracing::debug!(title = %final_title, artist = %final_artist, album = %final_album, "Extracted basic metadata");
 
+        // 歌词来源状态机：外部 .lrc 优先，其次内嵌标签，都没有则为 none
+        let mut lyrics: Option<String> = None;
+        let mut lyrics_source: &str = "none";
+
         // 策略 A: 外部 .lrc 文件
-        if let Some(lrc_path) = self.find_lrc_file(path).await {
-            if let Ok(bytes) = tokio::fs::read(&lrc_path).await {
-                // 尝试多种编码探测：UTF-8 -> GBK -> Big5
-                let (content, encoding_used, has_errors) = encoding_rs::UTF_8.decode(&bytes);
-                
-                let final_content = if has_errors {
-                    let (gbk_content, _, gbk_errors) = encoding_rs::GBK.decode(&bytes);
-                    if gbk_errors {
-                        let (big5_content, _, _) = encoding_rs::BIG5.decode(&bytes);
-                        big5_content.to_string()
-                    } else {
-                        gbk_content.to_string()
-                    }
-                } else {
-                    content.to_string()
-                };
-
-                lyrics = Some(final_content.replace('\0', ""));
-                lyrics_source = "file";
-                tracing::info!(
-                    "Loaded LRC file: {} (Encoding: {})",
-                    lrc_path.display(),
-                    encoding_used.name()
-                );
-            }
+        if let Some(content) = self.load_lrc_lyrics(path).await {
+            lyrics = Some(content);
+            lyrics_source = "file";
         }
 
         // 策略 B: 提取内嵌歌词 (USLT/LYRICS 标签)
         if lyrics.is_none() {
             for tag in tagged_file.tags() {
                 if let Some(content) = tag.get_string(&lofty::tag::ItemKey::Lyrics) {
-                    lyrics = Some(content.to_string());
-                    lyrics_source = "embedded";
-                    break;
+                    if !content.trim().is_empty() {
+                        lyrics = Some(content.to_string());
+                        lyrics_source = "embedded";
+                        break;
+                    }
                 }
             }
         }
 
+        let sync_status = initial_sync_status(lyrics.as_deref());
+
+        // 专辑按专辑歌手归并：合辑中每首曲目歌手不同，若按曲目歌手建专辑会拆成许多单曲专辑
+        let album_artist = album_artist_opt.unwrap_or_else(|| {
+            if compilation_flag {
+                VARIOUS_ARTISTS.to_string()
+            } else {
+                final_artist.clone()
+            }
+        });
+        let is_compilation = compilation_flag || is_various_artists(&album_artist);
+
         let artist_id = self.get_or_create_artist(&final_artist).await?;
+        let album_artist_id = if album_artist == final_artist {
+            artist_id
+        } else {
+            self.get_or_create_artist(&album_artist).await?
+        };
         let album_id = self
-            .get_or_create_album(&final_album, artist_id, year)
+            .get_or_create_album(&final_album, album_artist_id, year, release_date)
             .await?;
 
+        if is_compilation {
+            sqlx::query("UPDATE albums SET is_compilation = TRUE WHERE id = $1 AND NOT is_compilation")
+                .bind(album_id)
+                .execute(&self.db)
+                .await?;
+        }
+
+        // 专辑流派取首个出现的曲目流派
+        if let Some(first_genre) = genres.first() {
+            sqlx::query("UPDATE albums SET genre = $1 WHERE id = $2 AND genre IS NULL")
+                .bind(first_genre)
+                .bind(album_id)
+                .execute(&self.db)
+                .await?;
+        }
+
         // 封面提取：遍历所有标签尝试提取
         let mut cover_extracted = false;
         
@@ -369,27 +1086,55 @@ impl Scanner {
             }
         }
 
-        // 策略 D: 关联歌手头像 (探测歌手目录下的 folder.jpg)
-        let _ = self.link_existing_artist_image(path, artist_id).await;
+        // 策略 D: 关联歌手头像 (探测歌手目录下的 folder.jpg)，目录通常按专辑歌手组织
+        let _ = self.link_existing_artist_image(path, album_artist_id).await;
 
         tracing::debug!(track = %final_title, "Inserting track into database...");
 
+        let file_sig = path.metadata().ok().map(|m| file_signature(&m));
+
+        // 整轨 + CUE：按分轨写入多行，同一文件以 start_ms 区分
+        if let Some(sheet) = self.load_cue_sheet(path, &tagged_file).await {
+            let file_info = CueFileInfo {
+                path: path_str,
+                album_id,
+                artist_id,
+                duration_ms: properties.duration().as_millis() as i32,
+                bitrate: bitrate.map(|b| b as i32),
+                format: path.extension().and_then(|s| s.to_str()),
+                file_sig,
+                disc_number: disc_num.map(|n| n as i32).unwrap_or(1),
+                genre: genre.as_deref(),
+                album_gain,
+            };
+            return self.upsert_cue_tracks(&sheet, &file_info).await;
+        }
+
         let row = sqlx::query!(
             r#"
             INSERT INTO tracks (
                 title, album_id, artist_id, duration, path, bitrate, format, size, track_number,
-                lyrics, lyrics_source, sync_status
+                lyrics, lyrics_source, sync_status, file_mtime, disc_number, track_total, genre,
+                replaygain_track_gain, replaygain_album_gain
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11::lyrics_source_type, $12::lyric_sync_status)
-            ON CONFLICT (path) DO UPDATE SET
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11::lyrics_source_type, $12::lyric_sync_status, $13, $14, $15, $16, $17, $18)
+            ON CONFLICT (path, start_ms) DO UPDATE SET
                 title = EXCLUDED.title,
                 duration = EXCLUDED.duration,
                 bitrate = EXCLUDED.bitrate,
+                size = EXCLUDED.size,
+                file_mtime = EXCLUDED.file_mtime,
                 track_number = EXCLUDED.track_number,
+                disc_number = EXCLUDED.disc_number,
+                track_total = EXCLUDED.track_total,
+                genre = EXCLUDED.genre,
+                replaygain_track_gain = EXCLUDED.replaygain_track_gain,
+                replaygain_album_gain = EXCLUDED.replaygain_album_gain,
+                deleted_at = NULL,
                 lyrics = EXCLUDED.lyrics,
                 lyrics_source = EXCLUDED.lyrics_source,
                 sync_status = CASE
-                    WHEN tracks.lyrics <> EXCLUDED.lyrics THEN 'pending'::lyric_sync_status
+                    WHEN tracks.lyrics IS DISTINCT FROM EXCLUDED.lyrics THEN EXCLUDED.sync_status
                     ELSE tracks.sync_status
                 END,
                 updated_at = NOW()
@@ -397,11 +1142,17 @@ impl Scanner {
             "#,
             final_title, album_id, artist_id, duration, path_str,
             bitrate.map(|b| b as i32), path.extension().and_then(|s| s.to_str()),
-            path.metadata().map(|m| m.len() as i64).ok(),
+            file_sig.map(|(size, _)| size),
             track_num.map(|n| n as i32),
             lyrics,
             lyrics_source as &str,
-            sync_status as &str
+            sync_status as &str,
+            file_sig.and_then(|(_, mtime)| mtime),
+            disc_num.map(|n| n as i32).unwrap_or(1),
+            track_total.map(|n| n as i32),
+            genre,
+            track_gain,
+            album_gain
         )
         .fetch_one(&self.db).await
         .map_err(|e| {
@@ -412,6 +1163,152 @@ impl Scanner {
         let track_id = row.id;
         tracing::debug!(id = %track_id, "Track inserted/updated successfully");
 
+        if let Some(recording_id) = recording_id {
+            self.store_recording_id(track_id, recording_id).await?;
+        }
+
+        // 文件之前按 CUE 拆分过、现在不再有 CUE 时清理多余的分轨
+        sqlx::query("DELETE FROM tracks WHERE path = $1 AND start_ms <> 0")
+            .bind(path_str)
+            .execute(&self.db)
+            .await?;
+
+        Ok(())
+    }
+
+    /// 写入标签中的录音 MBID，只填补空缺。同一录音常有多个文件 (不同版本/格式)，
+    /// 已被其他曲目占用时跳过，避免触发唯一约束中断整个文件的入库。返回是否写入
+    pub(crate) async fn store_recording_id(&self, track_id: Uuid, recording_id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            r#"
+            UPDATE tracks SET musicbrainz_track_id = $1
+            WHERE id = $2 AND musicbrainz_track_id IS NULL
+              AND NOT EXISTS (SELECT 1 FROM tracks o WHERE o.musicbrainz_track_id = $1 AND o.id <> $2)
+            "#,
+        )
+        .bind(recording_id)
+        .bind(track_id)
+        .execute(&self.db)
+        .await;
+        // NOT EXISTS 挡不住并发扫描同时写入同一 MBID，输掉竞争的一方撞上唯一约束，同样按已占用处理
+        let result = match result {
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => {
+                tracing::debug!(id = %track_id, mbid = %recording_id, "Recording MBID claimed concurrently by another track, skipped");
+                return Ok(false);
+            }
+            other => other?,
+        };
+
+        if result.rows_affected() == 0 {
+            tracing::debug!(id = %track_id, mbid = %recording_id, "Recording MBID already set or taken by another track, skipped");
+        }
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// 优先读取内嵌 CUESHEET 标签，其次是同名 `.cue` 文件
+    async fn load_cue_sheet(
+        &self,
+        path: &Path,
+        tagged_file: &lofty::file::TaggedFile,
+    ) -> Option<cue::CueSheet> {
+        let cue_key = lofty::tag::ItemKey::Unknown("CUESHEET".to_string());
+        let embedded = tagged_file
+            .tags()
+            .iter()
+            .find_map(|tag| tag.get_string(&cue_key).map(|s| s.to_string()));
+
+        let content = match embedded {
+            Some(content) => content,
+            None => cue::read_cue_file(&cue::find_cue_file(path)?).await?,
+        };
+
+        let sheet = cue::parse_cue(&content)?;
+        tracing::info!(
+            "Detected cue sheet with {} tracks: {}",
+            sheet.tracks.len(),
+            path.display()
+        );
+        Some(sheet)
+    }
+
+    async fn upsert_cue_tracks(
+        &self,
+        sheet: &cue::CueSheet,
+        file: &CueFileInfo<'_>,
+    ) -> Result<(), AppError> {
+        let track_total = sheet.tracks.len() as i32;
+        let mut starts = Vec::with_capacity(sheet.tracks.len());
+
+        for (i, cue_track) in sheet.tracks.iter().enumerate() {
+            let end_ms = sheet.end_ms(i);
+            let duration_secs = (end_ms.unwrap_or(file.duration_ms) - cue_track.start_ms).max(0) / 1000;
+            let title = cue_track
+                .title
+                .clone()
+                .unwrap_or_else(|| format!("Track {:02}", cue_track.number));
+
+            // 分轨有独立演奏者时单独建档，否则沿用整轨歌手
+            let artist_id = match cue_track.performer.as_deref() {
+                Some(performer) if Some(performer) != sheet.performer.as_deref() => {
+                    self.get_or_create_artist(performer).await?
+                }
+                _ => file.artist_id,
+            };
+
+            sqlx::query(
+                r#"
+                INSERT INTO tracks (
+                    title, album_id, artist_id, duration, path, bitrate, format, size, track_number,
+                    file_mtime, disc_number, track_total, genre, start_ms, end_ms,
+                    replaygain_album_gain
+                )
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+                ON CONFLICT (path, start_ms) DO UPDATE SET
+                    title = EXCLUDED.title,
+                    artist_id = EXCLUDED.artist_id,
+                    duration = EXCLUDED.duration,
+                    bitrate = EXCLUDED.bitrate,
+                    size = EXCLUDED.size,
+                    file_mtime = EXCLUDED.file_mtime,
+                    track_number = EXCLUDED.track_number,
+                    disc_number = EXCLUDED.disc_number,
+                    track_total = EXCLUDED.track_total,
+                    genre = EXCLUDED.genre,
+                    end_ms = EXCLUDED.end_ms,
+                    replaygain_album_gain = EXCLUDED.replaygain_album_gain,
+                    deleted_at = NULL,
+                    updated_at = NOW()
+                "#,
+            )
+            .bind(&title)
+            .bind(file.album_id)
+            .bind(artist_id)
+            .bind(duration_secs)
+            .bind(file.path)
+            .bind(file.bitrate)
+            .bind(file.format)
+            .bind(file.file_sig.map(|(size, _)| size))
+            .bind(cue_track.number as i32)
+            .bind(file.file_sig.and_then(|(_, mtime)| mtime))
+            .bind(file.disc_number)
+            .bind(track_total)
+            .bind(file.genre)
+            .bind(cue_track.start_ms)
+            .bind(end_ms)
+            .bind(file.album_gain)
+            .execute(&self.db)
+            .await?;
+
+            starts.push(cue_track.start_ms);
+        }
+
+        // CUE 修改后可能残留旧的分轨
+        sqlx::query("DELETE FROM tracks WHERE path = $1 AND NOT (start_ms = ANY($2))")
+            .bind(file.path)
+            .bind(&starts)
+            .execute(&self.db)
+            .await?;
+
         Ok(())
     }
 
@@ -420,19 +1317,82 @@ impl Scanner {
         pic: &lofty::picture::Picture,
         album_id: Uuid,
     ) -> Result<(), AppError> {
-        // 获取专辑和歌手信息以确定路径
-        let album_info = sqlx::query!(
-            "SELECT a.title, ar.name as artist_name FROM albums a JOIN artists ar ON a.artist_id = ar.id WHERE a.id = $1",
-            album_id
-        ).fetch_one(&self.db).await.map_err(AppError::Database)?;
-
         let extension = match pic.mime_type() {
             Some(lofty::picture::MimeType::Jpeg) => "jpg",
             Some(lofty::picture::MimeType::Png) => "png",
             _ => "jpg",
         };
 
-        // 直接存入曲库目录
+        self.store_album_cover(album_id, pic.data(), extension, false)
+            .await
+            .map(|_| ())
+    }
+
+    /// 按 COVER_STORAGE 保存专辑封面并更新 `albums.cover_path`，返回相对路径。
+    /// `overwrite` 为 false 时保留曲库中已有的封面 (扫描)；手动上传时覆盖。
+    pub async fn store_album_cover(
+        &self,
+        album_id: Uuid,
+        data: &[u8],
+        extension: &str,
+        overwrite: bool,
+    ) -> Result<String, AppError> {
+        let previous: Option<String> =
+            sqlx::query_scalar("SELECT cover_path FROM albums WHERE id = $1")
+                .bind(album_id)
+                .fetch_optional(&self.db)
+                .await?
+                .flatten();
+
+        // library 模式写入曲库目录；只读挂载等写入失败时回退到内部封面目录
+        let rel_path = match *COVER_STORAGE {
+            CoverStorage::Library => match self.write_library_cover(data, album_id, extension, overwrite).await {
+                Ok(stored) => stored,
+                Err(e) => {
+                    tracing::warn!(album_id = %album_id, "Cannot write cover into library ({}), using COVER_DIR", e);
+                    write_internal_cover(data, album_id, extension).await?
+                }
+            },
+            CoverStorage::Internal => write_internal_cover(data, album_id, extension).await?,
+        };
+
+        sqlx::query("UPDATE albums SET cover_path = $1 WHERE id = $2")
+            .bind(&rel_path)
+            .bind(album_id)
+            .execute(&self.db)
+            .await?;
+
+        // 扩展名变化时 (cover.jpg → cover.png) 旧文件不会被覆盖，删除它以免残留。
+        // 只处理同目录同名的封面，曲库中其他图片不动
+        if let Some(previous) = previous.filter(|p| *p != rel_path) {
+            let same_stem = Path::new(&previous).with_extension("")
+                == Path::new(&rel_path).with_extension("");
+            if same_stem {
+                if let Some(old_file) = cover_file_path(&previous) {
+                    if let Err(e) = tfs::remove_file(&old_file).await {
+                        tracing::warn!(album_id = %album_id, "Failed to remove previous cover {}: {}", old_file.display(), e);
+                    }
+                }
+            }
+        }
+
+        Ok(rel_path)
+    }
+
+    /// 写入 `MUSIC_DIR/歌手/专辑/cover.ext`，返回相对 MUSIC_DIR 的路径
+    async fn write_library_cover(
+        &self,
+        data: &[u8],
+        album_id: Uuid,
+        extension: &str,
+        overwrite: bool,
+    ) -> Result<String, AppError> {
+        // 获取专辑和歌手信息以确定路径
+        let album_info = sqlx::query!(
+            "SELECT a.title, ar.name as artist_name FROM albums a JOIN artists ar ON a.artist_id = ar.id WHERE a.id = $1",
+            album_id
+        ).fetch_one(&self.db).await.map_err(AppError::Database)?;
+
         let music_root = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
         let safe_art = album_info
             .artist_name
@@ -456,36 +1416,22 @@ impl Scanner {
             .unwrap()
             .to_string();
 
-        // 如果文件已存在且不为空，更新数据库并跳过写入以节省 IO
-        if full_save_path.exists()
-            && fs::metadata(&full_save_path)
-                .await
-                .map(|m| m.len())
-                .unwrap_or(0)
-                > 0
-        {
-            sqlx::query("UPDATE albums SET cover_path = $1 WHERE id = $2")
-                .bind(rel_path)
-                .bind(album_id)
-                .execute(&self.db)
-                .await?;
-            return Ok(());
+        // 如果文件已存在且不为空，跳过写入以节省 IO
+        let existing = fs::metadata(&full_save_path)
+            .await
+            .map(|m| m.len())
+            .unwrap_or(0);
+        if existing > 0 && !overwrite {
+            return Ok(rel_path);
         }
 
         tracing::info!(album = %album_info.title, "Saving cover directly to library: {}", full_save_path.display());
-        tokio::fs::write(&full_save_path, pic.data()).await?;
-
-        sqlx::query("UPDATE albums SET cover_path = $1 WHERE id = $2")
-            .bind(rel_path)
-            .bind(album_id)
-            .execute(&self.db)
-            .await?;
-
-        Ok(())
+        tokio::fs::write(&full_save_path, data).await?;
+        Ok(rel_path)
     }
 
-    async fn get_or_create_artist(&self, name: &str) -> Result<Uuid, AppError> {
-        if let Some(id) = self.artist_cache.get(name) {
+    pub async fn get_or_create_artist(&self, name: &str) -> Result<Uuid, AppError> {
+        if let Some(id) = ARTIST_CACHE.get(name) {
             return Ok(*id);
         }
 
@@ -499,27 +1445,34 @@ impl Scanner {
             AppError::Database(e)
         })?;
 
-        self.artist_cache.insert(name.to_string(), res.id);
+        ARTIST_CACHE.insert(name.to_string(), res.id);
         Ok(res.id)
     }
 
-    async fn get_or_create_album(
+    pub async fn get_or_create_album(
         &self,
         title: &str,
         artist_id: Uuid,
         year: Option<u32>,
+        release_date: Option<chrono::NaiveDate>,
     ) -> Result<Uuid, AppError> {
         let cache_key = (title.to_string(), artist_id);
-        if let Some(id) = self.album_cache.get(&cache_key) {
+        if let Some(id) = ALBUM_CACHE.get(&cache_key) {
             return Ok(*id);
         }
 
+        // 只有完整日期没有年份标签时，由日期推出年份
+        let year = year
+            .map(|y| y as i32)
+            .or_else(|| release_date.map(|d| chrono::Datelike::year(&d)));
         let res = sqlx::query!(
-            "INSERT INTO albums (title, artist_id, release_year)
-             VALUES ($1, $2, $3)
-             ON CONFLICT (title, artist_id) DO UPDATE SET release_year = COALESCE(albums.release_year, EXCLUDED.release_year)
+            "INSERT INTO albums (title, artist_id, release_year, release_date)
+             VALUES ($1, $2, $3, $4)
+             ON CONFLICT (title, artist_id) DO UPDATE SET
+                release_year = COALESCE(albums.release_year, EXCLUDED.release_year),
+                release_date = COALESCE(albums.release_date, EXCLUDED.release_date)
              RETURNING id",
-            title, artist_id, year.map(|y| y as i32)
+            title, artist_id, year, release_date
         )
         .fetch_one(&self.db).await
         .map_err(|e| {
@@ -527,7 +1480,7 @@ impl Scanner {
             AppError::Database(e)
         })?;
 
-        self.album_cache.insert(cache_key, res.id);
+        ALBUM_CACHE.insert(cache_key, res.id);
         Ok(res.id)
     }
 
@@ -685,6 +1638,24 @@ impl Scanner {
         Ok(())
     }
 
+    /// 读取外部 .lrc 歌词，编码由 [`decode_lrc_bytes`] 判断
+    async fn load_lrc_lyrics(&self, audio_path: &Path) -> Option<String> {
+        let lrc_path = self.find_lrc_file(audio_path).await?;
+        let bytes = tokio::fs::read(&lrc_path).await.ok()?;
+
+        let (final_content, encoding_used) = decode_lrc_bytes(&bytes);
+        let final_content = final_content.replace('\0', "");
+        if final_content.trim().is_empty() {
+            return None;
+        }
+        tracing::info!(
+            "Loaded LRC file: {} (Encoding: {})",
+            lrc_path.display(),
+            encoding_used.name()
+        );
+        Some(final_content)
+    }
+
     async fn find_lrc_file(&self, audio_path: &Path) -> Option<PathBuf> {
         // 1. 同目录下同名文件 (最快路径)
         let same_dir_lrc = audio_path.with_extension("lrc");
@@ -723,6 +1694,81 @@ impl Scanner {
     }
 }
 
+/// 手动修正的元数据，`None` 的字段保持不变；`genre` 为空字符串时清除流派
+#[derive(Debug, Default, Clone)]
+pub struct TagEdits {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<u32>,
+    pub genre: Option<String>,
+}
+
+/// 把修正后的元数据写回音频文件的主标签，文件没有标签时按格式新建。
+/// 同步 IO，调用方应放在 `spawn_blocking` 中执行
+pub fn write_tags(path: &Path, edits: &TagEdits) -> Result<(), AppError> {
+    let mut tagged_file = Probe::open(path)
+        .and_then(|p| p.read())
+        .map_err(|e| AppError::Metadata(format!("Failed to read tags from {}: {}", path.display(), e)))?;
+
+    if tagged_file.primary_tag().is_none() {
+        let tag_type = tagged_file.primary_tag_type();
+        tagged_file.insert_tag(lofty::tag::Tag::new(tag_type));
+    }
+    let tag = tagged_file
+        .primary_tag_mut()
+        .ok_or_else(|| AppError::Metadata(format!("No writable tag for {}", path.display())))?;
+
+    if let Some(title) = &edits.title {
+        tag.set_title(title.clone());
+    }
+    if let Some(artist) = &edits.artist {
+        tag.set_artist(artist.clone());
+    }
+    if let Some(album) = &edits.album {
+        tag.set_album(album.clone());
+    }
+    if let Some(number) = edits.track_number {
+        tag.set_track(number);
+    }
+    match edits.genre.as_deref() {
+        Some("") => tag.remove_genre(),
+        Some(genre) => tag.set_genre(genre.to_string()),
+        None => {}
+    }
+
+    tag.save_to_path(path, lofty::config::WriteOptions::default())
+        .map_err(|e| AppError::Metadata(format!("Failed to write tags to {}: {}", path.display(), e)))
+}
+
+/// 歌词文件编码检测：合法 UTF-8 直接使用，否则交给 chardetng 判断；
+/// 判断把握不大或解码出错时再按 GBK -> Big5 依次尝试 (国内老资源最常见)
+pub(crate) fn decode_lrc_bytes(bytes: &[u8]) -> (String, &'static encoding_rs::Encoding) {
+    let (content, encoding, has_errors) = encoding_rs::UTF_8.decode(bytes);
+    if !has_errors {
+        return (content.into_owned(), encoding);
+    }
+
+    let mut detector = chardetng::EncodingDetector::new();
+    detector.feed(bytes, true);
+    let (guessed, confident) = detector.guess_assess(None, false);
+    if confident {
+        let (content, _, has_errors) = guessed.decode(bytes);
+        if !has_errors {
+            return (content.into_owned(), guessed);
+        }
+    }
+
+    for fallback in [encoding_rs::GBK, encoding_rs::BIG5] {
+        let (content, _, has_errors) = fallback.decode(bytes);
+        if !has_errors {
+            return (content.into_owned(), fallback);
+        }
+    }
+    let (content, _, _) = guessed.decode(bytes);
+    (content.into_owned(), guessed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -740,6 +1786,158 @@ mod tests {
         assert!(!scanner.is_audio_file(Path::new("test.exe")));
     }
 
+    async fn insert_track(db: &PgPool, title: &str) -> Uuid {
+        sqlx::query_scalar("INSERT INTO tracks (title, duration, path) VALUES ($1, 200, $2) RETURNING id")
+            .bind(title)
+            .bind(format!("/music/{}/{}.flac", Uuid::new_v4(), title))
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    async fn mbid_of(db: &PgPool, track_id: Uuid) -> Option<Uuid> {
+        sqlx::query_scalar("SELECT musicbrainz_track_id FROM tracks WHERE id = $1")
+            .bind(track_id)
+            .fetch_one(db)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test(migrations = "./migrations")]
+    async fn test_store_recording_id_skips_taken_mbid(db: PgPool) {
+        let scanner = Scanner::new(db.clone());
+        let flac = insert_track(&db, "Song").await;
+        let mp3 = insert_track(&db, "Song").await;
+        let mbid = Uuid::new_v4();
+
+        assert!(scanner.store_recording_id(flac, mbid).await.unwrap());
+        // 同一录音的第二个文件：跳过而不是违反唯一约束
+        assert!(!scanner.store_recording_id(mp3, mbid).await.unwrap());
+        assert_eq!(mbid_of(&db, flac).await, Some(mbid));
+        assert_eq!(mbid_of(&db, mp3).await, None);
+
+        // 已有 MBID 的曲目不被覆盖，重复写入自身也不报错
+        assert!(!scanner.store_recording_id(flac, Uuid::new_v4()).await.unwrap());
+        assert!(!scanner.store_recording_id(flac, mbid).await.unwrap());
+        assert_eq!(mbid_of(&db, flac).await, Some(mbid));
+    }
+
+    #[test]
+    fn test_parse_replay_gain() {
+        assert_eq!(parse_gain_db("-6.54 dB"), Some(-6.54));
+        assert_eq!(parse_gain_db("+1.20dB"), Some(1.2));
+        assert_eq!(parse_gain_db("loud"), None);
+        // -1280 / 256 = -5 dB (相对 -23 LUFS)，换算后为 0 dB
+        assert_eq!(parse_r128_gain("-1280"), Some(0.0));
+        assert_eq!(parse_r128_gain("abc"), None);
+    }
+
+    #[tokio::test]
+    async fn test_is_audio_file_extended_formats() {
+        let db = PgPool::connect_lazy("postgres://localhost/dummy").unwrap();
+        let scanner = Scanner::new(db);
+
+        for ext in ["opus", "aac", "aif", "aiff", "wv", "ape", "AIFF", "Opus"] {
+            let name = format!("test.{}", ext);
+            assert!(scanner.is_audio_file(Path::new(&name)), "{} should be audio", name);
+        }
+        assert!(!scanner.is_audio_file(Path::new("album.cue")));
+        assert!(!scanner.is_audio_file(Path::new("cover.jpg")));
+        assert!(!scanner.is_audio_file(Path::new("noextension")));
+    }
+
+    #[tokio::test]
+    async fn test_lyrics_initial_state() {
+        let db = PgPool::connect_lazy("postgres://localhost/dummy").unwrap();
+        let scanner = Scanner::new(db);
+        let dir = std::env::temp_dir().join(format!("papilio-lrc-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // 没有任何歌词：不进入对齐队列
+        let audio = dir.join("song.flac");
+        std::fs::write(&audio, b"").unwrap();
+        let lyrics = scanner.load_lrc_lyrics(&audio).await;
+        assert!(lyrics.is_none());
+        assert_eq!(initial_sync_status(lyrics.as_deref()), "none");
+
+        // 同名外部 .lrc：读取后进入 pending
+        std::fs::write(dir.join("song.lrc"), "[00:01.00]Hello\n").unwrap();
+        let lyrics = scanner.load_lrc_lyrics(&audio).await;
+        assert_eq!(lyrics.as_deref(), Some("[00:01.00]Hello\n"));
+        assert_eq!(initial_sync_status(lyrics.as_deref()), "pending");
+
+        // 空白歌词视同没有
+        assert_eq!(initial_sync_status(Some("  \n")), "none");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_empty_library_guard() {
+        assert!(library_looks_unmounted(0, 5000, 0.1));
+        assert!(library_looks_unmounted(40, 500, 0.1));
+        assert!(!library_looks_unmounted(60, 500, 0.1));
+        // 新曲库或很小的曲库不拦截
+        assert!(!library_looks_unmounted(0, 0, 0.1));
+        assert!(!library_looks_unmounted(0, 9, 0.1));
+        // 比例设为 0 时关闭保护
+        assert!(!library_looks_unmounted(0, 5000, 0.0));
+    }
+
+    #[test]
+    fn test_mount_down_heuristic() {
+        assert!(looks_like_mount_down(1000, 1000, 25.0));
+        assert!(looks_like_mount_down(300, 1000, 25.0));
+        assert!(!looks_like_mount_down(250, 1000, 25.0));
+        // 小曲库删掉几首不应被当成挂载失败
+        assert!(!looks_like_mount_down(3, 5, 25.0));
+        assert!(!looks_like_mount_down(0, 0, 25.0));
+    }
+
+    #[test]
+    fn test_compilation_detection() {
+        assert!(is_compilation_flag("1"));
+        assert!(is_compilation_flag(" TRUE "));
+        assert!(!is_compilation_flag("0"));
+        assert!(!is_compilation_flag(""));
+
+        assert!(is_various_artists("Various Artists"));
+        assert!(is_various_artists("VA"));
+        assert!(is_various_artists("群星"));
+        assert!(!is_various_artists("Vanessa Paradis"));
+    }
+
+    #[test]
+    fn test_parse_release_date() {
+        let date = chrono::NaiveDate::from_ymd_opt(2001, 5, 14);
+        assert_eq!(parse_release_date("2001-05-14"), date);
+        assert_eq!(parse_release_date(" 2001-05-14T00:00:00 "), date);
+        // 只有年份或年月时不构成完整日期
+        assert_eq!(parse_release_date("2001"), None);
+        assert_eq!(parse_release_date("2001-05"), None);
+        assert_eq!(parse_release_date("2001-13-40"), None);
+    }
+
+    #[test]
+    fn test_decode_lrc_shift_jis() {
+        let bytes = include_bytes!("../../tests/fixtures/shift_jis.lrc");
+        let (content, encoding) = decode_lrc_bytes(bytes);
+        assert_eq!(encoding, encoding_rs::SHIFT_JIS);
+        assert!(content.contains("[ti:春の歌]"));
+        assert!(content.contains("[00:01.00]さくらの花びらが風に舞う"));
+    }
+
+    #[test]
+    fn test_decode_lrc_keeps_utf8_and_gbk() {
+        let (content, encoding) = decode_lrc_bytes("[00:00.00]汪苏泷 - 万有引力".as_bytes());
+        assert_eq!(encoding, encoding_rs::UTF_8);
+        assert!(content.contains("万有引力"));
+
+        let (gbk, _, _) = encoding_rs::GBK.encode("[00:00.00]汪苏泷 - 万有引力\n[00:05.00]我们的爱情");
+        let (content, _) = decode_lrc_bytes(&gbk);
+        assert!(content.contains("汪苏泷 - 万有引力"));
+    }
+
     #[tokio::test]
     async fn test_scan_lock() {
         let db = PgPool::connect_lazy("postgres://localhost/dummy").unwrap();
@@ -750,7 +1948,7 @@ mod tests {
         
         // 尝试触发扫描应该失败
         assert!(scanner.is_scanning());
-        let result = scanner.scan_directory("/tmp").await;
+        let result = scanner.scan_directory("/tmp", false).await;
         assert!(matches!(result, Err(AppError::BadRequest(_))));
         
         drop(lock);
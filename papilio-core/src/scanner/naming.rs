@@ -0,0 +1,285 @@
+//! 曲库整理的目录命名模板，如 `{AlbumArtist}/{Year} - {Album}/{Track:02} - {Title}`。
+//! 模板保存在 `system_config.organize_pattern`，未设置时沿用 `歌手/专辑/标题`。
+
+use sqlx::{PgPool, Row};
+use std::path::PathBuf;
+
+/// 未配置模板时的默认方案
+pub const DEFAULT_PATTERN: &str = "{Artist}/{Album}/{Title}";
+
+/// 可用于模板的标签字段
+#[derive(Debug, Default, Clone)]
+pub struct NamingFields {
+    pub artist: Option<String>,
+    pub album_artist: Option<String>,
+    pub album: Option<String>,
+    pub title: Option<String>,
+    pub year: Option<u32>,
+    pub track: Option<u32>,
+    pub disc: Option<u32>,
+    pub genre: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Artist,
+    AlbumArtist,
+    Album,
+    Title,
+    Year,
+    Track,
+    Disc,
+    Genre,
+}
+
+impl Field {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "artist" => Self::Artist,
+            "albumartist" => Self::AlbumArtist,
+            "album" => Self::Album,
+            "title" => Self::Title,
+            "year" => Self::Year,
+            "track" => Self::Track,
+            "disc" => Self::Disc,
+            "genre" => Self::Genre,
+            _ => return None,
+        })
+    }
+
+    /// 缺少必需字段的文件归入 `Unsorted`；其余字段缺失时渲染为空
+    fn required(self) -> bool {
+        matches!(self, Self::Artist | Self::AlbumArtist | Self::Album | Self::Title)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Piece {
+    Literal(String),
+    Token { field: Field, width: usize },
+}
+
+/// 解析后的命名模板，`/` 分隔目录层级，最后一段为文件名 (不含扩展名)
+#[derive(Debug, Clone)]
+pub struct NamingPattern {
+    segments: Vec<Vec<Piece>>,
+}
+
+impl Default for NamingPattern {
+    fn default() -> Self {
+        Self::parse(DEFAULT_PATTERN).expect("default naming pattern is valid")
+    }
+}
+
+impl NamingPattern {
+    /// 支持的占位符：`{Artist}` `{AlbumArtist}` `{Album}` `{Title}` `{Year}` `{Track}` `{Disc}` `{Genre}`，
+    /// 数字字段可写作 `{Track:02}` 补零
+    pub fn parse(pattern: &str) -> Result<Self, String> {
+        let pattern = pattern.trim().trim_matches('/');
+        if pattern.is_empty() {
+            return Err("Naming pattern is empty".to_string());
+        }
+
+        let mut segments = Vec::new();
+        for raw in pattern.split('/') {
+            let mut pieces = Vec::new();
+            let mut literal = String::new();
+            let mut chars = raw.chars();
+            while let Some(c) = chars.next() {
+                if c != '{' {
+                    if c == '}' {
+                        return Err(format!("Unmatched '}}' in segment '{}'", raw));
+                    }
+                    literal.push(c);
+                    continue;
+                }
+                let mut token = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => token.push(c),
+                        None => return Err(format!("Unclosed '{{' in segment '{}'", raw)),
+                    }
+                }
+                let (name, spec) = token.split_once(':').unwrap_or((token.as_str(), ""));
+                let field = Field::parse(name.trim())
+                    .ok_or_else(|| format!("Unknown field '{{{}}}'", token))?;
+                let width = if spec.is_empty() {
+                    0
+                } else {
+                    if !matches!(field, Field::Year | Field::Track | Field::Disc) {
+                        return Err(format!("Padding is only supported for numbers: '{{{}}}'", token));
+                    }
+                    spec.parse::<usize>()
+                        .ok()
+                        .filter(|w| *w <= 6)
+                        .ok_or_else(|| format!("Invalid padding '{}'", spec))?
+                };
+                if !literal.is_empty() {
+                    pieces.push(Piece::Literal(std::mem::take(&mut literal)));
+                }
+                pieces.push(Piece::Token { field, width });
+            }
+            if !literal.is_empty() {
+                pieces.push(Piece::Literal(literal));
+            }
+            if pieces.is_empty() {
+                return Err("Naming pattern contains an empty path segment".to_string());
+            }
+            segments.push(pieces);
+        }
+
+        let has_title = segments
+            .last()
+            .is_some_and(|s| s.iter().any(|p| matches!(p, Piece::Token { .. })));
+        if !has_title {
+            return Err("The file name segment must contain at least one field".to_string());
+        }
+        Ok(Self { segments })
+    }
+
+    /// 相对曲库根目录的路径 (不含扩展名)。缺少必需字段或某一层渲染为空时返回 None
+    pub fn render(&self, fields: &NamingFields) -> Option<PathBuf> {
+        let mut path = PathBuf::new();
+        for segment in &self.segments {
+            let mut rendered = String::new();
+            for piece in segment {
+                match piece {
+                    Piece::Literal(text) => rendered.push_str(text),
+                    Piece::Token { field, width } => {
+                        let value = field_value(fields, *field, *width);
+                        match value {
+                            Some(v) => rendered.push_str(&v),
+                            None if field.required() => return None,
+                            None => {}
+                        }
+                    }
+                }
+            }
+            // 可选字段缺失时去掉残留的分隔符，如 " - Album" -> "Album"
+            let trimmed = rendered.trim_matches(|c: char| c.is_whitespace() || "-_.".contains(c));
+            let safe = sanitize_filename::sanitize(trimmed);
+            if safe.trim().is_empty() {
+                return None;
+            }
+            path.push(safe);
+        }
+        Some(path)
+    }
+}
+
+fn field_value(fields: &NamingFields, field: Field, width: usize) -> Option<String> {
+    let text = |v: &Option<String>| v.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+    let number = |v: Option<u32>| v.filter(|n| *n > 0).map(|n| format!("{:0width$}", n, width = width));
+    match field {
+        Field::Artist => text(&fields.artist),
+        // 合辑通常只有专辑歌手一致，缺失时退回曲目歌手
+        Field::AlbumArtist => text(&fields.album_artist).or_else(|| text(&fields.artist)),
+        Field::Album => text(&fields.album),
+        Field::Title => text(&fields.title),
+        Field::Year => number(fields.year),
+        Field::Track => number(fields.track),
+        Field::Disc => number(fields.disc),
+        Field::Genre => text(&fields.genre),
+    }
+}
+
+/// 当前生效的命名模板：读取 `system_config.organize_pattern`，未设置或无效时使用默认方案
+pub async fn current_pattern(db: &PgPool) -> NamingPattern {
+    let configured: Option<String> = sqlx::query("SELECT value #>> '{}' AS value FROM system_config WHERE key = 'organize_pattern'")
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|row| row.get::<Option<String>, _>("value"))
+        .filter(|v| !v.trim().is_empty());
+
+    match configured {
+        Some(raw) => NamingPattern::parse(&raw).unwrap_or_else(|e| {
+            tracing::warn!("Invalid organize_pattern '{}' ({}), using default", raw, e);
+            NamingPattern::default()
+        }),
+        None => NamingPattern::default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fields() -> NamingFields {
+        NamingFields {
+            artist: Some("Guest Singer".to_string()),
+            album_artist: Some("Various Artists".to_string()),
+            album: Some("Summer Hits".to_string()),
+            title: Some("Sunny Day".to_string()),
+            year: Some(2019),
+            track: Some(3),
+            disc: Some(1),
+            genre: Some("Pop".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_default_pattern_matches_legacy_layout() {
+        let path = NamingPattern::default().render(&fields()).unwrap();
+        assert_eq!(path, PathBuf::from("Guest Singer/Summer Hits/Sunny Day"));
+    }
+
+    #[test]
+    fn test_render_with_year_and_padded_track() {
+        let pattern = NamingPattern::parse("{AlbumArtist}/{Year} - {Album}/{Track:02} - {Title}").unwrap();
+        assert_eq!(
+            pattern.render(&fields()).unwrap(),
+            PathBuf::from("Various Artists/2019 - Summer Hits/03 - Sunny Day")
+        );
+    }
+
+    #[test]
+    fn test_album_artist_falls_back_to_track_artist() {
+        let pattern = NamingPattern::parse("{AlbumArtist}/{Album}/{Title}").unwrap();
+        let mut f = fields();
+        f.album_artist = None;
+        assert_eq!(pattern.render(&f).unwrap(), PathBuf::from("Guest Singer/Summer Hits/Sunny Day"));
+    }
+
+    #[test]
+    fn test_missing_optional_fields_drop_separators() {
+        let pattern = NamingPattern::parse("{Artist}/{Year} - {Album}/{Disc}-{Track:02} {Title}").unwrap();
+        let mut f = fields();
+        f.year = None;
+        f.disc = None;
+        f.track = None;
+        assert_eq!(pattern.render(&f).unwrap(), PathBuf::from("Guest Singer/Summer Hits/Sunny Day"));
+    }
+
+    #[test]
+    fn test_missing_required_field_goes_unsorted() {
+        let pattern = NamingPattern::default();
+        let mut f = fields();
+        f.album = None;
+        assert!(pattern.render(&f).is_none());
+        f = fields();
+        f.title = Some("   ".to_string());
+        assert!(pattern.render(&f).is_none());
+    }
+
+    #[test]
+    fn test_values_are_sanitized() {
+        let mut f = fields();
+        f.title = Some("AC/DC: Live?".to_string());
+        let path = NamingPattern::default().render(&f).unwrap();
+        assert_eq!(path.components().count(), 3);
+        assert!(!path.file_name().unwrap().to_string_lossy().contains('/'));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_patterns() {
+        assert!(NamingPattern::parse("").is_err());
+        assert!(NamingPattern::parse("{Artist}/{Nope}").is_err());
+        assert!(NamingPattern::parse("{Artist}//{Title}").is_err());
+        assert!(NamingPattern::parse("{Artist}/{Title:02}").is_err());
+        assert!(NamingPattern::parse("{Artist}/static").is_err());
+        assert!(NamingPattern::parse("{Artist}/{Title").is_err());
+    }
+}
@@ -1,49 +1,159 @@
 use crate::error::AppError;
 use lofty::{prelude::*, probe::Probe};
+use serde::Serialize;
 use sqlx::PgPool;
 use std::path::{Path, PathBuf};
 use tokio::fs;
 use uuid::Uuid;
 use walkdir::WalkDir;
-use super::SCAN_LOCK;
+use super::naming::{self, NamingFields, NamingPattern};
+use super::{ProgressDoneGuard, ScanOperation, ScanProgress, ScanProgressSender, SCAN_LOCK};
+
+/// 单次整理报告最多保留的明细条数，超出部分只计数不落库
+const MAX_REPORT_ENTRIES: usize = 500;
+/// 数据库中保留的历史报告份数
+const MAX_STORED_REPORTS: i64 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OrganizeOutcome {
+    Moved,
+    Skipped,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OrganizeEntry {
+    pub source: String,
+    pub destination: Option<String>,
+    pub outcome: OrganizeOutcome,
+    pub reason: Option<String>,
+}
+
+impl OrganizeEntry {
+    fn moved(src: &Path, dest: &Path) -> Self {
+        Self {
+            source: src.display().to_string(),
+            destination: Some(dest.display().to_string()),
+            outcome: OrganizeOutcome::Moved,
+            reason: None,
+        }
+    }
+
+    fn skipped(src: &Path, dest: &Path, reason: &str) -> Self {
+        Self {
+            source: src.display().to_string(),
+            destination: Some(dest.display().to_string()),
+            outcome: OrganizeOutcome::Skipped,
+            reason: Some(reason.to_string()),
+        }
+    }
+
+    fn failed(src: &Path, error: &AppError) -> Self {
+        Self {
+            source: src.display().to_string(),
+            destination: None,
+            outcome: OrganizeOutcome::Failed,
+            reason: Some(error.to_string()),
+        }
+    }
+}
+
+/// 一次整理任务的汇总：计数覆盖全部文件，明细受 `MAX_REPORT_ENTRIES` 限制
+#[derive(Debug, Default, Clone, Serialize)]
+pub struct OrganizeReport {
+    pub moved: i32,
+    pub skipped: i32,
+    pub failed: i32,
+    pub truncated: bool,
+    pub entries: Vec<OrganizeEntry>,
+}
+
+impl OrganizeReport {
+    pub fn record(&mut self, entry: OrganizeEntry) {
+        match entry.outcome {
+            OrganizeOutcome::Moved => self.moved += 1,
+            OrganizeOutcome::Skipped => self.skipped += 1,
+            OrganizeOutcome::Failed => self.failed += 1,
+        }
+        if self.entries.len() < MAX_REPORT_ENTRIES {
+            self.entries.push(entry);
+        } else {
+            self.truncated = true;
+        }
+    }
+}
 
 pub struct Organizer {
     db: PgPool,
     music_root: PathBuf,
+    progress_tx: Option<ScanProgressSender>,
 }
 
 impl Organizer {
     pub fn new(db: PgPool, music_root: PathBuf) -> Self {
-        Self { db, music_root }
+        Self {
+            db,
+            music_root,
+            progress_tx: None,
+        }
+    }
+
+    pub fn with_progress_channel(mut self, tx: ScanProgressSender) -> Self {
+        self.progress_tx = Some(tx);
+        self
+    }
+
+    fn publish_progress(&self, update: impl FnOnce(&mut ScanProgress)) {
+        if let Some(tx) = &self.progress_tx {
+            tx.send_modify(update);
+        }
     }
 
-    pub async fn organize(&self) -> Result<(), AppError> {
+    pub async fn organize(&self) -> Result<OrganizeReport, AppError> {
         let _lock = SCAN_LOCK.try_lock().map_err(|_| {
             AppError::BadRequest("A scan or reorganization is already in progress".to_string())
         })?;
+        let _done = ProgressDoneGuard(self.progress_tx.clone());
 
         tracing::info!("Starting library reorganization...");
+        reconcile_pending_moves(&self.db).await?;
 
         // 1. 递归扫描曲库
-        let entries: Vec<_> = WalkDir::new(&self.music_root)
-            .into_iter()
-            .filter_map(|e| e.ok())
-            .filter(|e| e.file_type().is_file() && self.is_audio_file(e.path()))
-            .collect();
+        let entries = self.collect_audio_files();
 
         let total = entries.len() as i32;
-        // 移除对 scan_status 的更新，或者使用专门的字段（如果以后支持）
-        // 目前为了不让前端误解为扫描，我们只记录日志，或者可以增加一个专用状态。
-        // sqlx::query("UPDATE scan_status SET is_scanning = TRUE, current_count = 0, total_count = $1 WHERE id = 1")
+        // operation 标记为 organizing，前端据此区分"扫描"与"整理"
+        sqlx::query("UPDATE scan_status SET is_scanning = TRUE, operation = $2, current_count = 0, total_count = $1 WHERE id = 1")
+            .bind(total)
+            .bind(ScanOperation::Organizing.as_str())
+            .execute(&self.db)
+            .await?;
 
+        self.publish_progress(|p| {
+            *p = ScanProgress {
+                is_scanning: true,
+                operation: ScanOperation::Organizing,
+                current_count: 0,
+                total_count: total,
+            }
+        });
+
+        let pattern = naming::current_pattern(&self.db).await;
+
+        let mut report = OrganizeReport::default();
         let mut current = 0;
         for entry in entries {
-            if let Err(e) = self.process_organize_file(entry.path()).await {
-                tracing::error!(
-                    "Failed to organize file {}: {:?}",
-                    entry.path().display(),
-                    e
-                );
+            match self.process_organize_file(entry.path(), &pattern).await {
+                Ok(result) => report.record(result),
+                Err(e) => {
+                    tracing::error!(
+                        "Failed to organize file {}: {:?}",
+                        entry.path().display(),
+                        e
+                    );
+                    report.record(OrganizeEntry::failed(entry.path(), &e));
+                }
             }
             current += 1;
             // 逐个更新进度，确保安卓端能即时看到进度条
@@ -53,8 +163,18 @@ impl Organizer {
             .bind(current)
             .execute(&self.db)
             .await;
+            self.publish_progress(|p| p.current_count = current);
         }
 
+        // 文件迁移阶段结束即落盘报告，确保后续资产整理失败时依然可审计
+        tracing::info!(
+            "Organize summary: {} moved, {} skipped, {} failed",
+            report.moved,
+            report.skipped,
+            report.failed
+        );
+        self.save_report(&report).await?;
+
         // 2. 整理关联图片资产 (歌手/专辑图片)
         self.organize_assets().await?;
 
@@ -62,12 +182,70 @@ impl Organizer {
         self.cleanup_root_lrc_files().await?;
 
         sqlx::query(
-            "UPDATE scan_status SET is_scanning = FALSE, last_scan_at = NOW() WHERE id = 1",
+            "UPDATE scan_status SET is_scanning = FALSE, operation = 'idle', last_scan_at = NOW() WHERE id = 1",
         )
         .execute(&self.db)
         .await?;
 
         tracing::info!("Library reorganization completed.");
+        Ok(report)
+    }
+
+    /// 只计算每个文件的目标路径，不移动文件、不改数据库也不保存报告，供正式整理前预览
+    pub async fn preview(&self) -> Result<OrganizeReport, AppError> {
+        let pattern = naming::current_pattern(&self.db).await;
+
+        let mut report = OrganizeReport::default();
+        for entry in self.collect_audio_files() {
+            let path = entry.path();
+            match self.plan_destination(path, &pattern) {
+                Ok(dest_path) if dest_path == path => {
+                    report.record(OrganizeEntry::skipped(path, &dest_path, "Already in place"))
+                }
+                Ok(dest_path) if dest_path.exists() => report.record(OrganizeEntry::skipped(
+                    path,
+                    &dest_path,
+                    "Destination already exists",
+                )),
+                Ok(dest_path) => report.record(OrganizeEntry::moved(path, &dest_path)),
+                Err(e) => report.record(OrganizeEntry::failed(path, &e)),
+            }
+        }
+        Ok(report)
+    }
+
+    fn collect_audio_files(&self) -> Vec<walkdir::DirEntry> {
+        WalkDir::new(&self.music_root)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_file() && self.is_audio_file(e.path()))
+            .collect()
+    }
+
+    async fn save_report(&self, report: &OrganizeReport) -> Result<(), AppError> {
+        let entries = serde_json::to_value(&report.entries)
+            .map_err(|e| AppError::Internal(format!("Failed to serialize organize report: {}", e)))?;
+
+        sqlx::query(
+            "INSERT INTO organize_reports (moved_count, skipped_count, failed_count, truncated, entries)
+             VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(report.moved)
+        .bind(report.skipped)
+        .bind(report.failed)
+        .bind(report.truncated)
+        .bind(entries)
+        .execute(&self.db)
+        .await?;
+
+        // 只保留最近几份报告，避免表无限增长
+        sqlx::query(
+            "DELETE FROM organize_reports WHERE id NOT IN (SELECT id FROM organize_reports ORDER BY id DESC LIMIT $1)",
+        )
+        .bind(MAX_STORED_REPORTS)
+        .execute(&self.db)
+        .await?;
+
         Ok(())
     }
 
@@ -127,7 +305,82 @@ impl Organizer {
         Ok(())
     }
 
-    async fn process_organize_file(&self, path: &Path) -> Result<(), AppError> {
+    async fn process_organize_file(
+        &self,
+        path: &Path,
+        pattern: &NamingPattern,
+    ) -> Result<OrganizeEntry, AppError> {
+        let dest_path = self.plan_destination(path, pattern)?;
+
+        if path == dest_path {
+            self.move_associated_files(path, &dest_path).await?;
+            return Ok(OrganizeEntry::skipped(path, &dest_path, "Already in place"));
+        }
+
+        if dest_path.exists() {
+            return Ok(OrganizeEntry::skipped(path, &dest_path, "Destination already exists"));
+        }
+
+        // 目标目录 (包括 Unsorted) 按需创建
+        if let Some(parent) = dest_path.parent() {
+            fs::create_dir_all(parent).await?;
+        }
+
+        let old_path_str = path.to_str().ok_or_else(|| AppError::Internal("Invalid path encoding".into()))?;
+        let new_path_str = dest_path.to_str().ok_or_else(|| AppError::Internal("Invalid path encoding".into()))?;
+
+        // 跨盘移动是整份拷贝，不能在此期间占用事务。先落一条意图记录再移动文件，
+        // 之后在同一事务里改写路径并删除记录；中途崩溃由 reconcile_pending_moves 对账
+        sqlx::query(
+            "INSERT INTO organize_pending_moves (source_path, dest_path) VALUES ($1, $2)
+             ON CONFLICT (source_path) DO UPDATE SET dest_path = EXCLUDED.dest_path, created_at = NOW()",
+        )
+        .bind(old_path_str)
+        .bind(new_path_str)
+        .execute(&self.db)
+        .await?;
+
+        if let Err(e) = self.robust_move(path, &dest_path).await {
+            let _ = forget_pending_move(&self.db, old_path_str).await;
+            return Err(e);
+        }
+
+        if let Err(e) = self.commit_move(old_path_str, new_path_str).await {
+            tracing::error!(
+                "Failed to update path for {}, moving file back: {:?}",
+                path.display(),
+                e
+            );
+            self.robust_move(&dest_path, path).await?;
+            let _ = forget_pending_move(&self.db, old_path_str).await;
+            return Err(e);
+        }
+
+        self.move_associated_files(path, &dest_path).await?;
+
+        Ok(OrganizeEntry::moved(path, &dest_path))
+    }
+
+    /// 改写音轨路径并清除对应的意图记录，两者同时生效
+    async fn commit_move(&self, old_path: &str, new_path: &str) -> Result<(), AppError> {
+        let mut tx = self.db.begin().await?;
+        sqlx::query!(
+            "UPDATE tracks SET path = $1, updated_at = NOW() WHERE path = $2",
+            new_path,
+            old_path
+        )
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query("DELETE FROM organize_pending_moves WHERE source_path = $1")
+            .bind(old_path)
+            .execute(&mut *tx)
+            .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// 按命名模板与文件标签计算目标路径，模板缺字段时归入 Unsorted。只读，不创建目录
+    fn plan_destination(&self, path: &Path, pattern: &NamingPattern) -> Result<PathBuf, AppError> {
         let tagged_file = Probe::open(path)
             .map_err(|e| AppError::Metadata(format!("Failed to open {}: {}", path.display(), e)))?
             .read()
@@ -139,68 +392,51 @@ impl Organizer {
                 ))
             })?;
 
-        let mut artist = None;
-        let mut album = None;
-        let mut title = None;
-
+        let mut fields = NamingFields::default();
         for tag in tagged_file.tags() {
-            if artist.is_none() {
-                artist = tag.artist().map(|s| s.to_string());
+            if fields.artist.is_none() {
+                fields.artist = tag.artist().map(|s| s.to_string());
+            }
+            if fields.album_artist.is_none() {
+                fields.album_artist = tag
+                    .get_string(&lofty::tag::ItemKey::AlbumArtist)
+                    .map(|s| s.to_string());
+            }
+            if fields.album.is_none() {
+                fields.album = tag.album().map(|s| s.to_string());
+            }
+            if fields.title.is_none() {
+                fields.title = tag.title().map(|s| s.to_string());
+            }
+            if fields.year.is_none() {
+                fields.year = tag.year();
+            }
+            if fields.track.is_none() {
+                fields.track = tag.track();
             }
-            if album.is_none() {
-                album = tag.album().map(|s| s.to_string());
+            if fields.disc.is_none() {
+                fields.disc = tag.disk();
             }
-            if title.is_none() {
-                title = tag.title().map(|s| s.to_string());
+            if fields.genre.is_none() {
+                fields.genre = tag.genre().map(|s| s.to_string());
             }
         }
 
         let extension = path.extension().and_then(|s| s.to_str()).unwrap_or("flac");
 
-        let dest_path = if let (Some(art), Some(alb), Some(tit)) = (artist, album, title) {
-            let safe_art = sanitize_filename::sanitize(&art);
-            let safe_alb = sanitize_filename::sanitize(&alb);
-            let safe_tit = sanitize_filename::sanitize(&tit);
-            self.music_root
-                .join(safe_art)
-                .join(safe_alb)
-                .join(format!("{}.{}", safe_tit, extension))
+        // 标题中可能带 "."，不能用 with_extension 替换扩展名
+        let dest_path = if let Some(relative) = pattern.render(&fields) {
+            let file_name = relative
+                .file_name()
+                .map(|n| format!("{}.{}", n.to_string_lossy(), extension))
+                .ok_or_else(|| AppError::Internal("Invalid filename".into()))?;
+            self.music_root.join(relative.with_file_name(file_name))
         } else {
-            let unsorted_dir = self.music_root.join("Unsorted");
-            if !unsorted_dir.exists() {
-                fs::create_dir_all(&unsorted_dir).await?;
-            }
-            unsorted_dir.join(path.file_name().ok_or_else(|| AppError::Internal("Invalid filename".into()))?)
+            self.music_root
+                .join("Unsorted")
+                .join(path.file_name().ok_or_else(|| AppError::Internal("Invalid filename".into()))?)
         };
-
-        if path == dest_path {
-            self.move_associated_files(path, &dest_path).await?;
-            return Ok(());
-        }
-
-        if dest_path.exists() {
-            return Ok(());
-        }
-
-        if let Some(parent) = dest_path.parent() {
-            fs::create_dir_all(parent).await?;
-        }
-
-        // 使用鲁棒的移动逻辑
-        self.robust_move(path, &dest_path).await?;
-        self.move_associated_files(path, &dest_path).await?;
-
-        let old_path_str = path.to_str().ok_or_else(|| AppError::Internal("Invalid path encoding".into()))?;
-        let new_path_str = dest_path.to_str().ok_or_else(|| AppError::Internal("Invalid path encoding".into()))?;
-        sqlx::query!(
-            "UPDATE tracks SET path = $1, updated_at = NOW() WHERE path = $2",
-            new_path_str,
-            old_path_str
-        )
-        .execute(&self.db)
-        .await?;
-
-        Ok(())
+        Ok(dest_path)
     }
 
     async fn robust_move(&self, src: &Path, dest: &Path) -> Result<(), AppError> {
@@ -226,7 +462,7 @@ impl Organizer {
         let dest_parent = dest_audio.parent().ok_or_else(|| AppError::Internal("Invalid dest path".into()))?;
 
         // 1. 移动与音轨关联的特定扩展名文件
-        let extensions = ["lrc", "jpg", "png", "jpeg", "txt", "pdf"];
+        let extensions = ["lrc", "cue", "jpg", "png", "jpeg", "txt", "pdf"];
         for ext in extensions {
             let asset_src = src_audio.with_extension(ext);
             let asset_dest = dest_audio.with_extension(ext);
@@ -255,10 +491,8 @@ impl Organizer {
 
     async fn organize_assets(&self) -> Result<(), AppError> {
         let music_root = &self.music_root;
-        let internal_avatar_dir =
-            std::env::var("AVATAR_DIR").unwrap_or_else(|_| "data/avatars".to_string());
-        let internal_cover_dir =
-            std::env::var("COVER_DIR").unwrap_or_else(|_| "data/covers".to_string());
+        let internal_avatar_dir = crate::storage::avatar_dir();
+        let internal_cover_dir = crate::storage::cover_dir();
 
         tracing::info!("Starting synchronization of internal assets to music library...");
 
@@ -354,14 +588,40 @@ impl Organizer {
     }
 
     fn is_audio_file(&self, path: &Path) -> bool {
-        path.extension()
-            .and_then(|s| s.to_str())
-            .map(|s| {
-                matches!(
-                    s.to_lowercase().as_str(),
-                    "flac" | "mp3" | "m4a" | "ogg" | "wav"
-                )
-            })
-            .unwrap_or(false)
+        super::is_audio_path(path)
+    }
+}
+
+async fn forget_pending_move(db: &PgPool, source_path: &str) -> Result<(), AppError> {
+    sqlx::query("DELETE FROM organize_pending_moves WHERE source_path = $1")
+        .bind(source_path)
+        .execute(db)
+        .await?;
+    Ok(())
+}
+
+/// 处理上次整理遗留的意图记录：文件已经到了目标位置而源文件不在时补写 tracks.path，
+/// 否则移动没有完成，直接丢弃记录。服务启动与每次整理开始前调用，返回补写的文件数
+pub async fn reconcile_pending_moves(db: &PgPool) -> Result<usize, AppError> {
+    let pending: Vec<(String, String)> =
+        sqlx::query_as("SELECT source_path, dest_path FROM organize_pending_moves")
+            .fetch_all(db)
+            .await?;
+
+    let mut repaired = 0;
+    for (source, dest) in pending {
+        let moved = fs::try_exists(&dest).await.unwrap_or(false)
+            && !fs::try_exists(&source).await.unwrap_or(true);
+        if moved {
+            tracing::warn!("Repairing path of interrupted move: {} -> {}", source, dest);
+            sqlx::query("UPDATE tracks SET path = $1, updated_at = NOW() WHERE path = $2")
+                .bind(&dest)
+                .bind(&source)
+                .execute(db)
+                .await?;
+            repaired += 1;
+        }
+        forget_pending_move(db, &source).await?;
     }
+    Ok(repaired)
 }
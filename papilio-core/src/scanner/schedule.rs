@@ -0,0 +1,164 @@
+//! 定时扫描：按 SCAN_SCHEDULE (或管理后台 `scan_schedule` 配置) 周期性增量扫描曲库
+
+use super::{ScanProgressSender, Scanner};
+use crate::metadata::MetadataService;
+use chrono::{DateTime, Local, NaiveTime, TimeZone, Utc};
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// 调度器空闲时的检查间隔；配置在后台修改后最多延迟这么久生效
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// 支持两种写法：
+/// - 固定间隔：`30m`、`6h`、`1d`
+/// - 每天定点 (服务器本地时区)：`03:00` 或 `daily@03:00`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScanSchedule {
+    Every(chrono::Duration),
+    DailyAt(NaiveTime),
+}
+
+impl ScanSchedule {
+    /// 解析配置值；空串、`off`、`none` 表示关闭
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        if value.is_empty() || value == "off" || value == "none" {
+            return None;
+        }
+
+        let time = value.strip_prefix("daily@").unwrap_or(&value);
+        if let Ok(t) = NaiveTime::parse_from_str(time, "%H:%M") {
+            return Some(Self::DailyAt(t));
+        }
+
+        let (number, unit) = value.split_at(value.char_indices().last()?.0);
+        let n: i64 = number.trim().parse().ok().filter(|n| *n > 0)?;
+        let duration = match unit {
+            "m" => chrono::Duration::minutes(n),
+            "h" => chrono::Duration::hours(n),
+            "d" => chrono::Duration::days(n),
+            _ => return None,
+        };
+        Some(Self::Every(duration))
+    }
+
+    /// 上次运行于 `last` 时，下一次应运行的时间
+    pub fn next_run_after(&self, last: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::Every(interval) => last + *interval,
+            Self::DailyAt(time) => {
+                let local = last.with_timezone(&Local);
+                let mut date = local.date_naive();
+                if local.time() >= *time {
+                    date = date.succ_opt().unwrap_or(date);
+                }
+                Local
+                    .from_local_datetime(&date.and_time(*time))
+                    .earliest()
+                    .map(|dt| dt.with_timezone(&Utc))
+                    // 夏令时跳过的时刻：顺延一小时
+                    .unwrap_or_else(|| last + chrono::Duration::hours(1))
+            }
+        }
+    }
+}
+
+/// 当前生效的调度配置：后台 `system_config.scan_schedule` 优先，其次 SCAN_SCHEDULE
+pub async fn current_schedule(db: &PgPool) -> Option<ScanSchedule> {
+    let configured: Option<String> = sqlx::query("SELECT value #>> '{}' AS value FROM system_config WHERE key = 'scan_schedule'")
+        .fetch_optional(db)
+        .await
+        .ok()
+        .flatten()
+        .and_then(|row| row.get::<Option<String>, _>("value"));
+
+    configured
+        .or_else(|| std::env::var("SCAN_SCHEDULE").ok())
+        .and_then(|v| ScanSchedule::parse(&v))
+}
+
+/// 常驻任务：到点时对 `music_root` 做一次增量扫描，手动扫描进行中时跳过本次
+pub async fn run_scheduled_scans(
+    db: PgPool,
+    music_root: String,
+    progress: ScanProgressSender,
+    metadata: Arc<MetadataService>,
+) {
+    let started_at = Utc::now();
+    tracing::info!("Scan scheduler started");
+
+    loop {
+        let Some(schedule) = current_schedule(&db).await else {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            continue;
+        };
+
+        let last: Option<DateTime<Utc>> = sqlx::query("SELECT last_scheduled_scan_at FROM scan_status WHERE id = 1")
+            .fetch_optional(&db)
+            .await
+            .ok()
+            .flatten()
+            .and_then(|row| row.get("last_scheduled_scan_at"));
+        let next = schedule.next_run_after(last.unwrap_or(started_at));
+        let now = Utc::now();
+
+        if now < next {
+            let wait = (next - now).to_std().unwrap_or(POLL_INTERVAL).min(POLL_INTERVAL);
+            tokio::time::sleep(wait).await;
+            continue;
+        }
+
+        // 无论本次是否真正执行都记录时间，避免手动扫描期间每分钟重试
+        let _ = sqlx::query("UPDATE scan_status SET last_scheduled_scan_at = NOW() WHERE id = 1")
+            .execute(&db)
+            .await;
+
+        let scanner = Scanner::new(db.clone())
+            .with_progress_channel(progress.clone())
+            .with_metadata_service(metadata.clone());
+        if scanner.is_scanning() {
+            tracing::info!("Scheduled scan skipped: another scan is in progress");
+            continue;
+        }
+        tracing::info!("Scheduled scan of {} starting ({:?})", music_root, schedule);
+        if let Err(e) = scanner.scan_directory(&music_root, false).await {
+            tracing::error!("Scheduled scan failed: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_schedule() {
+        assert_eq!(ScanSchedule::parse("6h"), Some(ScanSchedule::Every(chrono::Duration::hours(6))));
+        assert_eq!(ScanSchedule::parse(" 30M "), Some(ScanSchedule::Every(chrono::Duration::minutes(30))));
+        assert_eq!(
+            ScanSchedule::parse("daily@03:30"),
+            Some(ScanSchedule::DailyAt(NaiveTime::from_hms_opt(3, 30, 0).unwrap()))
+        );
+        assert_eq!(
+            ScanSchedule::parse("23:00"),
+            Some(ScanSchedule::DailyAt(NaiveTime::from_hms_opt(23, 0, 0).unwrap()))
+        );
+        assert_eq!(ScanSchedule::parse("off"), None);
+        assert_eq!(ScanSchedule::parse("0h"), None);
+        assert_eq!(ScanSchedule::parse("weekly"), None);
+    }
+
+    #[test]
+    fn test_next_run_after() {
+        let last = Utc::now();
+        let every = ScanSchedule::Every(chrono::Duration::hours(6));
+        assert_eq!(every.next_run_after(last), last + chrono::Duration::hours(6));
+
+        let daily = ScanSchedule::DailyAt(NaiveTime::from_hms_opt(3, 0, 0).unwrap());
+        let next = daily.next_run_after(last);
+        assert!(next > last);
+        assert!(next - last <= chrono::Duration::hours(25));
+        assert_eq!(next.with_timezone(&Local).time(), NaiveTime::from_hms_opt(3, 0, 0).unwrap());
+    }
+}
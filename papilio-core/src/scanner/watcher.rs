@@ -0,0 +1,92 @@
+//! 曲库目录监听：文件落盘后自动做增量入库 (WATCH_LIBRARY=true 时启用)
+
+use super::{is_audio_path, Scanner};
+use crate::error::AppError;
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use sqlx::PgPool;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// 事件去抖时间：拷贝大文件时会连续产生多次 Modify 事件
+const DEFAULT_DEBOUNCE_SECS: u64 = 3;
+
+/// 持续监听 `music_root`，直到监听器出错退出
+pub async fn watch_library(db: PgPool, music_root: PathBuf) -> Result<(), AppError> {
+    let debounce = Duration::from_secs(
+        std::env::var("WATCH_DEBOUNCE_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(DEFAULT_DEBOUNCE_SECS),
+    );
+
+    let (tx, mut rx) = mpsc::unbounded_channel::<Vec<PathBuf>>();
+    let mut watcher = RecommendedWatcher::new(
+        move |res: notify::Result<notify::Event>| match res {
+            Ok(event) => {
+                let _ = tx.send(event.paths);
+            }
+            Err(e) => tracing::warn!("Library watcher error: {}", e),
+        },
+        notify::Config::default(),
+    )
+    .map_err(|e| AppError::Internal(format!("Failed to create library watcher: {}", e)))?;
+
+    watcher
+        .watch(&music_root, RecursiveMode::Recursive)
+        .map_err(|e| {
+            AppError::Internal(format!(
+                "Failed to watch {}: {}",
+                music_root.display(),
+                e
+            ))
+        })?;
+    tracing::info!(
+        "Library watcher started on {} (debounce {:?})",
+        music_root.display(),
+        debounce
+    );
+
+    let scanner = Scanner::new(db);
+    while let Some(paths) = rx.recv().await {
+        let mut pending: HashSet<PathBuf> = paths.into_iter().collect();
+
+        // 静默 debounce 时长后再统一处理
+        loop {
+            match tokio::time::timeout(debounce, rx.recv()).await {
+                Ok(Some(paths)) => pending.extend(paths),
+                Ok(None) => return Ok(()),
+                Err(_) => break,
+            }
+        }
+
+        // 挂载点掉线时所有路径都会"消失"，此时不做任何删除
+        if !music_root.is_dir() {
+            tracing::error!(
+                "Library watcher: {} is unreachable, ignoring {} events",
+                music_root.display(),
+                pending.len()
+            );
+            continue;
+        }
+
+        let (changed, removed): (Vec<PathBuf>, Vec<PathBuf>) =
+            pending.into_iter().partition(|p| p.exists());
+        let changed: Vec<PathBuf> = changed
+            .into_iter()
+            .filter(|p| p.is_file() && is_audio_path(p))
+            .collect();
+
+        if changed.is_empty() && removed.is_empty() {
+            continue;
+        }
+        if let Err(e) = scanner.apply_fs_changes(&changed, &removed).await {
+            tracing::error!("Library watcher failed to apply changes: {}", e);
+        }
+    }
+
+    // 保持 watcher 存活直到通道关闭
+    drop(watcher);
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! 封面与头像的存储目录。写入、读取和对外提供静态文件都通过这里解析，
+//! 避免各处默认值不一致导致写进一个目录、却从另一个目录读取。
+
+use crate::error::AppError;
+use std::path::{Component, Path, PathBuf};
+
+pub const DEFAULT_COVER_DIR: &str = "data/covers";
+pub const DEFAULT_AVATAR_DIR: &str = "data/avatars";
+
+/// 内部封面目录 (COVER_DIR)，存放在线下载、手动上传与 `COVER_STORAGE=internal` 的封面
+pub fn cover_dir() -> PathBuf {
+    dir_from_env("COVER_DIR", DEFAULT_COVER_DIR)
+}
+
+/// `albums.cover_path` 中内部封面的前缀。这是与 COVER_DIR 无关的逻辑标识 (也是 `/data/covers` 静态路由)，
+/// 实际文件始终按 [`cover_dir`] 解析，修改 COVER_DIR 后已入库的记录仍然有效
+pub const INTERNAL_COVER_PREFIX: &str = "data/covers/";
+
+/// 内部封面文件名对应的 `cover_path` 取值
+pub fn internal_cover_ref(filename: &str) -> String {
+    format!("{}{}", INTERNAL_COVER_PREFIX, filename)
+}
+
+/// `cover_path` 指向内部封面时返回其在 [`cover_dir`] 下的文件路径；曲库内的相对路径返回 None。
+/// 兼容早期写入的 `/app/data/covers/...` 等带前缀的绝对路径
+pub fn internal_cover_file(cover_path: &str) -> Option<Result<PathBuf, AppError>> {
+    let (_, filename) = cover_path.split_once(INTERNAL_COVER_PREFIX)?;
+    Some(resolve_within(&cover_dir(), filename))
+}
+
+/// 头像目录 (AVATAR_DIR)，用户头像与歌手图片共用
+pub fn avatar_dir() -> PathBuf {
+    dir_from_env("AVATAR_DIR", DEFAULT_AVATAR_DIR)
+}
+
+fn dir_from_env(key: &str, default: &str) -> PathBuf {
+    match std::env::var(key) {
+        Ok(dir) if !dir.trim().is_empty() => PathBuf::from(dir.trim()),
+        _ => PathBuf::from(default),
+    }
+}
+
+/// 把文件名拼到基目录下。`sanitize_filename` 之外的第二道防线：
+/// 名称只能由普通路径段组成，绝对路径、`..` 等可能逃出基目录的名称一律拒绝
+pub fn resolve_within(base: &Path, name: &str) -> Result<PathBuf, AppError> {
+    let relative = Path::new(name);
+    let is_plain = relative.components().next().is_some()
+        && relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_)));
+    if !is_plain {
+        return Err(AppError::BadRequest(format!("Invalid file name: {}", name)));
+    }
+
+    let full = base.join(relative);
+    if !full.starts_with(base) {
+        return Err(AppError::BadRequest(format!("Invalid file name: {}", name)));
+    }
+    Ok(full)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve_within_accepts_plain_names() {
+        let base = Path::new("data/avatars");
+        assert_eq!(
+            resolve_within(base, "artist_1.jpg").unwrap(),
+            Path::new("data/avatars/artist_1.jpg")
+        );
+        assert_eq!(
+            resolve_within(base, "thumbs/a.jpg").unwrap(),
+            Path::new("data/avatars/thumbs/a.jpg")
+        );
+    }
+
+    #[test]
+    fn test_internal_cover_refs_resolve_under_cover_dir() {
+        let reference = internal_cover_ref("abc.jpg");
+        assert_eq!(reference, "data/covers/abc.jpg");
+        assert_eq!(
+            internal_cover_file(&reference).unwrap().unwrap(),
+            cover_dir().join("abc.jpg")
+        );
+        assert!(internal_cover_file("Artist/Album/cover.jpg").is_none());
+    }
+
+    #[test]
+    fn test_resolve_within_rejects_escapes() {
+        let base = Path::new("data/avatars");
+        for name in ["", "../secret", "a/../../b", "/etc/passwd", "./a.jpg"] {
+            assert!(resolve_within(base, name).is_err(), "{} should be rejected", name);
+        }
+    }
+}
@@ -1,7 +1,16 @@
 use crate::{ApiError, AppState};
-use axum::{extract::State, http::HeaderMap, response::IntoResponse, Json};
+use axum::{
+    extract::{Query, State},
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
+use futures_util::StreamExt;
 use papilio_core::error::AppError;
+use papilio_core::scanner::naming::{NamingPattern, DEFAULT_PATTERN};
 use papilio_core::scanner::organizer::Organizer;
+use papilio_core::scanner::schedule::ScanSchedule;
+use papilio_core::scanner::Scanner;
 use serde::Deserialize;
 use serde_json::json;
 use sqlx::Row;
@@ -32,6 +41,22 @@ pub async fn get_admin_config(
         config.insert(key, value);
     }
 
+    // 未在后台覆盖时展示环境变量中的定时扫描配置
+    config.entry("scan_schedule".to_string()).or_insert_with(|| {
+        std::env::var("SCAN_SCHEDULE")
+            .map(serde_json::Value::String)
+            .unwrap_or(serde_json::Value::Null)
+    });
+    let last_scheduled: Option<chrono::DateTime<chrono::Utc>> =
+        sqlx::query_scalar("SELECT last_scheduled_scan_at FROM scan_status WHERE id = 1")
+            .fetch_optional(&state.db)
+            .await?
+            .flatten();
+    config
+        .entry("organize_pattern".to_string())
+        .or_insert_with(|| json!(DEFAULT_PATTERN));
+    config.insert("last_scheduled_scan_at".to_string(), json!(last_scheduled));
+
     Ok(Json(config))
 }
 
@@ -42,6 +67,39 @@ pub async fn update_admin_config(
 ) -> Result<impl IntoResponse, ApiError> {
     check_admin(&headers, &state).await?;
 
+    if payload.key == "scan_schedule" {
+        let valid = match &payload.value {
+            serde_json::Value::Null => true,
+            serde_json::Value::String(v) => {
+                ScanSchedule::parse(v).is_some() || matches!(v.trim(), "" | "off" | "none")
+            }
+            _ => false,
+        };
+        if !valid {
+            return Err(ApiError(AppError::BadRequest(
+                "Invalid scan_schedule, expected an interval like '6h' / '1d' or a time like '03:00'".to_string(),
+            )));
+        }
+    }
+
+    // 整理模板：null / 空字符串恢复默认方案，其余必须能解析
+    if payload.key == "organize_pattern" {
+        match &payload.value {
+            serde_json::Value::Null => {}
+            serde_json::Value::String(v) if v.trim().is_empty() => {}
+            serde_json::Value::String(v) => {
+                NamingPattern::parse(v).map_err(|e| {
+                    ApiError(AppError::BadRequest(format!("Invalid organize_pattern: {}", e)))
+                })?;
+            }
+            _ => {
+                return Err(ApiError(AppError::BadRequest(
+                    "organize_pattern must be a string".to_string(),
+                )))
+            }
+        }
+    }
+
     sqlx::query("INSERT INTO system_config (key, value, updated_at) VALUES ($1, $2, NOW()) ON CONFLICT (key) DO UPDATE SET value = EXCLUDED.value, updated_at = NOW()")
         .bind(&payload.key)
         .bind(&payload.value)
@@ -62,6 +120,100 @@ pub async fn get_system_status(
     })))
 }
 
+/// 曲库统计：总量、容量、格式分布与元数据缺失情况，供管理面板展示
+pub async fn get_library_stats(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let totals = sqlx::query(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM tracks WHERE deleted_at IS NULL) AS tracks,
+            (SELECT COUNT(*) FROM tracks WHERE deleted_at IS NOT NULL) AS deleted_tracks,
+            (SELECT COUNT(*) FROM albums) AS albums,
+            (SELECT COUNT(*) FROM artists) AS artists,
+            (SELECT COALESCE(SUM(size), 0)::BIGINT FROM tracks WHERE deleted_at IS NULL) AS total_size,
+            (SELECT COALESCE(SUM(duration), 0)::BIGINT FROM tracks WHERE deleted_at IS NULL) AS total_duration,
+            (SELECT COUNT(*) FROM tracks t LEFT JOIN albums al ON t.album_id = al.id
+                WHERE t.deleted_at IS NULL
+                  AND (al.cover_path IS NULL OR al.cover_path = '')) AS tracks_missing_cover,
+            (SELECT COUNT(*) FROM tracks
+                WHERE deleted_at IS NULL
+                  AND (lyrics IS NULL OR btrim(lyrics) = '')
+                  AND (lyrics_ai IS NULL OR btrim(lyrics_ai) = '')) AS tracks_missing_lyrics,
+            (SELECT COUNT(*) FROM artists WHERE image_url IS NULL OR image_url = '') AS artists_missing_image
+        "#,
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    let formats = sqlx::query(
+        r#"
+        SELECT COALESCE(NULLIF(lower(format), ''), 'unknown') AS format,
+               COUNT(*) AS count,
+               COALESCE(SUM(size), 0)::BIGINT AS size
+        FROM tracks
+        WHERE deleted_at IS NULL
+        GROUP BY 1
+        ORDER BY count DESC
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(json!({
+        "tracks": totals.get::<i64, _>("tracks"),
+        "deleted_tracks": totals.get::<i64, _>("deleted_tracks"),
+        "albums": totals.get::<i64, _>("albums"),
+        "artists": totals.get::<i64, _>("artists"),
+        "total_size": totals.get::<i64, _>("total_size"),
+        "total_duration": totals.get::<i64, _>("total_duration"),
+        "formats": formats.iter().map(|row| json!({
+            "format": row.get::<String, _>("format"),
+            "count": row.get::<i64, _>("count"),
+            "size": row.get::<i64, _>("size"),
+        })).collect::<Vec<_>>(),
+        "missing": {
+            "track_covers": totals.get::<i64, _>("tracks_missing_cover"),
+            "track_lyrics": totals.get::<i64, _>("tracks_missing_lyrics"),
+            "artist_images": totals.get::<i64, _>("artists_missing_image"),
+        },
+        "ffmpeg_available": state.ffmpeg_available,
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct LogsQuery {
+    pub lines: Option<usize>,
+    /// 最低级别：error / warn / info / debug / trace
+    pub level: Option<String>,
+}
+
+/// 最近的服务端日志 (内存环形缓冲，重启后清空)
+pub async fn get_recent_logs(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<LogsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let min_level = match params.level.as_deref() {
+        None | Some("") => None,
+        Some(level) => Some(level.parse::<tracing::Level>().map_err(|_| {
+            ApiError(AppError::BadRequest(format!("Invalid log level: {}", level)))
+        })?),
+    };
+    let lines = params.lines.unwrap_or(200).clamp(1, 5000);
+    let entries = crate::log_buffer::recent(lines, min_level);
+
+    Ok(Json(json!({
+        "count": entries.len(),
+        "entries": entries,
+    })))
+}
+
 #[derive(Deserialize)]
 pub struct UpdateUserRolePayload {
     pub is_admin: bool,
@@ -126,6 +278,64 @@ pub async fn update_user_role(
     Ok(Json(json!({"status": "success"})))
 }
 
+#[derive(Deserialize)]
+pub struct UpdateUserQuotaPayload {
+    /// `null` 表示不限制
+    pub storage_quota_bytes: Option<i64>,
+}
+
+pub async fn get_user_quota(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(user_id): axum::extract::Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let row = sqlx::query("SELECT storage_quota_bytes, storage_used_bytes FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("User not found".to_string())))?;
+
+    Ok(Json(json!({
+        "user_id": user_id,
+        "storage_quota_bytes": row.get::<Option<i64>, _>("storage_quota_bytes"),
+        "storage_used_bytes": row.get::<i64, _>("storage_used_bytes"),
+    })))
+}
+
+pub async fn update_user_quota(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(user_id): axum::extract::Path<Uuid>,
+    Json(payload): Json<UpdateUserQuotaPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    if payload.storage_quota_bytes.is_some_and(|q| q < 0) {
+        return Err(ApiError(AppError::BadRequest(
+            "storage_quota_bytes must not be negative".to_string(),
+        )));
+    }
+
+    let result = sqlx::query("UPDATE users SET storage_quota_bytes = $1, updated_at = NOW() WHERE id = $2")
+        .bind(payload.storage_quota_bytes)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+    if result.rows_affected() == 0 {
+        return Err(ApiError(AppError::NotFound("User not found".to_string())));
+    }
+
+    tracing::info!(
+        "ADMIN: User {} storage quota set to {:?}",
+        user_id,
+        payload.storage_quota_bytes
+    );
+
+    Ok(Json(json!({"status": "success"})))
+}
+
 pub async fn delete_user(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -134,9 +344,7 @@ pub async fn delete_user(
     check_admin(&headers, &state).await?;
 
     // 防止管理员删除自己
-    let current_admin_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let current_admin_id = crate::require_user_id(&headers, &state).await?;
 
     if user_id == current_admin_id {
         return Err(ApiError(AppError::BadRequest(
@@ -154,9 +362,7 @@ pub async fn delete_user(
 }
 
 async fn check_admin(headers: &HeaderMap, state: &AppState) -> Result<(), ApiError> {
-    let user_id = crate::get_user_id(headers, state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(headers, state).await?;
 
     let is_admin = sqlx::query_scalar!("SELECT is_admin FROM users WHERE id = $1", user_id)
         .fetch_one(&state.db)
@@ -172,42 +378,134 @@ async fn check_admin(headers: &HeaderMap, state: &AppState) -> Result<(), ApiErr
     Ok(())
 }
 
+#[derive(Deserialize)]
+pub struct BatchArtistSyncQuery {
+    /// 为 true 时连已有 MusicBrainz ID 和头像的歌手也重新同步
+    pub force: Option<bool>,
+    /// 为 true 时丢弃上次中断留下的断点，从头开始
+    pub restart: Option<bool>,
+}
+
+/// 批量同步每处理一个歌手检查一次取消标志
+async fn artist_sync_cancelled(db: &sqlx::PgPool) -> bool {
+    sqlx::query_scalar("SELECT cancel_requested FROM artist_sync_status WHERE id = 1")
+        .fetch_one(db)
+        .await
+        .unwrap_or(false)
+}
+
+/// 批量同步同时处理的歌手数 (ARTIST_SYNC_CONCURRENCY，默认 2)。
+/// MusicBrainz 请求总频率由共享限流器保证，这里只决定等待图片下载时能否先处理下一位
+fn artist_sync_concurrency() -> usize {
+    std::env::var("ARTIST_SYNC_CONCURRENCY")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|n| *n > 0)
+        .unwrap_or(2)
+}
+
+/// 记录单个歌手的同步结果，覆盖该歌手上一次的记录
+async fn record_sync_result(db: &sqlx::PgPool, artist_id: Uuid, status: &str, error: Option<String>) {
+    let result = sqlx::query(
+        r#"
+        INSERT INTO sync_results (artist_id, status, error, synced_at)
+        VALUES ($1, $2, $3, NOW())
+        ON CONFLICT (artist_id) DO UPDATE
+        SET status = EXCLUDED.status, error = EXCLUDED.error, synced_at = EXCLUDED.synced_at
+        "#,
+    )
+    .bind(artist_id)
+    .bind(status)
+    .bind(error)
+    .execute(db)
+    .await;
+
+    if let Err(e) = result {
+        tracing::warn!("ADMIN: Failed to record sync result for {}: {}", artist_id, e);
+    }
+}
+
 pub async fn trigger_artist_sync(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    Query(params): Query<BatchArtistSyncQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     check_admin(&headers, &state).await?;
     tracing::info!("ADMIN: trigger_artist_sync called");
 
     // 检查是否已有同步任务在运行
-    let is_syncing: bool =
-        sqlx::query_scalar("SELECT is_syncing FROM artist_sync_status WHERE id = 1")
-            .fetch_one(&state.db)
-            .await?;
+    let status = sqlx::query(
+        "SELECT is_syncing, cursor_artist_id, cursor_force, current_count FROM artist_sync_status WHERE id = 1",
+    )
+    .fetch_one(&state.db)
+    .await?;
 
-    if is_syncing {
+    if status.get::<bool, _>("is_syncing") {
         return Err(ApiError(AppError::BadRequest(
             "A sync task is already in progress".to_string(),
         )));
     }
 
-    // 查找库中所有歌手并触发元数据同步
-    let artists_to_sync = sqlx::query!("SELECT id FROM artists")
-        .fetch_all(&state.db)
-        .await?;
+    let force = params.force.unwrap_or(false);
+    // 断点只在同一模式下续传，切换 force 后从头开始
+    let cursor: Option<Uuid> = if params.restart.unwrap_or(false)
+        || status.get::<bool, _>("cursor_force") != force
+    {
+        None
+    } else {
+        status.get("cursor_artist_id")
+    };
 
-    let total = artists_to_sync.len() as i32;
-    if total == 0 {
+    // 默认跳过 MusicBrainz ID 与头像都已齐全的歌手；按 id 排序以便断点续传
+    let candidates: Vec<Uuid> = sqlx::query_scalar(
+        r#"
+        SELECT id FROM artists
+        WHERE $1 OR musicbrainz_artist_id IS NULL OR image_url IS NULL OR image_url = ''
+        ORDER BY id
+        "#,
+    )
+    .bind(force)
+    .fetch_all(&state.db)
+    .await?;
+
+    let artists_to_sync: Vec<Uuid> = match cursor {
+        Some(cursor) => candidates.into_iter().filter(|id| *id > cursor).collect(),
+        None => candidates,
+    };
+    if artists_to_sync.is_empty() {
+        sqlx::query("UPDATE artist_sync_status SET cursor_artist_id = NULL WHERE id = 1")
+            .execute(&state.db)
+            .await?;
         return Ok(Json(
             json!({"status": "success", "message": "No artists need syncing"}),
         ));
     }
+    // 普通模式下已补全的歌手不再是候选，不能用候选数反推进度；沿用上次记录的已处理数
+    let resumed_from: i32 = if cursor.is_some() {
+        status.get("current_count")
+    } else {
+        0
+    };
+    let total = resumed_from + artists_to_sync.len() as i32;
+    if cursor.is_some() {
+        tracing::info!("ADMIN: Resuming artist sync after {} of {} artists", resumed_from, total);
+    }
 
     // 初始化状态
-    sqlx::query!(
-        "UPDATE artist_sync_status SET is_syncing = TRUE, current_count = 0, total_count = $1, last_error = NULL WHERE id = 1",
-        total
-    ).execute(&state.db).await?;
+    sqlx::query(
+        r#"
+        UPDATE artist_sync_status
+        SET is_syncing = TRUE, cancel_requested = FALSE, cursor_artist_id = $1, cursor_force = $4,
+            current_count = $2, total_count = $3, last_error = NULL
+        WHERE id = 1
+        "#,
+    )
+    .bind(cursor)
+    .bind(resumed_from)
+    .bind(total)
+    .bind(force)
+    .execute(&state.db)
+    .await?;
 
     let state_clone = state.clone();
     tokio::spawn(async move {
@@ -215,87 +513,157 @@ pub async fn trigger_artist_sync(
             "ADMIN: Background artist sync thread started. Total artists: {}",
             total
         );
-        let mut current = 0;
+        let mut current = resumed_from;
+        let mut cancelled = false;
+
+        // 请求频率由 MetadataService 的令牌桶控制；并发只用于让图片下载等非 MusicBrainz 请求重叠。
+        // buffered 按原顺序产出结果，断点始终是已完成前缀的最后一位
+        let mut results = futures_util::stream::iter(artists_to_sync)
+            .map(|artist_id| {
+                let metadata_service = state_clone.metadata_service.clone();
+                async move {
+                    // 单次同步超时保护 (120秒)，防止单个异常请求阻塞队列
+                    let sync_future = metadata_service.fetch_and_update_artist(artist_id, false);
+                    (artist_id, tokio::time::timeout(std::time::Duration::from_secs(120), sync_future).await)
+                }
+            })
+            .buffered(artist_sync_concurrency());
 
-        for artist in artists_to_sync {
+        while let Some((artist_id, outcome)) = results.next().await {
             current += 1;
-            println!(
-                "ADMIN_DEBUG: Syncing artist {}/{} (ID: {})",
-                current, total, artist.id
-            );
             tracing::info!(
-                "ADMIN: Syncing artist {}/{} (ID: {})",
+                "ADMIN: Synced artist {}/{} (ID: {})",
                 current,
                 total,
-                artist.id
+                artist_id
             );
 
-            // 单次同步超时保护 (120秒)，防止单个异常请求阻塞队列
-            let metadata_service = state_clone.metadata_service.clone();
-            let sync_future = metadata_service.fetch_and_update_artist(artist.id);
-            
-            match tokio::time::timeout(std::time::Duration::from_secs(120), sync_future).await {
+            match outcome {
                 Ok(Ok(_)) => {
-                    tracing::info!("ADMIN: Sync success for artist {}", artist.id);
+                    tracing::info!("ADMIN: Sync success for artist {}", artist_id);
+                    record_sync_result(&state_clone.db, artist_id, "success", None).await;
                 }
                 Ok(Err(e)) => {
-                    tracing::error!("ADMIN: Batch sync failed for artist {}: {:?}", artist.id, e);
+                    tracing::error!("ADMIN: Batch sync failed for artist {}: {:?}", artist_id, e);
                     let _ = sqlx::query!(
                         "UPDATE artist_sync_status SET last_error = $1 WHERE id = 1",
-                        format!("Artist {}: {:?}", artist.id, e)
+                        format!("Artist {}: {:?}", artist_id, e)
                     )
                     .execute(&state_clone.db)
                     .await;
+                    record_sync_result(&state_clone.db, artist_id, "failed", Some(e.to_string())).await;
                 }
                 Err(_) => {
-                    tracing::error!("ADMIN: Batch sync TIMEOUT for artist {}", artist.id);
+                    tracing::error!("ADMIN: Batch sync TIMEOUT for artist {}", artist_id);
                     let _ = sqlx::query!(
                         "UPDATE artist_sync_status SET last_error = $1 WHERE id = 1",
-                        format!("Timeout syncing artist {}", artist.id)
+                        format!("Timeout syncing artist {}", artist_id)
                     )
                     .execute(&state_clone.db)
                     .await;
+                    record_sync_result(
+                        &state_clone.db,
+                        artist_id,
+                        "timeout",
+                        Some("Timed out after 120s".to_string()),
+                    )
+                    .await;
                 }
             }
 
-            // 更新进度
-            let _ = sqlx::query!(
-                "UPDATE artist_sync_status SET current_count = $1 WHERE id = 1",
-                current
+            // 更新进度与断点
+            let _ = sqlx::query(
+                "UPDATE artist_sync_status SET current_count = $1, cursor_artist_id = $2 WHERE id = 1",
             )
+            .bind(current)
+            .bind(artist_id)
             .execute(&state_clone.db)
             .await;
 
-            // 增加请求间隔以符合 MusicBrainz API 频率限制建议
-            tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+            if artist_sync_cancelled(&state_clone.db).await {
+                cancelled = true;
+                break;
+            }
+        }
+        // 丢弃仍在进行中的同步，下次从断点重新处理
+        drop(results);
+
+        if cancelled {
+            // 保留断点，下次触发时从这里继续
+            tracing::info!("ADMIN: Batch sync cancelled at {}/{}", current, total);
+            let _ = sqlx::query(
+                "UPDATE artist_sync_status SET is_syncing = FALSE, cancel_requested = FALSE WHERE id = 1",
+            )
+            .execute(&state_clone.db)
+            .await;
+            return;
         }
 
         tracing::info!("ADMIN: Batch sync completed successfully.");
-        let _ = sqlx::query!(
-            "UPDATE artist_sync_status SET is_syncing = FALSE, last_sync_at = NOW() WHERE id = 1"
+        let _ = sqlx::query(
+            r#"
+            UPDATE artist_sync_status
+            SET is_syncing = FALSE, cancel_requested = FALSE, cursor_artist_id = NULL, last_sync_at = NOW()
+            WHERE id = 1
+            "#,
         )
         .execute(&state_clone.db)
         .await;
     });
 
-    Ok(Json(json!({"status": "success", "total": total})))
+    Ok(Json(json!({"status": "success", "total": total, "resumed_from": resumed_from})))
+}
+
+/// 请求停止正在进行的批量同步，当前歌手处理完后生效
+pub async fn cancel_artist_sync(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let result = sqlx::query(
+        "UPDATE artist_sync_status SET cancel_requested = TRUE WHERE id = 1 AND is_syncing",
+    )
+    .execute(&state.db)
+    .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(ApiError(AppError::BadRequest(
+            "No sync task is in progress".to_string(),
+        )));
+    }
+
+    tracing::info!("ADMIN: Artist sync cancellation requested");
+    Ok(Json(json!({"status": "success"})))
+}
+
+#[derive(Deserialize)]
+pub struct ArtistSyncQuery {
+    /// 为 true 时跳过元数据缓存，强制重新请求上游
+    pub refresh: Option<bool>,
 }
 
 pub async fn trigger_artist_sync_single(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     axum::extract::Path(artist_id): axum::extract::Path<Uuid>,
+    axum::extract::Query(query): axum::extract::Query<ArtistSyncQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     check_admin(&headers, &state).await?;
 
+    let refresh = query.refresh.unwrap_or(false);
     let state_clone = state.clone();
     tokio::spawn(async move {
-        if let Err(e) = state_clone
+        match state_clone
             .metadata_service
-            .fetch_and_update_artist(artist_id)
+            .fetch_and_update_artist(artist_id, refresh)
             .await
         {
-            tracing::error!("Single artist sync failed for {}: {:?}", artist_id, e);
+            Ok(()) => record_sync_result(&state_clone.db, artist_id, "success", None).await,
+            Err(e) => {
+                tracing::error!("Single artist sync failed for {}: {:?}", artist_id, e);
+                record_sync_result(&state_clone.db, artist_id, "failed", Some(e.to_string())).await;
+            }
         }
     });
 
@@ -308,15 +676,92 @@ pub async fn get_artist_sync_status(
 ) -> Result<impl IntoResponse, ApiError> {
     check_admin(&headers, &state).await?;
 
-    let row = sqlx::query!("SELECT is_syncing, current_count, total_count, last_sync_at, last_error FROM artist_sync_status WHERE id = 1")
-        .fetch_one(&state.db).await?;
+    let row = sqlx::query(
+        r#"
+        SELECT is_syncing, cancel_requested, cursor_artist_id, current_count, total_count, last_sync_at, last_error
+        FROM artist_sync_status WHERE id = 1
+        "#,
+    )
+    .fetch_one(&state.db)
+    .await?;
+    let is_syncing: bool = row.get("is_syncing");
+
+    Ok(Json(json!({
+        "is_syncing": is_syncing,
+        "cancel_requested": row.get::<bool, _>("cancel_requested"),
+        // 上次批量同步被取消或中断，再次触发会从断点继续
+        "resumable": !is_syncing && row.get::<Option<Uuid>, _>("cursor_artist_id").is_some(),
+        "current_count": row.get::<i32, _>("current_count"),
+        "total_count": row.get::<i32, _>("total_count"),
+        "last_sync_at": row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("last_sync_at"),
+        "last_error": row.get::<Option<String>, _>("last_error"),
+    })))
+}
+
+#[derive(Deserialize)]
+pub struct SyncResultsQuery {
+    /// success / failed / timeout，可用逗号分隔多个，不传则返回全部
+    pub status: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+/// 批量同步的逐条结果，最近同步的在前
+pub async fn list_artist_sync_results(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<SyncResultsQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let statuses: Option<Vec<String>> = params.status.as_deref().map(|s| {
+        s.split(',')
+            .map(|v| v.trim().to_lowercase())
+            .filter(|v| !v.is_empty())
+            .collect()
+    });
+
+    let rows = sqlx::query(
+        r#"
+        SELECT r.artist_id, a.name AS artist_name, r.status, r.error, r.synced_at
+        FROM sync_results r
+        JOIN artists a ON a.id = r.artist_id
+        WHERE ($1::text[] IS NULL OR r.status = ANY($1))
+        ORDER BY r.synced_at DESC
+        LIMIT $2 OFFSET $3
+        "#,
+    )
+    .bind(statuses)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(&state.db)
+    .await?;
+
+    let counts = sqlx::query("SELECT status, COUNT(*) AS count FROM sync_results GROUP BY status")
+        .fetch_all(&state.db)
+        .await?
+        .iter()
+        .map(|row| (row.get::<String, _>("status"), row.get::<i64, _>("count")))
+        .collect::<std::collections::HashMap<_, _>>();
+
+    let results = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "artist_id": row.get::<Uuid, _>("artist_id"),
+                "artist_name": row.get::<String, _>("artist_name"),
+                "status": row.get::<String, _>("status"),
+                "error": row.get::<Option<String>, _>("error"),
+                "synced_at": row.get::<chrono::DateTime<chrono::Utc>, _>("synced_at"),
+            })
+        })
+        .collect::<Vec<_>>();
 
     Ok(Json(json!({
-        "is_syncing": row.is_syncing,
-        "current_count": row.current_count,
-        "total_count": row.total_count,
-        "last_sync_at": row.last_sync_at,
-        "last_error": row.last_error,
+        "counts": counts,
+        "results": results,
     })))
 }
 
@@ -350,23 +795,32 @@ pub async fn trigger_artist_sync_missing(
         ));
     }
 
-    sqlx::query!(
-        "UPDATE artist_sync_status SET is_syncing = TRUE, current_count = 0, total_count = $1, last_error = NULL WHERE id = 1",
-        total
-    ).execute(&state.db).await?;
+    // 与批量同步共用进度计数，覆盖后旧断点的已处理数不再可信，一并清除
+    sqlx::query(
+        "UPDATE artist_sync_status SET is_syncing = TRUE, cancel_requested = FALSE, cursor_artist_id = NULL, current_count = 0, total_count = $1, last_error = NULL WHERE id = 1",
+    )
+    .bind(total)
+    .execute(&state.db)
+    .await?;
 
     let state_clone = state.clone();
     tokio::spawn(async move {
         tracing::info!("ADMIN: Missing artist sync started. Total: {}", total);
         let mut current = 0;
-        for artist in artists_to_sync {
+        let mut results = futures_util::stream::iter(artists_to_sync)
+            .map(|artist| {
+                let metadata_service = state_clone.metadata_service.clone();
+                async move { (artist.id, metadata_service.fetch_and_update_artist(artist.id, false).await) }
+            })
+            .buffer_unordered(artist_sync_concurrency());
+        while let Some((artist_id, result)) = results.next().await {
             current += 1;
-            if let Err(e) = state_clone
-                .metadata_service
-                .fetch_and_update_artist(artist.id)
-                .await
-            {
-                tracing::error!("Sync failed for artist {}: {:?}", artist.id, e);
+            match result {
+                Ok(()) => record_sync_result(&state_clone.db, artist_id, "success", None).await,
+                Err(e) => {
+                    tracing::error!("Sync failed for artist {}: {:?}", artist_id, e);
+                    record_sync_result(&state_clone.db, artist_id, "failed", Some(e.to_string())).await;
+                }
             }
             let _ = sqlx::query!(
                 "UPDATE artist_sync_status SET current_count = $1 WHERE id = 1",
@@ -374,10 +828,14 @@ pub async fn trigger_artist_sync_missing(
             )
             .execute(&state_clone.db)
             .await;
-            tokio::time::sleep(std::time::Duration::from_millis(1500)).await;
+            // 只处理缺图歌手，本身即可续传，取消时无需记录断点
+            if artist_sync_cancelled(&state_clone.db).await {
+                tracing::info!("ADMIN: Missing artist sync cancelled at {}/{}", current, total);
+                break;
+            }
         }
-        let _ = sqlx::query!(
-            "UPDATE artist_sync_status SET is_syncing = FALSE, last_sync_at = NOW() WHERE id = 1"
+        let _ = sqlx::query(
+            "UPDATE artist_sync_status SET is_syncing = FALSE, cancel_requested = FALSE, last_sync_at = NOW() WHERE id = 1",
         )
         .execute(&state_clone.db)
         .await;
@@ -393,58 +851,63 @@ pub async fn upload_artist_avatar(
     mut multipart: axum::extract::Multipart,
 ) -> Result<impl IntoResponse, ApiError> {
     check_admin(&headers, &state).await?;
+    let admin_id = crate::require_user_id(&headers, &state).await?;
 
-    if let Some(field) = multipart
-        .next_field()
-        .await
-        .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?
-    {
-        let data = field
-            .bytes()
-            .await
-            .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?;
-
-        if data.len() > 10 * 1024 * 1024 {
-            return Err(ApiError(AppError::BadRequest(
-                "Artist avatar too large (max 10MB)".to_string(),
-            )));
-        }
-
-        // 校验文件头（Magic Number）
-        let kind = infer::get(&data).ok_or_else(|| {
-            ApiError(AppError::BadRequest(
-                "Unknown file type. Only images are allowed.".to_string(),
-            ))
-        })?;
-
-        if !kind.mime_type().starts_with("image/") {
-            return Err(ApiError(AppError::BadRequest(format!(
-                "Invalid file type: {}. Only images are allowed.",
-                kind.mime_type()
-            ))));
-        }
+    if let Some((data, extension)) = read_image_upload(&mut multipart, "Artist avatar").await? {
+        let previous: Option<(Option<String>, Option<Uuid>, Option<i64>)> = sqlx::query_as(
+            "SELECT image_url, image_uploaded_by, image_size FROM artists WHERE id = $1",
+        )
+        .bind(artist_id)
+        .fetch_optional(&state.db)
+        .await?;
+        let (old_image, old_uploader, old_size) = previous
+            .ok_or_else(|| ApiError(AppError::NotFound("Artist not found".to_string())))?;
 
-        let extension = kind.extension();
         // 净化文件名，防止路径穿越攻击
         let filename = sanitize_filename::sanitize(format!("artist_{}.{}", artist_id, extension));
-        let base_dir =
-            std::env::var("AVATAR_DIR").unwrap_or_else(|_| "data/avatars".to_string());
-        let full_path = std::path::Path::new(&base_dir).join(&filename);
+        let base_dir = papilio_core::storage::avatar_dir();
+        let full_path = papilio_core::storage::resolve_within(&base_dir, &filename)?;
 
-        if !std::path::Path::new(&base_dir).exists() {
+        if !base_dir.exists() {
             tokio::fs::create_dir_all(&base_dir).await?;
         }
-        
-        tokio::fs::write(full_path, data).await?;
 
-        sqlx::query!(
-            "UPDATE artists SET image_url = $1 WHERE id = $2",
-            filename,
-            artist_id
+        // 新图片计入本次上传的管理员配额；旧图片的占用在替换成功后退还给它的上传者
+        let size = data.len() as i64;
+        crate::reserve_storage(&state.db, admin_id, size).await?;
+
+        if let Err(e) = tokio::fs::write(full_path, data).await {
+            let _ = crate::reserve_storage(&state.db, admin_id, -size).await;
+            return Err(e.into());
+        }
+
+        sqlx::query(
+            "UPDATE artists SET image_url = $1, image_uploaded_by = $2, image_size = $3 WHERE id = $4",
         )
+        .bind(&filename)
+        .bind(admin_id)
+        .bind(size)
+        .bind(artist_id)
         .execute(&state.db)
         .await?;
 
+        if let (Some(uploader), Some(old_size)) = (old_uploader, old_size) {
+            if let Err(e) = crate::reserve_storage(&state.db, uploader, -old_size).await {
+                tracing::error!("Failed to release storage for replaced artist image: {:?}", e);
+            }
+        }
+        // 扩展名变化时旧文件不会被覆盖，需要单独删除；只处理本接口生成的文件名
+        let own_prefix = format!("artist_{}.", artist_id);
+        if let Some(old_image) = old_image.filter(|old| *old != filename && old.starts_with(&own_prefix)) {
+            if let Ok(old_path) = papilio_core::storage::resolve_within(&base_dir, &old_image) {
+                if old_path.is_file() {
+                    if let Err(e) = tokio::fs::remove_file(&old_path).await {
+                        tracing::warn!("Failed to remove old artist image {:?}: {}", old_path, e);
+                    }
+                }
+            }
+        }
+
         tracing::info!(
             "ADMIN: Manual avatar upload success for artist {}",
             artist_id
@@ -457,6 +920,84 @@ pub async fn upload_artist_avatar(
     )))
 }
 
+/// 管理员上传图片的大小上限
+pub const MAX_IMAGE_UPLOAD_BYTES: usize = 10 * 1024 * 1024;
+
+/// 读取 multipart 中的第一个文件并校验大小与文件头 (Magic Number)，
+/// 返回 (内容, 扩展名)；没有上传文件时返回 None
+async fn read_image_upload(
+    multipart: &mut axum::extract::Multipart,
+    what: &str,
+) -> Result<Option<(axum::body::Bytes, &'static str)>, ApiError> {
+    let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?
+    else {
+        return Ok(None);
+    };
+    let data = field
+        .bytes()
+        .await
+        .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?;
+
+    if data.len() > MAX_IMAGE_UPLOAD_BYTES {
+        return Err(ApiError(AppError::BadRequest(format!(
+            "{} too large (max 10MB)",
+            what
+        ))));
+    }
+
+    // 校验文件头（Magic Number）
+    let kind = infer::get(&data).ok_or_else(|| {
+        ApiError(AppError::BadRequest(
+            "Unknown file type. Only images are allowed.".to_string(),
+        ))
+    })?;
+
+    if !kind.mime_type().starts_with("image/") {
+        return Err(ApiError(AppError::BadRequest(format!(
+            "Invalid file type: {}. Only images are allowed.",
+            kind.mime_type()
+        ))));
+    }
+
+    Ok(Some((data, kind.extension())))
+}
+
+/// 手动上传专辑封面，按 COVER_STORAGE 写入曲库目录或 COVER_DIR 并覆盖原有封面
+pub async fn upload_album_cover(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(album_id): axum::extract::Path<Uuid>,
+    mut multipart: axum::extract::Multipart,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM albums WHERE id = $1)")
+        .bind(album_id)
+        .fetch_one(&state.db)
+        .await?;
+    if !exists {
+        return Err(ApiError(AppError::NotFound("Album not found".to_string())));
+    }
+
+    let Some((data, extension)) = read_image_upload(&mut multipart, "Album cover").await? else {
+        return Err(ApiError(AppError::BadRequest(
+            "No file uploaded".to_string(),
+        )));
+    };
+
+    // 封面属于曲库资源，不计入操作者的个人配额
+    let scanner = Scanner::new(state.db.clone());
+    let cover_path = scanner
+        .store_album_cover(album_id, &data, extension, true)
+        .await?;
+
+    tracing::info!("ADMIN: Manual cover upload success for album {}", album_id);
+    Ok(Json(json!({"status": "success", "cover_path": cover_path})))
+}
+
 pub async fn trigger_library_organize(
     State(state): State<Arc<AppState>>,
     _headers: HeaderMap,
@@ -464,14 +1005,19 @@ pub async fn trigger_library_organize(
     check_admin(&_headers, &state).await?;
 
     let music_root = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
-    let organizer = Organizer::new(state.db.clone(), music_root.into());
+    let organizer = Organizer::new(state.db.clone(), music_root.into())
+        .with_progress_channel(state.scan_progress.clone());
 
     tokio::spawn(async move {
         tracing::warn!("ADMIN: Library reorganization started by administrator.");
-        if let Err(e) = organizer.organize().await {
-            tracing::error!("ADMIN: Library reorganization failed: {:?}", e);
-        } else {
-            tracing::info!("ADMIN: Library reorganization finished successfully.");
+        match organizer.organize().await {
+            Ok(report) => tracing::info!(
+                "ADMIN: Library reorganization finished: {} moved, {} skipped, {} failed.",
+                report.moved,
+                report.skipped,
+                report.failed
+            ),
+            Err(e) => tracing::error!("ADMIN: Library reorganization failed: {:?}", e),
         }
     });
 
@@ -480,3 +1026,216 @@ pub async fn trigger_library_organize(
         "message": "Library reorganization task has been queued in the background."
     })))
 }
+
+pub async fn get_organize_report(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let row = sqlx::query(
+        "SELECT id, moved_count, skipped_count, failed_count, truncated, entries, created_at
+         FROM organize_reports ORDER BY id DESC LIMIT 1",
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError(AppError::NotFound("No organize report yet".to_string())))?;
+
+    Ok(Json(json!({
+        "id": row.get::<i64, _>("id"),
+        "moved": row.get::<i32, _>("moved_count"),
+        "skipped": row.get::<i32, _>("skipped_count"),
+        "failed": row.get::<i32, _>("failed_count"),
+        "truncated": row.get::<bool, _>("truncated"),
+        "entries": row.get::<serde_json::Value, _>("entries"),
+        "created_at": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+    })))
+}
+
+pub async fn list_orphan_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    // 挂载点不可用时所有音轨都会被误判为孤儿，直接报错而不是返回整库列表
+    let music_root = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
+    if !std::path::Path::new(&music_root).is_dir() {
+        tracing::error!("ADMIN: MUSIC_DIR '{}' is unreachable, refusing orphan preview", music_root);
+        return Err(ApiError(AppError::Internal(format!(
+            "Music library root is unreachable: {}",
+            music_root
+        ))));
+    }
+
+    let scanner = Scanner::new(state.db.clone());
+    let orphans = scanner.find_orphan_tracks().await?;
+
+    Ok(Json(json!({
+        "music_root": music_root,
+        "count": orphans.len(),
+        "tracks": orphans,
+    })))
+}
+
+/// 已软删除 (扫描时文件缺失) 的音轨，按删除时间倒序
+pub async fn list_deleted_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let rows = sqlx::query(
+        "SELECT id, title, path, deleted_at FROM tracks WHERE deleted_at IS NOT NULL ORDER BY deleted_at DESC",
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    let tracks = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "title": row.get::<String, _>("title"),
+                "path": row.get::<String, _>("path"),
+                "deleted_at": row.get::<chrono::DateTime<chrono::Utc>, _>("deleted_at"),
+            })
+        })
+        .collect::<Vec<_>>();
+    Ok(Json(json!({"count": tracks.len(), "tracks": tracks})))
+}
+
+#[derive(Deserialize, Default)]
+pub struct TrackIdsPayload {
+    /// 省略时作用于全部已软删除的音轨
+    pub ids: Option<Vec<Uuid>>,
+}
+
+/// 恢复软删除的音轨。文件仍然缺失时，下次扫描会再次软删除
+pub async fn restore_deleted_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Option<Json<TrackIdsPayload>>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    let restored = Scanner::new(state.db.clone())
+        .restore_tracks(payload.ids.as_deref())
+        .await?;
+    tracing::info!("ADMIN: restored {} soft-deleted track(s)", restored);
+    Ok(Json(json!({"restored": restored})))
+}
+
+/// 立即物理删除软删除的音轨，不等宽限期。歌单与收藏中的引用随之删除
+pub async fn purge_deleted_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    payload: Option<Json<TrackIdsPayload>>,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+
+    let purged = Scanner::new(state.db.clone())
+        .purge_deleted_tracks(payload.ids.as_deref(), 0)
+        .await?;
+    tracing::warn!("ADMIN: purged {} soft-deleted track(s)", purged);
+    Ok(Json(json!({"purged": purged})))
+}
+
+/// 时长相差不超过该值 (秒) 的同名同歌手曲目视为同一首
+const DUPLICATE_DURATION_TOLERANCE_SECS: i32 = 2;
+
+/// 重复曲目报告：同一歌手、规范化后标题相同且时长相近的曲目归为一组，只读
+pub async fn list_duplicate_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let rows = sqlx::query(
+        r#"
+        WITH candidates AS (
+            SELECT t.*, papilio_search_norm(btrim(t.title)) AS norm_title
+            FROM tracks t
+            WHERE t.deleted_at IS NULL
+        )
+        SELECT c.id, c.title, c.norm_title, c.artist_id, a.name AS artist_name,
+               al.title AS album_title, c.duration, c.path, c.format, c.bitrate, c.size
+        FROM candidates c
+        -- 没有歌手的曲目 artist_id 为 NULL，IN 比较会漏掉，改用 IS NOT DISTINCT FROM
+        JOIN (
+            SELECT norm_title, artist_id FROM candidates
+            GROUP BY norm_title, artist_id
+            HAVING COUNT(*) > 1
+        ) g ON g.norm_title = c.norm_title AND g.artist_id IS NOT DISTINCT FROM c.artist_id
+        LEFT JOIN artists a ON c.artist_id = a.id
+        LEFT JOIN albums al ON c.album_id = al.id
+        ORDER BY c.artist_id, c.norm_title, c.duration, c.bitrate DESC NULLS LAST
+        "#,
+    )
+    .fetch_all(&state.db)
+    .await?;
+
+    // 同组内按时长排序后切分：相邻两条相差超过容差即另起一簇
+    let mut clusters: Vec<Vec<&sqlx::postgres::PgRow>> = Vec::new();
+    let mut prev: Option<(Option<Uuid>, String, i32)> = None;
+    for row in &rows {
+        let key: (Option<Uuid>, String, i32) = (
+            row.get("artist_id"),
+            row.get("norm_title"),
+            row.get("duration"),
+        );
+        let same_cluster = matches!(&prev, Some((artist, title, duration))
+            if *artist == key.0 && *title == key.1 && key.2 - duration <= DUPLICATE_DURATION_TOLERANCE_SECS);
+        match clusters.last_mut() {
+            Some(cluster) if same_cluster => cluster.push(row),
+            _ => clusters.push(vec![row]),
+        }
+        prev = Some(key);
+    }
+    clusters.retain(|c| c.len() > 1);
+
+    let groups = clusters
+        .iter()
+        .map(|cluster| {
+            let first = cluster[0];
+            json!({
+                "title": first.get::<String, _>("title"),
+                "artist_id": first.get::<Option<Uuid>, _>("artist_id"),
+                "artist_name": first.get::<Option<String>, _>("artist_name"),
+                "tracks": cluster.iter().map(|row| json!({
+                    "id": row.get::<Uuid, _>("id"),
+                    "title": row.get::<String, _>("title"),
+                    "album_title": row.get::<Option<String>, _>("album_title"),
+                    "duration": row.get::<i32, _>("duration"),
+                    "path": row.get::<String, _>("path"),
+                    "format": row.get::<Option<String>, _>("format"),
+                    "bitrate": row.get::<Option<i32>, _>("bitrate"),
+                    "size": row.get::<Option<i64>, _>("size"),
+                })).collect::<Vec<_>>(),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(json!({
+        "count": groups.len(),
+        "duplicate_tracks": clusters.iter().map(|c| c.len()).sum::<usize>(),
+        "groups": groups,
+    })))
+}
+
+pub async fn vacuum_library(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    check_admin(&headers, &state).await?;
+
+    let scanner = Scanner::new(state.db.clone());
+    let report = scanner.vacuum().await?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "report": report,
+    })))
+}
@@ -1,16 +1,26 @@
+use crate::handlers::music::{
+    fetch_favorite_albums, fetch_favorite_artists, fetch_favorite_tracks, fetch_history,
+    AlbumWithCount, ArtistWithCount, PlayHistoryEntry, TrackWithFavorite,
+};
+use crate::handlers::playlist::{fetch_owned_playlists, fetch_playlist_tracks, PlaylistWithFavoriteTracks};
 use crate::{ApiError, AppState};
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
+    extract::{ConnectInfo, State},
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use papilio_core::{
-    auth::{create_token, hash_password, verify_password},
+    auth::{
+        create_token, dummy_verify, generate_api_key, hash_api_key, hash_password, token_id,
+        validate_password_strength, verify_password, API_KEY_SCOPE_FULL, API_KEY_SCOPE_READ,
+    },
     error::AppError,
     models::user::{CreateUser, UpdateUser, User, UserResponse},
 };
 use serde_json::json;
+use sqlx::PgPool;
+use std::net::SocketAddr;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -18,9 +28,7 @@ pub async fn get_me(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let user = User::find_by_id(&state.db, user_id)
         .await?
@@ -41,11 +49,10 @@ pub async fn update_profile(
     headers: HeaderMap,
     Json(payload): Json<UpdateUser>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let password_hash = if let Some(p) = payload.password {
+        validate_password_strength(&p)?;
         Some(hash_password(&p).map_err(|e| ApiError(AppError::Internal(e.to_string())))?)
     } else {
         None
@@ -64,7 +71,6 @@ pub async fn update_profile(
 }
 
 use axum::extract::Multipart;
-use std::path::Path;
 use tokio::fs;
 
 pub async fn upload_avatar(
@@ -72,9 +78,7 @@ pub async fn upload_avatar(
     headers: HeaderMap,
     mut multipart: Multipart,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     // 1. 获取当前用户信息以进行物理删除
     let current_user = User::find_by_id(&state.db, user_id)
@@ -123,30 +127,46 @@ pub async fn upload_avatar(
             let extension = kind.extension();
             // 净化文件名，防止路径穿越攻击
             let safe_filename = sanitize_filename::sanitize(format!("{}.{}", user_id, extension));
-            let avatars_dir = Path::new("data/avatars");
-            let path = avatars_dir.join(&safe_filename);
+            let avatars_dir = papilio_core::storage::avatar_dir();
+            let path = papilio_core::storage::resolve_within(&avatars_dir, &safe_filename)?;
 
             // 确保物理目录存在
             if !avatars_dir.exists() {
-                fs::create_dir_all(avatars_dir).await.map_err(|e| {
+                fs::create_dir_all(&avatars_dir).await.map_err(|e| {
                     ApiError(AppError::Internal(format!("Failed to create avatars dir: {}", e)))
                 })?;
             }
 
+            // 新头像会替换旧文件，配额只按净增量计算
+            let old_size = match current_user.avatar.as_deref() {
+                Some(old_avatar) => match papilio_core::storage::resolve_within(&avatars_dir, old_avatar) {
+                    Ok(old_path) => fs::metadata(old_path).await.map(|m| m.len() as i64).unwrap_or(0),
+                    Err(_) => 0,
+                },
+                None => 0,
+            };
+            let delta = data.len() as i64 - old_size;
+            crate::reserve_storage(&state.db, user_id, delta).await?;
+
             // 物理删除旧头像 (如果存在且文件名不同)
             if let Some(ref old_avatar) = current_user.avatar {
                 if old_avatar != &safe_filename {
-                    let old_path = avatars_dir.join(old_avatar);
-                    if let Err(e) = fs::remove_file(&old_path).await {
-                        tracing::warn!("Failed to remove old avatar file {:?}: {}", old_path, e);
+                    match papilio_core::storage::resolve_within(&avatars_dir, old_avatar) {
+                        Ok(old_path) => {
+                            if let Err(e) = fs::remove_file(&old_path).await {
+                                tracing::warn!("Failed to remove old avatar file {:?}: {}", old_path, e);
+                            }
+                        }
+                        Err(e) => tracing::warn!("Skipping removal of old avatar: {}", e),
                     }
                 }
             }
 
             // 3. 写入文件
-            fs::write(&path, data)
-                .await
-                .map_err(|e: std::io::Error| ApiError(AppError::Internal(e.to_string())))?;
+            if let Err(e) = fs::write(&path, data).await {
+                let _ = crate::reserve_storage(&state.db, user_id, -delta).await;
+                return Err(ApiError(AppError::Internal(e.to_string())));
+            }
             filename = Some(safe_filename);
         }
     }
@@ -165,10 +185,18 @@ pub async fn register(
     State(state): State<Arc<AppState>>,
     Json(payload): Json<CreateUser>,
 ) -> Result<impl IntoResponse, ApiError> {
+    validate_password_strength(&payload.password)?;
     let password_hash = hash_password(&payload.password)
         .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
 
     let user = User::create(&state.db, payload, password_hash).await?;
+    if let Some(quota) = crate::default_storage_quota() {
+        sqlx::query("UPDATE users SET storage_quota_bytes = $1 WHERE id = $2")
+            .bind(quota)
+            .bind(user.id)
+            .execute(&state.db)
+            .await?;
+    }
     Ok((StatusCode::CREATED, Json(UserResponse::from(user))))
 }
 
@@ -180,30 +208,88 @@ pub struct LoginPayload {
     pub password: String,
 }
 
+/// 登录失败计数的 Redis 键。LOGIN_RATE_LIMIT_SCOPE 可选 `username_ip` (默认)、`username`、`ip`
+fn login_limit_key(username: &str, ip: &str) -> String {
+    let username = username.trim().to_lowercase();
+    match std::env::var("LOGIN_RATE_LIMIT_SCOPE").as_deref() {
+        Ok("username") => format!("login_failures:u:{}", username),
+        Ok("ip") => format!("login_failures:ip:{}", ip),
+        _ => format!("login_failures:{}:{}", username, ip),
+    }
+}
+
 pub async fn login(
     State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(payload): Json<LoginPayload>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user = User::find_by_username(&state.db, &payload.username)
-        .await?
-        .ok_or_else(|| ApiError(AppError::Auth("Invalid credentials".to_string())))?;
-
-    let is_valid = verify_password(&payload.password, &user.password_hash).map_err(|e| {
-        ApiError(AppError::Internal(format!(
-            "Password verification system error: {}",
-            e
-        )))
-    })?;
+    let max_failures: i64 = std::env::var("LOGIN_MAX_FAILURES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(5);
+    let window_secs: i64 = std::env::var("LOGIN_FAILURE_WINDOW_SECS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(900);
 
-    if !is_valid {
-        return Err(ApiError(AppError::Auth("Invalid credentials".to_string())));
+    let mut redis = state.redis.clone();
+    use redis::AsyncCommands;
+    let ip = crate::client_ip(&headers, peer.ip());
+    let limit_key = login_limit_key(&payload.username, &ip);
+
+    // 先计数再校验：并发请求各自拿到递增后的次数，不会在 Argon2 校验期间一起越过上限。
+    // 计数与首次设置过期在同一脚本中完成，登录成功后清零
+    let attempts: i64 = if max_failures > 0 {
+        let script = redis::Script::new(
+            "local n = redis.call('INCR', KEYS[1]) \
+             if n == 1 then redis.call('EXPIRE', KEYS[1], ARGV[1]) end \
+             return n",
+        );
+        script
+            .key(&limit_key)
+            .arg(window_secs)
+            .invoke_async(&mut redis)
+            .await
+            .unwrap_or_else(|e| {
+                tracing::warn!("Login rate limiter unavailable: {}", e);
+                0
+            })
+    } else {
+        0
+    };
+    if max_failures > 0 && attempts > max_failures {
+        let ttl: i64 = redis.ttl(&limit_key).await.unwrap_or(window_secs);
+        tracing::warn!("LOGIN_RATE_LIMITED: key={}", limit_key);
+        return Err(ApiError(AppError::TooManyRequests(format!(
+            "Too many failed login attempts, try again in {} seconds",
+            ttl.max(1)
+        ))));
     }
 
+    let user = User::find_by_username(&state.db, &payload.username).await?;
+    let is_valid = match &user {
+        Some(user) => verify_password(&payload.password, &user.password_hash).map_err(|e| {
+            ApiError(AppError::Internal(format!(
+                "Password verification system error: {}",
+                e
+            )))
+        })?,
+        None => {
+            dummy_verify(&payload.password);
+            false
+        }
+    };
+
+    let user = match user {
+        Some(user) if is_valid => user,
+        _ => return Err(ApiError(AppError::Auth("Invalid credentials".to_string()))),
+    };
+    let _: () = redis.del(&limit_key).await.unwrap_or(());
+
     let token = create_token(user.id, user.username.clone(), &state.jwt_secret)
         .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
 
-    let mut redis = state.redis.clone();
-    use redis::AsyncCommands;
     let session_key = format!("{}{}", crate::SESSION_PREFIX, token);
     let user_sessions_key = format!("{}{}", crate::USER_SESSIONS_PREFIX, user.id);
 
@@ -222,6 +308,29 @@ pub async fn login(
         .await
         .unwrap_or(());
 
+    // 记录设备信息，供 "我的设备" 列表展示
+    let meta_key = format!("{}{}", crate::SESSION_META_PREFIX, token);
+    let user_agent = headers
+        .get(axum::http::header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_string();
+    let _: () = redis
+        .hset_multiple(
+            &meta_key,
+            &[
+                ("user_agent", user_agent),
+                ("ip", ip),
+                ("created_at", chrono::Utc::now().timestamp().to_string()),
+            ],
+        )
+        .await
+        .unwrap_or(());
+    let _: () = redis
+        .expire(&meta_key, crate::SESSION_EXPIRATION as i64)
+        .await
+        .unwrap_or(());
+
     tracing::debug!(
         "LOGIN_SUCCESS: username={}, nickname={:?}",
         user.username,
@@ -249,6 +358,10 @@ pub async fn logout(
                 let user_sessions_key = format!("{}{}", crate::USER_SESSIONS_PREFIX, claims.sub);
 
                 let _: () = redis.del(&session_key).await.unwrap_or(());
+                let _: () = redis
+                    .del(format!("{}{}", crate::SESSION_META_PREFIX, token))
+                    .await
+                    .unwrap_or(());
                 let _: () = redis.srem(&user_sessions_key, token).await.unwrap_or(());
             }
         }
@@ -262,9 +375,7 @@ pub async fn kick_user(
     headers: HeaderMap,
     axum::extract::Path(target_user_id): axum::extract::Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     // 鉴权：允许管理员踢除任意用户，或普通用户踢除自己的其他会话
     let user_is_admin = sqlx::query_scalar!("SELECT is_admin FROM users WHERE id = $1", user_id)
@@ -289,7 +400,10 @@ pub async fn kick_user(
 
     for token in tokens {
         let _: () = redis
-            .del(format!("{}{}", crate::SESSION_PREFIX, token))
+            .del(&[
+                format!("{}{}", crate::SESSION_PREFIX, token),
+                format!("{}{}", crate::SESSION_META_PREFIX, token),
+            ])
             .await
             .unwrap_or(());
     }
@@ -298,3 +412,281 @@ pub async fn kick_user(
 
     Ok(StatusCode::OK)
 }
+
+/// 当前用户的所有会话 (设备)。顺带清理集合中已过期的 token
+pub async fn list_sessions(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let current_token = headers
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "));
+
+    let mut redis = state.redis.clone();
+    use redis::AsyncCommands;
+    let user_sessions_key = format!("{}{}", crate::USER_SESSIONS_PREFIX, user_id);
+    let tokens: Vec<String> = redis
+        .smembers(&user_sessions_key)
+        .await
+        .map_err(|e| ApiError(AppError::Internal(format!("Valkey error: {}", e))))?;
+
+    let mut sessions = Vec::new();
+    for token in tokens {
+        let alive: bool = redis
+            .exists(format!("{}{}", crate::SESSION_PREFIX, token))
+            .await
+            .unwrap_or(false);
+        if !alive {
+            let _: () = redis.srem(&user_sessions_key, &token).await.unwrap_or(());
+            continue;
+        }
+
+        let meta: std::collections::HashMap<String, String> = redis
+            .hgetall(format!("{}{}", crate::SESSION_META_PREFIX, token))
+            .await
+            .unwrap_or_default();
+        let issued_at = papilio_core::auth::verify_token(&token, &state.jwt_secret)
+            .ok()
+            .and_then(|claims| chrono::DateTime::from_timestamp(claims.iat as i64, 0));
+
+        sessions.push(json!({
+            "token_id": token_id(&token),
+            "issued_at": issued_at,
+            "user_agent": meta.get("user_agent").filter(|v| !v.is_empty()),
+            "ip": meta.get("ip").filter(|v| !v.is_empty()),
+            "current": Some(token.as_str()) == current_token,
+        }));
+    }
+
+    sessions.sort_by(|a, b| b["issued_at"].as_str().cmp(&a["issued_at"].as_str()));
+    Ok(Json(sessions))
+}
+
+/// 注销单个设备
+pub async fn revoke_session(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(target_id): axum::extract::Path<String>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let mut redis = state.redis.clone();
+    use redis::AsyncCommands;
+    let user_sessions_key = format!("{}{}", crate::USER_SESSIONS_PREFIX, user_id);
+    let tokens: Vec<String> = redis
+        .smembers(&user_sessions_key)
+        .await
+        .map_err(|e| ApiError(AppError::Internal(format!("Valkey error: {}", e))))?;
+
+    let token = tokens
+        .into_iter()
+        .find(|t| token_id(t) == target_id)
+        .ok_or_else(|| ApiError(AppError::NotFound("Session not found".to_string())))?;
+
+    let _: () = redis
+        .del(&[
+            format!("{}{}", crate::SESSION_PREFIX, token),
+            format!("{}{}", crate::SESSION_META_PREFIX, token),
+        ])
+        .await
+        .unwrap_or(());
+    let _: () = redis.srem(&user_sessions_key, &token).await.unwrap_or(());
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Deserialize)]
+pub struct CreateApiKeyPayload {
+    pub name: String,
+    /// `read` 或 `full`，默认 `full`
+    pub scopes: Option<Vec<String>>,
+}
+
+/// 创建 API Key，明文只在此响应中返回一次
+pub async fn create_api_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<CreateApiKeyPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let name = payload.name.trim();
+    if name.is_empty() || name.len() > 100 {
+        return Err(ApiError(AppError::BadRequest(
+            "API key name must be between 1 and 100 characters".to_string(),
+        )));
+    }
+    let scopes = payload
+        .scopes
+        .unwrap_or_else(|| vec![API_KEY_SCOPE_FULL.to_string()]);
+    if scopes.is_empty()
+        || scopes
+            .iter()
+            .any(|s| s != API_KEY_SCOPE_READ && s != API_KEY_SCOPE_FULL)
+    {
+        return Err(ApiError(AppError::BadRequest(format!(
+            "Invalid scopes, expected '{}' or '{}'",
+            API_KEY_SCOPE_READ, API_KEY_SCOPE_FULL
+        ))));
+    }
+
+    let key = generate_api_key();
+    let prefix: String = key.chars().take(12).collect();
+    let row = sqlx::query(
+        r#"
+        INSERT INTO api_keys (user_id, name, prefix, key_hash, scopes)
+        VALUES ($1, $2, $3, $4, $5)
+        RETURNING id, created_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(name)
+    .bind(&prefix)
+    .bind(hash_api_key(&key))
+    .bind(&scopes)
+    .fetch_one(&state.db)
+    .await?;
+
+    use sqlx::Row;
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "id": row.get::<Uuid, _>("id"),
+            "name": name,
+            "key": key,
+            "prefix": prefix,
+            "scopes": scopes,
+            "created_at": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+        })),
+    ))
+}
+
+pub async fn list_api_keys(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let rows = sqlx::query(
+        "SELECT id, name, prefix, scopes, last_used_at, created_at FROM api_keys WHERE user_id = $1 ORDER BY created_at DESC",
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    use sqlx::Row;
+    let keys = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "id": row.get::<Uuid, _>("id"),
+                "name": row.get::<String, _>("name"),
+                "prefix": row.get::<String, _>("prefix"),
+                "scopes": row.get::<Vec<String>, _>("scopes"),
+                "last_used_at": row.get::<Option<chrono::DateTime<chrono::Utc>>, _>("last_used_at"),
+                "created_at": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+            })
+        })
+        .collect::<Vec<_>>();
+
+    Ok(Json(keys))
+}
+
+pub async fn delete_api_key(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    axum::extract::Path(id): axum::extract::Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let res = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(ApiError(AppError::NotFound("API key not found".to_string())));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 导出文档的格式版本，以后的导入接口据此兼容旧文件
+pub const USER_EXPORT_VERSION: u32 = 1;
+
+#[derive(serde::Serialize)]
+pub struct FavoritesExport {
+    pub tracks: Vec<TrackWithFavorite>,
+    pub albums: Vec<AlbumWithCount>,
+    pub artists: Vec<ArtistWithCount>,
+}
+
+/// 个人数据导出：资料、收藏、自建歌单 (含曲目) 与完整播放历史，只包含当前用户自己的数据
+#[derive(serde::Serialize)]
+pub struct UserDataExport {
+    pub version: u32,
+    pub exported_at: chrono::DateTime<chrono::Utc>,
+    pub profile: UserResponse,
+    pub favorites: FavoritesExport,
+    pub playlists: Vec<PlaylistWithFavoriteTracks>,
+    pub history: Vec<PlayHistoryEntry>,
+}
+
+pub async fn export_user_data(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let user = User::find_by_id(&state.db, user_id)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("User not found".to_string())))?;
+
+    let filename = sanitize_filename::sanitize(format!(
+        "papilio-{}-{}.json",
+        user.username,
+        chrono::Utc::now().format("%Y%m%d")
+    ));
+    let export = collect_user_export(&state.db, user).await?;
+
+    Ok((
+        [(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"papilio-export.json\"; filename*=UTF-8''{}",
+                urlencoding::encode(&filename)
+            ),
+        )],
+        Json(export),
+    ))
+}
+
+/// 汇总 `user` 自己的收藏、自建歌单与完整播放历史
+pub async fn collect_user_export(db: &PgPool, user: User) -> Result<UserDataExport, ApiError> {
+    let user_id = user.id;
+    let favorites = FavoritesExport {
+        tracks: fetch_favorite_tracks(db, user_id).await?,
+        albums: fetch_favorite_albums(db, user_id).await?,
+        artists: fetch_favorite_artists(db, user_id).await?,
+    };
+
+    let mut playlists = Vec::new();
+    for playlist in fetch_owned_playlists(db, user_id).await? {
+        let tracks = fetch_playlist_tracks(db, playlist.id, Some(user_id)).await?;
+        playlists.push(PlaylistWithFavoriteTracks { playlist, tracks });
+    }
+
+    // 备份需要完整时间线：不去重、不分页
+    let history = fetch_history(db, user_id, true, None, None, None, 0).await?;
+
+    Ok(UserDataExport {
+        version: USER_EXPORT_VERSION,
+        exported_at: chrono::Utc::now(),
+        profile: UserResponse::from(user),
+        favorites,
+        playlists,
+        history,
+    })
+}
@@ -0,0 +1,342 @@
+//! HLS 分段流：移动端弱网下按段拉取、按码率切换，桌面端仍走 `/stream` 直链
+
+use crate::{ApiError, AppState};
+use axum::{
+    body::Body,
+    extract::{Path, State},
+    http::{header, HeaderMap},
+    response::{IntoResponse, Response},
+};
+use papilio_core::error::AppError;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::Arc;
+use std::time::SystemTime;
+use tokio::process::Command;
+use tokio_util::io::ReaderStream;
+use uuid::Uuid;
+
+/// 可选码率档位，同时作为缓存目录名，只接受列表内的值
+const HLS_VARIANTS: &[(&str, u32)] = &[("64k", 64_000), ("128k", 128_000), ("256k", 256_000)];
+
+/// 每段时长 (秒)
+const HLS_SEGMENT_SECS: &str = "6";
+
+const PLAYLIST_MIME: &str = "application/vnd.apple.mpegurl";
+
+/// 未配置 HLS_CACHE_MAX_BYTES 时的缓存上限 (2 GiB)
+const DEFAULT_MAX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+fn hls_cache_root() -> PathBuf {
+    PathBuf::from(std::env::var("HLS_CACHE_DIR").unwrap_or_else(|_| "data/hls".to_string()))
+}
+
+/// 切片缓存总大小上限，超出后按最近访问时间淘汰整个码率目录
+fn max_bytes() -> u64 {
+    std::env::var("HLS_CACHE_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+/// 切片所需的曲目字段，软删除的曲目视为不存在
+struct HlsSource {
+    path: String,
+    start_ms: i32,
+    end_ms: Option<i32>,
+    /// 源文件的 `{size}_{mtime}`，作为缓存目录的一级，替换文件后旧切片不再命中
+    fingerprint: String,
+}
+
+async fn load_source(state: &AppState, id: Uuid) -> Result<HlsSource, ApiError> {
+    let track = sqlx::query!(
+        "SELECT path, start_ms, end_ms FROM tracks WHERE id = $1 AND deleted_at IS NULL",
+        id
+    )
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError(AppError::NotFound("Track not found".to_string())))?;
+
+    let meta = tokio::fs::metadata(&track.path)
+        .await
+        .map_err(|_| ApiError(AppError::NotFound("File missing on disk".to_string())))?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis())
+        .unwrap_or_default();
+
+    Ok(HlsSource {
+        path: track.path,
+        start_ms: track.start_ms,
+        end_ms: track.end_ms,
+        fingerprint: format!("{}_{}", meta.len(), mtime),
+    })
+}
+
+/// `{HLS_CACHE_DIR}/{track_id}/{fingerprint}/{bitrate}`
+fn variant_dir(id: Uuid, source: &HlsSource, bitrate: &str) -> PathBuf {
+    hls_cache_root()
+        .join(id.to_string())
+        .join(&source.fingerprint)
+        .join(bitrate)
+}
+
+/// 刷新播放列表的修改时间，作为该码率目录的 LRU 访问记录
+async fn touch(dir: &std::path::Path) {
+    let index = dir.join("index.m3u8");
+    let _ = tokio::task::spawn_blocking(move || {
+        std::fs::File::options()
+            .write(true)
+            .open(&index)
+            .and_then(|f| f.set_modified(SystemTime::now()))
+    })
+    .await;
+}
+
+fn playlist_response(body: String) -> Result<Response, ApiError> {
+    Response::builder()
+        .header(header::CONTENT_TYPE, PLAYLIST_MIME)
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(Body::from(body))
+        .map_err(|e| ApiError(AppError::Internal(e.to_string())))
+}
+
+/// 主播放列表：列出所有码率档位，由播放器按带宽自行选择
+pub async fn master_playlist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    crate::browse_user_id(&headers, &state).await?;
+    load_source(&state, id).await?;
+
+    let mut body = String::from("#EXTM3U\n#EXT-X-VERSION:3\n");
+    for (name, bandwidth) in HLS_VARIANTS {
+        body.push_str(&format!(
+            "#EXT-X-STREAM-INF:BANDWIDTH={},CODECS=\"mp4a.40.2\"\n{}/index.m3u8\n",
+            bandwidth, name
+        ));
+    }
+    playlist_response(body)
+}
+
+/// 某一码率的媒体播放列表，首次请求时整轨切片并缓存到磁盘
+pub async fn variant_playlist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((id, bitrate)): Path<(Uuid, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    crate::browse_user_id(&headers, &state).await?;
+    let source = load_source(&state, id).await?;
+    let dir = ensure_segments(&state, id, &source, &bitrate).await?;
+    touch(&dir).await;
+    let body = tokio::fs::read_to_string(dir.join("index.m3u8")).await?;
+    playlist_response(body)
+}
+
+pub async fn segment(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((id, bitrate, name)): Path<(Uuid, String, String)>,
+) -> Result<impl IntoResponse, ApiError> {
+    variant_bandwidth(&bitrate)?;
+    // 分段文件名由 ffmpeg 生成，形如 seg_00001.ts；其余一律拒绝，防止路径穿越
+    let valid = name
+        .strip_prefix("seg_")
+        .and_then(|rest| rest.strip_suffix(".ts"))
+        .map(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()))
+        .unwrap_or(false);
+    if !valid {
+        return Err(ApiError(AppError::BadRequest("Invalid segment name".to_string())));
+    }
+
+    crate::browse_user_id(&headers, &state).await?;
+    // 按源文件当前的指纹定位，文件被替换后旧切片返回 404，播放器重新拉取播放列表
+    let source = load_source(&state, id).await?;
+    let dir = variant_dir(id, &source, &bitrate);
+    let file = tokio::fs::File::open(dir.join(&name))
+        .await
+        .map_err(|_| ApiError(AppError::NotFound("Segment not found".to_string())))?;
+    let size = file.metadata().await?.len();
+    touch(&dir).await;
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "video/mp2t")
+        .header(header::CONTENT_LENGTH, size)
+        .header(header::CACHE_CONTROL, "private, max-age=86400")
+        .body(Body::from_stream(ReaderStream::new(file)))
+        .map_err(|e| ApiError(AppError::Internal(e.to_string())))
+}
+
+fn variant_bandwidth(bitrate: &str) -> Result<u32, ApiError> {
+    HLS_VARIANTS
+        .iter()
+        .find(|(name, _)| *name == bitrate)
+        .map(|(_, bw)| *bw)
+        .ok_or_else(|| ApiError(AppError::BadRequest(format!("Unsupported HLS bitrate: {}", bitrate))))
+}
+
+/// 确保 `{HLS_CACHE_DIR}/{track_id}/{fingerprint}/{bitrate}` 下已有完整切片，返回该目录。
+/// 先切到临时目录再整体改名，并发请求最多重复切一次，不会读到半成品。
+async fn ensure_segments(
+    state: &AppState,
+    id: Uuid,
+    track: &HlsSource,
+    bitrate: &str,
+) -> Result<PathBuf, ApiError> {
+    variant_bandwidth(bitrate)?;
+
+    let target = variant_dir(id, track, bitrate);
+    if target.join("index.m3u8").exists() {
+        return Ok(target);
+    }
+
+    if !state.ffmpeg_available {
+        return Err(crate::transcoding_unavailable());
+    }
+
+    let source = std::path::Path::new(&track.path);
+    let source_str = source
+        .to_str()
+        .ok_or_else(|| ApiError(AppError::Internal("Invalid path encoding".to_string())))?;
+
+    let version_dir = target
+        .parent()
+        .map(std::path::Path::to_path_buf)
+        .unwrap_or_else(hls_cache_root);
+    let work_dir = version_dir.join(format!(".{}-{}", bitrate, Uuid::new_v4()));
+    tokio::fs::create_dir_all(&work_dir).await?;
+    let segment_pattern = work_dir.join("seg_%05d.ts");
+    let playlist_path = work_dir.join("index.m3u8");
+
+    let mut args: Vec<String> = Vec::new();
+    // CUE 分轨：只切出本轨对应的区间
+    if track.start_ms > 0 {
+        args.extend(["-ss".to_string(), (track.start_ms as f64 / 1000.0).to_string()]);
+    }
+    args.extend(["-i".to_string(), source_str.to_string()]);
+    if let Some(end_ms) = track.end_ms {
+        args.extend(["-t".to_string(), ((end_ms - track.start_ms) as f64 / 1000.0).to_string()]);
+    }
+    args.extend(
+        [
+            "-map", "0:a:0", "-c:a", "aac", "-b:a", bitrate,
+            "-f", "hls", "-hls_time", HLS_SEGMENT_SECS, "-hls_playlist_type", "vod",
+        ]
+        .iter()
+        .map(|s| s.to_string()),
+    );
+    args.push("-hls_segment_filename".to_string());
+    args.push(segment_pattern.to_string_lossy().into_owned());
+    args.push(playlist_path.to_string_lossy().into_owned());
+
+    tracing::info!("Generating HLS segments for {} at {}", id, bitrate);
+    let status = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(&args)
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .await
+        .map_err(|e| ApiError(AppError::Internal(format!("FFmpeg failed: {}", e))))?;
+
+    if !status.success() {
+        let _ = tokio::fs::remove_dir_all(&work_dir).await;
+        return Err(ApiError(AppError::Internal(format!(
+            "FFmpeg exited with {} while segmenting {}",
+            status, id
+        ))));
+    }
+
+    if tokio::fs::rename(&work_dir, &target).await.is_err() {
+        // 其他请求已抢先完成，丢弃本次结果
+        let _ = tokio::fs::remove_dir_all(&work_dir).await;
+    }
+
+    // 源文件替换前的切片不会再被命中，连同超出上限的旧条目一并清理
+    let root = hls_cache_root();
+    let current = version_dir.clone();
+    let limit = max_bytes();
+    if let Err(e) = tokio::task::spawn_blocking(move || {
+        remove_stale_versions(&current);
+        evict(&root, limit);
+    })
+    .await
+    {
+        tracing::warn!("HLS cache eviction failed: {}", e);
+    }
+    Ok(target)
+}
+
+/// 删除同一曲目下指纹不同的旧版本目录
+fn remove_stale_versions(current: &std::path::Path) {
+    let (Some(track_dir), Some(name)) = (current.parent(), current.file_name()) else {
+        return;
+    };
+    let Ok(entries) = std::fs::read_dir(track_dir) else {
+        return;
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if entry.file_name() != name && entry.path().is_dir() {
+            let _ = std::fs::remove_dir_all(entry.path());
+        }
+    }
+}
+
+/// 以码率目录为单位按最近访问时间从旧到新删除，直到总大小不超过上限。正在生成的临时目录不参与
+pub fn evict(root: &std::path::Path, limit: u64) {
+    let mut variants: Vec<(PathBuf, u64, SystemTime)> = Vec::new();
+    for track_dir in read_dirs(root) {
+        for version_dir in read_dirs(&track_dir) {
+            for variant in read_dirs(&version_dir) {
+                if variant.file_name().is_some_and(|n| n.to_string_lossy().starts_with('.')) {
+                    continue;
+                }
+                let size = file_sizes(&variant).sum();
+                let accessed = std::fs::metadata(variant.join("index.m3u8"))
+                    .and_then(|m| m.modified())
+                    .unwrap_or(SystemTime::UNIX_EPOCH);
+                variants.push((variant, size, accessed));
+            }
+        }
+    }
+
+    let mut total: u64 = variants.iter().map(|(_, size, _)| size).sum();
+    if total <= limit {
+        return;
+    }
+    variants.sort_by_key(|(_, _, accessed)| *accessed);
+    for (dir, size, _) in variants {
+        if total <= limit {
+            break;
+        }
+        match std::fs::remove_dir_all(&dir) {
+            Ok(()) => {
+                total -= size;
+                tracing::debug!("Evicted HLS cache entry {:?}", dir);
+            }
+            Err(e) => tracing::warn!("Failed to evict {:?}: {}", dir, e),
+        }
+    }
+}
+
+fn read_dirs(dir: &std::path::Path) -> impl Iterator<Item = PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+}
+
+fn file_sizes(dir: &std::path::Path) -> impl Iterator<Item = u64> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .filter_map(|e| e.ok())
+        .filter_map(|e| e.metadata().ok())
+        .filter(|m| m.is_file())
+        .map(|m| m.len())
+}
@@ -1,4 +1,6 @@
 pub mod admin;
 pub mod auth;
+pub mod hls;
 pub mod music;
 pub mod playlist;
+pub mod presence;
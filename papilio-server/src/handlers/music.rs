@@ -2,7 +2,7 @@ use crate::{ApiError, AppState};
 use axum::{
     body::Body,
     extract::{Path, Query, State},
-    http::{header, HeaderMap, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
@@ -12,46 +12,550 @@ use papilio_core::models::music::{Album, Artist, Track, UpdateLyricOffset};
 use papilio_core::{error::AppError, scanner::Scanner};
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use sqlx::Row;
+use sqlx::{PgPool, Row};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::process::Stdio;
-use std::sync::Arc;
+use std::time::Duration;
+use std::sync::{Arc, Mutex};
 use tokio::process::Command;
 use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Default)]
 pub struct SearchQuery {
     pub q: Option<String>,
     pub album_id: Option<Uuid>,
     pub artist_id: Option<Uuid>,
     pub limit: Option<i64>,
     pub offset: Option<i64>,
+    /// 为 true 时 `list_tracks` / `list_albums` / `list_artists` 返回带总数的分页对象，而不是裸数组
+    pub paginated: Option<bool>,
+    /// 专辑 / 歌手列表排序：`name`、`year` (仅专辑)、`recently_added`
+    pub sort: Option<String>,
+    /// 以下为曲目过滤条件 (`list_tracks` / `global_search`)，多个条件之间为 AND。
+    /// 文件格式，逗号分隔 (如 `flac,wav`)，`lossless` 代表所有无损格式
+    pub format: Option<String>,
+    /// 最低码率 (kbps)
+    pub min_bitrate: Option<i32>,
+    /// 专辑发行年份区间，两端均包含
+    pub year_from: Option<i32>,
+    pub year_to: Option<i32>,
+    /// 时长区间 (秒)，两端均包含
+    pub min_duration: Option<i32>,
+    pub max_duration: Option<i32>,
+}
+
+/// 列表接口单页条数上限，与播放历史一致
+const LIST_MAX_LIMIT: i64 = 500;
+
+/// `format=lossless` 展开成的扩展名
+const LOSSLESS_FORMATS: &[&str] = &["flac", "wav", "aiff", "aif", "ape", "wv", "dsf", "dff"];
+
+impl SearchQuery {
+    /// 夹到 [1, LIST_MAX_LIMIT] 的单页条数，未指定时为 None
+    fn page_limit(&self) -> Option<i64> {
+        self.limit.map(|limit| limit.clamp(1, LIST_MAX_LIMIT))
+    }
+
+    /// 小写的格式列表，未指定时为 None
+    fn format_filter(&self) -> Option<Vec<String>> {
+        let formats: Vec<String> = self
+            .format
+            .as_deref()?
+            .split(',')
+            .map(|f| f.trim().trim_start_matches('.').to_ascii_lowercase())
+            .filter(|f| !f.is_empty())
+            .flat_map(|f| match f.as_str() {
+                "lossless" => LOSSLESS_FORMATS.iter().map(|s| s.to_string()).collect(),
+                _ => vec![f],
+            })
+            .collect();
+        (!formats.is_empty()).then_some(formats)
+    }
+
+    fn validate_track_filters(&self) -> Result<(), ApiError> {
+        let negative = [self.min_bitrate, self.min_duration, self.max_duration]
+            .iter()
+            .flatten()
+            .any(|v| *v < 0);
+        if negative {
+            return Err(ApiError(AppError::BadRequest(
+                "min_bitrate, min_duration and max_duration must not be negative".to_string(),
+            )));
+        }
+        if let (Some(from), Some(to)) = (self.year_from, self.year_to) {
+            if from > to {
+                return Err(ApiError(AppError::BadRequest(
+                    "year_from must not be greater than year_to".to_string(),
+                )));
+            }
+        }
+        if let (Some(min), Some(max)) = (self.min_duration, self.max_duration) {
+            if min > max {
+                return Err(ApiError(AppError::BadRequest(
+                    "min_duration must not be greater than max_duration".to_string(),
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 分页响应：`total` 为同一过滤条件下的总条数
+#[derive(Serialize)]
+pub struct Paginated<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub limit: i64,
+    pub offset: i64,
 }
 
 #[derive(Deserialize)]
 pub struct StreamQuery {
     pub bitrate: Option<String>,
+    /// 显式转码格式：mp3 / opus / aac
+    pub format: Option<String>,
     pub start_time: Option<f64>,
 }
 
+/// 可作为转码目标的格式：(MIME, ffmpeg 编码器, ffmpeg 容器)
+type TranscodeTarget = (&'static str, &'static str, &'static str);
+
+const TRANSCODE_TARGETS: &[TranscodeTarget] = &[
+    ("audio/mpeg", "libmp3lame", "mp3"),
+    ("audio/ogg", "libopus", "ogg"),
+    ("audio/aac", "aac", "adts"),
+];
+
+/// CUE 分轨无法按字节切片，客户端未要求其他格式时以无损 FLAC 输出
+const SEGMENT_TARGET: TranscodeTarget = ("audio/flac", "flac", "flac");
+
+/// 协商转码时使用的默认码率
+const DEFAULT_TRANSCODE_BITRATE: &str = "192k";
+
+/// 允许客户端指定的转码码率
+const ALLOWED_BITRATES: &[&str] = &["64k", "96k", "128k", "160k", "192k", "256k", "320k"];
+
+/// 记录 /stream 是否转码及原因，便于排查：`direct`、`direct; ...`、`transcode; mp3@192k`
+pub const TRANSCODE_DECISION_HEADER: &str = "x-transcode-decision";
+
+/// 请求码率不低于源文件码率时，重新编码只会浪费 CPU 而不会提升音质。源码率未知时返回 false
+pub fn bitrate_covers_source(requested: &str, source_kbps: Option<i32>) -> bool {
+    let Some(source) = source_kbps.filter(|b| *b > 0) else {
+        return false;
+    };
+    requested
+        .trim_end_matches('k')
+        .parse::<i32>()
+        .is_ok_and(|requested| requested >= source)
+}
+
+/// 只指定码率时能否直接返回原文件：码率不低于源文件，且 Accept 头没有排除源格式。
+/// 客户端无法播放源格式时仍需转码，码率再高也不能跳过
+pub fn bitrate_only_passthrough(
+    accept: Option<&str>,
+    format: &str,
+    requested: &str,
+    source_kbps: Option<i32>,
+) -> bool {
+    negotiate_transcode(accept, format).is_none() && bitrate_covers_source(requested, source_kbps)
+}
+
+fn with_transcode_decision(mut response: Response, decision: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(decision) {
+        response.headers_mut().insert(TRANSCODE_DECISION_HEADER, value);
+    }
+    response
+}
+
+/// 本次响应对应的播放时长 (秒，保留三位小数)。实时转码的管道没有 Content-Length，
+/// 前端据此在无缝切歌前预估剩余时间
+pub const CONTENT_DURATION_HEADER: &str = "x-content-duration";
+
+pub fn content_duration_value(secs: f64) -> String {
+    format!("{:.3}", secs.max(0.0))
+}
+
+fn with_content_duration(mut response: Response, secs: f64) -> Response {
+    if secs > 0.0 {
+        if let Ok(value) = HeaderValue::from_str(&content_duration_value(secs)) {
+            response.headers_mut().insert(CONTENT_DURATION_HEADER, value);
+        }
+    }
+    response
+}
+
+/// `?format=` 取值到转码目标的映射
+fn transcode_target_by_name(name: &str) -> Option<TranscodeTarget> {
+    let index = match name.to_ascii_lowercase().as_str() {
+        "mp3" => 0,
+        "opus" => 1,
+        "aac" => 2,
+        _ => return None,
+    };
+    Some(TRANSCODE_TARGETS[index])
+}
+
+/// 校验 `?bitrate=`，接受 `128k` 或 `128` 两种写法
+fn normalize_bitrate(value: &str) -> Option<&'static str> {
+    let value = value.trim().to_ascii_lowercase();
+    let value = value.strip_suffix('k').unwrap_or(&value);
+    ALLOWED_BITRATES
+        .iter()
+        .copied()
+        .find(|allowed| allowed.trim_end_matches('k') == value)
+}
+
+/// 根据文件扩展名给出源文件可能对应的 MIME 别名
+fn source_mime_aliases(format: &str) -> &'static [&'static str] {
+    match format.to_ascii_lowercase().as_str() {
+        "mp3" => &["audio/mpeg", "audio/mp3"],
+        "flac" => &["audio/flac", "audio/x-flac"],
+        "m4a" | "mp4" | "aac" => &["audio/mp4", "audio/x-m4a", "audio/aac"],
+        "ogg" | "oga" => &["audio/ogg"],
+        "opus" => &["audio/ogg", "audio/opus"],
+        "wav" => &["audio/wav", "audio/x-wav", "audio/wave"],
+        _ => &[],
+    }
+}
+
+/// 解析 Accept 头为 (媒体范围, q 值) 列表
+fn parse_accept(accept: &str) -> Vec<(String, f32)> {
+    accept
+        .split(',')
+        .filter_map(|part| {
+            let mut pieces = part.split(';');
+            let range = pieces.next()?.trim().to_ascii_lowercase();
+            if range.is_empty() {
+                return None;
+            }
+            let q = pieces
+                .filter_map(|p| p.trim().strip_prefix("q="))
+                .find_map(|v| v.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((range, q))
+        })
+        .collect()
+}
+
+/// 按 RFC 9110 的优先级 (精确匹配 > type/* > */*) 计算某个 MIME 的可接受程度
+fn accept_quality(accepted: &[(String, f32)], mime: &str) -> f32 {
+    let main_type = mime.split('/').next().unwrap_or_default();
+    let wildcard = format!("{}/*", main_type);
+    [mime, wildcard.as_str(), "*/*"]
+        .iter()
+        .find_map(|candidate| {
+            accepted
+                .iter()
+                .find(|(range, _)| range == candidate)
+                .map(|(_, q)| *q)
+        })
+        .unwrap_or(0.0)
+}
+
+/// 根据 Accept 头决定是否需要转码：客户端能直接播放源格式时返回 None
+fn negotiate_transcode(accept: Option<&str>, format: &str) -> Option<TranscodeTarget> {
+    let accepted = parse_accept(accept?);
+    if accepted.is_empty() {
+        return None;
+    }
+
+    let source_q = source_mime_aliases(format)
+        .iter()
+        .map(|mime| accept_quality(&accepted, mime))
+        .fold(0.0, f32::max);
+    if source_q > 0.0 {
+        return None;
+    }
+
+    TRANSCODE_TARGETS
+        .iter()
+        .map(|target| (*target, accept_quality(&accepted, target.0)))
+        .filter(|(_, q)| *q > 0.0)
+        .fold(None, |best: Option<(TranscodeTarget, f32)>, (target, q)| match best {
+            Some((_, best_q)) if best_q >= q => best,
+            _ => Some((target, q)),
+        })
+        .map(|(target, _)| target)
+}
+
+/// 两段式 seek 中输入前粗跳预留的秒数：粗跳按关键帧定位可能落在目标之前，
+/// 余下部分由输入后的 `-ss` 逐帧解码跳过
+const SEEK_PREROLL_SECS: f64 = 5.0;
+
+/// 生成 FFmpeg 的两段式 seek 参数，返回 (放在 `-i` 之前的参数, 放在 `-i` 之后的参数)
+pub fn transcode_seek_args(start: f64) -> (Vec<String>, Vec<String>) {
+    if start <= 0.0 {
+        return (Vec::new(), Vec::new());
+    }
+    let coarse = (start - SEEK_PREROLL_SECS).max(0.0);
+    let fine = start - coarse;
+
+    let mut input_args = Vec::new();
+    if coarse > 0.0 {
+        input_args.extend(["-ss".to_string(), format!("{:.3}", coarse)]);
+    }
+    let output_args = vec!["-ss".to_string(), format!("{:.3}", fine)];
+    (input_args, output_args)
+}
+
+/// `Range` 请求头的解析结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// 无法识别或多段请求：按 RFC 9110 忽略 Range，返回 200 全量内容
+    Full,
+    /// 单段区间，两端均为闭区间
+    Partial { start: u64, end: u64 },
+    /// 区间完全落在文件之外，应返回 416
+    Unsatisfiable,
+}
+
+/// 解析单段字节区间，支持 `bytes=a-b`、开放结尾 `bytes=a-` 与后缀 `bytes=-n`。
+/// 多段请求 (`bytes=0-99,200-299`) 不做 multipart 响应，统一回退为全量内容。
+pub fn parse_byte_range(header: &str, file_size: u64) -> ByteRange {
+    let spec = match header.trim().strip_prefix("bytes=") {
+        Some(spec) => spec.trim(),
+        None => return ByteRange::Full,
+    };
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+    let (first, last) = match spec.split_once('-') {
+        Some((first, last)) => (first.trim(), last.trim()),
+        None => return ByteRange::Full,
+    };
+
+    if first.is_empty() {
+        // 后缀区间：最后 n 个字节
+        return match last.parse::<u64>() {
+            Ok(0) => ByteRange::Unsatisfiable,
+            Ok(_) if file_size == 0 => ByteRange::Unsatisfiable,
+            Ok(n) => ByteRange::Partial {
+                start: file_size.saturating_sub(n),
+                end: file_size - 1,
+            },
+            Err(_) => ByteRange::Full,
+        };
+    }
+
+    let start = match first.parse::<u64>() {
+        Ok(start) => start,
+        Err(_) => return ByteRange::Full,
+    };
+    let end = if last.is_empty() {
+        None
+    } else {
+        match last.parse::<u64>() {
+            Ok(end) if end >= start => Some(end),
+            _ => return ByteRange::Full,
+        }
+    };
+
+    if start >= file_size {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial {
+        start,
+        end: end.map_or(file_size - 1, |end| end.min(file_size - 1)),
+    }
+}
+
 #[derive(Serialize)]
 pub struct TrackWithFavorite {
     #[serde(flatten)]
     pub track: Track,
     pub is_favorite: bool,
+    /// 当前用户的计数播放次数与最近播放时间，仅 `get_track` / `list_tracks` 提供，匿名访问时为 null
+    pub play_count: Option<i64>,
+    pub last_played_at: Option<DateTime<Utc>>,
+}
+
+/// 运行时查询 `TrackWithFavorite` 所需的列，配合 `from_row` 使用。
+/// 约定别名：t = tracks, f = user_favorites, a = artists, al = albums, m = user_track_metadata
+pub const TRACK_COLUMNS: &str = r#"t.id, t.title, t.album_id, t.artist_id,
+               t.duration, t.track_number,
+               t.disc_number, t.track_total, t.genre,
+               t.replaygain_track_gain, t.replaygain_album_gain,
+               t.path, t.bitrate,
+               t.format, t.size, t.bpm,
+               t.musicbrainz_track_id, t.isrc,
+               t.lyrics,
+               t.created_at, t.updated_at,
+               (f.user_id IS NOT NULL) as is_favorite,
+               a.name as artist_name,
+               a.image_url as artist_image_url,
+               al.title as album_title,
+               COALESCE(m.lyric_offset_ms, 0) as lyric_offset_ms"#;
+
+/// 按用户聚合的播放统计，和 `PLAY_STATS_JOIN` 一起追加在 `TRACK_COLUMNS` 之后。
+/// 约定 `$2` 为当前用户 (可为 NULL)，只有 `counted` 的播放计入次数
+pub const PLAY_STATS_COLUMNS: &str = r#"CASE WHEN $2::uuid IS NULL THEN NULL ELSE COALESCE(ps.play_count, 0) END as play_count,
+               ps.last_played_at"#;
+
+pub const PLAY_STATS_JOIN: &str = r#"LEFT JOIN (
+            SELECT track_id,
+                   COUNT(*) FILTER (WHERE counted) as play_count,
+                   MAX(played_at) as last_played_at
+            FROM play_history WHERE user_id = $2
+            GROUP BY track_id
+        ) ps ON t.id = ps.track_id"#;
+
+impl TrackWithFavorite {
+    pub fn from_row(row: &sqlx::postgres::PgRow) -> Result<Self, sqlx::Error> {
+        Ok(TrackWithFavorite {
+            track: Track {
+                id: row.try_get("id")?,
+                title: row.try_get("title")?,
+                album_id: row.try_get("album_id")?,
+                artist_id: row.try_get("artist_id")?,
+                artist_name: row.try_get("artist_name")?,
+                album_title: row.try_get("album_title")?,
+                artist_image_url: row.try_get("artist_image_url")?,
+                duration: row.try_get("duration")?,
+                track_number: row.try_get("track_number")?,
+                track_total: row.try_get("track_total")?,
+                disc_number: row.try_get::<Option<i32>, _>("disc_number")?.unwrap_or(1),
+                genre: row.try_get("genre")?,
+                replaygain_track_gain: row.try_get("replaygain_track_gain")?,
+                replaygain_album_gain: row.try_get("replaygain_album_gain")?,
+                path: row.try_get("path")?,
+                bitrate: row.try_get("bitrate")?,
+                format: row.try_get("format")?,
+                size: row.try_get("size")?,
+                bpm: row.try_get("bpm")?,
+                musicbrainz_track_id: row.try_get("musicbrainz_track_id")?,
+                isrc: row.try_get("isrc")?,
+                lyrics: row.try_get("lyrics")?,
+                lyric_offset_ms: row.try_get::<i32, _>("lyric_offset_ms")?,
+                created_at: row.try_get("created_at")?,
+                updated_at: row.try_get("updated_at")?,
+            },
+            is_favorite: row.try_get("is_favorite")?,
+            play_count: optional_column(row, "play_count")?,
+            last_played_at: optional_column(row, "last_played_at")?,
+        })
+    }
+}
+
+/// 未拼接 PLAY_STATS_COLUMNS 的查询没有播放统计列，视为 None；
+/// 列存在但解码失败时照常返回错误，不吞掉
+fn optional_column<'r, T>(row: &'r sqlx::postgres::PgRow, name: &str) -> Result<Option<T>, sqlx::Error>
+where
+    T: sqlx::Decode<'r, sqlx::Postgres> + sqlx::Type<sqlx::Postgres>,
+{
+    match row.try_get::<Option<T>, _>(name) {
+        Err(sqlx::Error::ColumnNotFound(_)) => Ok(None),
+        other => other,
+    }
+}
+
+#[derive(Serialize)]
+pub struct AlbumWithCount {
+    #[serde(flatten)]
+    pub album: Album,
+    pub track_count: i64,
+}
+
+impl AlbumWithCount {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        AlbumWithCount {
+            album: Album {
+                id: row.get("id"),
+                title: row.get("title"),
+                artist_id: row.get("artist_id"),
+                release_year: row.get("release_year"),
+                release_date: row.get("release_date"),
+                cover_path: row.get("cover_path"),
+                musicbrainz_album_id: row.get("musicbrainz_album_id"),
+                musicbrainz_release_group_id: row.get("musicbrainz_release_group_id"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                genre: row.get("genre"),
+                is_compilation: row.get("is_compilation"),
+            },
+            track_count: row.get("track_count"),
+        }
+    }
+}
+
+/// 热门专辑：播放次数为窗口内所有曲目的有效播放之和，收藏数为收藏该专辑的用户数
+#[derive(Serialize)]
+pub struct TopAlbum {
+    #[serde(flatten)]
+    pub album: AlbumWithCount,
+    pub play_count: i64,
+    pub favorite_count: i64,
+}
+
+#[derive(Serialize)]
+pub struct ArtistWithCount {
+    #[serde(flatten)]
+    pub artist: Artist,
+    pub album_count: i64,
+}
+
+impl ArtistWithCount {
+    fn from_row(row: &sqlx::postgres::PgRow) -> Self {
+        ArtistWithCount {
+            artist: Artist {
+                id: row.get("id"),
+                name: row.get("name"),
+                bio: row.get("bio"),
+                image_url: row.get("image_url"),
+                musicbrainz_artist_id: row.get("musicbrainz_artist_id"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+                genres: row.get("genres"),
+            },
+            album_count: row.get("album_count"),
+        }
+    }
+}
+
+/// 浏览排序到 ORDER BY 子句的白名单映射，`year` 只对专辑有意义
+fn browse_order_by(sort: Option<&str>, default: &str, is_album: bool) -> Result<&'static str, ApiError> {
+    let order = match (sort.unwrap_or(default), is_album) {
+        ("name", true) => "lower(al.title), al.title",
+        ("name", false) => "lower(a.name), a.name",
+        ("year", true) => "al.release_year DESC NULLS LAST, al.release_date DESC NULLS LAST, lower(al.title)",
+        ("recently_added", true) => "al.created_at DESC, al.id",
+        ("recently_added", false) => "a.created_at DESC, a.id",
+        (other, _) => {
+            return Err(ApiError(AppError::BadRequest(format!(
+                "Unsupported sort: {} (expected {})",
+                other,
+                if is_album { "name, year or recently_added" } else { "name or recently_added" }
+            ))))
+        }
+    };
+    Ok(order)
+}
+
+#[derive(Deserialize)]
+pub struct ScanQuery {
+    pub force: Option<bool>,
 }
 
 #[derive(Serialize)]
 pub struct ScanStatusResponse {
     pub is_scanning: bool,
+    /// idle / scanning / organizing
+    pub operation: String,
     pub current_count: i32,
     pub total_count: i32,
+    pub skipped_count: i32,
 }
 
 #[derive(Deserialize)]
 pub struct UpdatePlaybackRequest {
     pub track_id: Uuid,
     pub position_ms: i32,
+    /// 旧客户端不传时视为正在播放
+    #[serde(default)]
+    pub is_playing: Option<bool>,
 }
 
 #[derive(Serialize)]
@@ -65,25 +569,27 @@ pub async fn get_scan_status(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
-    let _user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let _user_id = crate::require_user_id(&headers, &state).await?;
 
     let row =
-        sqlx::query("SELECT is_scanning, current_count, total_count FROM scan_status WHERE id = 1")
+        sqlx::query("SELECT is_scanning, operation, current_count, total_count, skipped_count FROM scan_status WHERE id = 1")
             .fetch_optional(&state.db)
             .await?;
 
     match row {
         Some(r) => Ok(Json(ScanStatusResponse {
             is_scanning: r.get("is_scanning"),
+            operation: r.get("operation"),
             current_count: r.get("current_count"),
             total_count: r.get("total_count"),
+            skipped_count: r.get("skipped_count"),
         })),
         None => Ok(Json(ScanStatusResponse {
             is_scanning: false,
+            operation: "idle".to_string(),
             current_count: 0,
             total_count: 0,
+            skipped_count: 0,
         })),
     }
 }
@@ -91,10 +597,9 @@ pub async fn get_scan_status(
 pub async fn trigger_scan(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
+    Query(params): Query<ScanQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let user_is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
         .bind(user_id)
@@ -115,21 +620,38 @@ pub async fn trigger_scan(
     // 注意：这里需要一个 Dummy 调用或者暴露锁状态。
     // 为了不破坏封装，我们直接调用一个检查方法。
     
-    let scanner = Scanner::new(state.db.clone());
+    let scanner = Scanner::new(state.db.clone())
+        .with_progress_channel(state.scan_progress.clone())
+        .with_metadata_service(state.metadata_service.clone());
     if scanner.is_scanning() {
         return Err(ApiError(AppError::BadRequest(
             "A scan is already in progress".to_string(),
         )));
     }
+    // SCAN_LOCK 只覆盖本进程；papilio-admin 等其他进程执行扫描/整理时会在 scan_status 中置位
+    let running_elsewhere: bool =
+        sqlx::query_scalar("SELECT COALESCE((SELECT is_scanning FROM scan_status WHERE id = 1), FALSE)")
+            .fetch_one(&state.db)
+            .await?;
+    if running_elsewhere {
+        return Err(ApiError(AppError::BadRequest(
+            "A scan is already in progress".to_string(),
+        )));
+    }
 
     let scan_path = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
+    // 默认增量扫描，?force=true 时强制重新处理所有文件
+    let force = params.force.unwrap_or(false);
 
     // 尝试在主线程触发一次，如果已经被锁，scan_directory 会返回 BadRequest
     // 但因为它是异步的且在后台运行，我们需要一个非阻塞的检查方式。
     // 修改：我们在 Scanner 中增加 is_locked 方法。
 
+    // 空库保护在返回 202 之前完成，曲库未挂载时请求方直接收到 400
+    scanner.check_library_mounted(&scan_path).await?;
+
     tokio::spawn(async move {
-        if let Err(e) = scanner.scan_directory(&scan_path).await {
+        if let Err(e) = scanner.scan_directory(&scan_path, force).await {
             tracing::error!("Scan task failed: {:?}", e);
         }
     });
@@ -140,14 +662,48 @@ pub async fn trigger_scan(
     ))
 }
 
+/// SSE 推送扫描进度。先推送当前快照；观察到扫描从进行中变为结束后关闭流，
+/// 订阅时处于空闲状态的连接会一直等到下一次扫描完成
+pub async fn scan_events(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    crate::require_user_id(&headers, &state).await?;
+
+    let rx = state.scan_progress.subscribe();
+    // 状态: (接收端, 是否已推送首个快照, 是否见过进行中的状态, 是否已结束)
+    let stream = futures_util::stream::unfold(
+        (rx, false, false, false),
+        |(mut rx, started, seen_running, done)| async move {
+            if done {
+                return None;
+            }
+            if started && rx.changed().await.is_err() {
+                return None;
+            }
+            let progress = *rx.borrow_and_update();
+            let event = Event::default()
+                .event("progress")
+                .retry(Duration::from_secs(5))
+                .json_data(progress)
+                .unwrap_or_else(|_| Event::default().event("progress"));
+            let finished = seen_running && !progress.is_scanning;
+            Some((
+                Ok::<_, Infallible>(event),
+                (rx, true, seen_running || progress.is_scanning, finished),
+            ))
+        },
+    );
+
+    Ok(Sse::new(stream).keep_alive(KeepAlive::default()))
+}
+
 pub async fn update_playback_state(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Json(payload): Json<UpdatePlaybackRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     sqlx::query(
         "INSERT INTO user_playback_state (user_id, track_id, position_ms, updated_at)
@@ -159,6 +715,19 @@ pub async fn update_playback_state(
     .bind(payload.position_ms)
     .execute(&state.db).await?;
 
+    // "正在收听"只是附带功能，失败不影响进度保存
+    if let Err(e) = crate::handlers::presence::publish_now_playing(
+        &state,
+        user_id,
+        payload.track_id,
+        payload.position_ms,
+        payload.is_playing.unwrap_or(true),
+    )
+    .await
+    {
+        tracing::warn!("Failed to publish now playing for {}: {}", user_id, e.0);
+    }
+
     Ok(StatusCode::OK)
 }
 
@@ -166,9 +735,7 @@ pub async fn get_playback_state(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let row = sqlx::query(
         "SELECT track_id, position_ms, updated_at FROM user_playback_state WHERE user_id = $1",
@@ -189,96 +756,234 @@ pub async fn get_playback_state(
 
 #[derive(Serialize)]
 pub struct GlobalSearchResponse {
-    pub artists: Vec<Artist>,
+    pub artists: Vec<ArtistSearchResult>,
     pub albums: Vec<Album>,
     pub tracks: Vec<TrackWithFavorite>,
 }
 
+/// 搜索命中的歌手，附带其专辑 (含参与演唱的合辑) 与热门曲目，供前端展示歌手卡片
+#[derive(Serialize)]
+pub struct ArtistSearchResult {
+    #[serde(flatten)]
+    pub artist: Artist,
+    pub albums: Vec<AlbumWithCount>,
+    pub top_tracks: Vec<TrackWithFavorite>,
+}
+
+/// 每个命中歌手附带的专辑数与热门曲目数
+const ARTIST_RESULT_ALBUMS: i64 = 6;
+const ARTIST_RESULT_TRACKS: i64 = 5;
+
+/// 为命中的歌手批量查询专辑与热门曲目 (各一次 LATERAL 查询，不随歌手数增加)。
+/// 专辑中本人名下的排在合辑之前；热门曲目按全站有效播放次数排序
+async fn attach_artist_content(
+    state: &AppState,
+    artists: Vec<Artist>,
+    user_id: Option<Uuid>,
+) -> Result<Vec<ArtistSearchResult>, ApiError> {
+    if artists.is_empty() {
+        return Ok(Vec::new());
+    }
+    let ids: Vec<Uuid> = artists.iter().map(|a| a.id).collect();
+
+    let album_rows = sqlx::query(
+        r#"
+        SELECT ma.id AS matched_artist_id, al.*,
+               (SELECT COUNT(*) FROM tracks ct WHERE ct.album_id = al.id AND ct.deleted_at IS NULL) AS track_count
+        FROM unnest($1::uuid[]) AS ma(id)
+        CROSS JOIN LATERAL (
+            SELECT x.* FROM albums x
+            WHERE x.artist_id = ma.id
+               OR (x.is_compilation AND EXISTS (
+                   SELECT 1 FROM tracks ct
+                   WHERE ct.album_id = x.id AND ct.artist_id = ma.id AND ct.deleted_at IS NULL))
+            ORDER BY (x.artist_id = ma.id) DESC, x.release_year DESC NULLS LAST, x.title
+            LIMIT $2
+        ) al
+        ORDER BY ma.id, (al.artist_id = ma.id) DESC, al.release_year DESC NULLS LAST, al.title
+        "#,
+    )
+    .bind(&ids)
+    .bind(ARTIST_RESULT_ALBUMS)
+    .fetch_all(&state.db)
+    .await?;
+
+    let track_rows = sqlx::query(&format!(
+        r#"
+        SELECT ma.id AS matched_artist_id, {TRACK_COLUMNS}
+        FROM unnest($1::uuid[]) AS ma(id)
+        CROSS JOIN LATERAL (
+            SELECT t2.id,
+                   (SELECT COUNT(*) FROM play_history ph WHERE ph.track_id = t2.id AND ph.counted) AS plays
+            FROM tracks t2
+            WHERE t2.artist_id = ma.id AND t2.deleted_at IS NULL
+            ORDER BY plays DESC, t2.title
+            LIMIT $3
+        ) top
+        JOIN tracks t ON t.id = top.id
+        LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
+        ORDER BY ma.id, top.plays DESC, t.title
+        "#
+    ))
+    .bind(&ids)
+    .bind(user_id)
+    .bind(ARTIST_RESULT_TRACKS)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut albums: HashMap<Uuid, Vec<AlbumWithCount>> = HashMap::new();
+    for row in &album_rows {
+        albums
+            .entry(row.get("matched_artist_id"))
+            .or_default()
+            .push(AlbumWithCount::from_row(row));
+    }
+    let mut top_tracks: HashMap<Uuid, Vec<TrackWithFavorite>> = HashMap::new();
+    for row in &track_rows {
+        top_tracks
+            .entry(row.get("matched_artist_id"))
+            .or_default()
+            .push(TrackWithFavorite::from_row(row)?);
+    }
+
+    Ok(artists
+        .into_iter()
+        .map(|artist| ArtistSearchResult {
+            albums: albums.remove(&artist.id).unwrap_or_default(),
+            top_tracks: top_tracks.remove(&artist.id).unwrap_or_default(),
+            artist,
+        })
+        .collect())
+}
+
+/// 短于该字符数的查询不走三元组模糊匹配 (pg_trgm 至少需要 3 个字符)，只做子串匹配
+const FUZZY_SEARCH_MIN_CHARS: usize = 3;
+
+/// 转义 LIKE 通配符，用户输入的 `%` / `_` 按字面匹配
+pub(crate) fn escape_like(q: &str) -> String {
+    q.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// 全局搜索：按相关度排序。评分取曲名/歌手/专辑中最高的一项：
+/// 完全相同 > 前缀 > 子串 > 三元组词相似度，歌手、专辑命中的权重略低于曲名
 pub async fn global_search(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Query(params): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state).await;
-    let q_str = params.q.clone().unwrap_or_default();
-    let q = format!("%{}%", q_str);
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    params.validate_track_filters()?;
+    let q_str = params.q.clone().unwrap_or_default().trim().to_string();
+    let pattern = escape_like(&q_str);
+    let fuzzy = q_str.chars().count() >= FUZZY_SEARCH_MIN_CHARS;
 
     // Search Artists
     let artists = sqlx::query_as!(
         Artist,
-        "SELECT * FROM artists WHERE name ILIKE $1 ORDER BY name LIMIT 5",
-        q
+        r#"
+        SELECT artists.* FROM artists
+        WHERE papilio_search_norm(name) LIKE '%' || papilio_search_norm($1) || '%'
+           OR ($3 AND papilio_search_norm($2) <% papilio_search_norm(name))
+        ORDER BY (papilio_search_norm(name) = papilio_search_norm($2)) DESC,
+                 word_similarity(papilio_search_norm($2), papilio_search_norm(name)) DESC,
+                 name
+        LIMIT 5
+        "#,
+        pattern,
+        q_str,
+        fuzzy
     )
     .fetch_all(&state.db)
     .await?;
 
+    let artists = attach_artist_content(&state, artists, user_id).await?;
+
     // Search Albums
     let albums = sqlx::query_as!(
         Album,
-        "SELECT * FROM albums WHERE title ILIKE $1 ORDER BY release_year DESC LIMIT 5",
-        q
+        r#"
+        SELECT albums.* FROM albums
+        WHERE papilio_search_norm(title) LIKE '%' || papilio_search_norm($1) || '%'
+           OR ($3 AND papilio_search_norm($2) <% papilio_search_norm(title))
+        ORDER BY (papilio_search_norm(title) = papilio_search_norm($2)) DESC,
+                 word_similarity(papilio_search_norm($2), papilio_search_norm(title)) DESC,
+                 release_year DESC
+        LIMIT 5
+        "#,
+        pattern,
+        q_str,
+        fuzzy
     )
     .fetch_all(&state.db)
     .await?;
 
     // Search Tracks
-    let rows = sqlx::query!(
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT t.id, t.title, t.album_id, t.artist_id,
-               t.duration, t.track_number,
-               t.disc_number, t.path, t.bitrate,
-               t.format, t.size, t.bpm,
-               t.musicbrainz_track_id,
-               t.lyrics,
-               t.created_at, t.updated_at,
-               (f.user_id IS NOT NULL) as "is_favorite!",
-               a.name as "artist_name?",
-               a.image_url as "artist_image_url?",
-               al.title as "album_title?",
-               COALESCE(m.lyric_offset_ms, 0) as "lyric_offset_ms!"
+        WITH q AS (
+            SELECT papilio_search_norm($3) AS v, '%' || papilio_search_norm($4) || '%' AS pat
+        )
+        SELECT {TRACK_COLUMNS}
         FROM tracks t
+        CROSS JOIN q
         LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
         LEFT JOIN artists a ON t.artist_id = a.id
         LEFT JOIN albums al ON t.album_id = al.id
         LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
-        WHERE t.title ILIKE $1
-        ORDER BY t.title
-        LIMIT 20
-        "#,
-        q,
-        user_id
-    )
+        WHERE (papilio_search_norm(t.title) LIKE q.pat
+           OR papilio_search_norm(a.name) LIKE q.pat
+           OR papilio_search_norm(al.title) LIKE q.pat
+           OR ($5 AND (q.v <% papilio_search_norm(t.title)
+                    OR q.v <% papilio_search_norm(a.name)
+                    OR q.v <% papilio_search_norm(al.title))))
+          AND ($6::text[] IS NULL OR LOWER(t.format) = ANY($6))
+          AND ($7::int IS NULL OR t.bitrate >= $7)
+          AND ($8::int IS NULL OR al.release_year >= $8)
+          AND ($9::int IS NULL OR al.release_year <= $9)
+          AND ($10::int IS NULL OR t.duration >= $10)
+          AND ($11::int IS NULL OR t.duration <= $11)
+          AND t.deleted_at IS NULL
+        ORDER BY GREATEST(
+                CASE
+                    WHEN papilio_search_norm(t.title) = q.v THEN 3.0
+                    WHEN papilio_search_norm(t.title) LIKE papilio_search_norm($4) || '%' THEN 2.0
+                    WHEN papilio_search_norm(t.title) LIKE q.pat THEN 1.5
+                    ELSE word_similarity(q.v, papilio_search_norm(t.title))
+                END,
+                CASE
+                    WHEN papilio_search_norm(a.name) LIKE q.pat THEN 1.2
+                    ELSE word_similarity(q.v, papilio_search_norm(a.name)) * 0.9
+                END,
+                CASE
+                    WHEN papilio_search_norm(al.title) LIKE q.pat THEN 1.1
+                    ELSE word_similarity(q.v, papilio_search_norm(al.title)) * 0.8
+                END
+            ) DESC,
+            t.title
+        LIMIT $1
+        "#
+    ))
+    .bind(params.limit.unwrap_or(20).clamp(1, 100))
+    .bind(user_id)
+    .bind(&q_str)
+    .bind(&pattern)
+    .bind(fuzzy)
+    .bind(params.format_filter())
+    .bind(params.min_bitrate)
+    .bind(params.year_from)
+    .bind(params.year_to)
+    .bind(params.min_duration)
+    .bind(params.max_duration)
     .fetch_all(&state.db)
     .await?;
 
     let tracks = rows
-        .into_iter()
-        .map(|row| TrackWithFavorite {
-            track: Track {
-                id: row.id,
-                title: row.title,
-                album_id: row.album_id,
-                artist_id: row.artist_id,
-                artist_name: row.artist_name,
-                album_title: row.album_title,
-                artist_image_url: row.artist_image_url,
-                duration: row.duration,
-                track_number: row.track_number,
-                disc_number: row.disc_number.unwrap_or(1),
-                path: row.path,
-                bitrate: row.bitrate,
-                format: row.format,
-                size: row.size,
-                bpm: row.bpm,
-                musicbrainz_track_id: row.musicbrainz_track_id,
-                lyrics: row.lyrics,
-                lyric_offset_ms: row.lyric_offset_ms,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-            },
-            is_favorite: row.is_favorite,
-        })
-        .collect::<Vec<_>>();
+        .iter()
+        .map(TrackWithFavorite::from_row)
+        .collect::<Result<Vec<_>, _>>()?;
 
     Ok(Json(GlobalSearchResponse {
         artists,
@@ -289,30 +994,113 @@ pub async fn global_search(
 
 pub async fn list_artists(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(params): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
+    crate::browse_user_id(&headers, &state).await?;
+    // 未指定 limit 时返回全部，客户端按 id 查找歌手时依赖这一点
+    let limit = params.page_limit();
     let q = format!("%{}%", params.q.unwrap_or_default());
-    let artists = sqlx::query_as!(
-        Artist,
-        "SELECT * FROM artists WHERE name ILIKE $1 ORDER BY name",
-        q
-    )
-    .fetch_all(&state.db)
-    .await?;
-    Ok(Json(artists))
+    let order_by = browse_order_by(params.sort.as_deref(), "name", false)?;
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let sql = format!(
+        r#"
+        SELECT a.*, COUNT(al.id) AS album_count
+        FROM artists a
+        LEFT JOIN albums al ON al.artist_id = a.id
+        WHERE a.name ILIKE $1
+        GROUP BY a.id
+        ORDER BY {}
+        LIMIT $2 OFFSET $3
+        "#,
+        order_by
+    );
+    let artists = sqlx::query(&sql)
+        .bind(&q)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await?
+        .iter()
+        .map(ArtistWithCount::from_row)
+        .collect::<Vec<_>>();
+
+    if !params.paginated.unwrap_or(false) {
+        return Ok(Json(artists).into_response());
+    }
+
+    let total: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM artists WHERE name ILIKE $1")
+        .bind(&q)
+        .fetch_one(&state.db)
+        .await?;
+    Ok(Json(Paginated {
+        items: artists,
+        total,
+        limit: limit.unwrap_or(total),
+        offset,
+    })
+    .into_response())
 }
 
+/// 按歌手筛选专辑：除该歌手名下的专辑外，也包含其参与演唱的合辑
+const ALBUM_ARTIST_FILTER: &str = "($2::uuid IS NULL OR al.artist_id = $2 OR (al.is_compilation AND EXISTS (SELECT 1 FROM tracks ct WHERE ct.album_id = al.id AND ct.artist_id = $2 AND ct.deleted_at IS NULL)))";
+
 pub async fn list_albums(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Query(params): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
+    crate::browse_user_id(&headers, &state).await?;
     println!("DEBUG: list_albums called");
+    // 未指定 limit 时返回全部，与歌手列表一致
+    let limit = params.page_limit();
     let q = format!("%{}%", params.q.unwrap_or_default());
-    let albums = sqlx::query_as!(Album,
-        "SELECT * FROM albums WHERE (title ILIKE $1 OR $1 = '%%') AND ($2::uuid IS NULL OR artist_id = $2) ORDER BY release_year DESC",
-        q, params.artist_id
-    ).fetch_all(&state.db).await?;
-    Ok(Json(albums))
+    let order_by = browse_order_by(params.sort.as_deref(), "year", true)?;
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let sql = format!(
+        r#"
+        SELECT al.*, COUNT(t.id) AS track_count
+        FROM albums al
+        LEFT JOIN tracks t ON t.album_id = al.id AND t.deleted_at IS NULL
+        WHERE (al.title ILIKE $1 OR $1 = '%%') AND {}
+        GROUP BY al.id
+        ORDER BY {}
+        LIMIT $3 OFFSET $4
+        "#,
+        ALBUM_ARTIST_FILTER, order_by
+    );
+    let albums = sqlx::query(&sql)
+        .bind(&q)
+        .bind(params.artist_id)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&state.db)
+        .await?
+        .iter()
+        .map(AlbumWithCount::from_row)
+        .collect::<Vec<_>>();
+
+    if !params.paginated.unwrap_or(false) {
+        return Ok(Json(albums).into_response());
+    }
+
+    let total: i64 = sqlx::query_scalar(&format!(
+        "SELECT COUNT(*) FROM albums al WHERE (al.title ILIKE $1 OR $1 = '%%') AND {}",
+        ALBUM_ARTIST_FILTER
+    ))
+    .bind(&q)
+    .bind(params.artist_id)
+    .fetch_one(&state.db)
+    .await?;
+    Ok(Json(Paginated {
+        items: albums,
+        total,
+        limit: limit.unwrap_or(total),
+        offset,
+    })
+    .into_response())
 }
 
 pub async fn get_track(
@@ -320,64 +1108,37 @@ pub async fn get_track(
     headers: HeaderMap,
     axum::extract::Path(id): axum::extract::Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state).await;
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    Ok(Json(fetch_track(&state.db, user_id, id).await?))
+}
 
-    // 安全审计修正：取消强制非空标志，处理匿名访问
-    let row = sqlx::query!(
+/// 单曲详情，已软删除的曲目视为不存在
+pub async fn fetch_track(
+    db: &PgPool,
+    user_id: Option<Uuid>,
+    id: Uuid,
+) -> Result<TrackWithFavorite, ApiError> {
+    // 匿名访问时 user_id 为 NULL，is_favorite 恒为 false
+    let row = sqlx::query(&format!(
         r#"
-        SELECT t.id, t.title, t.album_id, t.artist_id,
-               t.duration, t.track_number,
-               t.disc_number, t.path, t.bitrate,
-               t.format, t.size, t.bpm,
-               t.musicbrainz_track_id,
-               t.lyrics,
-               t.created_at, t.updated_at,
-               (f.user_id IS NOT NULL) as is_favorite,
-               a.name as "artist_name?",
-               a.image_url as "artist_image_url?",
-               al.title as "album_title?",
-               COALESCE(m.lyric_offset_ms, 0) as lyric_offset_ms
+        SELECT {TRACK_COLUMNS},
+               {PLAY_STATS_COLUMNS}
         FROM tracks t
         LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
         LEFT JOIN artists a ON t.artist_id = a.id
         LEFT JOIN albums al ON t.album_id = al.id
         LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
-        WHERE t.id = $1
-        "#,
-        id,
-        user_id
-    )
-    .fetch_optional(&state.db)
+        {PLAY_STATS_JOIN}
+        WHERE t.id = $1 AND t.deleted_at IS NULL
+        "#
+    ))
+    .bind(id)
+    .bind(user_id)
+    .fetch_optional(db)
     .await?
     .ok_or_else(|| ApiError(AppError::NotFound("Track not found".to_string())))?;
 
-    let track = Track {
-        id: row.id,
-        title: row.title,
-        album_id: row.album_id,
-        artist_id: row.artist_id,
-        artist_name: row.artist_name,
-        album_title: row.album_title,
-        artist_image_url: row.artist_image_url,
-        duration: row.duration,
-        track_number: row.track_number,
-        disc_number: row.disc_number.unwrap_or(1),
-        path: row.path,
-        bitrate: row.bitrate,
-        format: row.format,
-        size: row.size,
-        bpm: row.bpm,
-        musicbrainz_track_id: row.musicbrainz_track_id,
-        lyrics: row.lyrics,
-        lyric_offset_ms: row.lyric_offset_ms.unwrap_or(0),
-        created_at: row.created_at,
-        updated_at: row.updated_at,
-    };
-
-    Ok(Json(TrackWithFavorite {
-        track,
-        is_favorite: row.is_favorite.unwrap_or(false),
-    }))
+    Ok(TrackWithFavorite::from_row(&row)?)
 }
 
 pub async fn list_tracks(
@@ -386,74 +1147,379 @@ pub async fn list_tracks(
     Query(params): Query<SearchQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     println!("DEBUG: list_tracks called");
-    let user_id = crate::get_user_id(&headers, &state).await;
-    let q = format!("%{}%", params.q.unwrap_or_default());
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    params.validate_track_filters()?;
+    let formats = params.format_filter();
+    let q = escape_like(params.q.as_deref().unwrap_or_default().trim());
+    let limit = params.page_limit().unwrap_or(50);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let tracks = query_tracks(&state.db, user_id, &params).await?;
+
+    if !params.paginated.unwrap_or(false) {
+        return Ok(Json(tracks).into_response());
+    }
 
-    let rows = sqlx::query!(
+    // 计数与上面的列表使用完全相同的过滤条件
+    let total = sqlx::query_scalar!(
         r#"
-        SELECT t.id, t.title, t.album_id, t.artist_id,
-               t.duration, t.track_number,
-               t.disc_number, t.path, t.bitrate,
-               t.format, t.size, t.bpm,
-               t.musicbrainz_track_id,
-               t.lyrics,
-               t.created_at, t.updated_at,
-               (f.user_id IS NOT NULL) as is_favorite,
-               a.name as "artist_name?",
-               a.image_url as "artist_image_url?",
-               al.title as "album_title?",
-               COALESCE(m.lyric_offset_ms, 0) as lyric_offset_ms
+        SELECT COUNT(*) as "count!" FROM tracks t
+        LEFT JOIN albums al ON t.album_id = al.id
+        WHERE ($1 = '' OR papilio_search_norm(t.title) LIKE '%' || papilio_search_norm($1) || '%')
+          AND ($2::uuid IS NULL OR t.album_id = $2)
+          AND ($3::uuid IS NULL OR t.artist_id = $3)
+          AND ($4::text[] IS NULL OR LOWER(t.format) = ANY($4))
+          AND ($5::int IS NULL OR t.bitrate >= $5)
+          AND ($6::int IS NULL OR al.release_year >= $6)
+          AND ($7::int IS NULL OR al.release_year <= $7)
+          AND ($8::int IS NULL OR t.duration >= $8)
+          AND ($9::int IS NULL OR t.duration <= $9)
+          AND t.deleted_at IS NULL
+        "#,
+        q,
+        params.album_id,
+        params.artist_id,
+        formats.as_deref(),
+        params.min_bitrate,
+        params.year_from,
+        params.year_to,
+        params.min_duration,
+        params.max_duration
+    )
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(Paginated {
+        items: tracks,
+        total,
+        limit,
+        offset,
+    })
+    .into_response())
+}
+
+/// 曲目列表查询，`user_id` 为 None (匿名) 时 is_favorite 恒为 false、播放统计为 null。
+/// 调用方负责先执行 `validate_track_filters`
+pub async fn query_tracks(
+    db: &PgPool,
+    user_id: Option<Uuid>,
+    params: &SearchQuery,
+) -> Result<Vec<TrackWithFavorite>, ApiError> {
+    let formats = params.format_filter();
+    let q = escape_like(params.q.as_deref().unwrap_or_default().trim());
+    let limit = params.page_limit().unwrap_or(50);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS},
+               {PLAY_STATS_COLUMNS}
         FROM tracks t
         LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
         LEFT JOIN artists a ON t.artist_id = a.id
         LEFT JOIN albums al ON t.album_id = al.id
         LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
-        WHERE (t.title ILIKE $1 OR $1 = '%%')
+        {PLAY_STATS_JOIN}
+        WHERE ($1 = '' OR papilio_search_norm(t.title) LIKE '%' || papilio_search_norm($1) || '%')
           AND ($3::uuid IS NULL OR t.album_id = $3)
           AND ($4::uuid IS NULL OR t.artist_id = $4)
-        ORDER BY t.album_id, t.track_number, t.title
+          AND ($7::text[] IS NULL OR LOWER(t.format) = ANY($7))
+          AND ($8::int IS NULL OR t.bitrate >= $8)
+          AND ($9::int IS NULL OR al.release_year >= $9)
+          AND ($10::int IS NULL OR al.release_year <= $10)
+          AND ($11::int IS NULL OR t.duration >= $11)
+          AND ($12::int IS NULL OR t.duration <= $12)
+          AND t.deleted_at IS NULL
+        ORDER BY t.album_id, t.disc_number, t.track_number, t.title
         LIMIT $5 OFFSET $6
-        "#,
-        q,
-        user_id,
-        params.album_id,
-        params.artist_id,
-        params.limit.unwrap_or(50),
-        params.offset.unwrap_or(0)
-    )
+        "#
+    ))
+    .bind(&q)
+    .bind(user_id)
+    .bind(params.album_id)
+    .bind(params.artist_id)
+    .bind(limit)
+    .bind(offset)
+    .bind(formats.as_deref())
+    .bind(params.min_bitrate)
+    .bind(params.year_from)
+    .bind(params.year_to)
+    .bind(params.min_duration)
+    .bind(params.max_duration)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.iter().map(TrackWithFavorite::from_row).collect::<Result<_, _>>()?)
+}
+
+#[derive(Deserialize)]
+pub struct DiscoverQuery {
+    pub limit: Option<i64>,
+    /// `user` (默认，需登录) 或 `global`
+    pub scope: Option<String>,
+    /// 只统计最近 N 天的播放，省略时统计全部
+    pub days: Option<i32>,
+    /// 仅 `/albums/top`：`plays` (默认) 或 `favorites`
+    pub sort: Option<String>,
+}
+
+const DEFAULT_DISCOVER_LIMIT: i64 = 20;
+const MAX_DISCOVER_LIMIT: i64 = 200;
+
+/// 播放最多的曲目，只统计达到 scrobble 阈值的播放。未登录时退化为全站统计
+pub async fn list_top_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<DiscoverQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_DISCOVER_LIMIT).clamp(1, MAX_DISCOVER_LIMIT);
+    let scope_user = match params.scope.as_deref() {
+        None | Some("user") => user_id,
+        Some("global") => None,
+        Some(other) => {
+            return Err(ApiError(AppError::BadRequest(format!(
+                "Unknown scope: {} (expected user or global)",
+                other
+            ))))
+        }
+    };
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS}
+        FROM tracks t
+        JOIN (
+            SELECT track_id, COUNT(*) AS play_count, MAX(played_at) AS last_played
+            FROM play_history
+            WHERE counted
+              AND ($3::uuid IS NULL OR user_id = $3)
+              AND ($4::int IS NULL OR played_at > NOW() - make_interval(days => $4))
+            GROUP BY track_id
+        ) p ON t.id = p.track_id
+        LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
+        WHERE t.deleted_at IS NULL
+        ORDER BY p.play_count DESC, p.last_played DESC
+        LIMIT $1
+        "#
+    ))
+    .bind(limit)
+    .bind(user_id)
+    .bind(scope_user)
+    .bind(params.days.filter(|d| *d > 0))
     .fetch_all(&state.db)
     .await?;
 
-    let tracks = rows
-        .into_iter()
-        .map(|row| TrackWithFavorite {
-            track: Track {
-                id: row.id,
-                title: row.title,
-                album_id: row.album_id,
-                artist_id: row.artist_id,
-                artist_name: row.artist_name,
-                album_title: row.album_title,
-                artist_image_url: row.artist_image_url,
-                duration: row.duration,
-                track_number: row.track_number,
-                disc_number: row.disc_number.unwrap_or(1),
-                path: row.path,
-                bitrate: row.bitrate,
-                format: row.format,
-                size: row.size,
-                bpm: row.bpm,
-                musicbrainz_track_id: row.musicbrainz_track_id,
-                lyrics: row.lyrics,
-                lyric_offset_ms: row.lyric_offset_ms.unwrap_or(0),
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-            },
-            is_favorite: row.is_favorite.unwrap_or(false),
+    Ok(Json(
+        rows.iter()
+            .map(TrackWithFavorite::from_row)
+            .collect::<Result<Vec<_>, _>>()?,
+    ))
+}
+
+/// 播放最多 (或收藏最多) 的专辑，播放统计规则与 `list_top_tracks` 相同
+pub async fn list_top_albums(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<DiscoverQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_DISCOVER_LIMIT).clamp(1, MAX_DISCOVER_LIMIT);
+    let scope_user = match params.scope.as_deref() {
+        None | Some("user") => user_id,
+        Some("global") => None,
+        Some(other) => {
+            return Err(ApiError(AppError::BadRequest(format!(
+                "Unknown scope: {} (expected user or global)",
+                other
+            ))))
+        }
+    };
+    let albums = query_top_albums(
+        &state.db,
+        scope_user,
+        params.days.filter(|d| *d > 0),
+        params.sort.as_deref(),
+        limit,
+    )
+    .await?;
+    Ok(Json(albums))
+}
+
+/// `list_top_albums` 的查询部分。`scope_user` 为 None 时统计全站播放，`days` 为 None 时不限时间
+pub async fn query_top_albums(
+    db: &PgPool,
+    scope_user: Option<Uuid>,
+    days: Option<i32>,
+    sort: Option<&str>,
+    limit: i64,
+) -> Result<Vec<TopAlbum>, ApiError> {
+    let (filter, order_by) = match sort {
+        None | Some("plays") => ("p.play_count > 0", "p.play_count DESC, p.last_played DESC"),
+        Some("favorites") => (
+            "fc.favorite_count > 0",
+            "fc.favorite_count DESC, COALESCE(p.play_count, 0) DESC",
+        ),
+        Some(other) => {
+            return Err(ApiError(AppError::BadRequest(format!(
+                "Unknown sort: {} (expected plays or favorites)",
+                other
+            ))))
+        }
+    };
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT al.*, tc.track_count, COALESCE(p.play_count, 0) AS play_count, fc.favorite_count
+        FROM albums al
+        LEFT JOIN (
+            SELECT t.album_id, COUNT(*) AS play_count, MAX(h.played_at) AS last_played
+            FROM play_history h
+            JOIN tracks t ON t.id = h.track_id AND t.deleted_at IS NULL
+            WHERE h.counted
+              AND ($2::uuid IS NULL OR h.user_id = $2)
+              AND ($3::int IS NULL OR h.played_at > NOW() - make_interval(days => $3))
+            GROUP BY t.album_id
+        ) p ON p.album_id = al.id
+        CROSS JOIN LATERAL (
+            SELECT COUNT(*) AS track_count FROM tracks WHERE album_id = al.id AND deleted_at IS NULL
+        ) tc
+        CROSS JOIN LATERAL (
+            SELECT COUNT(*) AS favorite_count FROM user_favorite_albums WHERE album_id = al.id
+        ) fc
+        WHERE {filter}
+        ORDER BY {order_by}
+        LIMIT $1
+        "#
+    ))
+    .bind(limit)
+    .bind(scope_user)
+    .bind(days)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| TopAlbum {
+            album: AlbumWithCount::from_row(row),
+            play_count: row.get("play_count"),
+            favorite_count: row.get("favorite_count"),
         })
-        .collect::<Vec<_>>();
+        .collect())
+}
+
+/// 最近入库的曲目
+pub async fn list_recent_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<DiscoverQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_DISCOVER_LIMIT).clamp(1, MAX_DISCOVER_LIMIT);
 
-    Ok(Json(tracks))
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS}
+        FROM tracks t
+        LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
+        WHERE t.deleted_at IS NULL
+        ORDER BY t.created_at DESC, t.album_id, t.disc_number, t.track_number
+        LIMIT $1
+        "#
+    ))
+    .bind(limit)
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(
+        rows.iter()
+            .map(TrackWithFavorite::from_row)
+            .collect::<Result<Vec<_>, _>>()?,
+    ))
+}
+
+#[derive(Deserialize)]
+pub struct ShuffleQuery {
+    pub limit: Option<i64>,
+}
+
+const DEFAULT_SHUFFLE_LIMIT: i64 = 100;
+/// `ORDER BY RANDOM()` 需要排序整张表，数量上限防止一次拉走整个曲库
+const MAX_SHUFFLE_LIMIT: i64 = 500;
+
+/// 随机抽取的曲目，供“随便听听”使用
+pub async fn list_random_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ShuffleQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::browse_user_id(&headers, &state).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_DISCOVER_LIMIT).clamp(1, MAX_SHUFFLE_LIMIT);
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS}
+        FROM tracks t
+        LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
+        WHERE t.deleted_at IS NULL
+        ORDER BY RANDOM()
+        LIMIT $1
+        "#
+    ))
+    .bind(limit)
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(
+        rows.iter()
+            .map(TrackWithFavorite::from_row)
+            .collect::<Result<Vec<_>, _>>()?,
+    ))
+}
+
+/// 随机顺序的收藏曲目，前端一键随机播放收藏时不必先加载全部收藏
+pub async fn shuffle_favorites(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<ShuffleQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let limit = params.limit.unwrap_or(DEFAULT_SHUFFLE_LIMIT).clamp(1, MAX_SHUFFLE_LIMIT);
+
+    let rows = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS}
+        FROM tracks t
+        JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $1
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $1
+        WHERE t.deleted_at IS NULL
+        ORDER BY RANDOM()
+        LIMIT $2
+        "#
+    ))
+    .bind(user_id)
+    .bind(limit)
+    .fetch_all(&state.db)
+    .await?;
+
+    Ok(Json(
+        rows.iter()
+            .map(TrackWithFavorite::from_row)
+            .collect::<Result<Vec<_>, _>>()?,
+    ))
 }
 
 pub async fn stream_track(
@@ -469,41 +1535,53 @@ pub async fn stream_track(
         params.start_time
     );
 
-    let track = sqlx::query!("SELECT path FROM tracks WHERE id = $1", id)
-        .fetch_optional(&state.db)
-        .await?
-        .ok_or_else(|| {
-            tracing::error!("Track not found in DB: {}", id);
-            ApiError(AppError::NotFound("Track not found".to_string()))
-        })?;
-
+    let track = load_stream_source(&state, id).await?;
     let path = std::path::Path::new(&track.path);
-    if !path.exists() {
-        tracing::error!("File missing on disk: {:?}", path);
-        return Err(ApiError(AppError::NotFound(
-            "File missing on disk".to_string(),
-        )));
-    }
+    let StreamPlan {
+        transcode,
+        decision,
+        track_secs,
+        offset,
+        cache_target,
+        cached,
+    } = match plan_stream(&state, id, &track, &headers, &params).await {
+        Ok(plan) => plan,
+        Err(response) => return Ok(response),
+    };
 
     // 处理转码流
-    if let Some(br) = params.bitrate {
+    if let Some(((mime, codec, container), br)) = transcode {
         let path_str = path
             .to_str()
             .ok_or_else(|| ApiError(AppError::Internal("Invalid path encoding".to_string())))?;
-        let mut args = Vec::new();
 
-        // 必须通过 String 保持生命周期
-        let ss_val;
+        if let Some(cached) = cached {
+            tracing::debug!("Serving cached transcode for {}: {:?}", id, cached);
+            let response = serve_file(&cached, mime, &headers).await?;
+            return Ok(with_content_duration(
+                with_transcode_decision(response, &decision),
+                track_secs,
+            ));
+        }
+
+        // 起始时间 = CUE 分轨偏移 + 客户端请求的跳转位置
+        let start = track.start_ms as f64 / 1000.0 + offset;
+        let (input_seek, output_seek) = transcode_seek_args(start);
+
+        let mut args: Vec<String> = input_seek;
+        args.extend(["-i".to_string(), path_str.to_string()]);
+        args.extend(output_seek);
 
-        // 如果有起始时间，让 FFmpeg 直接跳过
-        if let Some(start) = params.start_time {
-            ss_val = start.to_string();
-            args.extend(["-ss", &ss_val]);
+        // CUE 分轨在下一轨起点处截止
+        if track.end_ms.is_some() {
+            args.extend(["-t".to_string(), format!("{:.3}", (track_secs - offset).max(0.0))]);
         }
 
-        args.extend([
-            "-i", path_str, "-map", "0:a:0", "-b:a", &br, "-f", "mp3", "pipe:1",
-        ]);
+        args.extend(["-map", "0:a:0", "-c:a", codec].map(String::from));
+        if codec != "flac" {
+            args.extend(["-b:a".to_string(), br]);
+        }
+        args.extend(["-f", container, "pipe:1"].map(String::from));
 
         let mut child = Command::new("ffmpeg")
             .args(&args)
@@ -517,70 +1595,319 @@ pub async fn stream_track(
                 "Failed to capture FFmpeg stdout".to_string(),
             ))
         })?;
-        let stream = ReaderStream::new(stdout);
+        let body = match cache_target {
+            Some(target) => Body::from_stream(ReaderStream::new(crate::transcode_cache::tee(
+                child, stdout, target,
+            ))),
+            None => Body::from_stream(ReaderStream::new(stdout)),
+        };
 
         let response = Response::builder()
-            .header(header::CONTENT_TYPE, "audio/mpeg")
-            .body(Body::from_stream(stream))
+            .header(header::CONTENT_TYPE, mime)
+            .header(header::VARY, "Accept")
+            .header(TRANSCODE_DECISION_HEADER, &decision)
+            .body(body)
             .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
-        return Ok(response);
+        return Ok(with_content_duration(response, track_secs - offset));
     }
 
-    // 核心改进：处理原始文件的 Range Request (206 Partial Content)
+    let mime = mime_guess::from_path(path).first_or_octet_stream();
+    let response = serve_file(path, mime.as_ref(), &headers).await?;
+    Ok(with_content_duration(
+        with_transcode_decision(response, &decision),
+        track_secs,
+    ))
+}
+
+/// `HEAD /stream/{id}`：按与 GET 相同的规则决定输出格式，只返回响应头。
+/// 直接输出或命中转码缓存时带 `Content-Length`；实时转码的长度未知，`Accept-Ranges: none`
+pub async fn stream_track_head(
+    State(state): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    headers: HeaderMap,
+    Query(params): Query<StreamQuery>,
+) -> Result<Response, ApiError> {
+    let track = load_stream_source(&state, id).await?;
+    let path = std::path::Path::new(&track.path);
+    let plan = match plan_stream(&state, id, &track, &headers, &params).await {
+        Ok(plan) => plan,
+        Err(response) => return Ok(response),
+    };
+
+    let (mime, file, secs) = match &plan.transcode {
+        Some(((mime, _, _), _)) => (
+            mime.to_string(),
+            plan.cached.clone(),
+            plan.track_secs - plan.offset,
+        ),
+        None => (
+            mime_guess::from_path(path).first_or_octet_stream().to_string(),
+            Some(path.to_path_buf()),
+            plan.track_secs,
+        ),
+    };
+
+    let mut builder = Response::builder()
+        .header(header::CONTENT_TYPE, mime)
+        .header(header::VARY, "Accept")
+        .header(TRANSCODE_DECISION_HEADER, &plan.decision);
+    builder = match file {
+        Some(file) => builder
+            .header(header::CONTENT_LENGTH, tokio::fs::metadata(&file).await?.len())
+            .header(header::ACCEPT_RANGES, "bytes"),
+        None => builder.header(header::ACCEPT_RANGES, "none"),
+    };
+    let response = builder
+        .body(Body::empty())
+        .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
+    Ok(with_content_duration(response, secs))
+}
+
+/// 流式输出需要的曲目字段
+#[derive(sqlx::FromRow)]
+struct StreamSource {
+    path: String,
+    format: Option<String>,
+    duration: i32,
+    bitrate: Option<i32>,
+    start_ms: i32,
+    end_ms: Option<i32>,
+}
+
+/// GET 与 HEAD 共用的输出决策
+struct StreamPlan {
+    /// 需要转码时的目标格式与码率，None 表示直接返回原文件
+    transcode: Option<(TranscodeTarget, String)>,
+    /// `X-Transcode-Decision` 的取值
+    decision: String,
+    /// 曲目时长 (秒)，CUE 分轨按起止时间计算，比标签里的整数秒更精确
+    track_secs: f64,
+    /// 转码时的跳转位置 (秒)，已校验为曲目时长内的非负数
+    offset: f64,
+    /// 从头播放的完整转码结果的缓存位置，None 表示不缓存
+    cache_target: Option<std::path::PathBuf>,
+    /// 已存在的转码缓存，命中后按普通文件输出
+    cached: Option<std::path::PathBuf>,
+}
+
+async fn load_stream_source(state: &AppState, id: Uuid) -> Result<StreamSource, ApiError> {
+    // 已软删除的曲目即使文件仍在磁盘上也不再提供播放
+    let track = sqlx::query_as::<_, StreamSource>(
+        "SELECT path, format, duration, bitrate, start_ms, end_ms FROM tracks WHERE id = $1 AND deleted_at IS NULL",
+    )
+    .bind(id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| {
+        tracing::error!("Track not found in DB: {}", id);
+        ApiError(AppError::NotFound("Track not found".to_string()))
+    })?;
+
+    let path = std::path::Path::new(&track.path);
+    if !path.exists() {
+        tracing::error!("File missing on disk: {:?}", path);
+        return Err(ApiError(AppError::NotFound(
+            "File missing on disk".to_string(),
+        )));
+    }
+    Ok(track)
+}
+
+/// 除输出决策外还完成所有会拒绝请求的检查 (跳转位置 400、超出时长 416、缺少 FFmpeg 501)，
+/// 保证 HEAD 与 GET 返回相同的状态码。拒绝时直接给出响应
+async fn plan_stream(
+    state: &AppState,
+    id: Uuid,
+    track: &StreamSource,
+    headers: &HeaderMap,
+    params: &StreamQuery,
+) -> Result<StreamPlan, Response> {
+    let path = std::path::Path::new(&track.path);
+
+    // 显式 ?format= / ?bitrate= 参数优先 (未指定格式时为 MP3)；否则依据 Accept 头协商，仅在客户端无法播放源格式时转码
+    let format = track.format.clone().unwrap_or_else(|| {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .unwrap_or_default()
+            .to_string()
+    });
+    let accept = headers.get(header::ACCEPT).and_then(|h| h.to_str().ok());
+    let is_segment = track.start_ms > 0 || track.end_ms.is_some();
+    let requested_target = params
+        .format
+        .as_deref()
+        .map(|name| {
+            transcode_target_by_name(name).ok_or_else(|| {
+                ApiError(AppError::BadRequest(format!(
+                    "Unsupported transcode format: {} (expected mp3, opus or aac)",
+                    name
+                )))
+            })
+        })
+        .transpose()
+        .map_err(IntoResponse::into_response)?;
+    let requested_bitrate = params
+        .bitrate
+        .as_deref()
+        .map(|br| {
+            normalize_bitrate(br).ok_or_else(|| {
+                ApiError(AppError::BadRequest(format!(
+                    "Unsupported bitrate: {} (allowed: {})",
+                    br,
+                    ALLOWED_BITRATES.join(", ")
+                )))
+            })
+        })
+        .transpose()
+        .map_err(IntoResponse::into_response)?;
+
+    // 只指定码率且不低于源码率时直接返回原文件；显式指定格式的转换照常执行
+    let covers_source = !is_segment
+        && requested_target.is_none()
+        && requested_bitrate
+            .is_some_and(|br| bitrate_only_passthrough(accept, &format, br, track.bitrate));
+
+    // 没有 ffmpeg 时协商出的转码退回原始文件；显式要求的转码与 CUE 分轨无法退回
+    let transcode = match (requested_target, requested_bitrate) {
+        (None, None) => negotiate_transcode(accept, &format)
+            .filter(|_| state.ffmpeg_available)
+            .map(|target| {
+                tracing::debug!("Negotiated transcode for {}: {} -> {}", id, format, target.0);
+                (target, DEFAULT_TRANSCODE_BITRATE.to_string())
+            })
+            .or_else(|| is_segment.then(|| (SEGMENT_TARGET, DEFAULT_TRANSCODE_BITRATE.to_string()))),
+        (None, Some(_)) if covers_source => None,
+        // 只给码率时沿用 Accept 协商出的格式，客户端不接受 MP3 时不强行输出 MP3
+        (target, br) => Some((
+            target
+                .or_else(|| negotiate_transcode(accept, &format))
+                .unwrap_or(TRANSCODE_TARGETS[0]),
+            br.unwrap_or(DEFAULT_TRANSCODE_BITRATE).to_string(),
+        )),
+    };
+
+    let track_secs = match track.end_ms {
+        Some(end_ms) => (end_ms - track.start_ms) as f64 / 1000.0,
+        None => track.duration as f64,
+    };
+
+    let decision = match &transcode {
+        Some(((_, _, container), br)) => format!("transcode; {}@{}", container, br),
+        None if covers_source => format!(
+            "direct; requested {} >= source {}k",
+            requested_bitrate.unwrap_or_default(),
+            track.bitrate.unwrap_or_default()
+        ),
+        None => "direct".to_string(),
+    };
+
+    let mut offset = 0.0;
+    let mut cache_target = None;
+    let mut cached = None;
+    if let Some(((mime, _, container), br)) = &transcode {
+        offset = params.start_time.unwrap_or(0.0);
+        if !offset.is_finite() || offset < 0.0 {
+            return Err(ApiError(AppError::BadRequest(
+                "start_time must be a non-negative number".to_string(),
+            ))
+            .into_response());
+        }
+
+        // 跳转位置超出曲目时长时不再启动注定输出为空的 FFmpeg
+        if track_secs > 0.0 && offset >= track_secs {
+            return Err((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_TYPE, *mime), (header::CONTENT_LENGTH, "0")],
+            )
+                .into_response());
+        }
+
+        // 从头播放的完整转码结果会缓存到磁盘，命中后按普通文件处理 (支持 Range)；
+        // 带 start_time 的跳转请求仍走实时管道
+        if offset == 0.0 && crate::transcode_cache::enabled() {
+            cache_target = crate::transcode_cache::cache_path(id, path, br, container).await;
+        }
+        if let Some(target) = &cache_target {
+            cached = crate::transcode_cache::lookup(target).await;
+        }
+
+        if cached.is_none() && !state.ffmpeg_available {
+            return Err(crate::transcoding_unavailable().into_response());
+        }
+    }
+
+    Ok(StreamPlan {
+        transcode,
+        decision,
+        track_secs,
+        offset,
+        cache_target,
+        cached,
+    })
+}
+
+/// 以文件响应，处理 Range Request (206 Partial Content)。原始文件与转码缓存共用
+async fn serve_file(
+    path: &std::path::Path,
+    mime: &str,
+    headers: &HeaderMap,
+) -> Result<Response, ApiError> {
     let file = tokio::fs::File::open(path).await?;
     let metadata = file.metadata().await?;
     let file_size = metadata.len();
-    let mime = mime_guess::from_path(path).first_or_octet_stream();
 
-    let range_header = headers.get(header::RANGE).and_then(|h| h.to_str().ok());
-
-    if let Some(range) = range_header {
-        if let Some(captures) = regex::Regex::new(r"bytes=(\d+)-(\d+)?")
-            .unwrap()
-            .captures(range)
-        {
-            let start = captures
-                .get(1)
-                .map(|m| m.as_str().parse::<u64>().unwrap_or(0))
-                .unwrap_or(0);
-            let end = captures
-                .get(2)
-                .map(|m| m.as_str().parse::<u64>().unwrap_or(file_size - 1))
-                .unwrap_or(file_size - 1);
-
-            if start < file_size {
-                let end = end.min(file_size - 1);
-                let content_length = end - start + 1;
-
-                use std::io::{Seek, SeekFrom};
-                let mut std_file = file.into_std().await;
-                std_file.seek(SeekFrom::Start(start))?;
-
-                let file = tokio::fs::File::from_std(std_file);
-                let stream = ReaderStream::new(file).take(content_length as usize);
-
-                let response = Response::builder()
-                    .status(StatusCode::PARTIAL_CONTENT)
-                    .header(header::CONTENT_TYPE, mime.as_ref())
-                    .header(
-                        header::CONTENT_RANGE,
-                        format!("bytes {}-{}/{}", start, end, file_size),
-                    )
-                    .header(header::ACCEPT_RANGES, "bytes")
-                    .header(header::CONTENT_LENGTH, content_length)
-                    .body(Body::from_stream(stream))
-                    .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
-                return Ok(response);
-            }
+    let range = headers
+        .get(header::RANGE)
+        .and_then(|h| h.to_str().ok())
+        .map(|h| parse_byte_range(h, file_size))
+        .unwrap_or(ByteRange::Full);
+
+    match range {
+        ByteRange::Partial { start, end } => {
+            let content_length = end - start + 1;
+
+            use std::io::{Seek, SeekFrom};
+            use tokio::io::AsyncReadExt;
+            let mut std_file = file.into_std().await;
+            std_file.seek(SeekFrom::Start(start))?;
+
+            let file = tokio::fs::File::from_std(std_file).take(content_length);
+            let stream = ReaderStream::new(file);
+
+            let response = Response::builder()
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(header::CONTENT_TYPE, mime)
+                .header(
+                    header::CONTENT_RANGE,
+                    format!("bytes {}-{}/{}", start, end, file_size),
+                )
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(header::VARY, "Accept")
+                .header(header::CONTENT_LENGTH, content_length)
+                .body(Body::from_stream(stream))
+                .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
+            return Ok(response);
         }
+        ByteRange::Unsatisfiable => {
+            let response = Response::builder()
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", file_size))
+                .header(header::ACCEPT_RANGES, "bytes")
+                .header(header::CONTENT_LENGTH, 0)
+                .body(Body::empty())
+                .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
+            return Ok(response);
+        }
+        ByteRange::Full => {}
     }
 
     // 默认全量响应
     let stream = ReaderStream::new(file);
     let response = Response::builder()
-        .header(header::CONTENT_TYPE, mime.as_ref())
+        .header(header::CONTENT_TYPE, mime)
         .header(header::CONTENT_LENGTH, file_size)
         .header(header::ACCEPT_RANGES, "bytes")
+        .header(header::VARY, "Accept")
         .body(Body::from_stream(stream))
         .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
     Ok(response)
@@ -588,10 +1915,15 @@ pub async fn stream_track(
 
 pub async fn get_lyrics(
     State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
+    crate::browse_user_id(&headers, &state).await?;
     let track = sqlx::query!(
-        "SELECT id, title, artist_id, lyrics FROM tracks WHERE id = $1",
+        r#"SELECT t.id, t.title, t.artist_id, t.duration, t.lyrics, al.title as "album_title?"
+        FROM tracks t
+        LEFT JOIN albums al ON t.album_id = al.id
+        WHERE t.id = $1"#,
         id
     )
     .fetch_optional(&state.db)
@@ -608,16 +1940,84 @@ pub async fn get_lyrics(
         .map(|a| a.name)
         .unwrap_or_else(|| "Unknown".to_string());
 
-    let db_clone = state.db.clone();
     let track_id = track.id;
+    {
+        let mut lookups = LYRICS_LOOKUPS.lock().unwrap();
+        match lookups.get(&track_id) {
+            Some(LyricsLookup::InFlight) => return Ok(lyrics_pending_response()),
+            Some(LyricsLookup::Missing(at)) if at.elapsed() < LYRICS_MISS_TTL => {
+                return Err(ApiError(AppError::NotFound("No lyrics found".to_string())));
+            }
+            _ => {}
+        }
+        lookups.insert(track_id, LyricsLookup::InFlight);
+    }
+
+    let db_clone = state.db.clone();
     let title = track.title.clone();
+    let album = track.album_title.clone();
+    let duration = track.duration;
+    let in_flight = LyricsInFlightGuard(track_id);
     tokio::spawn(async move {
+        let _in_flight = in_flight;
         let service = papilio_core::metadata::MetadataService::new(db_clone);
-        let _ = service.fetch_lyrics_online(track_id, &title, &artist).await;
+        let result = service
+            .fetch_lyrics_online(track_id, &title, &artist, album.as_deref(), duration)
+            .await;
+        let mut lookups = LYRICS_LOOKUPS.lock().unwrap();
+        match result {
+            Ok(()) => {
+                lookups.remove(&track_id);
+            }
+            // 只缓存 lrclib 确认没有的结果；网络错误等临时故障下次请求重新查找
+            Err(AppError::NotFound(msg)) => {
+                tracing::info!(track_id = %track_id, "{}", msg);
+                lookups.insert(track_id, LyricsLookup::Missing(std::time::Instant::now()));
+            }
+            Err(e) => {
+                tracing::warn!(track_id = %track_id, "Online lyrics lookup failed: {}", e);
+                lookups.remove(&track_id);
+            }
+        }
+        // 顺带清理早已过期的未命中记录
+        lookups.retain(|_, l| !matches!(l, LyricsLookup::Missing(at) if at.elapsed() >= LYRICS_MISS_TTL));
     });
-    Err(ApiError(AppError::NotFound(
-        "Lyrics fetching...".to_string(),
-    )))
+    Ok(lyrics_pending_response())
+}
+
+/// 在线歌词查找状态，按曲目去重，避免并发请求重复发起查询
+enum LyricsLookup {
+    InFlight,
+    /// 上次查找未命中的时间，期间直接返回 404，不再反复请求 lrclib
+    Missing(std::time::Instant),
+}
+
+static LYRICS_LOOKUPS: Lazy<Mutex<HashMap<Uuid, LyricsLookup>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// 查找任务结束时清除仍为 InFlight 的记录：任务 panic 或被取消时不走正常的收尾逻辑，
+/// 没有这一步该曲目会一直返回 202
+struct LyricsInFlightGuard(Uuid);
+
+impl Drop for LyricsInFlightGuard {
+    fn drop(&mut self) {
+        let mut lookups = LYRICS_LOOKUPS.lock().unwrap_or_else(|e| e.into_inner());
+        if matches!(lookups.get(&self.0), Some(LyricsLookup::InFlight)) {
+            lookups.remove(&self.0);
+        }
+    }
+}
+
+const LYRICS_MISS_TTL: Duration = Duration::from_secs(10 * 60);
+
+/// 查找进行中：202 + Retry-After，客户端稍后重试
+fn lyrics_pending_response() -> Response {
+    (
+        StatusCode::ACCEPTED,
+        [(header::RETRY_AFTER, "2")],
+        Json(json!({"status": "fetching"})),
+    )
+        .into_response()
 }
 
 pub async fn toggle_favorite(
@@ -625,9 +2025,7 @@ pub async fn toggle_favorite(
     headers: HeaderMap,
     Path(track_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let exists = sqlx::query!(
         "SELECT 1 as x FROM user_favorites WHERE user_id = $1 AND track_id = $2",
@@ -662,195 +2060,498 @@ pub async fn list_favorites(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    Ok(Json(fetch_favorite_tracks(&state.db, user_id).await?))
+}
 
-    let rows = sqlx::query!(
+/// 收藏的曲目，按收藏时间倒序
+pub async fn fetch_favorite_tracks(
+    db: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<TrackWithFavorite>, ApiError> {
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT t.id, t.title, t.album_id, t.artist_id,
-               t.duration, t.track_number,
-               t.disc_number, t.path, t.bitrate,
-               t.format, t.size, t.bpm,
-               t.musicbrainz_track_id,
-               t.lyrics,
-               t.created_at, t.updated_at,
-               TRUE as "is_favorite!",
-               a.name as "artist_name?",
-               a.image_url as "artist_image_url?",
-               al.title as "album_title?",
-               COALESCE(m.lyric_offset_ms, 0) as "lyric_offset_ms!"
+        SELECT {TRACK_COLUMNS}
         FROM tracks t
-        JOIN user_favorites f ON t.id = f.track_id
+        JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $1
         LEFT JOIN artists a ON t.artist_id = a.id
         LEFT JOIN albums al ON t.album_id = al.id
         LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $1
+        WHERE t.deleted_at IS NULL
+        ORDER BY f.created_at DESC
+        "#
+    ))
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.iter().map(TrackWithFavorite::from_row).collect::<Result<_, _>>()?)
+}
+
+/// 专辑 / 歌手收藏的表名与实体表，均为固定字符串，可安全拼入 SQL
+struct FavoriteKind {
+    table: &'static str,
+    column: &'static str,
+    entity_table: &'static str,
+    not_found: &'static str,
+}
+
+const FAVORITE_ALBUMS: FavoriteKind = FavoriteKind {
+    table: "user_favorite_albums",
+    column: "album_id",
+    entity_table: "albums",
+    not_found: "Album not found",
+};
+
+const FAVORITE_ARTISTS: FavoriteKind = FavoriteKind {
+    table: "user_favorite_artists",
+    column: "artist_id",
+    entity_table: "artists",
+    not_found: "Artist not found",
+};
+
+async fn toggle_entity_favorite(
+    state: &AppState,
+    kind: &FavoriteKind,
+    user_id: Uuid,
+    entity_id: Uuid,
+) -> Result<bool, ApiError> {
+    let exists = sqlx::query(&format!("SELECT 1 FROM {} WHERE id = $1", kind.entity_table))
+        .bind(entity_id)
+        .fetch_optional(&state.db)
+        .await?;
+    if exists.is_none() {
+        return Err(ApiError(AppError::NotFound(kind.not_found.to_string())));
+    }
+
+    // 插入未生效说明已收藏，改为取消
+    let inserted = sqlx::query(&format!(
+        "INSERT INTO {} (user_id, {}) VALUES ($1, $2) ON CONFLICT DO NOTHING",
+        kind.table, kind.column
+    ))
+    .bind(user_id)
+    .bind(entity_id)
+    .execute(&state.db)
+    .await?;
+    if inserted.rows_affected() > 0 {
+        return Ok(true);
+    }
+
+    sqlx::query(&format!(
+        "DELETE FROM {} WHERE user_id = $1 AND {} = $2",
+        kind.table, kind.column
+    ))
+    .bind(user_id)
+    .bind(entity_id)
+    .execute(&state.db)
+    .await?;
+    Ok(false)
+}
+
+pub async fn toggle_favorite_album(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(album_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let is_favorite = toggle_entity_favorite(&state, &FAVORITE_ALBUMS, user_id, album_id).await?;
+    Ok(Json(json!({"is_favorite": is_favorite})))
+}
+
+pub async fn toggle_favorite_artist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(artist_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let is_favorite = toggle_entity_favorite(&state, &FAVORITE_ARTISTS, user_id, artist_id).await?;
+    Ok(Json(json!({"is_favorite": is_favorite})))
+}
+
+pub async fn list_favorite_albums(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    Ok(Json(fetch_favorite_albums(&state.db, user_id).await?))
+}
+
+/// 收藏的专辑，按收藏时间倒序
+pub async fn fetch_favorite_albums(
+    db: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<AlbumWithCount>, ApiError> {
+    let albums = sqlx::query(
+        r#"
+        SELECT al.*, COUNT(t.id) AS track_count
+        FROM user_favorite_albums f
+        JOIN albums al ON al.id = f.album_id
+        LEFT JOIN tracks t ON t.album_id = al.id AND t.deleted_at IS NULL
         WHERE f.user_id = $1
+        GROUP BY al.id, f.created_at
         ORDER BY f.created_at DESC
         "#,
-        user_id
     )
-    .fetch_all(&state.db)
-    .await?;
+    .bind(user_id)
+    .fetch_all(db)
+    .await?
+    .iter()
+    .map(AlbumWithCount::from_row)
+    .collect::<Vec<_>>();
 
-    let tracks = rows
-        .into_iter()
-        .map(|row| TrackWithFavorite {
-            track: Track {
-                id: row.id,
-                title: row.title,
-                album_id: row.album_id,
-                artist_id: row.artist_id,
-                artist_name: row.artist_name,
-                album_title: row.album_title,
-                artist_image_url: row.artist_image_url,
-                duration: row.duration,
-                track_number: row.track_number,
-                disc_number: row.disc_number.unwrap_or(1),
-                path: row.path,
-                bitrate: row.bitrate,
-                format: row.format,
-                size: row.size,
-                bpm: row.bpm,
-                musicbrainz_track_id: row.musicbrainz_track_id,
-                lyrics: row.lyrics,
-                lyric_offset_ms: row.lyric_offset_ms,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-            },
-            is_favorite: row.is_favorite,
-        })
-        .collect::<Vec<_>>();
-    Ok(Json(tracks))
+    Ok(albums)
 }
 
+pub async fn list_favorite_artists(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    Ok(Json(fetch_favorite_artists(&state.db, user_id).await?))
+}
+
+/// 关注的歌手，按关注时间倒序
+pub async fn fetch_favorite_artists(
+    db: &PgPool,
+    user_id: Uuid,
+) -> Result<Vec<ArtistWithCount>, ApiError> {
+    let artists = sqlx::query(
+        r#"
+        SELECT a.*, COUNT(al.id) AS album_count
+        FROM user_favorite_artists f
+        JOIN artists a ON a.id = f.artist_id
+        LEFT JOIN albums al ON al.artist_id = a.id
+        WHERE f.user_id = $1
+        GROUP BY a.id, f.created_at
+        ORDER BY f.created_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?
+    .iter()
+    .map(ArtistWithCount::from_row)
+    .collect::<Vec<_>>();
+
+    Ok(artists)
+}
+
+#[derive(Deserialize, Default)]
+pub struct RecordPlayPayload {
+    /// 实际播放的累计时长 (毫秒)，不含拖动跳过的部分
+    #[serde(default)]
+    pub played_ms: i32,
+    #[serde(default)]
+    pub completed: bool,
+}
+
+/// 计为一次播放的默认阈值：收听过半，或满 4 分钟 (与常见 scrobble 规则一致)
+const DEFAULT_SCROBBLE_PERCENT: i64 = 50;
+const DEFAULT_SCROBBLE_MAX_MS: i64 = 240_000;
+
+/// 按 SCROBBLE_PERCENT / SCROBBLE_MAX_MS 判断一次收听是否计为播放。
+/// 只看实际收听时长：客户端的 `completed` 只说明播到了结尾，拖动到末尾同样会置位
+pub fn counts_as_play(played_ms: i32, duration_secs: i32) -> bool {
+    let percent = std::env::var("SCROBBLE_PERCENT")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_SCROBBLE_PERCENT)
+        .clamp(1, 100);
+    let max_ms = std::env::var("SCROBBLE_MAX_MS")
+        .ok()
+        .and_then(|v| v.parse::<i64>().ok())
+        .unwrap_or(DEFAULT_SCROBBLE_MAX_MS);
+
+    let duration_ms = duration_secs as i64 * 1000;
+    let threshold = if duration_ms > 0 {
+        (duration_ms * percent / 100).min(max_ms)
+    } else {
+        max_ms
+    };
+    played_ms as i64 >= threshold
+}
+
+/// 记录一次收听。旧客户端开始播放时不带请求体，只进入最近播放、不计入播放次数
 pub async fn record_play(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(track_id): Path<Uuid>,
+    payload: Option<Json<RecordPlayPayload>>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
-    sqlx::query!(
-        "INSERT INTO play_history (user_id, track_id) VALUES ($1, $2)",
-        user_id,
-        track_id
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let played_ms = payload.played_ms.max(0);
+
+    let duration: i32 = sqlx::query_scalar("SELECT duration FROM tracks WHERE id = $1")
+        .bind(track_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("Track not found".to_string())))?;
+    let counted = counts_as_play(played_ms, duration);
+
+    sqlx::query(
+        "INSERT INTO play_history (user_id, track_id, played_ms, completed, counted) VALUES ($1, $2, $3, $4, $5)",
     )
+    .bind(user_id)
+    .bind(track_id)
+    .bind(played_ms)
+    .bind(payload.completed)
+    .bind(counted)
     .execute(&state.db)
     .await?;
-    Ok(StatusCode::OK)
+    Ok(Json(json!({"status": "success", "counted": counted})))
 }
 
-pub async fn list_history(
-    State(state): State<Arc<AppState>>,
-    headers: HeaderMap,
-) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+#[derive(Serialize)]
+pub struct PlayHistoryEntry {
+    #[serde(flatten)]
+    pub track: TrackWithFavorite,
+    pub played_at: DateTime<Utc>,
+    pub played_ms: i32,
+    pub completed: bool,
+}
 
-    let rows = sqlx::query!(
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+    /// 播放时间区间 (RFC 3339)，两端均包含
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+    /// 为 true 时返回完整的播放时间线，同一曲目的每次播放各占一条；默认每首只保留最近一次
+    pub full: Option<bool>,
+    /// 为 true 时返回带总数的分页对象
+    pub paginated: Option<bool>,
+}
+
+/// 单页最多返回的历史条数
+const HISTORY_MAX_LIMIT: i64 = 500;
+
+/// 播放历史子查询，参数 $1 用户、$2/$3 时间区间。
+/// 去重模式下先按时间区间过滤再取每首最近一次播放，即“该区间内最后一次播放”
+fn history_source(full: bool) -> &'static str {
+    if full {
+        r#"SELECT track_id, played_at, played_ms, completed
+            FROM play_history
+            WHERE user_id = $1
+              AND ($2::timestamptz IS NULL OR played_at >= $2)
+              AND ($3::timestamptz IS NULL OR played_at <= $3)"#
+    } else {
+        r#"SELECT DISTINCT ON (track_id) track_id, played_at, played_ms, completed
+            FROM play_history
+            WHERE user_id = $1
+              AND ($2::timestamptz IS NULL OR played_at >= $2)
+              AND ($3::timestamptz IS NULL OR played_at <= $3)
+            ORDER BY track_id, played_at DESC"#
+    }
+}
+
+/// 按播放时间倒序的历史记录，`limit` 为 None 时不限条数
+pub async fn fetch_history(
+    db: &PgPool,
+    user_id: Uuid,
+    full: bool,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    limit: Option<i64>,
+    offset: i64,
+) -> Result<Vec<PlayHistoryEntry>, ApiError> {
+    let history = history_source(full);
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT t.id, t.title, t.album_id, t.artist_id,
-               t.duration, t.track_number,
-               t.disc_number, t.path, t.bitrate,
-               t.format, t.size, t.bpm,
-               t.musicbrainz_track_id,
-               t.lyrics,
-               t.created_at, t.updated_at,
-               (f.user_id IS NOT NULL) as "is_favorite!",
-               a.name as "artist_name?",
-               a.image_url as "artist_image_url?",
-               al.title as "album_title?",
-               COALESCE(m.lyric_offset_ms, 0) as "lyric_offset_ms!"
+        SELECT {TRACK_COLUMNS},
+               h.played_at, h.played_ms, h.completed
         FROM tracks t
-        JOIN (SELECT track_id, MAX(played_at) as last_p FROM play_history WHERE user_id = $1 GROUP BY track_id) h ON t.id = h.track_id
+        JOIN ({history}) h ON t.id = h.track_id
         LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $1
         LEFT JOIN artists a ON t.artist_id = a.id
         LEFT JOIN albums al ON t.album_id = al.id
         LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $1
-        ORDER BY h.last_p DESC LIMIT 50
-        "#,
-        user_id
-    ).fetch_all(&state.db).await?;
+        WHERE t.deleted_at IS NULL
+        ORDER BY h.played_at DESC
+        LIMIT $4 OFFSET $5
+        "#
+    ))
+    .bind(user_id)
+    .bind(from)
+    .bind(to)
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(db)
+    .await?;
 
-    let tracks = rows
-        .into_iter()
-        .map(|row| TrackWithFavorite {
-            track: Track {
-                id: row.id,
-                title: row.title,
-                album_id: row.album_id,
-                artist_id: row.artist_id,
-                artist_name: row.artist_name,
-                album_title: row.album_title,
-                artist_image_url: row.artist_image_url,
-                duration: row.duration,
-                track_number: row.track_number,
-                disc_number: row.disc_number.unwrap_or(1),
-                path: row.path,
-                bitrate: row.bitrate,
-                format: row.format,
-                size: row.size,
-                bpm: row.bpm,
-                musicbrainz_track_id: row.musicbrainz_track_id,
-                lyrics: row.lyrics,
-                lyric_offset_ms: row.lyric_offset_ms,
-                created_at: row.created_at,
-                updated_at: row.updated_at,
-            },
-            is_favorite: row.is_favorite,
+    Ok(rows
+        .iter()
+        .map(|row| {
+            Ok(PlayHistoryEntry {
+                track: TrackWithFavorite::from_row(row)?,
+                played_at: row.try_get("played_at")?,
+                played_ms: row.try_get("played_ms")?,
+                completed: row.try_get("completed")?,
+            })
         })
-        .collect::<Vec<_>>();
-    Ok(Json(tracks))
+        .collect::<Result<_, sqlx::Error>>()?)
+}
+
+pub async fn list_history(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Query(params): Query<HistoryQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    if let (Some(from), Some(to)) = (params.from, params.to) {
+        if from > to {
+            return Err(ApiError(AppError::BadRequest(
+                "from must not be later than to".to_string(),
+            )));
+        }
+    }
+    let limit = params.limit.unwrap_or(50).clamp(1, HISTORY_MAX_LIMIT);
+    let offset = params.offset.unwrap_or(0).max(0);
+    let full = params.full.unwrap_or(false);
+
+    let entries = fetch_history(&state.db, user_id, full, params.from, params.to, Some(limit), offset).await?;
+
+    if !params.paginated.unwrap_or(false) {
+        return Ok(Json(entries).into_response());
+    }
+
+    let history = history_source(full);
+    let total: i64 = sqlx::query_scalar(&format!(
+        r#"
+        SELECT COUNT(*) FROM tracks t
+        JOIN ({history}) h ON t.id = h.track_id
+        WHERE t.deleted_at IS NULL
+        "#
+    ))
+    .bind(user_id)
+    .bind(params.from)
+    .bind(params.to)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(Paginated {
+        items: entries,
+        total,
+        limit,
+        offset,
+    })
+    .into_response())
+}
+
+#[derive(Deserialize)]
+pub struct CoverQuery {
+    /// 缩略图边长 (像素)，省略时返回原图
+    pub size: Option<u32>,
+}
+
+/// 允许生成的缩略图尺寸，请求值向上取整到最近的一档，避免任意尺寸撑爆缓存目录
+const THUMBNAIL_SIZES: &[u32] = &[100, 200, 400, 800];
+
+fn snap_thumbnail_size(size: u32) -> u32 {
+    THUMBNAIL_SIZES
+        .iter()
+        .copied()
+        .find(|s| *s >= size)
+        .unwrap_or(THUMBNAIL_SIZES[THUMBNAIL_SIZES.len() - 1])
+}
+
+/// 返回 (必要时生成) 封面缩略图。原图不大于目标尺寸或无法解码时返回 None，由调用方回退到原图
+async fn cover_thumbnail(
+    source: &std::path::Path,
+    cover_dir: &std::path::Path,
+    album_id: Uuid,
+    size: u32,
+) -> Option<std::path::PathBuf> {
+    let thumb_dir = cover_dir.join("thumbs");
+    let thumb_path = thumb_dir.join(format!("{}_{}.jpg", album_id, size));
+
+    // 缓存比原图新时直接复用；原图被替换后重新生成
+    let source_mtime = tokio::fs::metadata(source).await.ok()?.modified().ok();
+    if let Ok(meta) = tokio::fs::metadata(&thumb_path).await {
+        if meta.modified().ok() >= source_mtime {
+            return Some(thumb_path);
+        }
+    }
+
+    tokio::fs::create_dir_all(&thumb_dir).await.ok()?;
+    let source = source.to_path_buf();
+    let target = thumb_path.clone();
+    let result = tokio::task::spawn_blocking(move || -> Result<bool, String> {
+        let img = image::open(&source).map_err(|e| e.to_string())?;
+        if img.width() <= size && img.height() <= size {
+            return Ok(false);
+        }
+        let thumb = img.thumbnail(size, size).to_rgb8();
+
+        // 先写临时文件再改名，避免并发请求读到半截图片
+        let tmp = target.with_extension("jpg.tmp");
+        let file = std::fs::File::create(&tmp).map_err(|e| e.to_string())?;
+        let mut writer = std::io::BufWriter::new(file);
+        image::codecs::jpeg::JpegEncoder::new_with_quality(&mut writer, 85)
+            .encode_image(&thumb)
+            .map_err(|e| e.to_string())?;
+        drop(writer);
+        std::fs::rename(&tmp, &target).map_err(|e| e.to_string())?;
+        Ok(true)
+    })
+    .await;
+
+    match result {
+        Ok(Ok(true)) => Some(thumb_path),
+        Ok(Ok(false)) => None,
+        Ok(Err(e)) => {
+            tracing::warn!(album_id = %album_id, "Failed to generate cover thumbnail: {}", e);
+            None
+        }
+        Err(e) => {
+            tracing::error!("Thumbnail task panicked: {}", e);
+            None
+        }
+    }
 }
 
 pub async fn get_cover(
     State(state): State<Arc<AppState>>,
     Path(album_id): Path<Uuid>,
+    Query(query): Query<CoverQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
     let album = sqlx::query!("SELECT cover_path FROM albums WHERE id = $1", album_id)
         .fetch_optional(&state.db)
         .await?
         .ok_or_else(|| ApiError(AppError::NotFound("Album not found".to_string())))?;
 
-    let internal_base_dir =
-        std::env::var("COVER_DIR").unwrap_or_else(|_| "data/covers".to_string());
+    let internal_base_dir = papilio_core::storage::cover_dir();
     let music_root = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
 
     let mut target_path = None;
     if let Some(ref path_str) = album.cover_path {
-        // 逻辑：如果路径是绝对路径或包含 /music/，尝试直接打开
-        // 如果路径是相对的且不包含 data/covers，尝试在 MUSIC_DIR 下找
+        // 内部封面 (`data/covers/...`) 一律在 COVER_DIR 下解析；
+        // 其余为绝对路径或整理后相对 MUSIC_DIR 的路径，如 "Artist/Album/cover.jpg"
         let p = std::path::Path::new(path_str);
 
-        if p.is_absolute() && p.exists() {
-            target_path = Some(p.to_path_buf());
-        } else if !path_str.contains("data/covers") {
-            // 它是整理后的相对路径，如 "Artist/Album/cover.jpg"
-            let full = std::path::Path::new(&music_root).join(path_str);
+        if let Some(internal) = papilio_core::storage::internal_cover_file(path_str) {
+            let full = internal?;
             if full.exists() {
                 target_path = Some(full);
             }
+        } else if p.is_absolute() {
+            if p.exists() {
+                target_path = Some(p.to_path_buf());
+            }
         } else {
-            // 它是旧的内部路径，如 "data/covers/uuid.jpg"
-            let filename = p.file_name().and_then(|f| f.to_str());
-            if let Some(f) = filename {
-                let full = std::path::Path::new(&internal_base_dir).join(f);
-                if full.exists() {
-                    target_path = Some(full);
-                }
+            let full = std::path::Path::new(&music_root).join(path_str);
+            if full.exists() {
+                target_path = Some(full);
             }
         }
     }
 
     // 回退逻辑：按 ID 匹配
     if target_path.is_none() {
-        let fallback_jpg =
-            std::path::Path::new(&internal_base_dir).join(format!("{}.jpg", album_id));
-        let fallback_png =
-            std::path::Path::new(&internal_base_dir).join(format!("{}.png", album_id));
+        let fallback_jpg = internal_base_dir.join(format!("{}.jpg", album_id));
+        let fallback_png = internal_base_dir.join(format!("{}.png", album_id));
         if fallback_jpg.exists() {
             target_path = Some(fallback_jpg);
         } else if fallback_png.exists() {
@@ -867,12 +2568,30 @@ pub async fn get_cover(
         ApiError(AppError::NotFound("Cover file missing on disk".to_string()))
     })?;
 
+    let full_path = match query.size.filter(|s| *s > 0) {
+        Some(size) => {
+            let size = snap_thumbnail_size(size);
+            cover_thumbnail(&full_path, &internal_base_dir, album_id, size)
+                .await
+                .unwrap_or(full_path)
+        }
+        None => full_path,
+    };
+
     let mime = mime_guess::from_path(&full_path).first_or_octet_stream();
     let file = tokio::fs::File::open(&full_path).await?;
+    let meta = file.metadata().await?;
     let stream = ReaderStream::new(file);
 
-    Response::builder()
+    // ETag / 304 由路由上的 `http_cache::conditional_get` 根据这两个头生成
+    let mut builder = Response::builder()
         .header(header::CONTENT_TYPE, mime.as_ref())
+        .header(header::CONTENT_LENGTH, meta.len())
+        .header(header::CACHE_CONTROL, crate::http_cache::IMAGE_CACHE_CONTROL);
+    if let Ok(modified) = meta.modified() {
+        builder = builder.header(header::LAST_MODIFIED, crate::http_cache::http_date(modified));
+    }
+    builder
         .body(Body::from_stream(stream))
         .map_err(|e| ApiError(AppError::Internal(e.to_string())))
 }
@@ -882,9 +2601,7 @@ pub async fn get_lyric_offset(
     headers: HeaderMap,
     Path(track_id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let row = sqlx::query!(
         "SELECT lyric_offset_ms FROM user_track_metadata WHERE user_id = $1 AND track_id = $2",
@@ -908,9 +2625,7 @@ pub async fn update_lyric_offset(
     Path(track_id): Path<Uuid>,
     Json(payload): Json<UpdateLyricOffset>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     sqlx::query!(
         "INSERT INTO user_track_metadata (user_id, track_id, lyric_offset_ms, updated_at)
@@ -923,14 +2638,27 @@ pub async fn update_lyric_offset(
     Ok(Json(json!({"status": "success"})))
 }
 
-pub async fn rescan_track_metadata(
+#[derive(Deserialize)]
+pub struct UpdateTrackRequest {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    pub track_number: Option<i32>,
+    /// 空字符串清除流派
+    pub genre: Option<String>,
+    /// 同时把修改写回音频文件的标签，避免下次扫描被旧标签覆盖
+    #[serde(default)]
+    pub write_tags: bool,
+}
+
+/// 修正扫描出错的元数据 (管理员)。歌手 / 专辑按名称复用已有条目，不存在时新建
+pub async fn update_track(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(track_id): Path<Uuid>,
+    Json(payload): Json<UpdateTrackRequest>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let user_is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
         .bind(user_id)
@@ -944,7 +2672,144 @@ pub async fn rescan_track_metadata(
         )));
     }
 
+    let non_empty = |field: &str, value: Option<String>| -> Result<Option<String>, ApiError> {
+        match value.map(|v| v.trim().to_string()) {
+            Some(v) if v.is_empty() => Err(ApiError(AppError::BadRequest(format!(
+                "{} must not be empty",
+                field
+            )))),
+            other => Ok(other),
+        }
+    };
+    let title = non_empty("title", payload.title)?;
+    let artist = non_empty("artist", payload.artist)?;
+    let album = non_empty("album", payload.album)?;
+    let genre = payload.genre.map(|g| g.trim().to_string());
+    if payload.track_number.is_some_and(|n| n <= 0) {
+        return Err(ApiError(AppError::BadRequest(
+            "track_number must be positive".to_string(),
+        )));
+    }
+
+    let current = sqlx::query(
+        "SELECT t.path, t.end_ms, t.artist_id, t.album_id, al.title AS album_title,
+                al.artist_id AS album_artist_id, COALESCE(al.is_compilation, FALSE) AS is_compilation
+         FROM tracks t LEFT JOIN albums al ON al.id = t.album_id
+         WHERE t.id = $1",
+    )
+    .bind(track_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError(AppError::NotFound("Track not found".to_string())))?;
+
+    if payload.write_tags {
+        // CUE 分轨共用一个音频文件，标签无法逐轨写入
+        if current.get::<Option<i32>, _>("end_ms").is_some() {
+            return Err(ApiError(AppError::BadRequest(
+                "Cannot write tags for a cue sheet track".to_string(),
+            )));
+        }
+        let path = std::path::PathBuf::from(current.get::<String, _>("path"));
+        let edits = papilio_core::scanner::TagEdits {
+            title: title.clone(),
+            artist: artist.clone(),
+            album: album.clone(),
+            track_number: payload.track_number.map(|n| n as u32),
+            genre: genre.clone(),
+        };
+        tokio::task::spawn_blocking(move || papilio_core::scanner::write_tags(&path, &edits))
+            .await
+            .map_err(|e| ApiError(AppError::Internal(e.to_string())))??;
+    }
+
     let scanner = Scanner::new(state.db.clone());
+    let previous_artist_id: Option<Uuid> = current.get("artist_id");
+    let mut artist_id = previous_artist_id;
+    let artist_changed = match &artist {
+        Some(name) => {
+            let id = scanner.get_or_create_artist(name).await?;
+            let changed = artist_id != Some(id);
+            artist_id = Some(id);
+            changed
+        }
+        None => false,
+    };
+
+    // 合辑或专辑歌手与曲目歌手不同时，专辑仍归属原专辑歌手，改曲目歌手不会把曲目拆出合辑；
+    // 否则专辑跟随曲目歌手，换了歌手但没指定专辑时把原专辑名挂到新歌手下
+    let mut album_id: Option<Uuid> = current.get("album_id");
+    let album_artist_id: Option<Uuid> = current.get("album_artist_id");
+    let keeps_album_artist = album_artist_id.is_some()
+        && (current.get::<bool, _>("is_compilation") || album_artist_id != previous_artist_id);
+    let album_owner = if keeps_album_artist { album_artist_id } else { artist_id };
+    let album_title = album.clone().or_else(|| {
+        (artist_changed && !keeps_album_artist)
+            .then(|| current.get::<Option<String>, _>("album_title"))
+            .flatten()
+    });
+    if let (Some(album_title), Some(owner)) = (album_title, album_owner) {
+        album_id = Some(scanner.get_or_create_album(&album_title, owner, None, None).await?);
+    }
+
+    sqlx::query(
+        "UPDATE tracks SET
+            title = COALESCE($2, title),
+            artist_id = $3,
+            album_id = $4,
+            track_number = COALESCE($5, track_number),
+            genre = CASE WHEN $6 THEN NULLIF($7, '') ELSE genre END,
+            updated_at = NOW()
+         WHERE id = $1",
+    )
+    .bind(track_id)
+    .bind(&title)
+    .bind(artist_id)
+    .bind(album_id)
+    .bind(payload.track_number)
+    .bind(genre.is_some())
+    .bind(&genre)
+    .execute(&state.db)
+    .await?;
+
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS}
+        FROM tracks t
+        LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
+        WHERE t.id = $1
+        "#
+    ))
+    .bind(track_id)
+    .bind(user_id)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(TrackWithFavorite::from_row(&row)?))
+}
+
+pub async fn rescan_track_metadata(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(track_id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let user_is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await?
+        .get::<bool, _>("is_admin");
+
+    if !user_is_admin {
+        return Err(ApiError(AppError::BadRequest(
+            "Requires administrator privileges".to_string(),
+        )));
+    }
+
+    let scanner = Scanner::new(state.db.clone()).with_metadata_service(state.metadata_service.clone());
     scanner.process_track_by_id(track_id).await?;
 
     Ok(Json(
@@ -956,10 +2821,9 @@ pub async fn sync_artist_metadata(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(artist_id): Path<Uuid>,
+    Query(query): Query<super::admin::ArtistSyncQuery>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = crate::get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = crate::require_user_id(&headers, &state).await?;
 
     let user_is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
         .bind(user_id)
@@ -973,10 +2837,11 @@ pub async fn sync_artist_metadata(
         )));
     }
 
-    let db_clone = state.db.clone();
+    let refresh = query.refresh.unwrap_or(false);
+    // 使用共享的 MetadataService，与批量同步共用 MusicBrainz 限流
+    let service = state.metadata_service.clone();
     tokio::spawn(async move {
-        let service = papilio_core::metadata::MetadataService::new(db_clone);
-        if let Err(e) = service.fetch_and_update_artist(artist_id).await {
+        if let Err(e) = service.fetch_and_update_artist(artist_id, refresh).await {
             tracing::error!("Failed to sync artist {}: {:?}", artist_id, e);
         }
     });
@@ -985,3 +2850,44 @@ pub async fn sync_artist_metadata(
         json!({"status": "success", "message": "Artist metadata sync started"}),
     ))
 }
+
+#[derive(Deserialize)]
+pub struct TrackSyncQuery {
+    /// 为 true 时忽略缓存的 MusicBrainz 搜索结果
+    pub refresh: Option<bool>,
+    /// 为 true 时用 MusicBrainz 的规范标题覆盖本地标题
+    pub fix_title: Option<bool>,
+}
+
+/// 单曲 MusicBrainz 同步：录音 MBID、ISRC，可选修正标题。单次请求很快，直接同步返回匹配结果
+pub async fn sync_track_metadata(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(track_id): Path<Uuid>,
+    Query(query): Query<TrackSyncQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+
+    let user_is_admin = sqlx::query("SELECT is_admin FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await?
+        .get::<bool, _>("is_admin");
+
+    if !user_is_admin {
+        return Err(ApiError(AppError::BadRequest(
+            "Requires administrator privileges".to_string(),
+        )));
+    }
+
+    let matched = state
+        .metadata_service
+        .fetch_and_update_track(
+            track_id,
+            query.fix_title.unwrap_or(false),
+            query.refresh.unwrap_or(false),
+        )
+        .await?;
+
+    Ok(Json(json!({"status": "success", "matched": matched})))
+}
@@ -1,14 +1,19 @@
-use crate::handlers::music::TrackWithFavorite;
-use crate::{get_user_id, ApiError, AppState};
+use crate::handlers::music::{TrackWithFavorite, TRACK_COLUMNS};
+use crate::{browse_user_id, require_user_id, ApiError, AppState};
 use axum::{
-    extract::{Json, Path, State},
-    http::{HeaderMap, StatusCode},
-    response::IntoResponse,
+    body::Body,
+    extract::{ConnectInfo, Json, Multipart, Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
 };
 use papilio_core::error::AppError;
-use papilio_core::models::music::{CreatePlaylist, Playlist, Track};
+use papilio_core::models::music::{CreatePlaylist, Playlist};
+use papilio_core::playlist_io::{self, ExportTrack, PlaylistEntry, PlaylistFormat};
+use serde::Deserialize;
 use serde_json::json;
-use sqlx::Row;
+use sqlx::{PgPool, Row};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use uuid::Uuid;
 
@@ -19,6 +24,55 @@ pub struct PlaylistWithFavoriteTracks {
     pub tracks: Vec<TrackWithFavorite>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlaylistAccess {
+    Owner,
+    Collaborator,
+}
+
+/// 写操作的权限检查：所有者或 can_edit 的协作者
+async fn ensure_can_edit(state: &AppState, playlist_id: Uuid, user_id: Uuid) -> Result<PlaylistAccess, ApiError> {
+    let row = sqlx::query(
+        r#"
+        SELECT p.user_id, c.can_edit
+        FROM playlists p
+        LEFT JOIN playlist_collaborators c ON c.playlist_id = p.id AND c.user_id = $2
+        WHERE p.id = $1
+        "#,
+    )
+    .bind(playlist_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
+
+    let owner: Uuid = row.get("user_id");
+    let can_edit: Option<bool> = row.get("can_edit");
+    if owner == user_id {
+        Ok(PlaylistAccess::Owner)
+    } else if can_edit == Some(true) {
+        Ok(PlaylistAccess::Collaborator)
+    } else {
+        Err(ApiError(AppError::Auth("Forbidden: Not your playlist".to_string())))
+    }
+}
+
+/// 私有歌单的读权限：所有者或任意协作者 (含只读)
+async fn can_view(state: &AppState, playlist: &Playlist, user_id: Option<Uuid>) -> Result<bool, ApiError> {
+    let Some(user_id) = user_id else {
+        return Ok(false);
+    };
+    if playlist.user_id == user_id {
+        return Ok(true);
+    }
+    let row = sqlx::query("SELECT 1 FROM playlist_collaborators WHERE playlist_id = $1 AND user_id = $2")
+        .bind(playlist.id)
+        .bind(user_id)
+        .fetch_optional(&state.db)
+        .await?;
+    Ok(row.is_some())
+}
+
 pub async fn create_playlist(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
@@ -31,9 +85,7 @@ pub async fn create_playlist(
         )));
     }
 
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
 
     let playlist = sqlx::query_as!(
         Playlist,
@@ -54,20 +106,68 @@ pub async fn list_my_playlists(
     headers: HeaderMap,
 ) -> Result<impl IntoResponse, ApiError> {
     println!("DEBUG: list_my_playlists called");
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
+    Ok(Json(fetch_my_playlists(&state.db, user_id).await?))
+}
+
+/// "我的歌单" 列表项：自建歌单之外还包括受邀协作的歌单
+#[derive(serde::Serialize)]
+pub struct MyPlaylist {
+    #[serde(flatten)]
+    pub playlist: Playlist,
+    /// 他人创建、当前用户作为协作者加入的歌单
+    pub shared: bool,
+    /// 当前用户能否修改曲目 (所有者恒为 true)
+    pub can_edit: bool,
+}
+
+/// 用户自建的歌单与作为协作者加入的歌单，按更新时间倒序
+pub async fn fetch_my_playlists(db: &PgPool, user_id: Uuid) -> Result<Vec<MyPlaylist>, ApiError> {
+    let rows = sqlx::query(
+        r#"
+        SELECT p.id, p.user_id, p.name, p.description, COALESCE(p.is_public, FALSE) AS is_public,
+               p.created_at, p.updated_at,
+               p.user_id <> $1 AS shared,
+               (p.user_id = $1 OR COALESCE(c.can_edit, FALSE)) AS can_edit
+        FROM playlists p
+        LEFT JOIN playlist_collaborators c ON c.playlist_id = p.id AND c.user_id = $1
+        WHERE p.user_id = $1 OR c.user_id IS NOT NULL
+        ORDER BY p.updated_at DESC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows
+        .iter()
+        .map(|row| MyPlaylist {
+            playlist: Playlist {
+                id: row.get("id"),
+                user_id: row.get("user_id"),
+                name: row.get("name"),
+                description: row.get("description"),
+                is_public: row.get("is_public"),
+                created_at: row.get("created_at"),
+                updated_at: row.get("updated_at"),
+            },
+            shared: row.get("shared"),
+            can_edit: row.get("can_edit"),
+        })
+        .collect())
+}
 
+/// 用户自己创建的歌单 (不含协作歌单)，按更新时间倒序；数据导出只包含这部分
+pub async fn fetch_owned_playlists(db: &PgPool, user_id: Uuid) -> Result<Vec<Playlist>, ApiError> {
     let playlists = sqlx::query_as!(
         Playlist,
         r#"SELECT id, user_id, name, description, is_public as "is_public!", created_at, updated_at
            FROM playlists WHERE user_id = $1 ORDER BY updated_at DESC"#,
         user_id
     )
-    .fetch_all(&state.db)
+    .fetch_all(db)
     .await?;
-
-    Ok(Json(playlists))
+    Ok(playlists)
 }
 
 pub async fn add_track(
@@ -75,18 +175,9 @@ pub async fn add_track(
     headers: HeaderMap,
     Path((id, track_id)): Path<(Uuid, Uuid)>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
 
-    let p = sqlx::query!("SELECT user_id FROM playlists WHERE id = $1", id)
-        .fetch_optional(&state.db)
-        .await?
-        .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
-
-    if p.user_id != user_id {
-        return Err(ApiError(AppError::Auth("Forbidden: Not your playlist".to_string())));
-    }
+    ensure_can_edit(&state, id, user_id).await?;
 
     let pos = sqlx::query!(r#"SELECT COALESCE(MAX(position), 0) as "max_pos!" FROM playlist_tracks WHERE playlist_id = $1"#, id)
         .fetch_one(&state.db).await?
@@ -100,24 +191,57 @@ pub async fn add_track(
     Ok(Json(json!({"status": "success"})))
 }
 
+#[derive(Deserialize)]
+pub struct AddTracksPayload {
+    pub track_ids: Vec<Uuid>,
+}
+
+/// 批量追加曲目：按请求顺序接在当前末尾之后，已在歌单中的曲目跳过，返回追加后的曲目总数
+pub async fn add_tracks(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<AddTracksPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = require_user_id(&headers, &state).await?;
+
+    ensure_can_edit(&state, id, user_id).await?;
+
+    // 单条语句完成：WITH ORDINALITY 保留请求顺序，JOIN tracks 过滤掉不存在的 ID
+    let added = sqlx::query(
+        r#"
+        INSERT INTO playlist_tracks (playlist_id, track_id, position)
+        SELECT $1, m.t_id,
+               (SELECT COALESCE(MAX(position), 0) FROM playlist_tracks WHERE playlist_id = $1) + m.ord::int
+        FROM UNNEST($2::uuid[]) WITH ORDINALITY AS m(t_id, ord)
+        JOIN tracks t ON t.id = m.t_id AND t.deleted_at IS NULL
+        ORDER BY m.ord
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(id)
+    .bind(&payload.track_ids)
+    .execute(&state.db)
+    .await?
+    .rows_affected();
+
+    let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM playlist_tracks WHERE playlist_id = $1")
+        .bind(id)
+        .fetch_one(&state.db)
+        .await?;
+
+    Ok(Json(json!({"status": "success", "added": added, "track_count": count})))
+}
+
 pub async fn reorder_tracks(
     State(state): State<Arc<AppState>>,
     headers: HeaderMap,
     Path(playlist_id): Path<Uuid>,
     Json(track_ids): Json<Vec<Uuid>>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
-
-    let p = sqlx::query!("SELECT user_id FROM playlists WHERE id = $1", playlist_id)
-        .fetch_optional(&state.db)
-        .await?
-        .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
 
-    if p.user_id != user_id {
-        return Err(ApiError(AppError::Auth("Forbidden: Not your playlist".to_string())));
-    }
+    ensure_can_edit(&state, playlist_id, user_id).await?;
 
     // 批量更新位置，利用 UNNEST 避免循环 SQL 查询以提升性能
     let positions: Vec<i32> = (1..=track_ids.len() as i32).collect();
@@ -144,18 +268,9 @@ pub async fn remove_track(
     headers: HeaderMap,
     Path((id, track_id)): Path<(Uuid, Uuid)>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
 
-    let p = sqlx::query!("SELECT user_id FROM playlists WHERE id = $1", id)
-        .fetch_optional(&state.db)
-        .await?
-        .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
-
-    if p.user_id != user_id {
-        return Err(ApiError(AppError::Auth("Forbidden: Not your playlist".to_string())));
-    }
+    ensure_can_edit(&state, id, user_id).await?;
 
     sqlx::query!(
         "DELETE FROM playlist_tracks WHERE playlist_id = $1 AND track_id = $2",
@@ -181,20 +296,23 @@ pub async fn update_playlist(
         )));
     }
 
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
+    let access = ensure_can_edit(&state, id, user_id).await?;
 
+    // 公开/私有只能由所有者切换，协作者的修改保留原值
     let playlist = sqlx::query_as!(
         Playlist,
-        r#"UPDATE playlists SET name = $1, description = $2, is_public = $3, updated_at = NOW()
-           WHERE id = $4 AND user_id = $5
+        r#"UPDATE playlists
+           SET name = $1, description = $2,
+               is_public = CASE WHEN $5 THEN $3 ELSE is_public END,
+               updated_at = NOW()
+           WHERE id = $4
            RETURNING id, user_id, name, description, is_public as "is_public!", created_at, updated_at"#,
         name,
         payload.description,
         payload.is_public.unwrap_or(false),
         id,
-        user_id
+        access == PlaylistAccess::Owner
     )
     .fetch_optional(&state.db).await?
     .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found or access denied".to_string())))?;
@@ -207,9 +325,7 @@ pub async fn delete_playlist(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = get_user_id(&headers, &state)
-        .await
-        .ok_or_else(|| ApiError(AppError::Auth("Unauthorized".to_string())))?;
+    let user_id = require_user_id(&headers, &state).await?;
 
     let res = sqlx::query!(
         "DELETE FROM playlists WHERE id = $1 AND user_id = $2",
@@ -233,7 +349,7 @@ pub async fn get_playlist(
     headers: HeaderMap,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, ApiError> {
-    let user_id = get_user_id(&headers, &state).await;
+    let user_id = browse_user_id(&headers, &state).await?;
 
     let playlist_opt = sqlx::query_as!(Playlist, r#"SELECT id, user_id, name, description, is_public as "is_public!", created_at, updated_at FROM playlists WHERE id = $1"#, id)
         .fetch_optional(&state.db).await?;
@@ -257,67 +373,610 @@ pub async fn get_playlist(
         }
     };
 
-    if !playlist.is_public && playlist.user_id != Uuid::nil() && Some(playlist.user_id) != user_id {
+    if !playlist.is_public && playlist.user_id != Uuid::nil() && !can_view(&state, &playlist, user_id).await? {
         return Err(ApiError(AppError::Auth("Forbidden: Private playlist".to_string())));
     }
 
-    let rows = sqlx::query(
+    let tracks = fetch_playlist_tracks(&state.db, id, user_id).await?;
+    Ok(Json(PlaylistWithFavoriteTracks { playlist, tracks }))
+}
+
+/// 歌单曲目按排序位置返回，收藏标记与个人元数据按 `viewer` 计算
+pub async fn fetch_playlist_tracks(
+    db: &PgPool,
+    playlist_id: Uuid,
+    viewer: Option<Uuid>,
+) -> Result<Vec<TrackWithFavorite>, ApiError> {
+    let rows = sqlx::query(&format!(
         r#"
-        SELECT t.id, t.title, t.album_id, t.artist_id,
-               t.duration, t.track_number,
-               t.disc_number, t.path, t.bitrate,
-               t.format, t.size, t.bpm,
-               t.musicbrainz_track_id,
-               t.lyrics,
-               t.created_at, t.updated_at,
-               (f.user_id IS NOT NULL) as is_favorite,
-               a.name as artist_name,
-               a.image_url as artist_image_url,
-               al.title as album_title,
-               COALESCE(m.lyric_offset_ms, 0) as lyric_offset_ms
+        SELECT {TRACK_COLUMNS}
         FROM tracks t
         JOIN playlist_tracks pt ON t.id = pt.track_id
         LEFT JOIN user_favorites f ON t.id = f.track_id AND f.user_id = $2
         LEFT JOIN artists a ON t.artist_id = a.id
         LEFT JOIN albums al ON t.album_id = al.id
         LEFT JOIN user_track_metadata m ON t.id = m.track_id AND m.user_id = $2
-        WHERE pt.playlist_id = $1
+        WHERE pt.playlist_id = $1 AND t.deleted_at IS NULL
+        ORDER BY pt.position
+        "#
+    ))
+    .bind(playlist_id)
+    .bind(viewer)
+    .fetch_all(db)
+    .await?;
+
+    Ok(rows.iter().map(TrackWithFavorite::from_row).collect::<Result<_, _>>()?)
+}
+
+/// 导入文件大小上限
+pub const IMPORT_MAX_BYTES: usize = 5 * 1024 * 1024;
+
+/// 单次导入的条目上限
+const IMPORT_MAX_ENTRIES: usize = 5000;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    pub format: Option<String>,
+}
+
+/// 导出歌单为扩展 M3U 或 XSPF，条目指向本服务的 `/stream` 直链
+pub async fn export_playlist(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> Result<impl IntoResponse, ApiError> {
+    let format_name = query.format.as_deref().unwrap_or("m3u8");
+    let format = PlaylistFormat::from_name(format_name).ok_or_else(|| {
+        ApiError(AppError::BadRequest(format!(
+            "Unsupported playlist format: {} (expected m3u8 or xspf)",
+            format_name
+        )))
+    })?;
+
+    let user_id = browse_user_id(&headers, &state).await?;
+
+    let playlist = sqlx::query_as!(Playlist, r#"SELECT id, user_id, name, description, is_public as "is_public!", created_at, updated_at FROM playlists WHERE id = $1"#, id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
+
+    if !playlist.is_public && !can_view(&state, &playlist, user_id).await? {
+        return Err(ApiError(AppError::Auth("Forbidden: Private playlist".to_string())));
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT t.id, t.title, t.duration, a.name AS artist_name, al.title AS album_title
+        FROM playlist_tracks pt
+        JOIN tracks t ON t.id = pt.track_id
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        WHERE pt.playlist_id = $1 AND t.deleted_at IS NULL
         ORDER BY pt.position
         "#,
     )
     .bind(id)
-    .bind(user_id)
     .fetch_all(&state.db)
     .await?;
 
-    let tracks = rows
+    let base_url = public_base_url(&headers, peer.ip());
+    let entries: Vec<(Uuid, String, i32, Option<String>, Option<String>)> = rows
+        .iter()
+        .map(|row| {
+            (
+                row.get("id"),
+                row.get("title"),
+                row.get("duration"),
+                row.get("artist_name"),
+                row.get("album_title"),
+            )
+        })
+        .collect();
+    let tracks: Vec<ExportTrack> = entries
+        .iter()
+        .map(|(track_id, title, duration, artist, album)| ExportTrack {
+            url: format!("{}{}/music/stream/{}", base_url, crate::routes::API_PREFIX, track_id),
+            title,
+            artist: artist.as_deref(),
+            album: album.as_deref(),
+            duration_secs: *duration,
+        })
+        .collect();
+
+    let body = match format {
+        PlaylistFormat::M3u8 => playlist_io::write_m3u8(&playlist.name, &tracks),
+        PlaylistFormat::Xspf => playlist_io::write_xspf(&playlist.name, &tracks),
+    };
+    let filename = sanitize_filename::sanitize(format!("{}.{}", playlist.name, format.extension()));
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, format.mime())
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "attachment; filename=\"playlist.{}\"; filename*=UTF-8''{}",
+                format.extension(),
+                urlencoding::encode(&filename)
+            ),
+        )
+        .body(Body::from(body))
+        .map_err(|e| ApiError(AppError::Internal(e.to_string())))
+}
+
+/// 外部访问地址：优先 PUBLIC_BASE_URL，否则取 Host 头；
+/// X-Forwarded-Host / X-Forwarded-Proto 只在对端属于 TRUSTED_PROXIES 时采信，与 `client_ip` 一致
+fn public_base_url(headers: &HeaderMap, peer: std::net::IpAddr) -> String {
+    if let Ok(url) = std::env::var("PUBLIC_BASE_URL") {
+        return url.trim_end_matches('/').to_string();
+    }
+    let trusted = crate::is_trusted_proxy(peer);
+    let forwarded = |name: &str| {
+        headers
+            .get(name)
+            .filter(|_| trusted)
+            .and_then(|v| v.to_str().ok())
+    };
+    let host = forwarded("x-forwarded-host")
+        .or_else(|| headers.get(header::HOST).and_then(|v| v.to_str().ok()))
+        .unwrap_or("localhost:3000");
+    let scheme = forwarded("x-forwarded-proto").unwrap_or("http");
+    format!("{}://{}", scheme, host)
+}
+
+/// 导入 M3U/XSPF 文件：按路径或 "标题 + 歌手" 匹配曲库中已有曲目，新建歌单，
+/// 未匹配的条目原样返回，方便用户手动补齐
+pub async fn import_playlist(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    mut multipart: Multipart,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = require_user_id(&headers, &state).await?;
+
+    let mut file: Option<(Option<String>, String)> = None;
+    let mut name: Option<String> = None;
+
+    while let Some(mut field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?
+    {
+        match field.name() {
+            Some("file") => {
+                let filename = field.file_name().map(|f| f.to_string());
+                let mut data: Vec<u8> = Vec::new();
+                while let Some(chunk) = field
+                    .chunk()
+                    .await
+                    .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?
+                {
+                    if data.len() + chunk.len() > IMPORT_MAX_BYTES {
+                        return Err(ApiError(AppError::BadRequest(
+                            "Playlist file too large (max 5MB)".to_string(),
+                        )));
+                    }
+                    data.extend_from_slice(&chunk);
+                }
+                // 老式 .m3u 多为本地编码，非 UTF-8 时按 GBK 解码
+                let (content, _, has_errors) = encoding_rs::UTF_8.decode(&data);
+                let content = if has_errors {
+                    encoding_rs::GBK.decode(&data).0.into_owned()
+                } else {
+                    content.into_owned()
+                };
+                file = Some((filename, content));
+            }
+            Some("name") => {
+                let value = field
+                    .text()
+                    .await
+                    .map_err(|e| ApiError(AppError::BadRequest(e.to_string())))?;
+                name = Some(value.trim().to_string()).filter(|v| !v.is_empty());
+            }
+            _ => {}
+        }
+    }
+
+    let (filename, content) = file
+        .ok_or_else(|| ApiError(AppError::BadRequest("Missing playlist file".to_string())))?;
+    let parsed = playlist_io::parse_playlist(&content, filename.as_deref());
+    if parsed.entries.is_empty() {
+        return Err(ApiError(AppError::BadRequest(
+            "No entries found in playlist file".to_string(),
+        )));
+    }
+    if parsed.entries.len() > IMPORT_MAX_ENTRIES {
+        return Err(ApiError(AppError::BadRequest(format!(
+            "Too many entries in playlist file (max {})",
+            IMPORT_MAX_ENTRIES
+        ))));
+    }
+
+    let fallback_name = filename
+        .as_deref()
+        .and_then(|f| std::path::Path::new(f).file_stem())
+        .and_then(|s| s.to_str())
+        .map(|s| s.to_string());
+    let playlist_name: String = name
+        .or(parsed.title.clone())
+        .or(fallback_name)
+        .unwrap_or_else(|| "Imported playlist".to_string())
+        .chars()
+        .take(100)
+        .collect();
+
+    let mut matched: Vec<Uuid> = Vec::new();
+    let mut unmatched: Vec<serde_json::Value> = Vec::new();
+    let resolved = match_entries(&state, &parsed.entries).await?;
+    for (index, (entry, found)) in parsed.entries.iter().zip(resolved).enumerate() {
+        match found {
+            Some(track_id) if !matched.contains(&track_id) => matched.push(track_id),
+            Some(_) => {}
+            None => unmatched.push(json!({
+                "line": index + 1,
+                "location": entry.location,
+                "title": entry.title,
+                "artist": entry.artist,
+            })),
+        }
+    }
+
+    let mut tx = state.db.begin().await?;
+    let playlist = sqlx::query_as!(
+        Playlist,
+        r#"INSERT INTO playlists (user_id, name, is_public) VALUES ($1, $2, FALSE)
+           RETURNING id, user_id, name, description, is_public as "is_public!", created_at, updated_at"#,
+        user_id,
+        playlist_name
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let positions: Vec<i32> = (1..=matched.len() as i32).collect();
+    sqlx::query(
+        r#"
+        INSERT INTO playlist_tracks (playlist_id, track_id, position)
+        SELECT $1, m.t_id, m.pos FROM UNNEST($2::uuid[], $3::int[]) AS m(t_id, pos)
+        ON CONFLICT DO NOTHING
+        "#,
+    )
+    .bind(playlist.id)
+    .bind(&matched)
+    .bind(&positions)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    tracing::info!(
+        "Imported playlist {} for {}: {} matched, {} unmatched",
+        playlist.id,
+        user_id,
+        matched.len(),
+        unmatched.len()
+    );
+
+    Ok((
+        StatusCode::CREATED,
+        Json(json!({
+            "status": "success",
+            "playlist": playlist,
+            "matched": matched.len(),
+            "unmatched": unmatched,
+        })),
+    ))
+}
+
+/// 条目 location 中可用于匹配的部分：本服务的 `/stream/{id}` 链接、规范化后的路径、末尾几级路径
+struct EntryLocation {
+    stream_id: Option<Uuid>,
+    path: String,
+    suffix: Option<String>,
+}
+
+fn entry_location(location: &str) -> EntryLocation {
+    let stream_id = location
+        .split_once("/stream/")
+        .and_then(|(_, rest)| rest.split(['?', '#', '/']).next())
+        .and_then(|id| Uuid::parse_str(id).ok());
+
+    let path = location.strip_prefix("file://").unwrap_or(location);
+    let path = urlencoding::decode(path)
+        .map(|p| p.into_owned())
+        .unwrap_or_else(|_| path.to_string())
+        .replace('\\', "/");
+
+    // 其他机器导出的歌单根目录不同，退而按末尾几级路径匹配
+    let suffix = path
+        .trim_start_matches("./")
+        .rsplit('/')
+        .take(3)
+        .collect::<Vec<_>>()
         .into_iter()
-        .map(|row| TrackWithFavorite {
-            track: Track {
-                id: row.get("id"),
-                title: row.get("title"),
-                album_id: row.get("album_id"),
-                artist_id: row.get("artist_id"),
-                artist_name: row.get("artist_name"),
-                album_title: row.get("album_title"),
-                artist_image_url: row.get("artist_image_url"),
-                duration: row.get("duration"),
-                track_number: row.get("track_number"),
-                disc_number: row.get::<Option<i32>, _>("disc_number").unwrap_or(1),
-                path: row.get("path"),
-                bitrate: row.get("bitrate"),
-                format: row.get("format"),
-                size: row.get("size"),
-                bpm: row.get("bpm"),
-                musicbrainz_track_id: row.get("musicbrainz_track_id"),
-                lyrics: row.get("lyrics"),
-                lyric_offset_ms: row.get::<i32, _>("lyric_offset_ms"),
-                created_at: row.get("created_at"),
-                updated_at: row.get("updated_at"),
-            },
-            is_favorite: row.get("is_favorite"),
+        .rev()
+        .collect::<Vec<_>>()
+        .join("/");
+    let suffix = Some(suffix).filter(|s| !s.is_empty() && !path.contains("://"));
+
+    EntryLocation {
+        stream_id,
+        path,
+        suffix,
+    }
+}
+
+/// 依次尝试：本服务导出的 `/stream/{id}` 链接 → 完整路径 → 相对路径后缀 → 标题 + 歌手。
+/// 每一步对所有尚未匹配的条目批量查询，整个导入最多四次查询
+async fn match_entries(
+    state: &AppState,
+    entries: &[PlaylistEntry],
+) -> Result<Vec<Option<Uuid>>, ApiError> {
+    let locations: Vec<Option<EntryLocation>> = entries
+        .iter()
+        .map(|e| e.location.as_deref().map(entry_location))
+        .collect();
+    let mut resolved: Vec<Option<Uuid>> = vec![None; entries.len()];
+
+    let stream_ids: Vec<Uuid> = locations
+        .iter()
+        .flatten()
+        .filter_map(|l| l.stream_id)
+        .collect();
+    if !stream_ids.is_empty() {
+        let existing: HashSet<Uuid> =
+            sqlx::query_scalar::<_, Uuid>("SELECT id FROM tracks WHERE id = ANY($1) AND deleted_at IS NULL")
+                .bind(&stream_ids)
+                .fetch_all(&state.db)
+                .await?
+                .into_iter()
+                .collect();
+        for (slot, location) in resolved.iter_mut().zip(&locations) {
+            if let Some(id) = location.as_ref().and_then(|l| l.stream_id) {
+                if existing.contains(&id) {
+                    *slot = Some(id);
+                }
+            }
+        }
+    }
+
+    let paths: Vec<String> = locations
+        .iter()
+        .zip(&resolved)
+        .filter(|(_, r)| r.is_none())
+        .filter_map(|(l, _)| l.as_ref().map(|l| l.path.clone()))
+        .collect();
+    if !paths.is_empty() {
+        let by_path: HashMap<String, Uuid> =
+            sqlx::query("SELECT id, path FROM tracks WHERE path = ANY($1) AND deleted_at IS NULL")
+                .bind(&paths)
+                .fetch_all(&state.db)
+                .await?
+                .iter()
+                .map(|row| (row.get("path"), row.get("id")))
+                .collect();
+        for (slot, location) in resolved.iter_mut().zip(&locations) {
+            if slot.is_none() {
+                if let Some(l) = location {
+                    *slot = by_path.get(&l.path).copied();
+                }
+            }
+        }
+    }
+
+    // 后缀匹配：按文件名一次取回候选路径，再在内存里比对目录后缀，
+    // 避免对每个条目各跑一次无法走索引的 `LIKE '%/…'`
+    let file_names: Vec<String> = locations
+        .iter()
+        .zip(&resolved)
+        .filter(|(_, r)| r.is_none())
+        .filter_map(|(l, _)| l.as_ref().and_then(|l| l.suffix.as_deref()))
+        .filter_map(|suffix| suffix.rsplit('/').next())
+        .map(str::to_string)
+        .collect();
+    if !file_names.is_empty() {
+        let mut by_name: HashMap<String, Vec<(String, Uuid)>> = HashMap::new();
+        let rows = sqlx::query(
+            r#"
+            SELECT id, path FROM tracks
+            WHERE deleted_at IS NULL
+              AND regexp_replace(path, '^.*/', '') = ANY($1)
+            "#,
+        )
+        .bind(&file_names)
+        .fetch_all(&state.db)
+        .await?;
+        for row in rows {
+            let path: String = row.get("path");
+            let name = path.rsplit('/').next().unwrap_or_default().to_string();
+            by_name.entry(name).or_default().push((path, row.get("id")));
+        }
+        for (slot, location) in resolved.iter_mut().zip(&locations) {
+            let Some(suffix) = location.as_ref().and_then(|l| l.suffix.as_deref()) else {
+                continue;
+            };
+            if slot.is_some() {
+                continue;
+            }
+            let name = suffix.rsplit('/').next().unwrap_or_default();
+            let tail = format!("/{}", suffix);
+            *slot = by_name.get(name).and_then(|candidates| {
+                candidates
+                    .iter()
+                    .filter(|(path, _)| path.ends_with(&tail))
+                    .min_by_key(|(path, _)| path.len())
+                    .map(|(_, id)| *id)
+            });
+        }
+    }
+
+    let mut title_idx: Vec<i32> = Vec::new();
+    let mut titles: Vec<String> = Vec::new();
+    let mut artists: Vec<Option<String>> = Vec::new();
+    let mut durations: Vec<Option<i32>> = Vec::new();
+    for (index, (entry, slot)) in entries.iter().zip(&resolved).enumerate() {
+        if let (Some(title), None) = (entry.title.as_ref(), slot) {
+            title_idx.push(index as i32);
+            titles.push(title.clone());
+            artists.push(entry.artist.clone());
+            durations.push(entry.duration_secs);
+        }
+    }
+    if !title_idx.is_empty() {
+        let rows = sqlx::query(
+            r#"
+            SELECT m.idx,
+                   (SELECT t.id FROM tracks t
+                    LEFT JOIN artists a ON t.artist_id = a.id
+                    WHERE t.deleted_at IS NULL
+                      AND lower(t.title) = lower(m.title)
+                      AND (m.artist IS NULL OR lower(a.name) = lower(m.artist))
+                    ORDER BY (m.duration IS NOT NULL AND abs(t.duration - m.duration) <= 3) DESC
+                    LIMIT 1) AS id
+            FROM UNNEST($1::int[], $2::text[], $3::text[], $4::int[])
+                AS m(idx, title, artist, duration)
+            "#,
+        )
+        .bind(&title_idx)
+        .bind(&titles)
+        .bind(&artists)
+        .bind(&durations)
+        .fetch_all(&state.db)
+        .await?;
+        for row in rows {
+            let index: i32 = row.get("idx");
+            resolved[index as usize] = row.get("id");
+        }
+    }
+
+    Ok(resolved)
+}
+
+#[derive(Deserialize)]
+pub struct InviteCollaborator {
+    pub username: String,
+    pub can_edit: Option<bool>,
+}
+
+pub async fn list_collaborators(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = require_user_id(&headers, &state).await?;
+
+    let playlist = sqlx::query_as!(Playlist, r#"SELECT id, user_id, name, description, is_public as "is_public!", created_at, updated_at FROM playlists WHERE id = $1"#, id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
+
+    if !can_view(&state, &playlist, Some(user_id)).await? {
+        return Err(ApiError(AppError::Auth("Forbidden: Not your playlist".to_string())));
+    }
+
+    let rows = sqlx::query(
+        r#"
+        SELECT u.id, u.username, u.nickname, c.can_edit, c.created_at
+        FROM playlist_collaborators c
+        JOIN users u ON u.id = c.user_id
+        WHERE c.playlist_id = $1
+        ORDER BY c.created_at
+        "#,
+    )
+    .bind(id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let collaborators = rows
+        .iter()
+        .map(|row| {
+            json!({
+                "user_id": row.get::<Uuid, _>("id"),
+                "username": row.get::<String, _>("username"),
+                "nickname": row.get::<Option<String>, _>("nickname"),
+                "can_edit": row.get::<bool, _>("can_edit"),
+                "added_at": row.get::<chrono::DateTime<chrono::Utc>, _>("created_at"),
+            })
         })
         .collect::<Vec<_>>();
 
-    Ok(Json(PlaylistWithFavoriteTracks { playlist, tracks }))
+    Ok(Json(json!({
+        "owner_id": playlist.user_id,
+        "collaborators": collaborators,
+    })))
+}
+
+/// 邀请协作者 (仅所有者)；重复邀请时更新 can_edit
+pub async fn invite_collaborator(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Json(payload): Json<InviteCollaborator>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = require_user_id(&headers, &state).await?;
+    if ensure_can_edit(&state, id, user_id).await? != PlaylistAccess::Owner {
+        return Err(ApiError(AppError::Auth(
+            "Forbidden: Only the owner can manage collaborators".to_string(),
+        )));
+    }
+
+    let invitee: Uuid = sqlx::query_scalar("SELECT id FROM users WHERE username = $1")
+        .bind(payload.username.trim())
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("User not found".to_string())))?;
+
+    if invitee == user_id {
+        return Err(ApiError(AppError::BadRequest(
+            "The owner is already a member of this playlist".to_string(),
+        )));
+    }
+
+    let can_edit = payload.can_edit.unwrap_or(true);
+    sqlx::query(
+        r#"
+        INSERT INTO playlist_collaborators (playlist_id, user_id, can_edit)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (playlist_id, user_id) DO UPDATE SET can_edit = EXCLUDED.can_edit
+        "#,
+    )
+    .bind(id)
+    .bind(invitee)
+    .bind(can_edit)
+    .execute(&state.db)
+    .await?;
+
+    Ok(Json(json!({"status": "success", "user_id": invitee, "can_edit": can_edit})))
+}
+
+/// 移除协作者：所有者可移除任何人，协作者可以退出
+pub async fn remove_collaborator(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Path((id, collaborator_id)): Path<(Uuid, Uuid)>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = require_user_id(&headers, &state).await?;
+
+    let owner: Uuid = sqlx::query_scalar("SELECT user_id FROM playlists WHERE id = $1")
+        .bind(id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| ApiError(AppError::NotFound("Playlist not found".to_string())))?;
+
+    if owner != user_id && collaborator_id != user_id {
+        return Err(ApiError(AppError::Auth(
+            "Forbidden: Only the owner can manage collaborators".to_string(),
+        )));
+    }
+
+    let res = sqlx::query("DELETE FROM playlist_collaborators WHERE playlist_id = $1 AND user_id = $2")
+        .bind(id)
+        .bind(collaborator_id)
+        .execute(&state.db)
+        .await?;
+
+    if res.rows_affected() == 0 {
+        return Err(ApiError(AppError::NotFound("Collaborator not found".to_string())));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,328 @@
+//! "正在收听"：播放进度上报时顺带记录每位用户当前的曲目，
+//! 通过 WebSocket 推送给同一服务器上的其他用户。用户可关闭公开。
+
+use crate::{ApiError, AppState};
+use axum::{
+    extract::{
+        ws::{Message, WebSocket, WebSocketUpgrade},
+        Query, State,
+    },
+    http::HeaderMap,
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use papilio_core::error::AppError;
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Row;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+/// 单个用户的收听状态：`now_playing:{user_id}`
+pub const NOW_PLAYING_PREFIX: &str = "now_playing:";
+/// 当前可能在收听的用户集合，快照时据此批量读取并清理已过期的成员
+const NOW_PLAYING_USERS_KEY: &str = "now_playing_users";
+
+/// 收听状态的有效期 (NOW_PLAYING_TTL_SECS，默认 60 秒)。
+/// 客户端约每 5 秒上报一次进度，停止上报后状态自然过期
+fn now_playing_ttl() -> u64 {
+    std::env::var("NOW_PLAYING_TTL_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(60)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NowPlaying {
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub avatar: Option<String>,
+    pub track_id: Uuid,
+    pub title: String,
+    pub artist_name: Option<String>,
+    pub album_id: Option<Uuid>,
+    pub is_playing: bool,
+    pub position_ms: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// 推送给 WebSocket 客户端的事件
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PresenceEvent {
+    Snapshot { listeners: Vec<NowPlaying> },
+    Update(NowPlaying),
+    Stopped { user_id: Uuid },
+}
+
+impl PresenceEvent {
+    fn user_id(&self) -> Option<Uuid> {
+        match self {
+            Self::Snapshot { .. } => None,
+            Self::Update(np) => Some(np.user_id),
+            Self::Stopped { user_id } => Some(*user_id),
+        }
+    }
+}
+
+pub fn presence_channel() -> broadcast::Sender<PresenceEvent> {
+    broadcast::channel(256).0
+}
+
+/// 由播放进度上报调用：写入 Redis 并广播。关闭公开的用户直接忽略
+pub async fn publish_now_playing(
+    state: &AppState,
+    user_id: Uuid,
+    track_id: Uuid,
+    position_ms: i32,
+    is_playing: bool,
+) -> Result<(), ApiError> {
+    let row = sqlx::query(
+        r#"
+        SELECT u.share_now_playing, COALESCE(u.nickname, u.username) AS display_name, u.avatar,
+               t.title, t.album_id, a.name AS artist_name
+        FROM users u
+        JOIN tracks t ON t.id = $2
+        LEFT JOIN artists a ON a.id = t.artist_id
+        WHERE u.id = $1
+        "#,
+    )
+    .bind(user_id)
+    .bind(track_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(());
+    };
+    if !row.get::<bool, _>("share_now_playing") {
+        return Ok(());
+    }
+
+    let now_playing = NowPlaying {
+        user_id,
+        display_name: row.get("display_name"),
+        avatar: row.get("avatar"),
+        track_id,
+        title: row.get("title"),
+        artist_name: row.get("artist_name"),
+        album_id: row.get("album_id"),
+        is_playing,
+        position_ms,
+        updated_at: Utc::now(),
+    };
+
+    let payload = serde_json::to_string(&now_playing)
+        .map_err(|e| ApiError(AppError::Internal(e.to_string())))?;
+    let mut redis = state.redis.clone();
+    let _: () = redis
+        .set_ex(format!("{}{}", NOW_PLAYING_PREFIX, user_id), payload, now_playing_ttl())
+        .await
+        .map_err(|e| ApiError(AppError::Internal(format!("Valkey error: {}", e))))?;
+    let _: () = redis
+        .sadd(NOW_PLAYING_USERS_KEY, user_id.to_string())
+        .await
+        .unwrap_or(());
+
+    // 没有订阅者时 send 返回错误，属正常情况
+    let _ = state.presence.send(PresenceEvent::Update(now_playing));
+    Ok(())
+}
+
+async fn clear_now_playing(state: &AppState, user_id: Uuid) {
+    let mut redis = state.redis.clone();
+    let _: () = redis
+        .del(format!("{}{}", NOW_PLAYING_PREFIX, user_id))
+        .await
+        .unwrap_or(());
+    let _: () = redis
+        .srem(NOW_PLAYING_USERS_KEY, user_id.to_string())
+        .await
+        .unwrap_or(());
+    let _ = state.presence.send(PresenceEvent::Stopped { user_id });
+}
+
+/// 当前所有公开的收听状态，过期的成员顺带从集合中移除
+async fn snapshot(state: &AppState) -> Result<Vec<NowPlaying>, ApiError> {
+    let mut redis = state.redis.clone();
+    let members: Vec<String> = redis
+        .smembers(NOW_PLAYING_USERS_KEY)
+        .await
+        .map_err(|e| ApiError(AppError::Internal(format!("Valkey error: {}", e))))?;
+    if members.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let keys: Vec<String> = members
+        .iter()
+        .map(|id| format!("{}{}", NOW_PLAYING_PREFIX, id))
+        .collect();
+    let values: Vec<Option<String>> = redis
+        .mget(&keys)
+        .await
+        .map_err(|e| ApiError(AppError::Internal(format!("Valkey error: {}", e))))?;
+
+    let mut listeners = Vec::new();
+    for (member, value) in members.iter().zip(values) {
+        match value.and_then(|v| serde_json::from_str::<NowPlaying>(&v).ok()) {
+            Some(np) => listeners.push(np),
+            None => {
+                let _: () = redis.srem(NOW_PLAYING_USERS_KEY, member).await.unwrap_or(());
+            }
+        }
+    }
+    listeners.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
+    Ok(listeners)
+}
+
+/// REST 快照：其他用户正在收听的曲目 (不含自己)
+pub async fn get_now_playing(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let listeners = snapshot(&state)
+        .await?
+        .into_iter()
+        .filter(|np| np.user_id != user_id)
+        .collect::<Vec<_>>();
+    Ok(Json(listeners))
+}
+
+#[derive(Deserialize)]
+pub struct PresenceSocketQuery {
+    /// 浏览器的 WebSocket 无法携带 Authorization 头，改由查询参数传入 JWT
+    pub token: Option<String>,
+}
+
+/// WebSocket：连接后先推送一次快照，之后推送其他用户的变化
+pub async fn now_playing_socket(
+    State(state): State<Arc<AppState>>,
+    mut headers: HeaderMap,
+    Query(query): Query<PresenceSocketQuery>,
+    ws: WebSocketUpgrade,
+) -> Result<impl IntoResponse, ApiError> {
+    if let Some(token) = query.token.filter(|t| !t.is_empty()) {
+        if let Ok(value) = format!("Bearer {}", token).parse() {
+            headers.insert(axum::http::header::AUTHORIZATION, value);
+        }
+    }
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let token = headers
+        .get(axum::http::header::AUTHORIZATION)
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.strip_prefix("Bearer "))
+        .map(str::to_string);
+
+    Ok(ws.on_upgrade(move |socket| presence_session(socket, state, user_id, token)))
+}
+
+/// 长连接建立后定期确认会话仍然存在，注销或被踢下线的会话不再继续接收推送
+const SESSION_RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+async fn presence_session(
+    mut socket: WebSocket,
+    state: Arc<AppState>,
+    user_id: Uuid,
+    token: Option<String>,
+) {
+    let mut events = state.presence.subscribe();
+
+    if send_snapshot(&mut socket, &state, user_id).await.is_err() {
+        return;
+    }
+
+    let mut recheck = tokio::time::interval(SESSION_RECHECK_INTERVAL);
+    recheck.tick().await;
+
+    loop {
+        tokio::select! {
+            _ = recheck.tick(), if token.is_some() => {
+                let token = token.as_deref().unwrap_or_default();
+                // Valkey 暂时不可用时保持连接，只有确认会话已删除才断开
+                if let Ok(false) = crate::session_exists(&state, token).await {
+                    tracing::debug!("Presence socket closed for revoked session of {}", user_id);
+                    let _ = socket.send(Message::Close(None)).await;
+                    break;
+                }
+            }
+            event = events.recv() => match event {
+                Ok(event) if event.user_id() == Some(user_id) => continue,
+                Ok(event) => {
+                    let Ok(text) = serde_json::to_string(&event) else { continue };
+                    if socket.send(Message::Text(text.into())).await.is_err() {
+                        break;
+                    }
+                }
+                // 消费太慢漏掉了部分事件，直接补发一次完整快照
+                Err(broadcast::error::RecvError::Lagged(_)) => {
+                    if send_snapshot(&mut socket, &state, user_id).await.is_err() {
+                        break;
+                    }
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            incoming = socket.recv() => match incoming {
+                Some(Ok(Message::Close(_))) | None | Some(Err(_)) => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
+
+async fn send_snapshot(
+    socket: &mut WebSocket,
+    state: &AppState,
+    user_id: Uuid,
+) -> Result<(), axum::Error> {
+    let listeners = snapshot(state)
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|np| np.user_id != user_id)
+        .collect();
+    let text = serde_json::to_string(&PresenceEvent::Snapshot { listeners })
+        .unwrap_or_else(|_| "{}".to_string());
+    socket.send(Message::Text(text.into())).await
+}
+
+#[derive(Deserialize)]
+pub struct PresenceSettingsPayload {
+    pub share_now_playing: bool,
+}
+
+pub async fn get_presence_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    let share: bool = sqlx::query_scalar("SELECT share_now_playing FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(&state.db)
+        .await?;
+    Ok(Json(json!({"share_now_playing": share})))
+}
+
+/// 关闭公开后立即撤下当前状态并通知其他客户端
+pub async fn update_presence_settings(
+    State(state): State<Arc<AppState>>,
+    headers: HeaderMap,
+    Json(payload): Json<PresenceSettingsPayload>,
+) -> Result<impl IntoResponse, ApiError> {
+    let user_id = crate::require_user_id(&headers, &state).await?;
+    sqlx::query("UPDATE users SET share_now_playing = $1, updated_at = NOW() WHERE id = $2")
+        .bind(payload.share_now_playing)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+
+    if !payload.share_now_playing {
+        clear_now_playing(&state, user_id).await;
+    }
+    Ok(Json(json!({"share_now_playing": payload.share_now_playing})))
+}
@@ -0,0 +1,81 @@
+//! 图片等静态资源的缓存头：根据 `Last-Modified` + `Content-Length` 生成 ETag，
+//! 命中 `If-None-Match` 时直接返回 304，浏览器无需重新下载。
+
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use std::hash::{Hash, Hasher};
+use std::time::SystemTime;
+
+/// 封面 / 头像按 id 存放，URL 不随内容变化，管理员替换后必须立即生效：
+/// 允许缓存但每次使用前凭 ETag 重新验证，未变化时只返回 304
+pub const IMAGE_CACHE_CONTROL: &str = "public, no-cache";
+
+/// `Last-Modified` 使用的 HTTP 日期格式 (RFC 7231 IMF-fixdate)
+pub fn http_date(time: SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time)
+        .format("%a, %d %b %Y %H:%M:%S GMT")
+        .to_string()
+}
+
+/// 强 ETag：文件大小 + 修改时间，任一变化即失效
+pub fn etag_for(content_length: &str, last_modified: &str) -> String {
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    last_modified.hash(&mut hasher);
+    let len: u64 = content_length.parse().unwrap_or_default();
+    format!("\"{:x}-{:x}\"", len, hasher.finish())
+}
+
+/// `If-None-Match` 可能是逗号分隔的列表或 `*`，弱校验时忽略 `W/` 前缀
+pub fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(value) = headers.get(header::IF_NONE_MATCH).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    value
+        .split(',')
+        .map(|t| t.trim())
+        .any(|t| t == "*" || t.trim_start_matches("W/") == etag)
+}
+
+/// 中间件：给 200 的文件响应补上 ETag 与 Cache-Control，条件请求命中时返回 304
+pub async fn conditional_get(req: Request, next: Next) -> Response {
+    let request_headers = req.headers().clone();
+    let mut response = next.run(req).await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+
+    let headers = response.headers();
+    let (Some(length), Some(modified)) = (
+        headers.get(header::CONTENT_LENGTH).and_then(|v| v.to_str().ok()),
+        headers.get(header::LAST_MODIFIED).and_then(|v| v.to_str().ok()),
+    ) else {
+        return response;
+    };
+    let etag = etag_for(length, modified);
+    let Ok(etag_value) = HeaderValue::from_str(&etag) else {
+        return response;
+    };
+
+    let headers = response.headers_mut();
+    headers.insert(header::ETAG, etag_value.clone());
+    headers
+        .entry(header::CACHE_CONTROL)
+        .or_insert(HeaderValue::from_static(IMAGE_CACHE_CONTROL));
+
+    if !etag_matches(&request_headers, &etag) {
+        return response;
+    }
+
+    let mut not_modified = (StatusCode::NOT_MODIFIED, Body::empty()).into_response();
+    for name in [header::ETAG, header::CACHE_CONTROL, header::LAST_MODIFIED] {
+        if let Some(value) = response.headers().get(&name) {
+            not_modified.headers_mut().insert(name, value.clone());
+        }
+    }
+    not_modified
+}
@@ -1,9 +1,16 @@
 pub mod handlers;
+pub mod http_cache;
+pub mod log_buffer;
 pub mod routes;
+pub mod transcode_cache;
 
 pub const SESSION_EXPIRATION: u64 = 7 * 24 * 60 * 60; // 7 days
 pub const SESSION_PREFIX: &str = "session:";
 pub const USER_SESSIONS_PREFIX: &str = "user_sessions:";
+/// 会话附加信息 (Hash)：user_agent / ip / created_at，与 `session:` 同时过期
+pub const SESSION_META_PREFIX: &str = "session_meta:";
+/// 无 Bearer token 时用于认证的请求头
+pub const API_KEY_HEADER: &str = "X-API-Key";
 
 use axum::{
     http::{HeaderMap, StatusCode},
@@ -16,6 +23,7 @@ use sqlx::PgPool;
 use uuid::Uuid;
 
 use papilio_core::metadata::MetadataService;
+use papilio_core::scanner::ScanProgressSender;
 use redis::aio::ConnectionManager;
 use std::sync::Arc;
 
@@ -24,6 +32,12 @@ pub struct AppState {
     pub redis: ConnectionManager,
     pub jwt_secret: String,
     pub metadata_service: Arc<MetadataService>,
+    /// 扫描/整理进度广播，供 SSE 订阅
+    pub scan_progress: ScanProgressSender,
+    /// "正在收听"事件广播，供 WebSocket 订阅
+    pub presence: tokio::sync::broadcast::Sender<handlers::presence::PresenceEvent>,
+    /// 启动时探测到 ffmpeg，为 false 时仅能直接播放原始文件
+    pub ffmpeg_available: bool,
 }
 
 // 定义 Server 本地的错误包装器
@@ -50,12 +64,13 @@ impl From<std::io::Error> for ApiError {
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        let code = self.0.code();
         let (status, message) = match self.0 {
             AppError::Database(ref e) => {
                 tracing::error!("Database error: {:?}", e);
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
-            AppError::Auth(m) => (StatusCode::UNAUTHORIZED, m),
+            AppError::Auth(m) | AppError::AuthExpired(m) => (StatusCode::UNAUTHORIZED, m),
             AppError::NotFound(m) => (StatusCode::NOT_FOUND, m),
             AppError::BadRequest(m) => (StatusCode::BAD_REQUEST, m),
             AppError::Io(ref e) => {
@@ -63,22 +78,61 @@ impl IntoResponse for ApiError {
                 (StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
             }
             AppError::Metadata(m) => (StatusCode::UNPROCESSABLE_ENTITY, m),
+            AppError::TooManyRequests(m) => (StatusCode::TOO_MANY_REQUESTS, m),
+            AppError::NotImplemented(m) => (StatusCode::NOT_IMPLEMENTED, m),
             AppError::Internal(m) => {
                 tracing::error!("Internal error: {}", m);
                 (StatusCode::INTERNAL_SERVER_ERROR, m)
             }
         };
 
-        (status, Json(ErrorResponse { error: message })).into_response()
+        (status, Json(ErrorResponse { code, error: message })).into_response()
     }
 }
 
-pub async fn get_user_id(headers: &HeaderMap, state: &AppState) -> Option<Uuid> {
+/// 请求未能识别出用户的原因。`Expired` / `Revoked` 说明客户端持有的是曾经有效的 token，
+/// 以 `AUTH_EXPIRED` 错误码返回，前端据此清除本地登录状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthFailure {
+    /// 未携带任何凭据
+    Missing,
+    /// 凭据格式错误、签名无效或 API Key 不存在
+    Invalid,
+    /// JWT 已过期
+    Expired,
+    /// 会话已注销、被踢下线或在 Valkey 中过期
+    Revoked,
+    /// Valkey (外部 token 时为数据库) 查询失败，无法确认会话状态。不能当作注销处理，否则一次短暂故障会让所有用户退出登录
+    Unavailable,
+}
+
+impl From<AuthFailure> for ApiError {
+    fn from(failure: AuthFailure) -> Self {
+        match failure {
+            AuthFailure::Expired => ApiError(AppError::AuthExpired("Token expired".to_string())),
+            AuthFailure::Revoked => ApiError(AppError::AuthExpired("Session revoked".to_string())),
+            AuthFailure::Unavailable => {
+                ApiError(AppError::Internal("Session store unavailable".to_string()))
+            }
+            AuthFailure::Missing | AuthFailure::Invalid => {
+                ApiError(AppError::Auth("Unauthorized".to_string()))
+            }
+        }
+    }
+}
+
+pub async fn get_user_id(headers: &HeaderMap, state: &AppState) -> Result<Uuid, AuthFailure> {
     let auth_header = match headers.get("Authorization") {
         Some(h) => h,
         None => {
+            if let Some(key) = headers.get(API_KEY_HEADER).and_then(|h| h.to_str().ok()) {
+                return api_key_user(state, key)
+                    .await
+                    .map(|(user_id, _)| user_id)
+                    .ok_or(AuthFailure::Invalid);
+            }
             tracing::debug!("Auth: Missing Authorization header");
-            return None;
+            return Err(AuthFailure::Missing);
         }
     };
 
@@ -86,7 +140,7 @@ pub async fn get_user_id(headers: &HeaderMap, state: &AppState) -> Option<Uuid>
         Ok(s) => s,
         Err(_) => {
             tracing::warn!("Auth: Authorization header is not a valid string");
-            return None;
+            return Err(AuthFailure::Invalid);
         }
     };
 
@@ -94,31 +148,54 @@ pub async fn get_user_id(headers: &HeaderMap, state: &AppState) -> Option<Uuid>
         t
     } else {
         tracing::warn!("Auth: Missing Bearer prefix");
-        return None;
+        return Err(AuthFailure::Invalid);
     };
 
     // 1. 验证 JWT 基础有效性
     let claims = match verify_token(token, &state.jwt_secret) {
         Ok(c) => c,
+        Err(e) if papilio_core::auth::is_expired(&e) => {
+            tracing::debug!("Auth: JWT expired");
+            return Err(AuthFailure::Expired);
+        }
         Err(e) => {
-            tracing::warn!("Auth: JWT verification failed for token {}: {:?}", token, e);
-            return None;
+            tracing::warn!("Auth: JWT verification failed: {:?}", e);
+            return Err(AuthFailure::Invalid);
         }
     };
 
+    // 外部身份提供方签发的 token 没有本地会话，签名有效即可；只确认对应用户存在
+    if claims.is_external() {
+        let known: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE id = $1)")
+            .bind(claims.sub)
+            .fetch_one(&state.db)
+            .await
+            .map_err(|e| {
+                tracing::error!("Auth: User lookup failed: {}", e);
+                AuthFailure::Unavailable
+            })?;
+        if !known {
+            tracing::warn!("Auth: External token for unknown user {}", claims.sub);
+            return Err(AuthFailure::Invalid);
+        }
+        return Ok(claims.sub);
+    }
+
     // 2. 检查 Valkey 中是否存在该 Session
     let mut redis = state.redis.clone();
     use redis::AsyncCommands;
     let session_key = format!("{}{}", SESSION_PREFIX, token);
-    let exists: bool = redis.exists(&session_key).await.unwrap_or(false);
+    let exists: bool = match redis.exists(&session_key).await {
+        Ok(exists) => exists,
+        Err(e) => {
+            tracing::error!("Auth: Session lookup failed: {}", e);
+            return Err(AuthFailure::Unavailable);
+        }
+    };
 
     if !exists {
-        tracing::warn!(
-            "Auth: Session key NOT FOUND in Redis: {}. User: {}",
-            session_key,
-            claims.sub
-        );
-        return None;
+        tracing::warn!("Auth: Session not found in Redis for user {}", claims.sub);
+        return Err(AuthFailure::Revoked);
     }
 
     // 3. 续签
@@ -127,5 +204,345 @@ pub async fn get_user_id(headers: &HeaderMap, state: &AppState) -> Option<Uuid>
         .await
         .unwrap_or(());
 
-    Some(claims.sub)
+    Ok(claims.sub)
+}
+
+/// 校验 API Key 并刷新 last_used_at，返回所属用户与 scopes
+async fn api_key_user(state: &AppState, key: &str) -> Option<(Uuid, Vec<String>)> {
+    let row = sqlx::query(
+        "UPDATE api_keys SET last_used_at = NOW() WHERE key_hash = $1 RETURNING user_id, scopes",
+    )
+    .bind(papilio_core::auth::hash_api_key(key))
+    .fetch_optional(&state.db)
+    .await
+    .map_err(|e| tracing::error!("Auth: API key lookup failed: {}", e))
+    .ok()??;
+
+    use sqlx::Row;
+    Some((row.get("user_id"), row.get("scopes")))
+}
+
+/// 只读查询 API Key 的 scopes，不刷新 last_used_at：
+/// 中间件只做 scope 检查，使用时间由随后处理请求的 `get_user_id` 刷新，每个请求只写一次
+async fn api_key_scopes(state: &AppState, key: &str) -> Option<Vec<String>> {
+    sqlx::query_scalar("SELECT scopes FROM api_keys WHERE key_hash = $1")
+        .bind(papilio_core::auth::hash_api_key(key))
+        .fetch_optional(&state.db)
+        .await
+        .map_err(|e| tracing::error!("Auth: API key lookup failed: {}", e))
+        .ok()?
+}
+
+/// API Key scope 检查中间件：只读 Key 只能发起 GET/HEAD 请求
+pub async fn api_key_scope_guard(
+    axum::extract::State(state): axum::extract::State<Arc<AppState>>,
+    req: axum::extract::Request,
+    next: axum::middleware::Next,
+) -> Response {
+    let read_only_method = matches!(*req.method(), axum::http::Method::GET | axum::http::Method::HEAD);
+    // 带 Bearer token 时 get_user_id 不会使用 API Key，无需检查
+    let api_key = req
+        .headers()
+        .get(API_KEY_HEADER)
+        .filter(|_| !read_only_method && !req.headers().contains_key("Authorization"))
+        .and_then(|h| h.to_str().ok())
+        .map(|k| k.to_string());
+
+    if let Some(key) = api_key {
+        if let Some(scopes) = api_key_scopes(&state, &key).await {
+            if !scopes.iter().any(|s| s == papilio_core::auth::API_KEY_SCOPE_FULL) {
+                return ApiError(AppError::Auth(
+                    "Forbidden: API key is read-only".to_string(),
+                ))
+                .into_response();
+            }
+        }
+    }
+
+    next.run(req).await
+}
+
+/// 写日志用的 URI：`token` 查询参数 (WebSocket 无法携带 Authorization 头时以此传 JWT) 的值替换为 `***`，
+/// 避免凭据经请求日志出现在 `/admin/logs` 中
+pub fn redact_uri(uri: &axum::http::Uri) -> String {
+    let Some(query) = uri.query() else {
+        return uri.to_string();
+    };
+    let redacted = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some(("token", _)) => "token=***",
+            _ => pair,
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{}?{}", uri.path(), redacted)
+}
+
+/// 会话是否仍然存在。只有 Valkey 明确返回不存在时才为 `Ok(false)`，连接错误原样返回
+pub async fn session_exists(state: &AppState, token: &str) -> redis::RedisResult<bool> {
+    use redis::AsyncCommands;
+    let mut redis = state.redis.clone();
+    redis.exists(format!("{}{}", SESSION_PREFIX, token)).await
+}
+
+/// TRUSTED_PROXIES 中配置的反向代理地址
+pub fn trusted_proxies() -> Vec<std::net::IpAddr> {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter_map(|ip| ip.trim().parse().ok())
+        .collect()
+}
+
+/// 对端是否为受信反向代理，只有此时才采信 X-Forwarded-* 头
+pub fn is_trusted_proxy(peer: std::net::IpAddr) -> bool {
+    trusted_proxies().contains(&peer)
+}
+
+/// 客户端 IP：默认取 TCP 连接的对端地址。只有对端在 TRUSTED_PROXIES (逗号分隔的 IP) 中时
+/// 才采信 X-Forwarded-For —— 从右往左取第一个不属于受信代理的地址，没有该头时退而取 X-Real-IP；
+/// 否则这些头可由客户端任意伪造
+pub fn client_ip(headers: &HeaderMap, peer: std::net::IpAddr) -> String {
+    let trusted = trusted_proxies();
+    if !trusted.contains(&peer) {
+        return peer.to_string();
+    }
+
+    let forwarded = headers
+        .get("x-forwarded-for")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| {
+            v.rsplit(',')
+                .map(|ip| ip.trim())
+                .filter_map(|ip| ip.parse::<std::net::IpAddr>().ok())
+                .find(|ip| !trusted.contains(ip))
+        });
+    let real_ip = || {
+        headers
+            .get("x-real-ip")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.trim().parse::<std::net::IpAddr>().ok())
+    };
+    forwarded.or_else(real_ip).unwrap_or(peer).to_string()
+}
+
+/// 是否允许匿名浏览曲库 (PUBLIC_BROWSE，默认开启以兼容既有部署)
+pub fn public_browse_enabled() -> bool {
+    std::env::var("PUBLIC_BROWSE")
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "1" | "true" | "yes" | "on"))
+        .unwrap_or(true)
+}
+
+/// 访问策略统一入口 —— 浏览类接口 (曲库列表、搜索、详情、歌词、公开歌单)。
+/// 开启 PUBLIC_BROWSE 时允许匿名访问，返回 `None`；关闭时与个人数据接口一样要求登录。
+///
+/// 注意：`/stream/{id}` 与 `/covers/{album_id}` 由 `<audio>`/`<img>` 直接加载，
+/// 浏览器无法附带 Authorization 头，因此不受该策略约束。
+/// 携带的 token 已过期或被注销时即使允许匿名访问也返回 `AUTH_EXPIRED`，让客户端及时退出登录。
+pub async fn browse_user_id(headers: &HeaderMap, state: &AppState) -> Result<Option<Uuid>, ApiError> {
+    match get_user_id(headers, state).await {
+        Ok(id) => Ok(Some(id)),
+        Err(AuthFailure::Missing | AuthFailure::Invalid) if public_browse_enabled() => Ok(None),
+        Err(failure) => Err(failure.into()),
+    }
+}
+
+/// 访问策略统一入口 —— 个人数据接口 (收藏、歌单、播放历史、播放进度)，始终要求登录。
+pub async fn require_user_id(headers: &HeaderMap, state: &AppState) -> Result<Uuid, ApiError> {
+    Ok(get_user_id(headers, state).await?)
+}
+
+/// 新用户的默认存储配额 (DEFAULT_STORAGE_QUOTA_BYTES)，未配置时不限制
+pub fn default_storage_quota() -> Option<i64> {
+    std::env::var("DEFAULT_STORAGE_QUOTA_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse::<i64>().ok())
+        .filter(|v| *v >= 0)
+}
+
+/// 在写盘之前按字节数占用配额；`delta` 为负时释放。
+/// 检查与累加在同一条 UPDATE 中完成，并发上传不会越过配额。
+/// 释放后出现负数说明记账有误，不做截断而是记录错误并返回 500。
+pub async fn reserve_storage(db: &PgPool, user_id: Uuid, delta: i64) -> Result<(), ApiError> {
+    let updated = sqlx::query(
+        r#"
+        UPDATE users
+        SET storage_used_bytes = storage_used_bytes + $2
+        WHERE id = $1
+          AND storage_used_bytes + $2 >= 0
+          AND ($2 <= 0 OR storage_quota_bytes IS NULL OR storage_used_bytes + $2 <= storage_quota_bytes)
+        "#,
+    )
+    .bind(user_id)
+    .bind(delta)
+    .execute(db)
+    .await?;
+
+    if updated.rows_affected() > 0 {
+        return Ok(());
+    }
+    if delta <= 0 {
+        tracing::error!(user_id = %user_id, delta, "Storage accounting underflow");
+        return Err(ApiError(AppError::Internal(
+            "Storage accounting underflow".to_string(),
+        )));
+    }
+    Err(ApiError(AppError::BadRequest(
+        "Storage quota exceeded".to_string(),
+    )))
+}
+
+/// 配额上线前已存在的头像没有计入 storage_used_bytes：首次启动时按磁盘上的实际大小重算一次，
+/// 完成后在 `system_config.storage_usage_backfilled` 留下标记。
+/// 此前只有用户头像参与计费，历史歌手图片没有上传者记录，不追溯。
+pub async fn backfill_storage_usage(db: &PgPool) -> Result<usize, AppError> {
+    let done: Option<i32> =
+        sqlx::query_scalar("SELECT 1 FROM system_config WHERE key = 'storage_usage_backfilled'")
+            .fetch_optional(db)
+            .await?;
+    if done.is_some() {
+        return Ok(0);
+    }
+
+    let rows: Vec<(Uuid, String)> =
+        sqlx::query_as("SELECT id, avatar FROM users WHERE avatar IS NOT NULL AND avatar <> ''")
+            .fetch_all(db)
+            .await?;
+    let avatars_dir = papilio_core::storage::avatar_dir();
+
+    let mut tx = db.begin().await?;
+    sqlx::query("UPDATE users SET storage_used_bytes = 0")
+        .execute(&mut *tx)
+        .await?;
+    let mut charged = 0;
+    for (user_id, avatar) in rows {
+        let size = match papilio_core::storage::resolve_within(&avatars_dir, &avatar) {
+            Ok(path) => tokio::fs::metadata(path).await.map(|m| m.len() as i64).unwrap_or(0),
+            Err(_) => 0,
+        };
+        if size == 0 {
+            continue;
+        }
+        sqlx::query("UPDATE users SET storage_used_bytes = $2 WHERE id = $1")
+            .bind(user_id)
+            .bind(size)
+            .execute(&mut *tx)
+            .await?;
+        charged += 1;
+    }
+    sqlx::query(
+        "INSERT INTO system_config (key, value, updated_at) VALUES ('storage_usage_backfilled', 'true', NOW()) ON CONFLICT (key) DO NOTHING",
+    )
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
+    Ok(charged)
+}
+
+/// 跨域配置 (CORS_ORIGINS)：逗号分隔的来源列表，如 `https://music.example.com`；
+/// `*` 表示接受任意来源。未设置时只放行 localhost / 127.0.0.1 的任意端口，便于本地开发。
+/// 浏览器需携带凭据，因此不使用通配响应头，而是回显命中的 Origin。
+pub fn cors_layer() -> tower_http::cors::CorsLayer {
+    use axum::http::{header, HeaderName, HeaderValue, Method};
+    use tower_http::cors::{AllowOrigin, CorsLayer};
+
+    let raw = std::env::var("CORS_ORIGINS").unwrap_or_default();
+    let origins: Vec<String> = raw
+        .split(',')
+        .map(|o| o.trim().trim_end_matches('/').to_string())
+        .filter(|o| !o.is_empty())
+        .collect();
+
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        tracing::warn!("CORS_ORIGINS=*: accepting credentialed requests from any origin");
+        AllowOrigin::mirror_request()
+    } else if origins.is_empty() {
+        AllowOrigin::predicate(|origin: &HeaderValue, _| {
+            origin.to_str().map(is_localhost_origin).unwrap_or(false)
+        })
+    } else {
+        tracing::info!("CORS allowed origins: {}", origins.join(", "));
+        AllowOrigin::list(origins.iter().filter_map(|o| match HeaderValue::from_str(o) {
+            Ok(v) => Some(v),
+            Err(_) => {
+                tracing::warn!("Ignoring invalid CORS origin: {}", o);
+                None
+            }
+        }))
+    };
+
+    CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::RANGE,
+            HeaderName::from_static("x-api-key"),
+        ])
+        .expose_headers([
+            header::CONTENT_RANGE,
+            header::ACCEPT_RANGES,
+            header::CONTENT_LENGTH,
+            HeaderName::from_static(handlers::music::TRANSCODE_DECISION_HEADER),
+            HeaderName::from_static(handlers::music::CONTENT_DURATION_HEADER),
+        ])
+        .allow_credentials(true)
+}
+
+/// `http(s)://localhost[:port]`、`127.0.0.1`、`[::1]`
+pub fn is_localhost_origin(origin: &str) -> bool {
+    let Some(rest) = origin
+        .strip_prefix("http://")
+        .or_else(|| origin.strip_prefix("https://"))
+    else {
+        return false;
+    };
+    let host = if rest.starts_with("[::1]") {
+        "[::1]"
+    } else {
+        rest.split(':').next().unwrap_or_default()
+    };
+    matches!(host, "localhost" | "127.0.0.1" | "[::1]")
+}
+
+/// 探测 PATH 中是否有可执行的 ffmpeg (`ffmpeg -version` 正常退出)
+pub async fn probe_ffmpeg() -> bool {
+    tokio::process::Command::new("ffmpeg")
+        .arg("-version")
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .status()
+        .await
+        .is_ok_and(|status| status.success())
+}
+
+/// 未安装 ffmpeg 时转码类请求统一返回 501
+pub fn transcoding_unavailable() -> ApiError {
+    ApiError(AppError::NotImplemented(
+        "Transcoding unavailable: ffmpeg is not installed on the server".to_string(),
+    ))
+}
+
+/// 响应压缩 (gzip / brotli，按 Accept-Encoding 协商)。音视频流已是压缩格式且依赖 Range，
+/// 206 / WebSocket 握手等非 200 响应也保持原样
+pub fn compression_layer() -> tower_http::compression::CompressionLayer<
+    impl tower_http::compression::Predicate,
+> {
+    use axum::http::{Extensions, HeaderMap, StatusCode, Version};
+    use tower_http::compression::{
+        predicate::{DefaultPredicate, NotForContentType},
+        CompressionLayer, Predicate,
+    };
+
+    let predicate = DefaultPredicate::new()
+        .and(NotForContentType::const_new("audio/"))
+        .and(NotForContentType::const_new("video/"))
+        .and(NotForContentType::const_new("application/octet-stream"))
+        .and(|status: StatusCode, _: Version, _: &HeaderMap, _: &Extensions| {
+            status == StatusCode::OK
+        });
+
+    CompressionLayer::new().compress_when(predicate)
 }
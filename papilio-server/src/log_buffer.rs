@@ -0,0 +1,114 @@
+//! 内存中的最近日志环形缓冲，供管理后台 `/api/admin/logs` 查看，免去登录服务器翻日志
+
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::{Context, Layer};
+
+const DEFAULT_CAPACITY: usize = 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LogEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub level: String,
+    pub target: String,
+    pub message: String,
+}
+
+struct RingBuffer {
+    capacity: usize,
+    entries: VecDeque<(Level, LogEntry)>,
+}
+
+static BUFFER: Lazy<Mutex<RingBuffer>> = Lazy::new(|| {
+    let capacity = std::env::var("LOG_BUFFER_LINES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|v| *v > 0)
+        .unwrap_or(DEFAULT_CAPACITY);
+    Mutex::new(RingBuffer {
+        capacity,
+        entries: VecDeque::with_capacity(capacity),
+    })
+});
+
+/// 与 fmt layer 并列挂到 registry 上，共用同一个 EnvFilter
+pub struct LogBufferLayer;
+
+impl<S: Subscriber> Layer<S> for LogBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+
+        let metadata = event.metadata();
+        let entry = LogEntry {
+            timestamp: chrono::Utc::now(),
+            level: metadata.level().to_string(),
+            target: metadata.target().to_string(),
+            message: visitor.finish(),
+        };
+
+        if let Ok(mut buffer) = BUFFER.lock() {
+            if buffer.entries.len() >= buffer.capacity {
+                buffer.entries.pop_front();
+            }
+            buffer.entries.push_back((*metadata.level(), entry));
+        }
+    }
+}
+
+/// 最近 `lines` 条日志 (按时间正序)，`min_level` 为最低级别，如 WARN 时只返回 WARN 与 ERROR
+pub fn recent(lines: usize, min_level: Option<Level>) -> Vec<LogEntry> {
+    let Ok(buffer) = BUFFER.lock() else {
+        return Vec::new();
+    };
+    let mut entries: Vec<LogEntry> = buffer
+        .entries
+        .iter()
+        .rev()
+        // tracing 中越详细的级别越 "大"：ERROR < WARN < INFO < DEBUG < TRACE
+        .filter(|(level, _)| min_level.map_or(true, |min| *level <= min))
+        .take(lines)
+        .map(|(_, entry)| entry.clone())
+        .collect();
+    entries.reverse();
+    entries
+}
+
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+    fields: String,
+}
+
+impl MessageVisitor {
+    fn finish(self) -> String {
+        if self.fields.is_empty() {
+            self.message
+        } else {
+            format!("{}{}", self.message, self.fields)
+        }
+    }
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.message, "{:?}", value);
+        } else {
+            let _ = write!(self.fields, " {}={:?}", field.name(), value);
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            let _ = write!(self.fields, " {}={}", field.name(), value);
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use axum::{routing::get, Router};
+use axum::Router;
+use papilio_core::scanner::ScanProgress;
 use papilio_server::AppState;
+use std::future::IntoFuture;
 use std::net::SocketAddr;
 use std::sync::Arc;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -14,12 +16,14 @@ async fn main() -> anyhow::Result<()> {
                 .unwrap_or_else(|_| "papilio_server=info,papilio_core=info,tower_http=info".into()),
         )
         .with(tracing_subscriber::fmt::layer())
+        .with(papilio_server::log_buffer::LogBufferLayer)
         .init();
 
     let database_url = std::env::var("DATABASE_URL")?;
     let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".to_string());
     let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "default_secret".to_string());
-    let cover_dir = std::env::var("COVER_DIR").unwrap_or_else(|_| "../data/covers".to_string());
+    let jwt_alg = papilio_core::auth::check_jwt_config(&jwt_secret)?;
+    tracing::info!("JWT signing algorithm: {:?}", jwt_alg);
 
     let pool = sqlx::postgres::PgPoolOptions::new()
         .max_connections(20)
@@ -37,8 +41,19 @@ async fn main() -> anyhow::Result<()> {
 
     // 启动时清理状态标志，防止因服务异常宕机导致的扫描状态挂起
     tracing::info!("Cleaning up stale scan/sync flags...");
-    let _ = sqlx::query("UPDATE scan_status SET is_scanning = FALSE WHERE id = 1").execute(&pool).await;
-    let _ = sqlx::query("UPDATE artist_sync_status SET is_syncing = FALSE WHERE id = 1").execute(&pool).await;
+    let _ = sqlx::query("UPDATE scan_status SET is_scanning = FALSE, operation = 'idle' WHERE id = 1").execute(&pool).await;
+    let _ = sqlx::query("UPDATE artist_sync_status SET is_syncing = FALSE, cancel_requested = FALSE WHERE id = 1").execute(&pool).await;
+    // 上次整理中途退出时，已移动的文件补写数据库路径
+    match papilio_core::scanner::organizer::reconcile_pending_moves(&pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::warn!("Repaired {} track paths left by an interrupted reorganization", n),
+        Err(e) => tracing::error!("Failed to reconcile pending organize moves: {:?}", e),
+    }
+    match papilio_server::backfill_storage_usage(&pool).await {
+        Ok(0) => {}
+        Ok(n) => tracing::info!("Backfilled storage usage for {} users with existing avatars", n),
+        Err(e) => tracing::error!("Failed to backfill storage usage: {:?}", e),
+    }
 
     // 如果库中不存在管理员，初始化默认账号
     let admin_count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users WHERE is_admin = TRUE")
@@ -59,38 +74,66 @@ async fn main() -> anyhow::Result<()> {
         tracing::info!("Seed admin user 'chi' created successfully.");
     }
 
+    let ffmpeg_available = papilio_server::probe_ffmpeg().await;
+    if !ffmpeg_available {
+        tracing::warn!("ffmpeg not found in PATH: transcoding and HLS are disabled, only direct playback of original files is available");
+    }
+
     let state = Arc::new(AppState {
         db: pool,
         redis: redis_manager,
         jwt_secret,
         metadata_service,
+        scan_progress: Arc::new(tokio::sync::watch::channel(ScanProgress::default()).0),
+        presence: papilio_server::handlers::presence::presence_channel(),
+        ffmpeg_available,
     });
 
     let music_root = std::env::var("MUSIC_DIR").unwrap_or_else(|_| "data/music".to_string());
 
+    // 可选的曲库目录监听，新文件落盘后自动入库
+    let watch_library = std::env::var("WATCH_LIBRARY")
+        .map(|v| v == "true" || v == "1")
+        .unwrap_or(false);
+    if watch_library {
+        let db = state.db.clone();
+        let root = std::path::PathBuf::from(&music_root);
+        tokio::spawn(async move {
+            if let Err(e) = papilio_core::scanner::watcher::watch_library(db, root).await {
+                tracing::error!("Library watcher stopped: {}", e);
+            }
+        });
+    }
+
+    // 定时扫描：SCAN_SCHEDULE 或后台 scan_schedule 配置，未配置时调度器空转
+    {
+        let db = state.db.clone();
+        let root = music_root.clone();
+        let progress = state.scan_progress.clone();
+        let metadata = state.metadata_service.clone();
+        tokio::spawn(papilio_core::scanner::schedule::run_scheduled_scans(db, root, progress, metadata));
+    }
+
+    let db = state.db.clone();
+
     // 定义 API 路由树
     let app = Router::new()
-        .route("/api/health", get(|| async { "OK" }))
-        .nest("/api/music", papilio_server::routes::music_routes())
-        .nest("/api/playlists", papilio_server::routes::playlist_routes())
-        .nest("/api/auth", papilio_server::routes::auth_routes())
-        .nest("/api/admin", papilio_server::routes::admin_routes())
-        .nest_service(
-            "/data/covers",
-            tower_http::services::ServeDir::new(cover_dir),
-        )
-        .nest_service(
-            "/data/avatars",
-            tower_http::services::ServeDir::new("data/avatars"),
-        )
+        .nest(papilio_server::routes::API_PREFIX, papilio_server::routes::api_routes())
+        .nest(papilio_server::routes::LEGACY_API_PREFIX, papilio_server::routes::api_routes())
+        .nest_service("/data/covers", static_images(papilio_core::storage::cover_dir()))
+        .nest_service("/data/avatars", static_images(papilio_core::storage::avatar_dir()))
         .nest_service(
             "/data/music",
             tower_http::services::ServeDir::new(music_root),
         )
+        .layer(axum::middleware::from_fn_with_state(
+            state.clone(),
+            papilio_server::api_key_scope_guard,
+        ))
         .layer(axum::middleware::from_fn(
             |req: axum::extract::Request, next: axum::middleware::Next| async move {
                 let method = req.method().clone();
-                let uri = req.uri().clone();
+                let uri = papilio_server::redact_uri(req.uri());
                 // 记录请求审计日志
                 tracing::info!("REQ: {} {}", method, uri);
                 let response = next.run(req).await;
@@ -98,12 +141,121 @@ async fn main() -> anyhow::Result<()> {
                 response
             },
         ))
+        .layer(papilio_server::compression_layer())
+        // 放在最外层，预检请求不经过鉴权
+        .layer(papilio_server::cors_layer())
         .with_state(state);
 
     let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
     tracing::info!("listening on {}", addr);
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
 
+    // 收到信号后停止接受新连接；SSE 等长连接不会自行结束，超过宽限期即不再等待
+    let (shutdown_tx, mut shutdown_rx) = tokio::sync::watch::channel(false);
+    let mut server = tokio::spawn(
+        // 登录限流等需要 TCP 对端地址，见 `client_ip`
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move {
+                let _ = shutdown_rx.changed().await;
+            })
+            .into_future(),
+    );
+
+    tokio::select! {
+        result = &mut server => return Ok(result??),
+        _ = shutdown_signal() => {}
+    }
+
+    let grace = shutdown_grace();
+    tracing::info!("Shutdown signal received, draining for up to {:?}...", grace);
+    let _ = shutdown_tx.send(true);
+    if tokio::time::timeout(grace, &mut server).await.is_err() {
+        tracing::warn!("Open connections did not close within {:?}", grace);
+    }
+
+    finish_background_work(&db, grace).await;
+    tracing::info!("Shutdown complete");
     Ok(())
 }
+
+/// 封面 / 头像目录：在 ServeDir 的 Last-Modified 基础上补充 ETag 与 Cache-Control
+fn static_images(dir: std::path::PathBuf) -> Router {
+    Router::new()
+        .fallback_service(tower_http::services::ServeDir::new(dir))
+        .layer(axum::middleware::from_fn(
+            papilio_server::http_cache::conditional_get,
+        ))
+}
+
+/// 等待 Ctrl-C 或 SIGTERM (容器停止时发送)
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            tracing::error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sig) => {
+                sig.recv().await;
+            }
+            Err(e) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// 关闭时的宽限期 (SHUTDOWN_GRACE_SECS，默认 10 秒)
+fn shutdown_grace() -> std::time::Duration {
+    let secs = std::env::var("SHUTDOWN_GRACE_SECS")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(10);
+    std::time::Duration::from_secs(secs)
+}
+
+/// 给正在进行的扫描 / 歌手同步一点时间收尾，到期后仍未结束则直接复位状态标志，
+/// 避免下次启动前管理后台一直显示"扫描中"
+async fn finish_background_work(db: &sqlx::PgPool, grace: std::time::Duration) {
+    // 批量歌手同步会在处理完当前歌手后停下并保留断点
+    let _ = sqlx::query("UPDATE artist_sync_status SET cancel_requested = TRUE WHERE id = 1 AND is_syncing")
+        .execute(db)
+        .await;
+
+    let deadline = tokio::time::Instant::now() + grace;
+    loop {
+        let busy: bool = sqlx::query_scalar(
+            r#"
+            SELECT COALESCE((SELECT is_scanning FROM scan_status WHERE id = 1), FALSE)
+                OR COALESCE((SELECT is_syncing FROM artist_sync_status WHERE id = 1), FALSE)
+            "#,
+        )
+        .fetch_one(db)
+        .await
+        .unwrap_or(false);
+
+        if !busy {
+            return;
+        }
+        if tokio::time::Instant::now() >= deadline {
+            tracing::warn!("Background scan/sync still running at shutdown, resetting flags");
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+    }
+
+    let _ = sqlx::query("UPDATE scan_status SET is_scanning = FALSE, operation = 'idle' WHERE id = 1").execute(db).await;
+    let _ = sqlx::query("UPDATE artist_sync_status SET is_syncing = FALSE, cancel_requested = FALSE WHERE id = 1").execute(db).await;
+}
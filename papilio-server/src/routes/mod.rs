@@ -1,11 +1,25 @@
-use crate::handlers::{admin, auth, music, playlist};
-use crate::AppState;
+use crate::handlers::{admin, auth, hls, music, playlist, presence};
+use crate::{http_cache, AppState};
 use axum::{
-    routing::{get, post},
+    routing::{delete, get, post},
     Router,
 };
 use std::sync::Arc;
 
+/// 版本化的 API 前缀；无版本的 `/api` 在过渡期内作为别名保留
+pub const API_PREFIX: &str = "/api/v1";
+pub const LEGACY_API_PREFIX: &str = "/api";
+
+/// 完整的 API 路由树，由 `main` 同时挂载到 `API_PREFIX` 与 `LEGACY_API_PREFIX` 下
+pub fn api_routes() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/health", get(|| async { "OK" }))
+        .nest("/music", music_routes())
+        .nest("/playlists", playlist_routes())
+        .nest("/auth", auth_routes())
+        .nest("/admin", admin_routes())
+}
+
 pub fn auth_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/register", post(auth::register))
@@ -14,26 +28,59 @@ pub fn auth_routes() -> Router<Arc<AppState>> {
         .route("/kick/{user_id}", post(auth::kick_user))
         .route("/me", get(auth::get_me).patch(auth::update_profile))
         .route("/avatar", post(auth::upload_avatar))
+        .route("/export", get(auth::export_user_data))
+        .route("/sessions", get(auth::list_sessions))
+        .route("/sessions/{token_id}", delete(auth::revoke_session))
+        .route(
+            "/api-keys",
+            get(auth::list_api_keys).post(auth::create_api_key),
+        )
+        .route("/api-keys/{id}", delete(auth::delete_api_key))
 }
 
 pub fn music_routes() -> Router<Arc<AppState>> {
     Router::new()
         .route("/scan", post(music::trigger_scan))
         .route("/scan/status", get(music::get_scan_status))
+        .route("/scan/events", get(music::scan_events))
         .route(
             "/playback",
             get(music::get_playback_state).post(music::update_playback_state),
         )
-        .route("/stream/{id}", get(music::stream_track))
-        .route("/covers/{album_id}", get(music::get_cover))
+        .route("/now-playing", get(presence::now_playing_socket))
+        .route("/now-playing/snapshot", get(presence::get_now_playing))
+        .route(
+            "/now-playing/settings",
+            get(presence::get_presence_settings).post(presence::update_presence_settings),
+        )
+        .route(
+            "/stream/{id}",
+            get(music::stream_track).head(music::stream_track_head),
+        )
+        .route("/hls/{id}/playlist.m3u8", get(hls::master_playlist))
+        .route("/hls/{id}/{bitrate}/index.m3u8", get(hls::variant_playlist))
+        .route("/hls/{id}/{bitrate}/{segment}", get(hls::segment))
+        .route(
+            "/covers/{album_id}",
+            get(music::get_cover).layer(axum::middleware::from_fn(http_cache::conditional_get)),
+        )
         .route("/lyrics/{id}", get(music::get_lyrics))
         .route("/artists", get(music::list_artists))
         .route("/albums", get(music::list_albums))
+        .route("/albums/top", get(music::list_top_albums))
         .route("/tracks", get(music::list_tracks))
-        .route("/tracks/{id}", get(music::get_track))
+        .route("/tracks/top", get(music::list_top_tracks))
+        .route("/tracks/recent", get(music::list_recent_tracks))
+        .route("/tracks/random", get(music::list_random_tracks))
+        .route("/tracks/{id}", get(music::get_track).patch(music::update_track))
         .route("/search", get(music::global_search))
         .route("/favorites", get(music::list_favorites))
+        .route("/favorites/shuffle", get(music::shuffle_favorites))
         .route("/favorites/{track_id}", post(music::toggle_favorite))
+        .route("/favorites/albums", get(music::list_favorite_albums))
+        .route("/favorites/albums/{id}", post(music::toggle_favorite_album))
+        .route("/favorites/artists", get(music::list_favorite_artists))
+        .route("/favorites/artists/{id}", post(music::toggle_favorite_artist))
         .route("/history", get(music::list_history))
         .route("/play/{id}", post(music::record_play))
         .route(
@@ -44,6 +91,7 @@ pub fn music_routes() -> Router<Arc<AppState>> {
             "/tracks/{track_id}/rescan",
             post(music::rescan_track_metadata),
         )
+        .route("/tracks/{id}/sync", post(music::sync_track_metadata))
         .route("/artists/{id}/sync", post(music::sync_artist_metadata))
 }
 
@@ -59,7 +107,21 @@ pub fn playlist_routes() -> Router<Arc<AppState>> {
                 .delete(playlist::delete_playlist)
                 .patch(playlist::update_playlist),
         )
+        .route(
+            "/import",
+            post(playlist::import_playlist).layer(playlist_import_limit()),
+        )
+        .route("/{id}/export", get(playlist::export_playlist))
         .route("/{id}/reorder", post(playlist::reorder_tracks))
+        .route("/{id}/tracks", post(playlist::add_tracks))
+        .route(
+            "/{id}/collaborators",
+            get(playlist::list_collaborators).post(playlist::invite_collaborator),
+        )
+        .route(
+            "/{id}/collaborators/{user_id}",
+            delete(playlist::remove_collaborator),
+        )
         .route(
             "/{id}/tracks/{track_id}",
             post(playlist::add_track).delete(playlist::remove_track),
@@ -73,6 +135,8 @@ pub fn admin_routes() -> Router<Arc<AppState>> {
             get(admin::get_admin_config).post(admin::update_admin_config),
         )
         .route("/status", get(admin::get_system_status))
+        .route("/stats", get(admin::get_library_stats))
+        .route("/logs", get(admin::get_recent_logs))
         .route("/sync-artists", post(admin::trigger_artist_sync))
         .route(
             "/sync-artists/missing",
@@ -83,9 +147,39 @@ pub fn admin_routes() -> Router<Arc<AppState>> {
             post(admin::trigger_artist_sync_single),
         )
         .route("/sync-artists/status", get(admin::get_artist_sync_status))
-        .route("/artists/{id}/avatar", post(admin::upload_artist_avatar))
+        .route("/sync-artists/cancel", post(admin::cancel_artist_sync))
+        .route("/sync-artists/results", get(admin::list_artist_sync_results))
+        .route(
+            "/artists/{id}/avatar",
+            post(admin::upload_artist_avatar).layer(image_upload_limit()),
+        )
+        .route(
+            "/albums/{id}/cover",
+            post(admin::upload_album_cover).layer(image_upload_limit()),
+        )
         .route("/users", get(admin::list_users))
         .route("/users/{id}/role", post(admin::update_user_role))
+        .route(
+            "/users/{id}/quota",
+            get(admin::get_user_quota).post(admin::update_user_quota),
+        )
         .route("/users/{id}", axum::routing::delete(admin::delete_user))
         .route("/library/organize", post(admin::trigger_library_organize))
+        .route("/library/organize/report", get(admin::get_organize_report))
+        .route("/library/orphans", get(admin::list_orphan_tracks))
+        .route("/tracks/deleted", get(admin::list_deleted_tracks))
+        .route("/tracks/restore", post(admin::restore_deleted_tracks))
+        .route("/tracks/purge", post(admin::purge_deleted_tracks))
+        .route("/duplicates", get(admin::list_duplicate_tracks))
+        .route("/library/vacuum", post(admin::vacuum_library))
+}
+
+/// axum 默认只接受 2MB 请求体，图片上传放宽到上限加上 multipart 头部的余量
+fn image_upload_limit() -> axum::extract::DefaultBodyLimit {
+    axum::extract::DefaultBodyLimit::max(admin::MAX_IMAGE_UPLOAD_BYTES + 64 * 1024)
+}
+
+/// 歌单导入同样放宽到导入上限，否则 5MB 的限制会先被默认的 2MB 挡住
+fn playlist_import_limit() -> axum::extract::DefaultBodyLimit {
+    axum::extract::DefaultBodyLimit::max(playlist::IMPORT_MAX_BYTES + 64 * 1024)
 }
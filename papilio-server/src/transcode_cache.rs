@@ -0,0 +1,199 @@
+//! 转码结果的磁盘缓存：从头播放时把 FFmpeg 输出同时写入缓存，
+//! 之后同一曲目 + 码率 + 格式的请求直接读文件 (支持 Range)，不再重复编码。
+//! 源文件变化后键随之改变，不会继续返回旧内容。
+//! 按文件修改时间做 LRU，总大小超过上限时淘汰最久未用的条目。
+
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::process::{Child, ChildStdout};
+use uuid::Uuid;
+
+/// 未配置 TRANSCODE_CACHE_MAX_BYTES 时的缓存上限 (2 GiB)
+const DEFAULT_MAX_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+/// 未完成的写入以此后缀存放，完成后改名，读取方不会看到半成品
+const PARTIAL_SUFFIX: &str = ".part";
+
+fn cache_root() -> PathBuf {
+    PathBuf::from(
+        std::env::var("TRANSCODE_CACHE_DIR").unwrap_or_else(|_| "data/transcode".to_string()),
+    )
+}
+
+/// 缓存总大小上限，设为 0 关闭缓存
+fn max_bytes() -> u64 {
+    std::env::var("TRANSCODE_CACHE_MAX_BYTES")
+        .ok()
+        .and_then(|v| v.trim().parse().ok())
+        .unwrap_or(DEFAULT_MAX_BYTES)
+}
+
+pub fn enabled() -> bool {
+    max_bytes() > 0
+}
+
+/// `{TRANSCODE_CACHE_DIR}/{track_id}_{size}_{mtime}_{bitrate}_{container}.{ext}`。
+/// 键中带源文件的大小与修改时间，替换或重新打标签后旧缓存不再命中，由 LRU 自然淘汰；
+/// 源文件不可读时返回 None，不使用缓存
+pub async fn cache_path(
+    track_id: Uuid,
+    source: &Path,
+    bitrate: &str,
+    container: &str,
+) -> Option<PathBuf> {
+    let meta = tokio::fs::metadata(source).await.ok()?;
+    let mtime = meta
+        .modified()
+        .ok()?
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .ok()?
+        .as_millis();
+    let ext = match container {
+        "adts" => "aac",
+        other => other,
+    };
+    Some(cache_root().join(format!(
+        "{}_{}_{}_{}_{}.{}",
+        track_id,
+        meta.len(),
+        mtime,
+        bitrate,
+        container,
+        ext
+    )))
+}
+
+/// 命中时刷新修改时间作为 LRU 的访问记录
+pub async fn lookup(path: &Path) -> Option<PathBuf> {
+    let path = path.to_path_buf();
+    tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::options().write(true).open(&path).ok()?;
+        if let Err(e) = file.set_modified(SystemTime::now()) {
+            tracing::debug!("Failed to touch transcode cache entry {:?}: {}", path, e);
+        }
+        Some(path)
+    })
+    .await
+    .ok()
+    .flatten()
+}
+
+/// 把 FFmpeg 的输出一分为二：返回的读端交给客户端，同时完整写入缓存。
+/// 客户端中途断开时继续编码到结束，下次播放即可命中缓存。
+pub fn tee(mut child: Child, mut stdout: ChildStdout, target: PathBuf) -> impl AsyncRead {
+    let (mut client, reader) = tokio::io::duplex(64 * 1024);
+
+    tokio::spawn(async move {
+        let partial = target.with_extension(format!("{}{}", Uuid::new_v4(), PARTIAL_SUFFIX));
+        let mut file = match prepare_partial(&partial).await {
+            Ok(file) => Some(file),
+            Err(e) => {
+                tracing::warn!("Transcode cache disabled for {:?}: {}", target, e);
+                None
+            }
+        };
+
+        let mut client_open = true;
+        let mut buf = vec![0u8; 64 * 1024];
+        let mut complete = false;
+        loop {
+            let n = match stdout.read(&mut buf).await {
+                Ok(0) => {
+                    complete = true;
+                    break;
+                }
+                Ok(n) => n,
+                Err(e) => {
+                    tracing::warn!("Reading FFmpeg output failed: {}", e);
+                    break;
+                }
+            };
+            if let Some(f) = file.as_mut() {
+                if let Err(e) = f.write_all(&buf[..n]).await {
+                    tracing::warn!("Writing transcode cache {:?} failed: {}", partial, e);
+                    file = None;
+                    let _ = tokio::fs::remove_file(&partial).await;
+                }
+            }
+            if client_open && client.write_all(&buf[..n]).await.is_err() {
+                client_open = false;
+            }
+            if !client_open && file.is_none() {
+                break;
+            }
+        }
+        drop(client);
+        drop(stdout);
+
+        // 写缓存失败或输出不完整：停掉仍在运行的 FFmpeg，丢弃半成品
+        let Some(mut file) = file.filter(|_| complete) else {
+            let _ = child.kill().await;
+            let _ = tokio::fs::remove_file(&partial).await;
+            return;
+        };
+        let success = match child.wait().await {
+            Ok(status) => status.success(),
+            Err(_) => false,
+        };
+        if !success || file.flush().await.is_err() {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return;
+        }
+        drop(file);
+        if let Err(e) = tokio::fs::rename(&partial, &target).await {
+            tracing::warn!("Failed to publish transcode cache {:?}: {}", target, e);
+            let _ = tokio::fs::remove_file(&partial).await;
+            return;
+        }
+        tracing::debug!("Cached transcode output {:?}", target);
+
+        let limit = max_bytes();
+        if let Err(e) = tokio::task::spawn_blocking(move || evict(&cache_root(), limit)).await {
+            tracing::warn!("Transcode cache eviction failed: {}", e);
+        }
+    });
+
+    reader
+}
+
+async fn prepare_partial(partial: &Path) -> std::io::Result<tokio::fs::File> {
+    if let Some(dir) = partial.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    tokio::fs::File::create(partial).await
+}
+
+/// 按修改时间从旧到新删除，直到总大小不超过上限。未完成的写入不参与统计
+pub fn evict(root: &Path, limit: u64) {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return;
+    };
+    let mut files: Vec<(PathBuf, u64, SystemTime)> = entries
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_name().to_string_lossy().ends_with(PARTIAL_SUFFIX))
+        .filter_map(|e| {
+            let meta = e.metadata().ok()?;
+            meta.is_file()
+                .then(|| (e.path(), meta.len(), meta.modified().unwrap_or(SystemTime::UNIX_EPOCH)))
+        })
+        .collect();
+
+    let mut total: u64 = files.iter().map(|(_, size, _)| size).sum();
+    if total <= limit {
+        return;
+    }
+    files.sort_by_key(|(_, _, modified)| *modified);
+    for (path, size, _) in files {
+        if total <= limit {
+            break;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                total -= size;
+                tracing::debug!("Evicted transcode cache entry {:?}", path);
+            }
+            Err(e) => tracing::warn!("Failed to evict {:?}: {}", path, e),
+        }
+    }
+}
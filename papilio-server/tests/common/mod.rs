@@ -0,0 +1,69 @@
+//! 数据库测试的最小化造数：只填必填列，其余保持默认值
+#![allow(dead_code)]
+
+use sqlx::PgPool;
+use uuid::Uuid;
+
+pub async fn user(db: &PgPool, username: &str) -> Uuid {
+    sqlx::query_scalar("INSERT INTO users (username, password_hash) VALUES ($1, 'x') RETURNING id")
+        .bind(username)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+pub async fn artist(db: &PgPool, name: &str) -> Uuid {
+    sqlx::query_scalar("INSERT INTO artists (name) VALUES ($1) RETURNING id")
+        .bind(name)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+pub async fn album(db: &PgPool, title: &str, artist_id: Uuid) -> Uuid {
+    sqlx::query_scalar("INSERT INTO albums (title, artist_id) VALUES ($1, $2) RETURNING id")
+        .bind(title)
+        .bind(artist_id)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+pub async fn track(db: &PgPool, title: &str, album_id: Option<Uuid>, artist_id: Option<Uuid>) -> Uuid {
+    sqlx::query_scalar(
+        "INSERT INTO tracks (title, album_id, artist_id, duration, path) VALUES ($1, $2, $3, 200, $4) RETURNING id",
+    )
+    .bind(title)
+    .bind(album_id)
+    .bind(artist_id)
+    .bind(format!("/music/{}/{}.flac", Uuid::new_v4(), title))
+    .fetch_one(db)
+    .await
+    .unwrap()
+}
+
+pub async fn favorite(db: &PgPool, user_id: Uuid, track_id: Uuid) {
+    sqlx::query("INSERT INTO user_favorites (user_id, track_id) VALUES ($1, $2)")
+        .bind(user_id)
+        .bind(track_id)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+/// 一条播放记录，`days_ago` 天前播放
+pub async fn play(db: &PgPool, user_id: Uuid, track_id: Uuid, counted: bool, days_ago: i32) {
+    sqlx::query(
+        r#"
+        INSERT INTO play_history (user_id, track_id, played_ms, completed, counted, played_at)
+        VALUES ($1, $2, 180000, $3, $3, NOW() - make_interval(days => $4))
+        "#,
+    )
+    .bind(user_id)
+    .bind(track_id)
+    .bind(counted)
+    .bind(days_ago)
+    .execute(db)
+    .await
+    .unwrap();
+}
@@ -0,0 +1,17 @@
+use papilio_server::is_localhost_origin;
+
+#[test]
+fn test_localhost_origins_are_allowed_by_default() {
+    assert!(is_localhost_origin("http://localhost:8080"));
+    assert!(is_localhost_origin("http://127.0.0.1:3000"));
+    assert!(is_localhost_origin("https://localhost"));
+    assert!(is_localhost_origin("http://[::1]:8081"));
+}
+
+#[test]
+fn test_lookalike_origins_are_rejected() {
+    assert!(!is_localhost_origin("http://localhost.evil.com"));
+    assert!(!is_localhost_origin("https://music.example.com"));
+    assert!(!is_localhost_origin("localhost:8080"));
+    assert!(!is_localhost_origin("null"));
+}
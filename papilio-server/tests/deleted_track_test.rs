@@ -0,0 +1,25 @@
+mod common;
+
+use axum::response::IntoResponse;
+use papilio_server::handlers::music::fetch_track;
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_soft_deleted_track_returns_404(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let kept = common::track(&db, "Kept", None, None).await;
+    let gone = common::track(&db, "Gone", None, None).await;
+    sqlx::query("UPDATE tracks SET deleted_at = NOW() WHERE id = $1")
+        .bind(gone)
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let track = fetch_track(&db, Some(alice), kept).await.unwrap();
+    assert_eq!(track.track.id, kept);
+
+    let err = fetch_track(&db, Some(alice), gone).await.err().unwrap();
+    assert_eq!(err.into_response().status().as_u16(), 404);
+    let err = fetch_track(&db, None, gone).await.err().unwrap();
+    assert_eq!(err.into_response().status().as_u16(), 404);
+}
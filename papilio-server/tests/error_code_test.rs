@@ -0,0 +1,63 @@
+use axum::response::IntoResponse;
+use papilio_core::error::AppError;
+use papilio_server::ApiError;
+use serde_json::Value;
+
+async fn error_body(err: AppError) -> (u16, Value) {
+    let response = ApiError(err).into_response();
+    let status = response.status().as_u16();
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    (status, serde_json::from_slice(&bytes).unwrap())
+}
+
+#[tokio::test]
+async fn test_error_response_carries_code_and_message() {
+    let (status, body) = error_body(AppError::NotFound("Track not found".to_string())).await;
+    assert_eq!(status, 404);
+    assert_eq!(body["code"], "NOT_FOUND");
+    assert_eq!(body["error"], "Track not found");
+
+    let (status, body) = error_body(AppError::TooManyRequests("Slow down".to_string())).await;
+    assert_eq!(status, 429);
+    assert_eq!(body["code"], "RATE_LIMITED");
+}
+
+#[tokio::test]
+async fn test_internal_failures_share_one_code() {
+    for err in [
+        AppError::Internal("boom".to_string()),
+        AppError::Io(std::io::Error::other("disk full")),
+    ] {
+        let (status, body) = error_body(err).await;
+        assert_eq!(status, 500);
+        assert_eq!(body["code"], "INTERNAL_ERROR");
+    }
+}
+
+#[tokio::test]
+async fn test_expired_and_revoked_sessions_report_auth_expired() {
+    use papilio_server::AuthFailure;
+
+    for failure in [AuthFailure::Expired, AuthFailure::Revoked] {
+        let ApiError(err) = failure.into();
+        let (status, body) = error_body(err).await;
+        assert_eq!(status, 401);
+        assert_eq!(body["code"], "AUTH_EXPIRED");
+    }
+    for failure in [AuthFailure::Missing, AuthFailure::Invalid] {
+        let ApiError(err) = failure.into();
+        let (status, body) = error_body(err).await;
+        assert_eq!(status, 401);
+        assert_eq!(body["code"], "UNAUTHORIZED");
+    }
+}
+
+#[tokio::test]
+async fn test_session_store_outage_does_not_log_users_out() {
+    let ApiError(err) = papilio_server::AuthFailure::Unavailable.into();
+    let (status, body) = error_body(err).await;
+    assert_eq!(status, 500);
+    assert_eq!(body["code"], "INTERNAL_ERROR");
+}
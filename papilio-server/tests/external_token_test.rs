@@ -0,0 +1,89 @@
+mod common;
+
+use axum::extract::State;
+use axum::http::{header, HeaderMap};
+use axum::response::IntoResponse;
+use papilio_core::auth::{create_token, sign_claims, Claims};
+use papilio_core::metadata::MetadataService;
+use papilio_core::scanner::ScanProgress;
+use papilio_server::handlers::auth::get_me;
+use papilio_server::AppState;
+use serde_json::Value;
+use sqlx::PgPool;
+use std::sync::Arc;
+use uuid::Uuid;
+
+const SECRET: &str = "external-token-test";
+
+/// 需要可连接的 Valkey (REDIS_URL，默认本机)，外部 token 本身不会读取会话
+async fn test_state(db: PgPool) -> Arc<AppState> {
+    let redis_url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1:6379".to_string());
+    let redis = redis::aio::ConnectionManager::new(redis::Client::open(redis_url).unwrap())
+        .await
+        .unwrap();
+    Arc::new(AppState {
+        metadata_service: Arc::new(MetadataService::new(db.clone())),
+        db,
+        redis,
+        jwt_secret: SECRET.to_string(),
+        scan_progress: Arc::new(tokio::sync::watch::channel(ScanProgress::default()).0),
+        presence: papilio_server::handlers::presence::presence_channel(),
+        ffmpeg_available: false,
+    })
+}
+
+fn bearer(token: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+    headers
+}
+
+fn external_token(user_id: Uuid) -> String {
+    let now = chrono::Utc::now().timestamp();
+    let claims = Claims {
+        sub: user_id,
+        username: "alice".to_string(),
+        exp: (now + 3600) as usize,
+        iat: now as usize,
+        iss: Some("https://idp.example.com".to_string()),
+    };
+    sign_claims(&claims, SECRET).unwrap()
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_external_token_reaches_protected_handler(db: PgPool) {
+    let user_id = common::user(&db, "alice").await;
+    let state = test_state(db).await;
+
+    let response = get_me(State(state), bearer(&external_token(user_id)))
+        .await
+        .into_response();
+    assert_eq!(response.status(), 200);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["username"], "alice");
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_external_token_for_unknown_user_is_rejected(db: PgPool) {
+    let state = test_state(db).await;
+
+    let response = get_me(State(state), bearer(&external_token(Uuid::new_v4())))
+        .await
+        .into_response();
+    assert_eq!(response.status(), 401);
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_local_token_still_requires_a_session(db: PgPool) {
+    let user_id = common::user(&db, "alice").await;
+    let state = test_state(db).await;
+    // 本服务签发但从未登录建立会话的 token
+    let token = create_token(user_id, "alice".to_string(), SECRET).unwrap();
+
+    let response = get_me(State(state), bearer(&token)).await.into_response();
+    assert_eq!(response.status(), 401);
+    let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+    let body: Value = serde_json::from_slice(&bytes).unwrap();
+    assert_eq!(body["code"], "AUTH_EXPIRED");
+}
@@ -0,0 +1,60 @@
+mod common;
+
+use papilio_server::handlers::music::{
+    fetch_favorite_tracks, query_tracks, SearchQuery, TrackWithFavorite,
+};
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_favorites_are_flagged_per_user(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let bob = common::user(&db, "bob").await;
+    let artist = common::artist(&db, "Guest Singer").await;
+    let album = common::album(&db, "Summer Hits", artist).await;
+    let sunny = common::track(&db, "Sunny Day", Some(album), Some(artist)).await;
+    let rainy = common::track(&db, "Rainy Day", Some(album), Some(artist)).await;
+    common::favorite(&db, alice, sunny).await;
+
+    let favorites = fetch_favorite_tracks(&db, alice).await.unwrap();
+    assert_eq!(favorites.len(), 1);
+    assert_eq!(favorites[0].track.id, sunny);
+    assert!(favorites[0].is_favorite);
+    assert_eq!(favorites[0].track.artist_name.as_deref(), Some("Guest Singer"));
+    assert!(fetch_favorite_tracks(&db, bob).await.unwrap().is_empty());
+
+    let flags = |tracks: Vec<TrackWithFavorite>| {
+        tracks
+            .into_iter()
+            .map(|t| (t.track.id, t.is_favorite))
+            .collect::<std::collections::HashMap<_, _>>()
+    };
+    let query = SearchQuery::default();
+    let for_alice = flags(query_tracks(&db, Some(alice), &query).await.unwrap());
+    assert!(for_alice[&sunny]);
+    assert!(!for_alice[&rainy]);
+
+    // 其他用户与匿名访问看不到 alice 的收藏
+    let for_bob = flags(query_tracks(&db, Some(bob), &query).await.unwrap());
+    assert!(for_bob.values().all(|favorite| !favorite));
+    let anonymous = flags(query_tracks(&db, None, &query).await.unwrap());
+    assert_eq!(anonymous.len(), 2);
+    assert!(anonymous.values().all(|favorite| !favorite));
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_soft_deleted_favorites_are_hidden(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let gone = common::track(&db, "Gone", None, None).await;
+    common::favorite(&db, alice, gone).await;
+    sqlx::query("UPDATE tracks SET deleted_at = NOW() WHERE id = $1")
+        .bind(gone)
+        .execute(&db)
+        .await
+        .unwrap();
+
+    assert!(fetch_favorite_tracks(&db, alice).await.unwrap().is_empty());
+    assert!(query_tracks(&db, Some(alice), &SearchQuery::default())
+        .await
+        .unwrap()
+        .is_empty());
+}
@@ -0,0 +1,81 @@
+use axum::{
+    http::{header, HeaderMap, StatusCode},
+    routing::get,
+    Router,
+};
+use papilio_server::http_cache::{conditional_get, etag_for, etag_matches};
+
+#[test]
+fn test_etag_changes_with_size_or_mtime() {
+    let a = etag_for("1024", "Wed, 21 Oct 2025 07:28:00 GMT");
+    assert_eq!(a, etag_for("1024", "Wed, 21 Oct 2025 07:28:00 GMT"));
+    assert_ne!(a, etag_for("2048", "Wed, 21 Oct 2025 07:28:00 GMT"));
+    assert_ne!(a, etag_for("1024", "Thu, 22 Oct 2025 07:28:00 GMT"));
+    assert!(a.starts_with('"') && a.ends_with('"'));
+}
+
+#[test]
+fn test_if_none_match_accepts_lists_weak_tags_and_wildcard() {
+    let etag = "\"400-abc\"";
+    let mut headers = HeaderMap::new();
+    assert!(!etag_matches(&headers, etag));
+
+    headers.insert(header::IF_NONE_MATCH, "\"other\", W/\"400-abc\"".parse().unwrap());
+    assert!(etag_matches(&headers, etag));
+
+    headers.insert(header::IF_NONE_MATCH, "*".parse().unwrap());
+    assert!(etag_matches(&headers, etag));
+
+    headers.insert(header::IF_NONE_MATCH, "\"400-abd\"".parse().unwrap());
+    assert!(!etag_matches(&headers, etag));
+}
+
+#[tokio::test]
+async fn test_conditional_request_returns_not_modified() -> Result<(), Box<dyn std::error::Error>> {
+    let app = Router::new()
+        .route(
+            "/image",
+            get(|| async {
+                (
+                    [
+                        (header::CONTENT_TYPE, "image/jpeg"),
+                        (header::CONTENT_LENGTH, "10"),
+                        (header::LAST_MODIFIED, "Wed, 21 Oct 2025 07:28:00 GMT"),
+                    ],
+                    "jpeg-bytes",
+                )
+            }),
+        )
+        .layer(axum::middleware::from_fn(conditional_get));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+    tokio::spawn(async move {
+        let _ = axum::serve(listener, app).await;
+    });
+
+    let client = reqwest::Client::new();
+    let url = format!("http://{}/image", addr);
+
+    let first = client.get(&url).send().await?;
+    assert_eq!(first.status(), StatusCode::OK.as_u16());
+    assert_eq!(
+        first.headers().get("cache-control").and_then(|v| v.to_str().ok()),
+        Some("public, no-cache")
+    );
+    let etag = first
+        .headers()
+        .get("etag")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string)
+        .expect("ETag header");
+
+    let second = client.get(&url).header("If-None-Match", &etag).send().await?;
+    assert_eq!(second.status(), StatusCode::NOT_MODIFIED.as_u16());
+    assert_eq!(second.headers().get("etag").and_then(|v| v.to_str().ok()), Some(etag.as_str()));
+    assert!(second.bytes().await?.is_empty());
+
+    let stale = client.get(&url).header("If-None-Match", "\"stale\"").send().await?;
+    assert_eq!(stale.status(), StatusCode::OK.as_u16());
+    Ok(())
+}
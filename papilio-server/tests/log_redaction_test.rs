@@ -0,0 +1,20 @@
+use axum::http::Uri;
+use papilio_server::redact_uri;
+
+#[test]
+fn test_token_query_parameter_is_redacted() {
+    let uri: Uri = "/api/presence/socket?token=eyJhbGciOi.secret&x=1".parse().unwrap();
+    assert_eq!(redact_uri(&uri), "/api/presence/socket?token=***&x=1");
+
+    let uri: Uri = "/api/stream/abc?bitrate=192k&token=abc".parse().unwrap();
+    assert_eq!(redact_uri(&uri), "/api/stream/abc?bitrate=192k&token=***");
+}
+
+#[test]
+fn test_uris_without_token_are_unchanged() {
+    let uri: Uri = "/api/tracks?q=token&limit=5".parse().unwrap();
+    assert_eq!(redact_uri(&uri), "/api/tracks?q=token&limit=5");
+
+    let uri: Uri = "/api/tracks".parse().unwrap();
+    assert_eq!(redact_uri(&uri), "/api/tracks");
+}
@@ -6,7 +6,7 @@ use uuid::Uuid;
 #[tokio::test]
 async fn test_lyric_offset_api_with_auth() -> Result<(), Box<dyn std::error::Error>> {
     let client = Client::new();
-    let base_url = "http://localhost:3000/api/v1";
+    let base_url = "http://localhost:3000/api/v1/music";
 
     // 1. 构造真实的测试 Token
     let secret = std::env::var("JWT_SECRET")
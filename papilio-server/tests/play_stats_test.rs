@@ -0,0 +1,73 @@
+mod common;
+
+use papilio_server::handlers::music::{
+    fetch_favorite_tracks, query_tracks, SearchQuery, TrackWithFavorite, TRACK_COLUMNS,
+};
+use sqlx::PgPool;
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_play_count_only_includes_counted_plays_of_the_viewer(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let bob = common::user(&db, "bob").await;
+    let played = common::track(&db, "Played", None, None).await;
+    let unplayed = common::track(&db, "Unplayed", None, None).await;
+    common::play(&db, alice, played, true, 3).await;
+    common::play(&db, alice, played, true, 1).await;
+    // 误触跳过的记录不计次数，但仍是最近一次播放
+    common::play(&db, alice, played, false, 0).await;
+    common::play(&db, bob, played, true, 0).await;
+
+    let tracks = query_tracks(&db, Some(alice), &SearchQuery::default()).await.unwrap();
+    let find = |id| tracks.iter().find(|t| t.track.id == id).unwrap();
+    assert_eq!(find(played).play_count, Some(2));
+    let last_played: chrono::DateTime<chrono::Utc> = sqlx::query_scalar(
+        "SELECT MAX(played_at) FROM play_history WHERE user_id = $1 AND track_id = $2",
+    )
+    .bind(alice)
+    .bind(played)
+    .fetch_one(&db)
+    .await
+    .unwrap();
+    assert_eq!(find(played).last_played_at, Some(last_played));
+    assert_eq!(find(unplayed).play_count, Some(0));
+    assert_eq!(find(unplayed).last_played_at, None);
+
+    // 匿名访问没有个人统计
+    let anonymous = query_tracks(&db, None, &SearchQuery::default()).await.unwrap();
+    assert!(anonymous.iter().all(|t| t.play_count.is_none() && t.last_played_at.is_none()));
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_queries_without_play_stats_columns_yield_none(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let track = common::track(&db, "Liked", None, None).await;
+    common::favorite(&db, alice, track).await;
+    common::play(&db, alice, track, true, 0).await;
+
+    // 收藏列表没有拼接 PLAY_STATS_COLUMNS
+    let favorites = fetch_favorite_tracks(&db, alice).await.unwrap();
+    assert_eq!(favorites.len(), 1);
+    assert_eq!(favorites[0].play_count, None);
+    assert_eq!(favorites[0].last_played_at, None);
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_play_stats_decode_errors_are_not_swallowed(db: PgPool) {
+    common::track(&db, "Broken", None, None).await;
+
+    let row = sqlx::query(&format!(
+        r#"
+        SELECT {TRACK_COLUMNS}, 'many'::text AS play_count
+        FROM tracks t
+        LEFT JOIN user_favorites f ON false
+        LEFT JOIN artists a ON t.artist_id = a.id
+        LEFT JOIN albums al ON t.album_id = al.id
+        LEFT JOIN user_track_metadata m ON false
+        "#
+    ))
+    .fetch_one(&db)
+    .await
+    .unwrap();
+
+    assert!(TrackWithFavorite::from_row(&row).is_err());
+}
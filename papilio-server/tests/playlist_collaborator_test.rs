@@ -0,0 +1,48 @@
+mod common;
+
+use papilio_server::handlers::playlist::{fetch_my_playlists, fetch_owned_playlists};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn playlist(db: &PgPool, owner: Uuid, name: &str) -> Uuid {
+    sqlx::query_scalar("INSERT INTO playlists (user_id, name) VALUES ($1, $2) RETURNING id")
+        .bind(owner)
+        .bind(name)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+async fn invite(db: &PgPool, playlist_id: Uuid, user_id: Uuid, can_edit: bool) {
+    sqlx::query("INSERT INTO playlist_collaborators (playlist_id, user_id, can_edit) VALUES ($1, $2, $3)")
+        .bind(playlist_id)
+        .bind(user_id)
+        .bind(can_edit)
+        .execute(db)
+        .await
+        .unwrap();
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_collaborator_sees_shared_playlists(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let bob = common::user(&db, "bob").await;
+    let own = playlist(&db, bob, "Bob's Mix").await;
+    let editable = playlist(&db, alice, "Road Trip").await;
+    let readonly = playlist(&db, alice, "Dinner").await;
+    playlist(&db, alice, "Private").await;
+    invite(&db, editable, bob, true).await;
+    invite(&db, readonly, bob, false).await;
+
+    let mine = fetch_my_playlists(&db, bob).await.unwrap();
+    assert_eq!(mine.len(), 3);
+    let find = |id| mine.iter().find(|p| p.playlist.id == id).unwrap();
+    assert!(!find(own).shared && find(own).can_edit);
+    assert!(find(editable).shared && find(editable).can_edit);
+    assert!(find(readonly).shared && !find(readonly).can_edit);
+
+    // 数据导出仍只包含自建歌单
+    let owned = fetch_owned_playlists(&db, bob).await.unwrap();
+    assert_eq!(owned.len(), 1);
+    assert_eq!(owned[0].id, own);
+}
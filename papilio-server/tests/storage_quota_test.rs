@@ -0,0 +1,41 @@
+mod common;
+
+use papilio_core::error::AppError;
+use papilio_server::{reserve_storage, ApiError};
+use sqlx::PgPool;
+
+async fn used_bytes(db: &PgPool, user_id: uuid::Uuid) -> i64 {
+    sqlx::query_scalar("SELECT storage_used_bytes FROM users WHERE id = $1")
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+        .unwrap()
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_reserve_storage_respects_quota(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    sqlx::query("UPDATE users SET storage_quota_bytes = 100 WHERE id = $1")
+        .bind(alice)
+        .execute(&db)
+        .await
+        .unwrap();
+
+    reserve_storage(&db, alice, 80).await.unwrap();
+    let err = reserve_storage(&db, alice, 30).await.unwrap_err();
+    assert!(matches!(err, ApiError(AppError::BadRequest(_))));
+    assert_eq!(used_bytes(&db, alice).await, 80);
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_over_release_is_reported_instead_of_clamped(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    reserve_storage(&db, alice, 50).await.unwrap();
+
+    let err = reserve_storage(&db, alice, -80).await.unwrap_err();
+    assert!(matches!(err, ApiError(AppError::Internal(_))));
+    assert_eq!(used_bytes(&db, alice).await, 50);
+
+    reserve_storage(&db, alice, -50).await.unwrap();
+    assert_eq!(used_bytes(&db, alice).await, 0);
+}
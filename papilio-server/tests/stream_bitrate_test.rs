@@ -0,0 +1,28 @@
+use papilio_server::handlers::music::{bitrate_covers_source, bitrate_only_passthrough};
+
+#[test]
+fn test_requested_bitrate_at_or_above_source_is_skipped() {
+    assert!(bitrate_covers_source("320k", Some(128)));
+    assert!(bitrate_covers_source("128k", Some(128)));
+}
+
+#[test]
+fn test_lower_bitrate_still_transcodes() {
+    assert!(!bitrate_covers_source("128k", Some(320)));
+    // 无损源码率远高于任何可选码率
+    assert!(!bitrate_covers_source("320k", Some(1411)));
+}
+
+#[test]
+fn test_unknown_source_bitrate_transcodes() {
+    assert!(!bitrate_covers_source("320k", None));
+    assert!(!bitrate_covers_source("320k", Some(0)));
+}
+
+#[test]
+fn test_bitrate_passthrough_respects_accept() {
+    assert!(bitrate_only_passthrough(None, "flac", "320k", Some(256)));
+    assert!(bitrate_only_passthrough(Some("audio/flac"), "flac", "320k", Some(256)));
+    // 客户端只接受 MP3 时，即使请求码率高于源文件也不能直接返回 FLAC
+    assert!(!bitrate_only_passthrough(Some("audio/mpeg"), "flac", "320k", Some(256)));
+}
@@ -0,0 +1,48 @@
+use papilio_server::handlers::music::{parse_byte_range, ByteRange};
+
+const SIZE: u64 = 1000;
+
+#[test]
+fn test_closed_range() {
+    assert_eq!(
+        parse_byte_range("bytes=0-99", SIZE),
+        ByteRange::Partial { start: 0, end: 99 }
+    );
+    // 结尾超出文件时截断到最后一个字节
+    assert_eq!(
+        parse_byte_range("bytes=900-5000", SIZE),
+        ByteRange::Partial { start: 900, end: 999 }
+    );
+}
+
+#[test]
+fn test_open_ended_range() {
+    assert_eq!(
+        parse_byte_range("bytes=500-", SIZE),
+        ByteRange::Partial { start: 500, end: 999 }
+    );
+    assert_eq!(parse_byte_range("bytes=1000-", SIZE), ByteRange::Unsatisfiable);
+}
+
+#[test]
+fn test_suffix_range() {
+    assert_eq!(
+        parse_byte_range("bytes=-500", SIZE),
+        ByteRange::Partial { start: 500, end: 999 }
+    );
+    // 后缀长度超过文件大小时返回整个文件
+    assert_eq!(
+        parse_byte_range("bytes=-5000", SIZE),
+        ByteRange::Partial { start: 0, end: 999 }
+    );
+    assert_eq!(parse_byte_range("bytes=-0", SIZE), ByteRange::Unsatisfiable);
+    assert_eq!(parse_byte_range("bytes=-10", 0), ByteRange::Unsatisfiable);
+}
+
+#[test]
+fn test_multi_and_invalid_ranges_fall_back_to_full() {
+    assert_eq!(parse_byte_range("bytes=0-99,200-299", SIZE), ByteRange::Full);
+    assert_eq!(parse_byte_range("bytes=abc-def", SIZE), ByteRange::Full);
+    assert_eq!(parse_byte_range("bytes=200-100", SIZE), ByteRange::Full);
+    assert_eq!(parse_byte_range("items=0-10", SIZE), ByteRange::Full);
+}
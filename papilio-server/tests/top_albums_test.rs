@@ -0,0 +1,106 @@
+mod common;
+
+use papilio_server::handlers::music::query_top_albums;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+struct Library {
+    alice: Uuid,
+    bob: Uuid,
+    summer: Uuid,
+    winter: Uuid,
+    quiet: Uuid,
+}
+
+/// summer：alice 近期 2 次；winter：alice 40 天前 1 次、bob 近期 3 次；quiet 没有播放但被收藏两次
+async fn seed(db: &PgPool) -> Library {
+    let alice = common::user(db, "alice").await;
+    let bob = common::user(db, "bob").await;
+    let artist = common::artist(db, "Guest Singer").await;
+    let summer = common::album(db, "Summer Hits", artist).await;
+    let winter = common::album(db, "Winter Songs", artist).await;
+    let quiet = common::album(db, "Quiet Hours", artist).await;
+
+    let sunny = common::track(db, "Sunny Day", Some(summer), Some(artist)).await;
+    let snow = common::track(db, "Snow", Some(winter), Some(artist)).await;
+    common::track(db, "Hush", Some(quiet), Some(artist)).await;
+
+    common::play(db, alice, sunny, true, 1).await;
+    common::play(db, alice, sunny, true, 2).await;
+    // 未达到阈值的播放不计入
+    common::play(db, alice, sunny, false, 1).await;
+    common::play(db, alice, snow, true, 40).await;
+    for _ in 0..3 {
+        common::play(db, bob, snow, true, 1).await;
+    }
+
+    for user in [alice, bob] {
+        sqlx::query("INSERT INTO user_favorite_albums (user_id, album_id) VALUES ($1, $2)")
+            .bind(user)
+            .bind(quiet)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+    sqlx::query("INSERT INTO user_favorite_albums (user_id, album_id) VALUES ($1, $2)")
+        .bind(alice)
+        .bind(summer)
+        .execute(db)
+        .await
+        .unwrap();
+
+    Library {
+        alice,
+        bob,
+        summer,
+        winter,
+        quiet,
+    }
+}
+
+fn ranking(albums: &[papilio_server::handlers::music::TopAlbum]) -> Vec<(Uuid, i64)> {
+    albums.iter().map(|a| (a.album.album.id, a.play_count)).collect()
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_global_ranking_counts_all_users(db: PgPool) {
+    let lib = seed(&db).await;
+
+    let albums = query_top_albums(&db, None, None, None, 20).await.unwrap();
+    assert_eq!(ranking(&albums), vec![(lib.winter, 4), (lib.summer, 2)]);
+    assert_eq!(albums[0].album.track_count, 1);
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_user_scope_and_days_window(db: PgPool) {
+    let lib = seed(&db).await;
+
+    let alice = query_top_albums(&db, Some(lib.alice), None, None, 20).await.unwrap();
+    assert_eq!(ranking(&alice), vec![(lib.summer, 2), (lib.winter, 1)]);
+
+    // 40 天前的播放落在 30 天窗口之外
+    let recent = query_top_albums(&db, Some(lib.alice), Some(30), None, 20).await.unwrap();
+    assert_eq!(ranking(&recent), vec![(lib.summer, 2)]);
+
+    let bob = query_top_albums(&db, Some(lib.bob), Some(30), None, 20).await.unwrap();
+    assert_eq!(ranking(&bob), vec![(lib.winter, 3)]);
+
+    let limited = query_top_albums(&db, None, None, None, 1).await.unwrap();
+    assert_eq!(ranking(&limited), vec![(lib.winter, 4)]);
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_sort_by_favorites(db: PgPool) {
+    let lib = seed(&db).await;
+
+    let albums = query_top_albums(&db, None, None, Some("favorites"), 20).await.unwrap();
+    let order: Vec<(Uuid, i64)> = albums
+        .iter()
+        .map(|a| (a.album.album.id, a.favorite_count))
+        .collect();
+    // 没有收藏的 winter 不出现；quiet 没有播放也按收藏数排在前面
+    assert_eq!(order, vec![(lib.quiet, 2), (lib.summer, 1)]);
+    assert_eq!(albums[0].play_count, 0);
+
+    assert!(query_top_albums(&db, None, None, Some("likes"), 20).await.is_err());
+}
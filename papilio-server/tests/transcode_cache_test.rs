@@ -0,0 +1,57 @@
+use papilio_server::transcode_cache::{cache_path, evict};
+use std::time::{Duration, SystemTime};
+
+#[tokio::test]
+async fn test_cache_path_is_keyed_by_track_source_bitrate_and_format() {
+    let id = uuid::Uuid::new_v4();
+    let source = std::env::temp_dir().join(format!("papilio-tsource-{}.flac", id));
+    std::fs::write(&source, vec![0u8; 100]).unwrap();
+    let file = std::fs::File::options().write(true).open(&source).unwrap();
+    file.set_modified(SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000))
+        .unwrap();
+
+    let mp3 = cache_path(id, &source, "192k", "mp3").await.unwrap();
+    let aac = cache_path(id, &source, "192k", "adts").await.unwrap();
+    assert_eq!(
+        mp3.file_name().unwrap().to_string_lossy(),
+        format!("{}_100_1700000000000_192k_mp3.mp3", id)
+    );
+    assert_eq!(
+        aac.file_name().unwrap().to_string_lossy(),
+        format!("{}_100_1700000000000_192k_adts.aac", id)
+    );
+    assert_ne!(mp3, cache_path(id, &source, "320k", "mp3").await.unwrap());
+
+    // 源文件被替换后不再命中旧缓存
+    std::fs::write(&source, vec![0u8; 200]).unwrap();
+    assert_ne!(mp3, cache_path(id, &source, "192k", "mp3").await.unwrap());
+
+    std::fs::remove_file(&source).unwrap();
+    assert!(cache_path(id, &source, "192k", "mp3").await.is_none());
+}
+
+#[test]
+fn test_evict_removes_least_recently_used_first() {
+    let dir = std::env::temp_dir().join(format!("papilio-tcache-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+
+    let now = SystemTime::now();
+    for (i, name) in ["old.mp3", "mid.mp3", "new.mp3"].iter().enumerate() {
+        let path = dir.join(name);
+        std::fs::write(&path, vec![0u8; 100]).unwrap();
+        let file = std::fs::File::options().write(true).open(&path).unwrap();
+        file.set_modified(now - Duration::from_secs(300 - i as u64 * 100))
+            .unwrap();
+    }
+    // 未完成的写入不计入总量，也不会被删除
+    std::fs::write(dir.join("pending.part"), vec![0u8; 1000]).unwrap();
+
+    evict(&dir, 200);
+
+    assert!(!dir.join("old.mp3").exists());
+    assert!(dir.join("mid.mp3").exists());
+    assert!(dir.join("new.mp3").exists());
+    assert!(dir.join("pending.part").exists());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
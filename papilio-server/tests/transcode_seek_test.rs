@@ -0,0 +1,73 @@
+use papilio_server::handlers::music::transcode_seek_args;
+use std::process::Command;
+
+fn ffmpeg_available() -> bool {
+    Command::new("ffmpeg").arg("-version").output().is_ok()
+        && Command::new("ffprobe").arg("-version").output().is_ok()
+}
+
+fn probe_duration(path: &std::path::Path) -> f64 {
+    let output = Command::new("ffprobe")
+        .args(["-v", "error", "-show_entries", "format=duration", "-of", "csv=p=0"])
+        .arg(path)
+        .output()
+        .expect("ffprobe should run");
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .expect("ffprobe should print a duration")
+}
+
+#[test]
+fn test_seek_args_split_coarse_and_fine() {
+    let (input, output) = transcode_seek_args(0.0);
+    assert!(input.is_empty() && output.is_empty());
+
+    // 目标在预留区间内：只需输入后的精确 seek
+    let (input, output) = transcode_seek_args(3.0);
+    assert!(input.is_empty());
+    assert_eq!(output, vec!["-ss", "3.000"]);
+
+    let (input, output) = transcode_seek_args(65.5);
+    assert_eq!(input, vec!["-ss", "60.500"]);
+    assert_eq!(output, vec!["-ss", "5.000"]);
+}
+
+#[test]
+fn test_transcode_from_start_time_keeps_remaining_duration() {
+    // 没有 ffmpeg / ffprobe 的环境无法生成素材，直接跳过
+    if !ffmpeg_available() {
+        return;
+    }
+
+    let dir = std::env::temp_dir().join(format!("papilio-seek-{}", uuid::Uuid::new_v4()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let fixture = dir.join("fixture.flac");
+    let output = dir.join("out.mp3");
+
+    // 10 秒正弦波作为测试素材
+    let status = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y", "-f", "lavfi", "-i", "sine=frequency=440:duration=10"])
+        .arg(&fixture)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let (input_seek, output_seek) = transcode_seek_args(5.0);
+    let status = Command::new("ffmpeg")
+        .args(["-hide_banner", "-loglevel", "error", "-y"])
+        .args(&input_seek)
+        .arg("-i")
+        .arg(&fixture)
+        .args(&output_seek)
+        .args(["-map", "0:a:0", "-c:a", "libmp3lame", "-b:a", "128k", "-f", "mp3"])
+        .arg(&output)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let duration = probe_duration(&output);
+    assert!((duration - 5.0).abs() < 0.2, "expected ~5s of audio, got {}", duration);
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,88 @@
+mod common;
+
+use papilio_core::models::user::User;
+use papilio_server::handlers::auth::{collect_user_export, USER_EXPORT_VERSION};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+async fn playlist(db: &PgPool, owner: Uuid, name: &str, tracks: &[Uuid]) -> Uuid {
+    let id: Uuid = sqlx::query_scalar("INSERT INTO playlists (user_id, name) VALUES ($1, $2) RETURNING id")
+        .bind(owner)
+        .bind(name)
+        .fetch_one(db)
+        .await
+        .unwrap();
+    for (position, track_id) in tracks.iter().enumerate() {
+        sqlx::query("INSERT INTO playlist_tracks (playlist_id, track_id, position) VALUES ($1, $2, $3)")
+            .bind(id)
+            .bind(track_id)
+            .bind(position as i32 + 1)
+            .execute(db)
+            .await
+            .unwrap();
+    }
+    id
+}
+
+#[sqlx::test(migrations = "../papilio-core/migrations")]
+async fn test_export_only_contains_the_requesters_data(db: PgPool) {
+    let alice = common::user(&db, "alice").await;
+    let bob = common::user(&db, "bob").await;
+    let artist = common::artist(&db, "Guest Singer").await;
+    let album = common::album(&db, "Summer Hits", artist).await;
+    let sunny = common::track(&db, "Sunny Day", Some(album), Some(artist)).await;
+    let rainy = common::track(&db, "Rainy Day", Some(album), Some(artist)).await;
+
+    common::favorite(&db, alice, sunny).await;
+    common::favorite(&db, bob, rainy).await;
+    sqlx::query("INSERT INTO user_favorite_albums (user_id, album_id) VALUES ($1, $2)")
+        .bind(bob)
+        .bind(album)
+        .execute(&db)
+        .await
+        .unwrap();
+    sqlx::query("INSERT INTO user_favorite_artists (user_id, artist_id) VALUES ($1, $2)")
+        .bind(alice)
+        .bind(artist)
+        .execute(&db)
+        .await
+        .unwrap();
+
+    let road_trip = playlist(&db, alice, "Road Trip", &[rainy, sunny]).await;
+    playlist(&db, bob, "Bob's Mix", &[sunny]).await;
+
+    // 超过历史接口默认的 50 条，导出不应截断
+    for day in 0..60 {
+        common::play(&db, alice, sunny, day % 2 == 0, day).await;
+    }
+    common::play(&db, bob, rainy, true, 0).await;
+
+    let user = User::find_by_id(&db, alice).await.unwrap().unwrap();
+    let export = collect_user_export(&db, user).await.unwrap();
+
+    assert_eq!(export.version, USER_EXPORT_VERSION);
+    assert_eq!(export.profile.id, alice);
+
+    let favorite_tracks: Vec<Uuid> = export.favorites.tracks.iter().map(|t| t.track.id).collect();
+    assert_eq!(favorite_tracks, vec![sunny]);
+    assert!(export.favorites.albums.is_empty());
+    assert_eq!(export.favorites.artists.len(), 1);
+    assert_eq!(export.favorites.artists[0].artist.id, artist);
+
+    assert_eq!(export.playlists.len(), 1);
+    assert_eq!(export.playlists[0].playlist.id, road_trip);
+    let playlist_tracks: Vec<(Uuid, bool)> = export.playlists[0]
+        .tracks
+        .iter()
+        .map(|t| (t.track.id, t.is_favorite))
+        .collect();
+    assert_eq!(playlist_tracks, vec![(rainy, false), (sunny, true)]);
+
+    // 完整时间线：同一曲目的每次播放都保留，按时间倒序
+    assert_eq!(export.history.len(), 60);
+    assert!(export.history.iter().all(|h| h.track.track.id == sunny));
+    assert!(export
+        .history
+        .windows(2)
+        .all(|pair| pair[0].played_at >= pair[1].played_at));
+}
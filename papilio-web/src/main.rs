@@ -24,40 +24,98 @@ pub struct Track {
     pub format: Option<String>,
     pub lyrics: Option<String>,
     pub sync_status: SyncStatus,
+    /// 服务端 `TrackWithFavorite` 展开后的收藏标记，是前端唯一的收藏状态来源
     #[serde(default)]
     pub is_favorite: bool,
+    #[serde(default)]
+    pub artist_name: Option<String>,
+    #[serde(default)]
+    pub album_title: Option<String>,
+    #[serde(default)]
+    pub artist_image_url: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Artist {
     pub id: Uuid,
     pub name: String,
+    #[serde(default)]
+    pub bio: Option<String>,
+    #[serde(default)]
+    pub genres: Vec<String>,
+    #[serde(default)]
+    pub image_url: Option<String>,
+    #[serde(default)]
+    pub album_count: i64,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct Album {
     pub id: Uuid,
     pub title: String,
+    #[serde(default)]
+    pub track_count: i64,
 }
 
+/// 搜索命中的歌手，附带其专辑与热门曲目
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct GlobalSearchResponse {
-    pub artists: Vec<Artist>,
+pub struct ArtistSearchResult {
+    #[serde(flatten)]
+    pub artist: Artist,
+    #[serde(default)]
     pub albums: Vec<Album>,
-    pub tracks: Vec<TrackWithFavorite>,
+    #[serde(default)]
+    pub top_tracks: Vec<Track>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-pub struct TrackWithFavorite {
-    #[serde(flatten)]
-    pub track: Track,
-    pub is_favorite: bool,
+pub struct GlobalSearchResponse {
+    pub artists: Vec<ArtistSearchResult>,
+    pub albums: Vec<Album>,
+    pub tracks: Vec<Track>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct LyricLine {
     pub time: f64,
     pub text: String,
+    /// 增强 LRC 的逐字时间 `<mm:ss.xx>`，为空时按整行显示
+    pub words: Vec<(f64, String)>,
+    /// 与本行时间戳相同的后续行 (罗马音、翻译等)，显示在主歌词下方
+    pub translations: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RepeatMode {
+    Off,
+    One,
+    All,
+}
+
+impl RepeatMode {
+    fn next(self) -> Self {
+        match self {
+            RepeatMode::Off => RepeatMode::All,
+            RepeatMode::All => RepeatMode::One,
+            RepeatMode::One => RepeatMode::Off,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            RepeatMode::Off => "off",
+            RepeatMode::One => "one",
+            RepeatMode::All => "all",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "one" => RepeatMode::One,
+            "all" => RepeatMode::All,
+            _ => RepeatMode::Off,
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -65,10 +123,25 @@ struct PlayerContext {
     current_track: RwSignal<Option<Track>>,
     is_playing: RwSignal<bool>,
     is_fullscreen: RwSignal<bool>,
+    is_queue_open: RwSignal<bool>,
     playlist: RwSignal<Vec<Track>>,
     progress: RwSignal<f64>,
     duration: RwSignal<f64>,
     lyrics: RwSignal<Vec<LyricLine>>,
+    /// 服务器返回的原始歌词，`None` 表示该曲目没有歌词
+    lyrics_raw: RwSignal<Option<String>>,
+    /// 歌词带 `[mm:ss]` 时间戳；为 false 且有原始歌词时按纯文本静态显示
+    lyrics_synced: RwSignal<bool>,
+    /// 是否显示双语歌词中的译文行
+    show_translation: RwSignal<bool>,
+    /// 当前曲目的歌词偏移 (毫秒)，正值表示歌词整体延后
+    lyric_offset_ms: RwSignal<i32>,
+    repeat: RwSignal<RepeatMode>,
+    shuffle: RwSignal<bool>,
+    volume: RwSignal<f64>,
+    muted: RwSignal<bool>,
+    /// 从上次会话恢复时要跳转到的位置 (秒)，此时只载入不自动播放
+    resume_at: RwSignal<Option<f64>>,
 }
 
 #[derive(Clone, Copy)]
@@ -76,50 +149,163 @@ struct AuthContext {
     token: RwSignal<Option<String>>,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Theme {
+    Dark,
+    Light,
+}
+
+impl Theme {
+    fn as_str(self) -> &'static str {
+        match self {
+            Theme::Dark => "dark",
+            Theme::Light => "light",
+        }
+    }
+
+    fn from_str(value: &str) -> Self {
+        match value {
+            "light" => Theme::Light,
+            _ => Theme::Dark,
+        }
+    }
+}
+
+/// 主题与强调色，写入 `<html>` 的 `data-theme` 属性与 CSS 变量，并持久化到 localStorage
+#[derive(Clone, Copy)]
+struct ThemeContext {
+    theme: RwSignal<Theme>,
+    /// 自定义强调色 `#rrggbb`，为 None 时使用默认的紫罗兰 / 极光青配色
+    accent: RwSignal<Option<String>>,
+}
+
+/// 强调色预设，第一个为默认的极光青
+const ACCENT_PRESETS: &[&str] = &["#06B6D4", "#8B5CF6", "#F43F5E", "#F59E0B", "#10B981"];
+
+/// `#rrggbb` 转为 Tailwind `<alpha-value>` 所需的 `r g b` 形式
+fn hex_to_rgb_channels(hex: &str) -> Option<String> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some(format!("{} {} {}", channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// `mm`、`ss`、小数部分 (两位为百分秒，三位为毫秒) 换算为秒
+fn lrc_timestamp(min: &str, sec: &str, frac: &str) -> f64 {
+    let min: f64 = min.parse().unwrap_or(0.0);
+    let sec: f64 = sec.parse().unwrap_or(0.0);
+    let mut ms: f64 = frac.parse().unwrap_or(0.0);
+    if frac.len() == 2 {
+        ms /= 100.0;
+    } else if frac.len() == 3 {
+        ms /= 1000.0;
+    }
+    min * 60.0 + sec + ms
+}
+
 fn parse_lrc(lrc: &str) -> Vec<LyricLine> {
     let mut lines = Vec::new();
     // 使用 expect 替代 unwrap，虽然 pattern 是常量，但工业级代码需要明确意图
     let time_re = regex::Regex::new(r"\[(\d+):(\d+)[.:](\d+)\]").expect("Invalid regex pattern");
+    let word_re = regex::Regex::new(r"<(\d+):(\d+)[.:](\d+)>").expect("Invalid regex pattern");
+    let line_tag_re = regex::Regex::new(r"\[[^\]]+\]").expect("Invalid regex pattern");
     let clean_re = regex::Regex::new(r"\[[^\]]+\]|<[^>]+>").expect("Invalid regex pattern");
 
     for line in lrc.lines() {
         // 1. 查找这一行中所有的标准时间戳 [mm:ss.xx]
-        let mut timestamps = Vec::new();
-        for cap in time_re.captures_iter(line) {
-            let min: f64 = cap[1].parse().unwrap_or(0.0);
-            let sec: f64 = cap[2].parse().unwrap_or(0.0);
-            let ms_str = &cap[3];
-            let mut ms: f64 = ms_str.parse().unwrap_or(0.0);
-            if ms_str.len() == 2 {
-                ms /= 100.0;
-            } else if ms_str.len() == 3 {
-                ms /= 1000.0;
-            }
-
-            timestamps.push(min * 60.0 + sec + ms);
-        }
+        let timestamps: Vec<f64> = time_re
+            .captures_iter(line)
+            .map(|cap| lrc_timestamp(&cap[1], &cap[2], &cap[3]))
+            .collect();
 
         if !timestamps.is_empty() {
-            // 2. 清理掉所有的标签（包括时间戳本身和 AI 逐字标签）
+            // 2. 清理掉所有的标签（包括时间戳本身和逐字标签）
             let text = clean_re.replace_all(line, "").trim().to_string();
 
             if !text.is_empty() {
+                let body = line_tag_re.replace_all(line, "");
+                let first = timestamps[0];
+                let words = parse_lrc_words(&body, &word_re, &clean_re, first);
                 for time in timestamps {
+                    // 同一行歌词对应多个时间戳时，逐字时间随行时间平移
+                    let shift = time - first;
                     lines.push(LyricLine {
                         time,
                         text: text.clone(),
+                        words: words.iter().map(|(t, w)| (t + shift, w.clone())).collect(),
+                        translations: Vec::new(),
                     });
                 }
             }
         }
     }
     // 修复：f64 排序必须处理 NaN 情况，严禁 unwrap
+    // 稳定排序，同一时间戳的行保持文件中的先后顺序
     lines.sort_by(|a, b| {
         a.time
             .partial_cmp(&b.time)
             .unwrap_or(std::cmp::Ordering::Equal)
     });
-    lines
+    group_bilingual_lines(lines)
+}
+
+/// 双语歌词：时间戳相同的行中第一行为原文，其余作为译文挂在原文下
+fn group_bilingual_lines(lines: Vec<LyricLine>) -> Vec<LyricLine> {
+    let mut grouped: Vec<LyricLine> = Vec::with_capacity(lines.len());
+    for line in lines {
+        match grouped.last_mut() {
+            Some(prev) if (prev.time - line.time).abs() < 0.001 => {
+                if line.text != prev.text && !prev.translations.contains(&line.text) {
+                    prev.translations.push(line.text);
+                }
+            }
+            _ => grouped.push(line),
+        }
+    }
+    grouped
+}
+
+/// 按 `<mm:ss.xx>` 把一行切成 (开始时间, 文本)；首个标签前的文字使用行时间。
+/// 没有逐字标签时返回空列表
+fn parse_lrc_words(
+    body: &str,
+    word_re: &regex::Regex,
+    clean_re: &regex::Regex,
+    line_time: f64,
+) -> Vec<(f64, String)> {
+    let mut words = Vec::new();
+    let mut cursor = (line_time, 0);
+    let mut push = |time: f64, raw: &str| {
+        let word = clean_re.replace_all(raw, "").to_string();
+        if !word.trim().is_empty() {
+            words.push((time, word));
+        }
+    };
+    for cap in word_re.captures_iter(body) {
+        let Some(tag) = cap.get(0) else { continue };
+        push(cursor.0, &body[cursor.1..tag.start()]);
+        cursor = (lrc_timestamp(&cap[1], &cap[2], &cap[3]), tag.end());
+    }
+    if cursor.1 == 0 {
+        return Vec::new();
+    }
+    push(cursor.0, &body[cursor.1..]);
+    words
+}
+
+/// 无时间戳歌词的显示文本：去掉 `[ar:...]` 之类的标签行
+fn plain_lyrics_text(raw: &str) -> String {
+    raw.lines()
+        .filter(|l| {
+            let l = l.trim();
+            !(l.starts_with('[') && l.ends_with(']') && l.contains(':'))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
@@ -147,7 +333,7 @@ impl User {
 }
 
 async fn fetch_me() -> Result<User, String> {
-    api_request("GET", "/api/auth/me", None)
+    api_request("GET", "/api/v1/auth/me", None)
         .await?
         .json()
         .await
@@ -164,7 +350,7 @@ async fn update_profile_api(
         "email": email,
         "password": password,
     });
-    api_request("PATCH", "/api/auth/me", Some(body))
+    api_request("PATCH", "/api/v1/auth/me", Some(body))
         .await?
         .json()
         .await
@@ -174,7 +360,7 @@ async fn update_profile_api(
 async fn upload_avatar_api(file: web_sys::File) -> Result<User, String> {
     let storage = window().local_storage().ok().flatten();
     let token = storage.and_then(|s| s.get_item("auth_token").ok().flatten());
-    let url = format!("{}/api/auth/avatar", get_api_base_url());
+    let url = format!("{}/api/v1/auth/avatar", get_api_base_url());
 
     let form_data = web_sys::FormData::new().map_err(|_| "Failed to create form")?;
     form_data
@@ -212,18 +398,60 @@ async fn api_request(
     if let Some(t) = token {
         req = req.header("Authorization", &format!("Bearer {}", t));
     }
-    if let Some(b) = body {
+    let resp = if let Some(b) = body {
         req.json(&b)
             .map_err(|e| e.to_string())?
             .send()
             .await
-            .map_err(|e| e.to_string())
+            .map_err(|e| e.to_string())?
     } else {
-        req.send().await.map_err(|e| e.to_string())
+        req.send().await.map_err(|e| e.to_string())?
+    };
+
+    if resp.status() != 401 {
+        return Ok(resp);
+    }
+    // 响应体只能读取一次，检查错误码后原样重建，登录失败等普通 401 仍交给调用方处理
+    let text = resp.text().await.map_err(|e| e.to_string())?;
+    let code = serde_json::from_str::<serde_json::Value>(&text)
+        .ok()
+        .and_then(|v| v["code"].as_str().map(str::to_string));
+    if code.as_deref() == Some("AUTH_EXPIRED") {
+        expire_session();
+    }
+    gloo_net::http::Response::builder()
+        .status(401)
+        .body(Some(text.as_str()))
+        .map_err(|e| e.to_string())
+}
+
+/// token 过期或会话被注销：清除本地 token 并刷新页面，`App` 读不到 token 时显示登录页
+fn expire_session() {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        let _ = storage.remove_item("auth_token");
+    }
+    let _ = window().location().reload();
+}
+
+fn load_pref(key: &str) -> Option<String> {
+    window()
+        .local_storage()
+        .ok()
+        .flatten()
+        .and_then(|s| s.get_item(key).ok().flatten())
+}
+
+fn save_pref(key: &str, value: &str) {
+    if let Some(storage) = window().local_storage().ok().flatten() {
+        let _ = storage.set_item(key, value);
     }
 }
 
 fn get_api_base_url() -> String {
+    // 前端与 API 分开部署时，构建时通过 PAPILIO_API_BASE 指定 API 地址 (服务端需配置 CORS_ORIGINS)
+    if let Some(base) = option_env!("PAPILIO_API_BASE").filter(|b| !b.is_empty()) {
+        return base.trim_end_matches('/').to_string();
+    }
     let location = window().location();
     let origin = location
         .origin()
@@ -237,17 +465,32 @@ fn get_api_base_url() -> String {
     }
 }
 
-fn get_cover_url(album_id: Option<Uuid>) -> String {
-    match album_id {
-        Some(id) => format!("{}/api/music/covers/{}", get_api_base_url(), id),
-        None => "".to_string(),
+/// `size` 为缩略图边长，None 时加载原图
+fn get_cover_url(album_id: Option<Uuid>, size: Option<u32>) -> String {
+    match (album_id, size) {
+        (Some(id), Some(size)) => format!("{}/api/v1/music/covers/{}?size={}", get_api_base_url(), id, size),
+        (Some(id), None) => format!("{}/api/v1/music/covers/{}", get_api_base_url(), id),
+        (None, _) => "".to_string(),
+    }
+}
+
+fn get_stream_url(track_id: Uuid) -> String {
+    format!("{}/api/v1/music/stream/{}", get_api_base_url(), track_id)
+}
+
+/// 歌手图片：本地上传 / 同步下来的只存文件名，外链原样使用
+fn artist_image_src(image_url: &str) -> String {
+    if image_url.starts_with("http://") || image_url.starts_with("https://") {
+        image_url.to_string()
+    } else {
+        format!("{}/data/avatars/{}", get_api_base_url(), image_url)
     }
 }
 
 async fn fetch_tracks(q: Option<String>) -> Result<Vec<Track>, String> {
     let path = match q {
-        Some(query) if !query.is_empty() => format!("/api/music/tracks?q={}", query),
-        _ => "/api/music/tracks".to_string(),
+        Some(query) if !query.is_empty() => format!("/api/v1/music/tracks?q={}", query),
+        _ => "/api/v1/music/tracks".to_string(),
     };
     api_request("GET", &path, None)
         .await?
@@ -256,6 +499,24 @@ async fn fetch_tracks(q: Option<String>) -> Result<Vec<Track>, String> {
         .map_err(|e| e.to_string())
 }
 
+/// `filter` 为 `artist_id` 或 `album_id`
+async fn fetch_tracks_by(filter: &str, id: Uuid) -> Result<Vec<Track>, String> {
+    api_request("GET", &format!("/api/v1/music/tracks?{}={}&limit=500", filter, id), None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// `kind` 为 `top` (最常播放) 或 `recent` (最近添加)
+async fn fetch_discover_tracks(kind: &str, limit: u32) -> Result<Vec<Track>, String> {
+    api_request("GET", &format!("/api/v1/music/tracks/{}?limit={}", kind, limit), None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
 async fn fetch_global_search(q: String) -> Result<GlobalSearchResponse, String> {
     if q.is_empty() {
         return Ok(GlobalSearchResponse {
@@ -264,7 +525,7 @@ async fn fetch_global_search(q: String) -> Result<GlobalSearchResponse, String>
             tracks: vec![],
         });
     }
-    api_request("GET", &format!("/api/music/search?q={}", q), None)
+    api_request("GET", &format!("/api/v1/music/search?q={}", q), None)
         .await?
         .json()
         .await
@@ -272,16 +533,47 @@ async fn fetch_global_search(q: String) -> Result<GlobalSearchResponse, String>
 }
 
 async fn fetch_favorites() -> Result<Vec<Track>, String> {
-    api_request("GET", "/api/music/favorites", None)
+    api_request("GET", "/api/v1/music/favorites", None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn fetch_favorite_albums() -> Result<Vec<Album>, String> {
+    api_request("GET", "/api/v1/music/favorites/albums", None)
         .await?
         .json()
         .await
         .map_err(|e| e.to_string())
 }
 
+async fn fetch_favorite_artists() -> Result<Vec<Artist>, String> {
+    api_request("GET", "/api/v1/music/favorites/artists", None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+/// 收藏专辑 / 关注歌手，返回切换后的状态
+async fn toggle_collection_favorite_api(kind: CollectionKind, id: Uuid) -> Result<bool, String> {
+    let segment = match kind {
+        CollectionKind::Artist => "artists",
+        CollectionKind::Album => "albums",
+    };
+    let res: serde_json::Value =
+        api_request("POST", &format!("/api/v1/music/favorites/{}/{}", segment, id), None)
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(res["is_favorite"].as_bool().unwrap_or(false))
+}
+
 async fn toggle_favorite_api(track_id: Uuid) -> Result<bool, String> {
     let res: serde_json::Value =
-        api_request("POST", &format!("/api/music/favorites/{}", track_id), None)
+        api_request("POST", &format!("/api/v1/music/favorites/{}", track_id), None)
             .await?
             .json()
             .await
@@ -290,7 +582,7 @@ async fn toggle_favorite_api(track_id: Uuid) -> Result<bool, String> {
 }
 
 async fn trigger_scan_api() -> Result<(), String> {
-    api_request("POST", "/api/music/scan", None)
+    api_request("POST", "/api/v1/music/scan", None)
         .await
         .map(|_| ())
         .map_err(|e| e.to_string())
@@ -305,7 +597,7 @@ pub struct Playlist {
 }
 
 async fn fetch_playlists() -> Result<Vec<Playlist>, String> {
-    api_request("GET", "/api/playlists", None)
+    api_request("GET", "/api/v1/playlists", None)
         .await?
         .json()
         .await
@@ -314,24 +606,142 @@ async fn fetch_playlists() -> Result<Vec<Playlist>, String> {
 
 async fn create_playlist_api(name: String) -> Result<Playlist, String> {
     let body = serde_json::json!({ "name": name, "is_public": false });
-    api_request("POST", "/api/playlists", Some(body))
+    api_request("POST", "/api/v1/playlists", Some(body))
         .await?
         .json()
         .await
         .map_err(|e| e.to_string())
 }
 
+/// 本地没有歌词时服务器会去线上查找并返回 202，按 Retry-After 轮询几次后放弃
 async fn fetch_lyrics(id: Uuid) -> Option<String> {
-    let resp = api_request("GET", &format!("/api/music/lyrics/{}", id), None)
+    const MAX_ATTEMPTS: u32 = 5;
+    for _ in 0..MAX_ATTEMPTS {
+        let resp = api_request("GET", &format!("/api/v1/music/lyrics/{}", id), None)
+            .await
+            .ok()?;
+        match resp.status() {
+            200 => return resp.text().await.ok(),
+            202 => {
+                let wait_secs = resp
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|v| v.trim().parse::<u32>().ok())
+                    .unwrap_or(2)
+                    .clamp(1, 30);
+                gloo_timers::future::TimeoutFuture::new(wait_secs * 1000).await;
+            }
+            _ => return None,
+        }
+    }
+    None
+}
+
+async fn fetch_lyric_offset(track_id: Uuid) -> Result<i32, String> {
+    let res: serde_json::Value =
+        api_request("GET", &format!("/api/v1/music/tracks/{}/lyric-offset", track_id), None)
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+    Ok(res["offset_ms"].as_i64().unwrap_or(0) as i32)
+}
+
+async fn update_lyric_offset_api(track_id: Uuid, offset_ms: i32) -> Result<(), String> {
+    let body = serde_json::json!({ "offset_ms": offset_ms });
+    api_request("POST", &format!("/api/v1/music/tracks/{}/lyric-offset", track_id), Some(body))
+        .await
+        .map(|_| ())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PlaybackState {
+    pub track_id: Uuid,
+    pub position_ms: i32,
+}
+
+async fn fetch_playback_state() -> Result<Option<PlaybackState>, String> {
+    api_request("GET", "/api/v1/music/playback", None)
+        .await?
+        .json()
+        .await
+        .map_err(|e| e.to_string())
+}
+
+async fn update_playback_api(track_id: Uuid, position_ms: i32, is_playing: bool) -> Result<(), String> {
+    let body = serde_json::json!({ "track_id": track_id, "position_ms": position_ms, "is_playing": is_playing });
+    api_request("POST", "/api/v1/music/playback", Some(body))
         .await
-        .ok()?;
-    if resp.status() == 200 {
-        resp.text().await.ok()
+        .map(|_| ())
+}
+
+/// 离开一首曲目时上报收听时长，服务端据此判断是否计入播放次数
+async fn record_play_api(track_id: Uuid, played_ms: i32, completed: bool) -> Result<(), String> {
+    let body = serde_json::json!({ "played_ms": played_ms, "completed": completed });
+    api_request("POST", &format!("/api/v1/music/play/{}", track_id), Some(body))
+        .await
+        .map(|_| ())
+}
+
+/// 其他用户正在收听的曲目
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NowPlayingEntry {
+    pub user_id: Uuid,
+    pub display_name: String,
+    pub avatar: Option<String>,
+    pub track_id: Uuid,
+    pub title: String,
+    pub artist_name: Option<String>,
+    pub album_id: Option<Uuid>,
+    pub is_playing: bool,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum PresenceEvent {
+    Snapshot { listeners: Vec<NowPlayingEntry> },
+    Update(NowPlayingEntry),
+    Stopped { user_id: Uuid },
+}
+
+/// 浏览器的 WebSocket 不能带 Authorization 头，token 放在查询参数里
+fn now_playing_socket_url() -> String {
+    let token = load_pref("auth_token").unwrap_or_default();
+    let base = get_api_base_url()
+        .replacen("https://", "wss://", 1)
+        .replacen("http://", "ws://", 1);
+    format!(
+        "{}/api/v1/music/now-playing?token={}",
+        base,
+        String::from(js_sys::encode_uri_component(&token))
+    )
+}
+
+async fn fetch_share_now_playing() -> Result<bool, String> {
+    let resp = api_request("GET", "/api/v1/music/now-playing/settings", None).await?;
+    let json: serde_json::Value = resp.json().await.map_err(|e| e.to_string())?;
+    Ok(json["share_now_playing"].as_bool().unwrap_or(true))
+}
+
+async fn update_share_now_playing_api(share: bool) -> Result<(), String> {
+    let body = serde_json::json!({ "share_now_playing": share });
+    let resp = api_request("POST", "/api/v1/music/now-playing/settings", Some(body)).await?;
+    if resp.ok() {
+        Ok(())
     } else {
-        None
+        Err(format!("HTTP {}", resp.status()))
     }
 }
 
+async fn fetch_track(id: Uuid) -> Result<Track, String> {
+    let resp = api_request("GET", &format!("/api/v1/music/tracks/{}", id), None).await?;
+    if !resp.ok() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    resp.json().await.map_err(|e| e.to_string())
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
 pub struct ArtistSyncStatus {
     pub is_syncing: bool,
@@ -339,25 +749,116 @@ pub struct ArtistSyncStatus {
     pub total_count: i32,
     pub last_sync_at: Option<chrono::DateTime<chrono::Utc>>,
     pub last_error: Option<String>,
+    /// 上次批量同步被取消或中断，再次触发会从断点继续
+    #[serde(default)]
+    pub resumable: bool,
 }
 
 async fn fetch_artist_sync_status() -> Result<ArtistSyncStatus, String> {
-    api_request("GET", "/api/admin/sync-artists/status", None)
+    api_request("GET", "/api/v1/admin/sync-artists/status", None)
         .await?
         .json()
         .await
         .map_err(|e| e.to_string())
 }
 
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FormatStat {
+    pub format: String,
+    pub count: i64,
+    pub size: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MissingStats {
+    pub track_covers: i64,
+    pub track_lyrics: i64,
+    pub artist_images: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LibraryStats {
+    pub tracks: i64,
+    pub albums: i64,
+    pub artists: i64,
+    pub total_size: i64,
+    pub total_duration: i64,
+    pub formats: Vec<FormatStat>,
+    pub missing: MissingStats,
+}
+
+async fn fetch_library_stats() -> Result<LibraryStats, String> {
+    let resp = api_request("GET", "/api/v1/admin/stats", None).await?;
+    if !resp.ok() {
+        return Err(format!("HTTP {}", resp.status()));
+    }
+    resp.json().await.map_err(|e| e.to_string())
+}
+
+fn format_bytes(bytes: i64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// 秒数格式化为 `m:ss`，超过一小时为 `h:mm:ss`；时长未知 (NaN / 无穷) 时显示 `--:--`
+fn format_duration(secs: f64) -> String {
+    if !secs.is_finite() {
+        return "--:--".to_string();
+    }
+    let total = secs.max(0.0) as u64;
+    let (hours, minutes, seconds) = (total / 3600, (total % 3600) / 60, total % 60);
+    if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
 async fn trigger_artist_sync_api() -> Result<(), String> {
-    api_request("POST", "/api/admin/sync-artists", None)
+    api_request("POST", "/api/v1/admin/sync-artists", None)
+        .await
+        .map(|_| ())
+        .map_err(|e| e.to_string())
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ArtistSyncResult {
+    pub artist_id: Uuid,
+    pub artist_name: String,
+    pub status: String,
+    pub error: Option<String>,
+    pub synced_at: chrono::DateTime<chrono::Utc>,
+}
+
+async fn fetch_artist_sync_failures() -> Result<Vec<ArtistSyncResult>, String> {
+    let res: serde_json::Value =
+        api_request("GET", "/api/v1/admin/sync-artists/results?status=failed,timeout&limit=50", None)
+            .await?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+    serde_json::from_value(res["results"].clone()).map_err(|e| e.to_string())
+}
+
+async fn cancel_artist_sync_api() -> Result<(), String> {
+    api_request("POST", "/api/v1/admin/sync-artists/cancel", None)
         .await
         .map(|_| ())
         .map_err(|e| e.to_string())
 }
 
 async fn trigger_library_organize_api() -> Result<(), String> {
-    api_request("POST", "/api/admin/library/organize", None)
+    api_request("POST", "/api/v1/admin/library/organize", None)
         .await
         .map(|_| ())
         .map_err(|e| e.to_string())
@@ -374,14 +875,55 @@ fn App() -> impl IntoView {
     let is_mobile_menu_open = create_rw_signal(false);
 
     provide_context(AuthContext { token });
+
+    let theme = create_rw_signal(Theme::from_str(&load_pref("theme").unwrap_or_default()));
+    let accent = create_rw_signal(load_pref("accent_color").filter(|c| hex_to_rgb_channels(c).is_some()));
+    provide_context(ThemeContext { theme, accent });
+    create_effect(move |_| {
+        save_pref("theme", theme.get().as_str());
+        save_pref("accent_color", accent.get().as_deref().unwrap_or_default());
+        let Some(root) = window().document().and_then(|d| d.document_element()) else {
+            return;
+        };
+        let _ = root.set_attribute("data-theme", theme.get().as_str());
+        match accent.get().as_deref().and_then(hex_to_rgb_channels) {
+            // 强调色同时覆盖 accent 与 cyan 两个变量，按钮、高亮与进度条统一换色
+            Some(rgb) => {
+                let _ = root.set_attribute(
+                    "style",
+                    &format!("--papilio-accent: {rgb}; --papilio-cyan: {rgb};"),
+                );
+            }
+            None => {
+                let _ = root.remove_attribute("style");
+            }
+        }
+    });
     provide_context(PlayerContext {
         current_track: create_rw_signal(None),
         is_playing: create_rw_signal(false),
         is_fullscreen: create_rw_signal(false),
+        is_queue_open: create_rw_signal(false),
         playlist: create_rw_signal(Vec::new()),
         progress: create_rw_signal(0.0),
         duration: create_rw_signal(0.0),
         lyrics: create_rw_signal(Vec::new()),
+        lyrics_raw: create_rw_signal(None),
+        lyrics_synced: create_rw_signal(false),
+        show_translation: create_rw_signal(load_pref("lyrics_translation").as_deref() != Some("false")),
+        lyric_offset_ms: create_rw_signal(0),
+        repeat: create_rw_signal(RepeatMode::from_str(
+            &load_pref("player_repeat").unwrap_or_default(),
+        )),
+        shuffle: create_rw_signal(load_pref("player_shuffle").as_deref() == Some("true")),
+        volume: create_rw_signal(
+            load_pref("player_volume")
+                .and_then(|v| v.parse::<f64>().ok())
+                .map(|v| v.clamp(0.0, 1.0))
+                .unwrap_or(1.0),
+        ),
+        muted: create_rw_signal(false),
+        resume_at: create_rw_signal(None),
     });
 
     view! {
@@ -405,11 +947,14 @@ fn App() -> impl IntoView {
                                     <Route path="" view=move || view! { <Home /> }/>
                                     <Route path="/search" view=move || view! { <Search /> }/>
                                     <Route path="/favorites" view=move || view! { <Favorites /> }/>
+                                    <Route path="/artist/:id" view=move || view! { <CollectionPage kind=CollectionKind::Artist /> }/>
+                                    <Route path="/album/:id" view=move || view! { <CollectionPage kind=CollectionKind::Album /> }/>
                                     <Route path="/profile" view=move || view! { <Profile /> }/>
                                     <Route path="/admin" view=move || view! { <Admin /> }/>
                                 </Routes>
                             </main>
                             <PlayerBar />
+                            <QueuePanel />
                             <FullscreenPlayer />
                         </>
                     }.into_view()
@@ -493,13 +1038,87 @@ fn Sidebar(is_open: RwSignal<bool>) -> impl IntoView {
                 </button>
             </nav>
 
+            <ListeningNow />
+
             <button class="text-left text-xs text-papilio-muted hover:text-red-400 p-2 transition-colors" on:click=logout>"登出账号"</button>
         </aside>
     }
 }
 
+/// 侧边栏的"正在收听"：WebSocket 断开后每 10 秒重连一次
+#[component]
+fn ListeningNow() -> impl IntoView {
+    use futures::StreamExt;
+    use gloo_net::websocket::{futures::WebSocket, Message};
+
+    let listeners = create_rw_signal(Vec::<NowPlayingEntry>::new());
+    let alive = std::rc::Rc::new(std::cell::Cell::new(true));
+
+    // 停止上报的用户不会收到 stopped 事件，状态过期后在本地移除
+    let prune_handle = gloo_timers::callback::Interval::new(30_000, move || {
+        let cutoff = chrono::Utc::now() - chrono::Duration::seconds(90);
+        listeners.update(|list| list.retain(|l| l.updated_at > cutoff));
+    });
+
+    let running = alive.clone();
+    spawn_local(async move {
+        while running.get() {
+            if let Ok(ws) = WebSocket::open(&now_playing_socket_url()) {
+                let (_, mut read) = ws.split();
+                while let Some(Ok(Message::Text(text))) = read.next().await {
+                    if !running.get() {
+                        return;
+                    }
+                    let Ok(event) = serde_json::from_str::<PresenceEvent>(&text) else { continue };
+                    listeners.update(|list| match event {
+                        PresenceEvent::Snapshot { listeners } => *list = listeners,
+                        PresenceEvent::Update(entry) => {
+                            list.retain(|l| l.user_id != entry.user_id);
+                            list.insert(0, entry);
+                        }
+                        PresenceEvent::Stopped { user_id } => list.retain(|l| l.user_id != user_id),
+                    });
+                }
+            }
+            gloo_timers::future::TimeoutFuture::new(10_000).await;
+        }
+    });
+
+    on_cleanup(move || {
+        alive.set(false);
+        drop(prune_handle);
+    });
+
+    view! {
+        <Show when=move || !listeners.get().is_empty()>
+            <div class="flex flex-col gap-2">
+                <div class="text-[10px] text-papilio-muted uppercase tracking-widest px-3">"他们正在听"</div>
+                <For
+                    each=move || listeners.get()
+                    key=|l| (l.user_id, l.track_id, l.is_playing)
+                    children=move |l| view! {
+                        <div class="flex items-center gap-3 px-3 py-2 rounded-xl bg-white/5 text-xs">
+                            <img src=get_cover_url(l.album_id, Some(64)) class="w-8 h-8 rounded-lg object-cover bg-white/10 shrink-0" />
+                            <div class="min-w-0 flex-1">
+                                <div class="truncate font-bold">{l.title.clone()}</div>
+                                <div class="truncate text-papilio-muted">
+                                    {l.display_name.clone()}
+                                    {l.artist_name.clone().map(|a| format!(" · {}", a))}
+                                </div>
+                            </div>
+                            <span class="shrink-0">{if l.is_playing { "♪" } else { "⏸" }}</span>
+                        </div>
+                    }
+                />
+            </div>
+        </Show>
+    }
+}
+
 #[component]
 fn Home() -> impl IntoView {
+    let top_res = create_resource(|| (), |_| async move { fetch_discover_tracks("top", 10).await });
+    let recent_res = create_resource(|| (), |_| async move { fetch_discover_tracks("recent", 10).await });
     let tracks_res = create_resource(|| (), |_| async move { fetch_tracks(None).await });
     view! {
         <div class="p-6 md:p-10 flex flex-col gap-10">
@@ -507,33 +1126,95 @@ fn Home() -> impl IntoView {
                 <h2 class="text-4xl md:text-7xl font-black tracking-tighter mb-2 leading-none">"蝶变音律"</h2>
                 <p class="text-white/70 text-base md:text-xl font-light">"私人高保真资源已同步。"</p>
             </section>
-            <div class="flex-1">
-                <h3 class="text-2xl font-bold mb-8 flex items-center gap-3"><span class="w-1.5 h-6 bg-papilio-cyan rounded-full"></span>"推荐曲目"</h3>
-                <Suspense fallback=move || view! { <div class="text-papilio-muted">"Loading..."</div> }>
-                    {move || tracks_res.get().map(|res| match res {
-                        Ok(data) => view! {
-                            <div class="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-5 2xl:grid-cols-6 gap-6 md:gap-8">
-                                {data.clone().into_iter().map(|track| {
-                                    let full_list = data.clone();
-                                    view! { <TrackCard track=track playlist=full_list /> }
-                                }).collect_view()}
-                            </div>
-                        }.into_view(),
-                        Err(_) => view! { <p class="text-red-400 text-center py-20">"权限验证失败，请重新登录"</p> }.into_view()
-                    })}
-                </Suspense>
-            </div>
+            <TrackSection title="最常播放" tracks=top_res hide_when_empty=true />
+            <TrackSection title="最近添加" tracks=recent_res hide_when_empty=true />
+            <TrackSection title="全部曲目" tracks=tracks_res hide_when_empty=false />
         </div>
     }
 }
 
+#[component]
+fn TrackSection(
+    title: &'static str,
+    tracks: Resource<(), Result<Vec<Track>, String>>,
+    hide_when_empty: bool,
+) -> impl IntoView {
+    view! {
+        <Suspense fallback=move || view! { <div class="text-papilio-muted">"Loading..."</div> }>
+            {move || tracks.get().map(|res| match res {
+                Ok(data) if data.is_empty() && hide_when_empty => ().into_view(),
+                Ok(data) => view! {
+                    <div class="flex-1">
+                        <h3 class="text-2xl font-bold mb-8 flex items-center gap-3"><span class="w-1.5 h-6 bg-papilio-cyan rounded-full"></span>{title}</h3>
+                        <div class="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-5 2xl:grid-cols-6 gap-6 md:gap-8">
+                            {data.clone().into_iter().map(|track| {
+                                let full_list = data.clone();
+                                view! { <TrackCard track=track playlist=full_list /> }
+                            }).collect_view()}
+                        </div>
+                    </div>
+                }.into_view(),
+                Err(_) => view! { <p class="text-red-400 text-center py-20">"权限验证失败，请重新登录"</p> }.into_view()
+            })}
+        </Suspense>
+    }
+}
+
 #[component]
 fn Favorites() -> impl IntoView {
     let tracks_res = create_resource(|| (), |_| async move { fetch_favorites().await });
+    let albums_res = create_resource(|| (), |_| async move { fetch_favorite_albums().await });
+    let artists_res = create_resource(|| (), |_| async move { fetch_favorite_artists().await });
     view! {
         <div class="p-6 md:p-10 flex flex-col gap-10">
             <h2 class="text-4xl md:text-6xl font-black tracking-tighter">"我的收藏"</h2>
-            <div class="flex-1">
+
+            <Suspense fallback=move || view! { <div></div> }>
+                {move || artists_res.get().and_then(|res| res.ok()).filter(|a| !a.is_empty()).map(|artists| view! {
+                    <section>
+                        <h3 class="text-xl font-bold mb-6 opacity-60 uppercase tracking-widest text-papilio-cyan">"关注的歌手"</h3>
+                        <div class="flex flex-wrap gap-6">
+                            {artists.into_iter().map(|artist| {
+                                let image = artist.image_url.as_deref().map(artist_image_src);
+                                view! {
+                                    <A href=format!("/artist/{}", artist.id) class="group flex flex-col items-center gap-3 w-28">
+                                        <div class="w-28 h-28 rounded-full overflow-hidden border border-white/10 bg-white/5 flex items-center justify-center text-3xl transition-all duration-500 group-hover:scale-105">
+                                            {match image {
+                                                Some(src) => view! { <img src=src class="w-full h-full object-cover" /> }.into_view(),
+                                                None => view! { <span class="opacity-40">"🎤"</span> }.into_view(),
+                                            }}
+                                        </div>
+                                        <div class="text-sm font-bold text-white/90 group-hover:text-papilio-cyan transition-colors truncate w-full text-center">{artist.name}</div>
+                                        <div class="text-xs text-papilio-muted -mt-2">{format!("{} 张专辑", artist.album_count)}</div>
+                                    </A>
+                                }
+                            }).collect_view()}
+                        </div>
+                    </section>
+                })}
+            </Suspense>
+
+            <Suspense fallback=move || view! { <div></div> }>
+                {move || albums_res.get().and_then(|res| res.ok()).filter(|a| !a.is_empty()).map(|albums| view! {
+                    <section>
+                        <h3 class="text-xl font-bold mb-6 opacity-60 uppercase tracking-widest text-papilio-cyan">"收藏的专辑"</h3>
+                        <div class="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-5 2xl:grid-cols-6 gap-6 md:gap-8">
+                            {albums.into_iter().map(|album| view! {
+                                <A href=format!("/album/{}", album.id) class="group">
+                                    <div class="aspect-square rounded-[2rem] overflow-hidden border border-white/10 shadow-xl transition-all duration-500 hover:scale-[1.02]">
+                                        <img src=get_cover_url(Some(album.id), Some(400)) class="w-full h-full object-cover" />
+                                    </div>
+                                    <div class="mt-4 px-2 font-bold text-white/90 text-sm md:text-base group-hover:text-papilio-cyan transition-colors truncate">{album.title}</div>
+                                    <div class="px-2 text-xs text-papilio-muted">{format!("{} 首曲目", album.track_count)}</div>
+                                </A>
+                            }).collect_view()}
+                        </div>
+                    </section>
+                })}
+            </Suspense>
+
+            <section class="flex-1">
+                <h3 class="text-xl font-bold mb-6 opacity-60 uppercase tracking-widest text-papilio-cyan">"收藏的单曲"</h3>
                 <Suspense fallback=move || view! { <div class="text-papilio-muted text-center py-20 animate-pulse">"加载中..."</div> }>
                     {move || tracks_res.get().map(|res| match res {
                         Ok(data) => if data.is_empty() {
@@ -546,12 +1227,130 @@ fn Favorites() -> impl IntoView {
                                         view! { <TrackCard track=track playlist=full_list /> }
                                     }).collect_view()}
                                 </div>
-                            }.into_view()
-                        },
-                        Err(_) => view! { <p class="text-red-400 text-center py-20">"获取收藏失败"</p> }.into_view()
-                    })}
-                </Suspense>
-            </div>
+                            }.into_view()
+                        },
+                        Err(_) => view! { <p class="text-red-400 text-center py-20">"获取收藏失败"</p> }.into_view()
+                    })}
+                </Suspense>
+            </section>
+        </div>
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum CollectionKind {
+    Artist,
+    Album,
+}
+
+/// 歌手 / 专辑详情页：复用 `list_tracks` 的 artist_id / album_id 过滤
+#[component]
+fn CollectionPage(kind: CollectionKind) -> impl IntoView {
+    let player = use_context::<PlayerContext>().expect("context not found");
+    let params = use_params_map();
+    let id = move || params.with(|p| p.get("id").and_then(|id| Uuid::parse_str(id).ok()));
+    let tracks_res = create_resource(id, move |id| async move {
+        let filter = match kind {
+            CollectionKind::Artist => "artist_id",
+            CollectionKind::Album => "album_id",
+        };
+        match id {
+            Some(id) => fetch_tracks_by(filter, id).await,
+            None => Err("Invalid id".to_string()),
+        }
+    });
+
+    // 当前专辑 / 歌手是否已收藏：取收藏列表比对，未登录时请求失败即视为未收藏
+    let is_favorite = create_rw_signal(false);
+    create_effect(move |_| {
+        let Some(current) = id() else { return };
+        spawn_local(async move {
+            let favorited = match kind {
+                CollectionKind::Artist => fetch_favorite_artists()
+                    .await
+                    .map(|list| list.iter().any(|a| a.id == current)),
+                CollectionKind::Album => fetch_favorite_albums()
+                    .await
+                    .map(|list| list.iter().any(|a| a.id == current)),
+            };
+            is_favorite.set(favorited.unwrap_or(false));
+        });
+    });
+    let toggle_favorite = move |_| {
+        let Some(current) = id() else { return };
+        spawn_local(async move {
+            if let Ok(state) = toggle_collection_favorite_api(kind, current).await {
+                is_favorite.set(state);
+            }
+        });
+    };
+
+    view! {
+        <div class="p-6 md:p-10 flex flex-col gap-10">
+            <Suspense fallback=move || view! { <div class="text-papilio-muted text-center py-20 animate-pulse">"加载中..."</div> }>
+                {move || tracks_res.get().map(|res| match res {
+                    Ok(data) if data.is_empty() => view! {
+                        <div class="text-center py-20 text-papilio-muted text-xl border border-dashed border-white/10 rounded-3xl">"暂无曲目"</div>
+                    }.into_view(),
+                    Ok(data) => {
+                        let first = data[0].clone();
+                        let (title, image) = match kind {
+                            CollectionKind::Artist => (
+                                first.artist_name.clone().unwrap_or_default(),
+                                first.artist_image_url.as_deref().map(artist_image_src).unwrap_or_else(|| get_cover_url(first.album_id, Some(400))),
+                            ),
+                            CollectionKind::Album => (
+                                first.album_title.clone().unwrap_or_default(),
+                                get_cover_url(first.album_id, Some(400)),
+                            ),
+                        };
+                        let subtitle = match kind {
+                            CollectionKind::Artist => format!("{} 首曲目", data.len()),
+                            CollectionKind::Album => format!("{} · {} 首曲目", first.artist_name.clone().unwrap_or_default(), data.len()),
+                        };
+                        let play_all = {
+                            let data = data.clone();
+                            move |_| {
+                                player.playlist.set(data.clone());
+                                player.current_track.set(data.first().cloned());
+                                player.is_playing.set(true);
+                            }
+                        };
+                        view! {
+                            <section class="flex flex-col md:flex-row md:items-end gap-6 md:gap-10">
+                                <img src=image class="w-40 h-40 md:w-60 md:h-60 object-cover border border-white/10 shadow-2xl" class:rounded-full=kind == CollectionKind::Artist class:rounded-3xl=kind == CollectionKind::Album />
+                                <div class="flex flex-col gap-4">
+                                    <div class="text-[10px] uppercase tracking-[0.4em] text-papilio-cyan font-bold opacity-80">{if kind == CollectionKind::Artist { "Artist" } else { "Album" }}</div>
+                                    <h2 class="text-4xl md:text-6xl font-black tracking-tighter">{title}</h2>
+                                    <div class="text-papilio-muted">{subtitle}</div>
+                                    <div class="flex items-center gap-3">
+                                        <button class="px-8 py-3 rounded-full bg-white text-papilio-bg font-bold hover:scale-105 active:scale-95 transition-all" on:click=play_all>"▶ 播放全部"</button>
+                                        <button
+                                            class="px-6 py-3 rounded-full border border-white/10 bg-white/5 font-bold hover:bg-white/10 active:scale-95 transition-all"
+                                            class:text-papilio-accent=move || is_favorite.get()
+                                            on:click=toggle_favorite
+                                        >
+                                            {move || match (kind, is_favorite.get()) {
+                                                (CollectionKind::Artist, true) => "✓ 已关注",
+                                                (CollectionKind::Artist, false) => "+ 关注",
+                                                (CollectionKind::Album, true) => "♥ 已收藏",
+                                                (CollectionKind::Album, false) => "♡ 收藏",
+                                            }}
+                                        </button>
+                                    </div>
+                                </div>
+                            </section>
+                            <div class="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-5 2xl:grid-cols-6 gap-6 md:gap-8">
+                                {data.clone().into_iter().map(|track| {
+                                    let full_list = data.clone();
+                                    view! { <TrackCard track=track playlist=full_list /> }
+                                }).collect_view()}
+                            </div>
+                        }.into_view()
+                    }
+                    Err(_) => view! { <p class="text-red-400 text-center py-20">"加载失败"</p> }.into_view()
+                })}
+            </Suspense>
         </div>
     }
 }
@@ -584,11 +1383,25 @@ fn Search() -> impl IntoView {
                                             view! {
                                                 <section>
                                                     <h3 class="text-xl font-bold mb-4 opacity-60 uppercase tracking-widest text-papilio-cyan">"匹配到的艺人"</h3>
-                                                    <div class="flex flex-wrap gap-4">
-                                                        {data.artists.into_iter().map(|artist| view! {
-                                                            <div class="bg-white/5 border border-white/10 px-6 py-3 rounded-2xl hover:bg-papilio-accent/20 transition-all cursor-pointer group">
-                                                                <span class="text-white/60 group-hover:text-white transition-colors">{artist.name}</span>
-                                                            </div>
+                                                    <div class="flex flex-col gap-6">
+                                                        {data.artists.into_iter().map(|result| view! { <ArtistResultCard result=result /> }).collect_view()}
+                                                    </div>
+                                                </section>
+                                            }.into_view()
+                                        } else { view! {}.into_view() }}
+
+                                        {if !data.albums.is_empty() {
+                                            view! {
+                                                <section>
+                                                    <h3 class="text-xl font-bold mb-6 opacity-60 uppercase tracking-widest text-papilio-cyan">"匹配到的专辑"</h3>
+                                                    <div class="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-5 2xl:grid-cols-6 gap-6 md:gap-8">
+                                                        {data.albums.into_iter().map(|album| view! {
+                                                            <A href=format!("/album/{}", album.id) class="group">
+                                                                <div class="aspect-square rounded-[2rem] overflow-hidden border border-white/10 shadow-xl transition-all duration-500 hover:scale-[1.02]">
+                                                                    <img src=get_cover_url(Some(album.id), Some(400)) class="w-full h-full object-cover" />
+                                                                </div>
+                                                                <div class="mt-4 px-2 font-bold text-white/90 text-sm md:text-base group-hover:text-papilio-cyan transition-colors truncate">{album.title}</div>
+                                                            </A>
                                                         }).collect_view()}
                                                     </div>
                                                 </section>
@@ -596,14 +1409,14 @@ fn Search() -> impl IntoView {
                                         } else { view! {}.into_view() }}
 
                                         {if !data.tracks.is_empty() {
-                                            let tracks_only: Vec<Track> = data.tracks.iter().map(|t| t.track.clone()).collect();
+                                            let tracks_only = data.tracks.clone();
                                             view! {
                                                 <section>
                                                     <h3 class="text-xl font-bold mb-6 opacity-60 uppercase tracking-widest text-papilio-cyan">"匹配到的单曲"</h3>
                                                     <div class="grid grid-cols-2 sm:grid-cols-3 lg:grid-cols-4 xl:grid-cols-5 2xl:grid-cols-6 gap-6 md:gap-8">
                                                         {data.tracks.into_iter().map(|t| {
                                                             let full_list = tracks_only.clone();
-                                                            view! { <TrackCard track=t.track playlist=full_list /> }
+                                                            view! { <TrackCard track=t playlist=full_list /> }
                                                         }).collect_view()}
                                                     </div>
                                                 </section>
@@ -621,11 +1434,75 @@ fn Search() -> impl IntoView {
     }
 }
 
+/// 搜索结果中的歌手卡片：头像、名称、专辑封面行与热门曲目
+#[component]
+fn ArtistResultCard(result: ArtistSearchResult) -> impl IntoView {
+    let player = use_context::<PlayerContext>().expect("context not found");
+    let artist = result.artist;
+    let image = artist.image_url.as_deref().map(artist_image_src);
+    let top_tracks = result.top_tracks;
+
+    view! {
+        <div class="bg-white/5 border border-white/10 rounded-3xl p-5 md:p-6 flex flex-col md:flex-row gap-6">
+            <A href=format!("/artist/{}", artist.id) class="group flex md:flex-col items-center gap-4 md:w-32 shrink-0">
+                <div class="w-20 h-20 md:w-28 md:h-28 rounded-full overflow-hidden border border-white/10 bg-white/5 flex items-center justify-center text-3xl transition-all duration-500 group-hover:scale-105">
+                    {match image {
+                        Some(src) => view! { <img src=src class="w-full h-full object-cover" /> }.into_view(),
+                        None => view! { <span class="opacity-40">"🎤"</span> }.into_view(),
+                    }}
+                </div>
+                <div class="text-lg font-bold text-white/90 group-hover:text-papilio-cyan transition-colors truncate md:text-center md:w-full">{artist.name}</div>
+            </A>
+
+            <div class="flex-1 min-w-0 flex flex-col gap-5">
+                {(!result.albums.is_empty()).then(|| view! {
+                    <div class="flex gap-4 overflow-x-auto pb-1">
+                        {result.albums.into_iter().map(|album| view! {
+                            <A href=format!("/album/{}", album.id) class="group w-24 shrink-0">
+                                <div class="aspect-square rounded-2xl overflow-hidden border border-white/10">
+                                    <img src=get_cover_url(Some(album.id), Some(200)) class="w-full h-full object-cover transition-all duration-500 group-hover:scale-105" />
+                                </div>
+                                <div class="mt-2 text-xs text-white/70 group-hover:text-papilio-cyan transition-colors truncate">{album.title}</div>
+                            </A>
+                        }).collect_view()}
+                    </div>
+                })}
+
+                {(!top_tracks.is_empty()).then(|| {
+                    let playlist = top_tracks.clone();
+                    view! {
+                        <div class="flex flex-col">
+                            <div class="text-[10px] uppercase tracking-widest text-papilio-muted mb-2">"热门曲目"</div>
+                            {top_tracks.into_iter().map(|track| {
+                                let playlist = playlist.clone();
+                                let title = track.title.clone();
+                                let album_title = track.album_title.clone().unwrap_or_default();
+                                let on_click = move |_| {
+                                    player.playlist.set(playlist.clone());
+                                    player.current_track.set(Some(track.clone()));
+                                    player.is_playing.set(true);
+                                };
+                                view! {
+                                    <div class="flex items-center gap-3 px-3 py-2 rounded-xl hover:bg-white/10 cursor-pointer transition-colors" on:click=on_click>
+                                        <span class="text-xs opacity-40">"▶"</span>
+                                        <span class="text-sm text-white/90 truncate">{title}</span>
+                                        <span class="text-xs text-papilio-muted truncate ml-auto">{album_title}</span>
+                                    </div>
+                                }
+                            }).collect_view()}
+                        </div>
+                    }
+                })}
+            </div>
+        </div>
+    }
+}
+
 #[component]
 fn TrackCard(track: Track, playlist: Vec<Track>) -> impl IntoView {
     let player = use_context::<PlayerContext>().expect("context not found");
     let (is_fav, set_is_fav) = create_signal(track.is_favorite);
-    let cover_url = get_cover_url(track.album_id);
+    let cover_url = get_cover_url(track.album_id, Some(400));
     let on_click = {
         let track = track.clone();
         let playlist = playlist.clone();
@@ -635,6 +1512,13 @@ fn TrackCard(track: Track, playlist: Vec<Track>) -> impl IntoView {
             player.is_playing.set(true);
         }
     };
+    let play_next = {
+        let track = track.clone();
+        move |ev: web_sys::MouseEvent| {
+            ev.stop_propagation();
+            queue_play_next(&player, track.clone());
+        }
+    };
     let toggle_fav = {
         let track_id = track.id;
         move |ev: web_sys::MouseEvent| {
@@ -651,6 +1535,7 @@ fn TrackCard(track: Track, playlist: Vec<Track>) -> impl IntoView {
             <div class="aspect-square rounded-[2rem] overflow-hidden relative border border-white/10 shadow-xl transition-all duration-500 hover:scale-[1.02] active:scale-[0.98] group-hover:shadow-[0_20px_40px_rgba(0,0,0,0.4)]">
                 <img src=cover_url class="w-full h-full object-cover transition-all duration-700 group-hover:scale-110" />
                 <button class="absolute top-4 right-4 w-10 h-10 rounded-full bg-black/40 backdrop-blur-md flex items-center justify-center transition-all opacity-0 group-hover:opacity-100 hover:scale-110 active:scale-90 z-10" on:click=toggle_fav>{move || if is_fav.get() { "❤️" } else { "🤍" }}</button>
+                <button class="absolute top-4 left-4 h-10 px-3 rounded-full bg-black/40 backdrop-blur-md flex items-center justify-center text-[10px] font-bold uppercase tracking-widest transition-all opacity-0 group-hover:opacity-100 hover:scale-110 active:scale-90 z-10" title="Play Next" on:click=play_next>"+ Next"</button>
                 <div class="absolute inset-0 bg-black/40 opacity-0 group-hover:opacity-100 transition-all duration-500 flex items-center justify-center pointer-events-none">
                     <div class="w-16 h-16 rounded-full bg-white/10 backdrop-blur-md border border-white/20 flex items-center justify-center text-white text-3xl">"▶"</div>
                 </div>
@@ -663,59 +1548,491 @@ fn TrackCard(track: Track, playlist: Vec<Track>) -> impl IntoView {
     }
 }
 
+/// 距曲目结束还剩多少秒时预载下一首
+const PREFETCH_SECS: f64 = 20.0;
+
+/// 相邻两次 timeupdate 之间超过此值的前进视为拖动进度条，不计入收听时长
+const MAX_LISTEN_STEP_SECS: f64 = 2.0;
+
 #[component]
 fn PlayerBar() -> impl IntoView {
     let player = use_context::<PlayerContext>().expect("context not found");
-    let audio_ref = create_node_ref::<leptos::html::Audio>();
+    // 两个 <audio> 轮流使用：当前曲目快播完时由备用元素预载下一首，切歌时直接换用备用元素，
+    // 省去重新设置 src 带来的停顿
+    let audio_refs = [
+        create_node_ref::<leptos::html::Audio>(),
+        create_node_ref::<leptos::html::Audio>(),
+    ];
+    let active = store_value(0_usize);
+    let audio_ref = move || audio_refs[active.get_value()].get();
+    // 备用元素中已预载的曲目
+    let prefetched = store_value(None::<Uuid>);
+    // 当前曲目因流错误已重试的次数，切歌或恢复播放后清零
+    let stream_retries = store_value(0_usize);
+    // 连续重试用尽而被跳过的曲目数，任一曲目开始播放后清零；
+    // 达到列表长度说明整个列表都无法播放 (多半是服务不可用)，不再循环跳歌
+    let failed_tracks = store_value(0_usize);
+    // 当前曲目、实际播放的累计秒数与上次 timeupdate 时的位置，切歌或播完时上报；
+    // 拖动进度条造成的跳变不计入。不足 1 秒视为直接跳过，不上报
+    let listened = store_value(None::<(Uuid, f64, f64)>);
+    let report_listen = move |completed: bool| {
+        let Some((track_id, secs, _)) = listened.get_value() else { return };
+        listened.set_value(None);
+        if secs < 1.0 && !completed {
+            return;
+        }
+        spawn_local(async move {
+            let _ = record_play_api(track_id, (secs * 1000.0) as i32, completed).await;
+        });
+    };
     create_effect(move |_| {
         if let Some(track) = player.current_track.get() {
-            if let Some(audio) = audio_ref.get() {
-                audio.set_src(&format!(
-                    "{}/api/music/stream/{}",
-                    get_api_base_url(),
-                    track.id
-                ));
-                let _ = audio.play();
+            stream_retries.set_value(0);
+            report_listen(false);
+            listened.set_value(Some((track.id, 0.0, 0.0)));
+            let idx = active.get_value();
+            // 恢复会话需要先跳转位置，不走预载
+            let swap = prefetched.get_value() == Some(track.id) && player.resume_at.get_untracked().is_none();
+            prefetched.set_value(None);
+            if swap {
+                if let Some(old) = audio_refs[idx].get() {
+                    let _ = old.pause();
+                }
+                active.set_value(1 - idx);
+            } else if let Some(standby) = audio_refs[1 - idx].get() {
+                // 跳到了别的曲目，丢弃未用上的预载
+                let _ = standby.remove_attribute("src");
+                standby.load();
+            }
+            if let Some(audio) = audio_ref() {
+                if !swap {
+                    audio.set_src(&get_stream_url(track.id));
+                }
+                audio.set_volume(player.volume.get_untracked());
+                audio.set_muted(player.muted.get_untracked());
+                match player.resume_at.get_untracked() {
+                    Some(position) => {
+                        audio.set_current_time(position);
+                        player.resume_at.set(None);
+                        player.is_playing.set(false);
+                    }
+                    None => {
+                        let _ = audio.play();
+                    }
+                }
 
+                player.lyric_offset_ms.set(0);
+                spawn_local(async move {
+                    if let Ok(offset) = fetch_lyric_offset(track.id).await {
+                        player.lyric_offset_ms.set(offset);
+                    }
+                });
+                // 先清掉上一首的歌词，查找或轮询期间不显示旧内容
+                player.lyrics.set(Vec::new());
+                player.lyrics_raw.set(None);
+                player.lyrics_synced.set(false);
                 // 使用歌词服务
                 spawn_local(async move {
-                    if let Some(lrc_text) = fetch_lyrics(track.id).await {
-                        player.lyrics.set(parse_lrc(&lrc_text));
-                    } else {
-                        player.lyrics.set(Vec::new());
+                    let lyrics = fetch_lyrics(track.id).await;
+                    // 轮询期间可能已经切歌
+                    if player.current_track.get_untracked().map(|t| t.id) != Some(track.id) {
+                        return;
                     }
+                    let lines = lyrics.as_deref().map(parse_lrc).unwrap_or_default();
+                    player.lyrics_synced.set(!lines.is_empty());
+                    player.lyrics.set(lines);
+                    player.lyrics_raw.set(lyrics.filter(|t| !t.trim().is_empty()));
                 });
             }
         }
     });
-    let next_track = move || {
+    // 随机模式下本轮已播放过的曲目，全部播完后清空
+    let shuffle_played = store_value(std::collections::HashSet::<Uuid>::new());
+    // `manual` 为用户点击下一首；自然播完时才遵循单曲循环，列表末尾仅在列表循环时回到开头
+    let advance = move |manual: bool| {
+        let repeat = player.repeat.get_untracked();
+        if !manual && repeat == RepeatMode::One {
+            if let Some(audio) = audio_ref() {
+                audio.set_current_time(0.0);
+                let _ = audio.play();
+            }
+            // 单曲循环不会触发切歌，重新开始记录本轮收听
+            if let Some(curr) = player.current_track.get_untracked() {
+                listened.set_value(Some((curr.id, 0.0, 0.0)));
+            }
+            return;
+        }
+        let current = player.current_track.get();
+        let list = player.playlist.get();
+        let Some(curr) = current else { return };
+        let Some(pos) = list.iter().position(|t| t.id == curr.id) else { return };
+
+        let next_idx = if player.shuffle.get_untracked() {
+            shuffle_played.update_value(|played| {
+                played.insert(curr.id);
+            });
+            let mut unplayed: Vec<usize> = shuffle_played.with_value(|played| {
+                (0..list.len()).filter(|i| !played.contains(&list[*i].id)).collect()
+            });
+            if unplayed.is_empty() {
+                if !manual && repeat == RepeatMode::Off {
+                    player.is_playing.set(false);
+                    return;
+                }
+                shuffle_played.update_value(|played| played.clear());
+                unplayed = (0..list.len()).filter(|i| *i != pos || list.len() == 1).collect();
+            }
+            unplayed[(js_sys::Math::random() * unplayed.len() as f64) as usize % unplayed.len()]
+        } else {
+            if pos + 1 == list.len() && !manual && repeat == RepeatMode::Off {
+                player.is_playing.set(false);
+                return;
+            }
+            (pos + 1) % list.len()
+        };
+        player.current_track.set(Some(list[next_idx].clone()));
+        player.is_playing.set(true);
+    };
+    let next_track = move || advance(true);
+    // 剩余不足 PREFETCH_SECS 秒时预载自然播完后的下一首；随机与单曲循环的下一首无法提前确定，不预载
+    let maybe_prefetch = move |remaining: f64| {
+        // 元数据载入前 duration 为 NaN
+        if !remaining.is_finite() || remaining > PREFETCH_SECS || prefetched.get_value().is_some() {
+            return;
+        }
+        if player.shuffle.get_untracked() || player.repeat.get_untracked() == RepeatMode::One {
+            return;
+        }
+        let Some(curr) = player.current_track.get_untracked() else { return };
+        let next = player.playlist.with_untracked(|list| {
+            let pos = list.iter().position(|t| t.id == curr.id)?;
+            match list.get(pos + 1) {
+                Some(next) => Some(next.id),
+                None if player.repeat.get_untracked() == RepeatMode::All && list.len() > 1 => Some(list[0].id),
+                None => None,
+            }
+        });
+        let Some(next_id) = next else { return };
+        if let Some(standby) = audio_refs[1 - active.get_value()].get() {
+            standby.set_src(&get_stream_url(next_id));
+            standby.set_preload("auto");
+            prefetched.set_value(Some(next_id));
+        }
+    };
+    let toggle_repeat = move |_| {
+        let mode = player.repeat.get_untracked().next();
+        player.repeat.set(mode);
+        save_pref("player_repeat", mode.as_str());
+    };
+    let toggle_shuffle = move |_| {
+        let enabled = !player.shuffle.get_untracked();
+        player.shuffle.set(enabled);
+        shuffle_played.update_value(|played| played.clear());
+        save_pref("player_shuffle", if enabled { "true" } else { "false" });
+    };
+    // 恢复上次会话的曲目与位置；曲目已被删除时忽略
+    spawn_local(async move {
+        if let Ok(Some(state)) = fetch_playback_state().await {
+            if player.current_track.get_untracked().is_some() {
+                return;
+            }
+            if let Ok(track) = fetch_track(state.track_id).await {
+                player.resume_at.set(Some(state.position_ms as f64 / 1000.0));
+                if player.playlist.get_untracked().is_empty() {
+                    player.playlist.set(vec![track.clone()]);
+                }
+                player.current_track.set(Some(track));
+            }
+        }
+    });
+    // 播放进度每 5 秒同步一次到服务器，暂停时立即同步
+    let last_synced = store_value(0.0_f64);
+    let sync_playback = move |force: bool| {
+        let Some(track) = player.current_track.get_untracked() else { return };
+        let now = js_sys::Date::now();
+        if !force && now - last_synced.get_value() < 5000.0 {
+            return;
+        }
+        last_synced.set_value(now);
+        let position_ms = (player.progress.get_untracked() * 1000.0) as i32;
+        let is_playing = audio_ref().map(|a| !a.paused()).unwrap_or(false);
+        spawn_local(async move {
+            let _ = update_playback_api(track.id, position_ms, is_playing).await;
+        });
+    };
+    // 音量与静音只改元素属性，不会打断播放；两个元素同步设置，切换后无需再调整
+    create_effect(move |_| {
+        let volume = player.volume.get();
+        let muted = player.muted.get();
+        for audio in audio_refs.iter().filter_map(|r| r.get()) {
+            audio.set_volume(volume);
+            audio.set_muted(muted);
+        }
+    });
+    let on_volume = move |ev: web_sys::Event| {
+        if let Ok(volume) = event_target_value(&ev).parse::<f64>() {
+            let volume = volume.clamp(0.0, 1.0);
+            player.volume.set(volume);
+            player.muted.set(volume == 0.0);
+            save_pref("player_volume", &volume.to_string());
+        }
+    };
+    // 已播放超过 3 秒时先回到本曲开头，与常见播放器行为一致
+    let prev_track = move || {
+        if player.progress.get_untracked() > 3.0 {
+            if let Some(audio) = audio_ref() {
+                audio.set_current_time(0.0);
+                return;
+            }
+        }
         let current = player.current_track.get();
         let list = player.playlist.get();
         if let Some(curr) = current {
             if let Some(pos) = list.iter().position(|t| t.id == curr.id) {
-                let next_idx = (pos + 1) % list.len();
-                player.current_track.set(Some(list[next_idx].clone()));
+                let prev_idx = (pos + list.len() - 1) % list.len();
+                player.current_track.set(Some(list[prev_idx].clone()));
                 player.is_playing.set(true);
             }
         }
     };
-    let toggle_play = move |_| {
-        if let Some(audio) = audio_ref.get() {
-            if player.is_playing.get() {
-                let _ = audio.pause();
-                player.is_playing.set(false);
+    let pause_playback = move || {
+        if let Some(audio) = audio_ref() {
+            let _ = audio.pause();
+            player.is_playing.set(false);
+        }
+    };
+    let play_pause = move || {
+        if let Some(audio) = audio_ref() {
+            if player.is_playing.get_untracked() {
+                pause_playback();
             } else {
                 let _ = audio.play();
                 player.is_playing.set(true);
             }
         }
     };
+    let toggle_play = move |_| play_pause();
+    let seek_by = move |delta: f64| {
+        if let Some(audio) = audio_ref() {
+            let mut target = (audio.current_time() + delta).max(0.0);
+            let duration = audio.duration();
+            if duration.is_finite() {
+                target = target.min(duration);
+            }
+            audio.set_current_time(target);
+            player.progress.set(target);
+        }
+    };
+    // 底部提示条，数秒后自动消失
+    let toast = create_rw_signal(None::<String>);
+    let toast_timer = store_value(None::<gloo_timers::callback::Timeout>);
+    let show_toast = move |message: String| {
+        toast.set(Some(message));
+        toast_timer.set_value(Some(gloo_timers::callback::Timeout::new(4000, move || toast.set(None))));
+    };
+    on_cleanup(move || toast_timer.set_value(None));
+    // 流请求失败 (服务重启、网络中断) 时按退避间隔重新请求并回到原位置，重试用尽后跳到下一首
+    let on_stream_error = move || {
+        let Some(track) = player.current_track.get_untracked() else { return };
+        let attempt = stream_retries.get_value();
+        let Some(&delay) = STREAM_RETRY_DELAYS_MS.get(attempt) else {
+            let failed = failed_tracks.get_value() + 1;
+            if failed >= player.playlist.with_untracked(|list| list.len()).max(1) {
+                failed_tracks.set_value(0);
+                pause_playback();
+                show_toast("无法连接服务器，播放已停止".to_string());
+                return;
+            }
+            failed_tracks.set_value(failed);
+            show_toast(format!("无法播放《{}》，已跳到下一首", track.title));
+            next_track();
+            return;
+        };
+        stream_retries.set_value(attempt + 1);
+        show_toast(format!(
+            "播放中断，{} 秒后重试 ({}/{})",
+            delay / 1000,
+            attempt + 1,
+            STREAM_RETRY_DELAYS_MS.len()
+        ));
+        let position = player.progress.get_untracked();
+        gloo_timers::callback::Timeout::new(delay, move || {
+            // 等待期间已切歌
+            if player.current_track.get_untracked().map(|t| t.id) != Some(track.id) {
+                return;
+            }
+            if let Some(audio) = audio_ref() {
+                // 每次重新生成地址，服务端地址或登录状态变化后也能接上
+                audio.set_src(&get_stream_url(track.id));
+                if position > 0.0 {
+                    audio.set_current_time(position);
+                }
+                if player.is_playing.get_untracked() {
+                    let _ = audio.play();
+                }
+            }
+        })
+        .forget();
+    };
+    // 实时转码的流拿不到时长 (NaN / 无穷)，退回曲目元数据中的时长
+    let total_secs = move || {
+        let duration = player.duration.get();
+        if duration.is_finite() && duration > 0.0 {
+            duration
+        } else {
+            player.current_track.with(|t| t.as_ref().map(|t| t.duration as f64).unwrap_or(0.0))
+        }
+    };
+    // `waiting` 到 `canplay` / `playing` 之间为缓冲中，播放按钮显示加载圈
+    let buffering = create_rw_signal(false);
+    // 进度条拖动：按下后捕获指针，拖动中只移动进度显示，松开时才跳转，避免转码流反复重新请求
+    let progress_bar = create_node_ref::<html::Div>();
+    let drag_fraction = create_rw_signal(None::<f64>);
+    let pointer_fraction = move |ev: &web_sys::PointerEvent| {
+        let rect = progress_bar.get_untracked()?.get_bounding_client_rect();
+        (rect.width() > 0.0).then(|| ((ev.client_x() as f64 - rect.left()) / rect.width()).clamp(0.0, 1.0))
+    };
+    let seek_to_fraction = move |fraction: f64| {
+        let Some(audio) = audio_ref() else { return };
+        let duration = total_secs();
+        if duration <= 0.0 {
+            return;
+        }
+        let target = fraction * duration;
+        audio.set_current_time(target);
+        player.progress.set(target);
+    };
+    let on_progress_down = move |ev: web_sys::PointerEvent| {
+        if let Some(bar) = progress_bar.get_untracked() {
+            let _ = bar.set_pointer_capture(ev.pointer_id());
+        }
+        drag_fraction.set(pointer_fraction(&ev));
+    };
+    let on_progress_move = move |ev: web_sys::PointerEvent| {
+        if drag_fraction.get_untracked().is_some() {
+            drag_fraction.set(pointer_fraction(&ev));
+        }
+    };
+    let on_progress_up = move |ev: web_sys::PointerEvent| {
+        if drag_fraction.get_untracked().is_some() {
+            if let Some(fraction) = pointer_fraction(&ev) {
+                seek_to_fraction(fraction);
+            }
+            drag_fraction.set(None);
+        }
+    };
+    // 全局快捷键，焦点在输入框内或带修饰键时不拦截
+    let show_shortcuts = create_rw_signal(false);
+    let keydown = window_event_listener(ev::keydown, move |ev| {
+        if ev.ctrl_key() || ev.meta_key() || ev.alt_key() {
+            return;
+        }
+        let editing = ev
+            .target()
+            .and_then(|t| t.dyn_into::<web_sys::Element>().ok())
+            .is_some_and(|el| {
+                matches!(el.tag_name().as_str(), "INPUT" | "TEXTAREA" | "SELECT")
+                    || el.has_attribute("contenteditable")
+            });
+        if editing {
+            return;
+        }
+        match ev.key().as_str() {
+            " " => play_pause(),
+            "ArrowLeft" if ev.shift_key() => prev_track(),
+            "ArrowRight" if ev.shift_key() => next_track(),
+            "ArrowLeft" => seek_by(-SEEK_STEP_SECS),
+            "ArrowRight" => seek_by(SEEK_STEP_SECS),
+            "f" | "F" => player.is_fullscreen.set(!player.is_fullscreen.get_untracked()),
+            "?" => show_shortcuts.set(!show_shortcuts.get_untracked()),
+            "Escape" if show_shortcuts.get_untracked() => show_shortcuts.set(false),
+            "Escape" => player.is_fullscreen.set(false),
+            _ => return,
+        }
+        ev.prevent_default();
+    });
+    on_cleanup(move || keydown.remove());
+    // 睡眠定时：`sleep_until` 为截止时间戳 (毫秒)，`sleep_end_of_track` 为播完本曲即停。
+    // 最后 10 秒只调 `<audio>` 元素音量淡出，不改动 `player.volume`，取消或到点后恢复原音量
+    let sleep_until = create_rw_signal(None::<f64>);
+    let sleep_end_of_track = create_rw_signal(false);
+    let sleep_remaining = create_rw_signal(None::<f64>);
+    let sleep_choice = create_rw_signal(String::new());
+    let sleep_ticker = store_value(None::<gloo_timers::callback::Interval>);
+    let restore_volume = move || {
+        if let Some(audio) = audio_ref() {
+            audio.set_volume(player.volume.get_untracked());
+        }
+    };
+    let cancel_sleep = move || {
+        sleep_ticker.set_value(None);
+        sleep_until.set(None);
+        sleep_end_of_track.set(false);
+        sleep_remaining.set(None);
+        sleep_choice.set(String::new());
+        restore_volume();
+    };
+    let start_sleep = move |choice: String| {
+        cancel_sleep();
+        match choice.as_str() {
+            "end" => sleep_end_of_track.set(true),
+            minutes => match minutes.parse::<f64>() {
+                Ok(m) => sleep_until.set(Some(js_sys::Date::now() + m * 60_000.0)),
+                Err(_) => return,
+            },
+        }
+        sleep_choice.set(choice);
+        let ticker = gloo_timers::callback::Interval::new(500, move || {
+            let remaining = if sleep_end_of_track.get_untracked() {
+                (player.duration.get_untracked() - player.progress.get_untracked()).max(0.0) * 1000.0
+            } else if let Some(deadline) = sleep_until.get_untracked() {
+                deadline - js_sys::Date::now()
+            } else {
+                return;
+            };
+            sleep_remaining.set(Some(remaining));
+            if !player.is_playing.get_untracked() {
+                return;
+            }
+            if remaining <= 0.0 {
+                pause_playback();
+                // 不能在定时器回调里直接销毁它自己，推迟到下一轮事件循环
+                gloo_timers::callback::Timeout::new(0, cancel_sleep).forget();
+            } else if remaining <= 10_000.0 {
+                if let Some(audio) = audio_ref() {
+                    audio.set_volume(player.volume.get_untracked() * remaining / 10_000.0);
+                }
+            }
+        });
+        sleep_ticker.set_value(Some(ticker));
+    };
+    on_cleanup(move || sleep_ticker.set_value(None));
     view! {
+        <>
         <footer class="fixed bottom-0 left-0 right-0 h-24 bg-papilio-surface/80 backdrop-blur-[40px] border-t border-white/5 px-4 md:px-8 flex items-center justify-between z-[60] shadow-2xl">
-            <audio node_ref=audio_ref on:timeupdate=move |_| if let Some(a) = audio_ref.get() { player.progress.set(a.current_time()); player.duration.set(a.duration()); } on:ended=move |_| next_track() />
+            // 事件只处理当前使用中的元素，预载中的备用元素不影响进度
+            {(0..2).map(|i| view! {
+                <audio node_ref=audio_refs[i] preload="auto"
+                    on:timeupdate=move |_| if active.get_value() == i { if let Some(a) = audio_ref() { player.progress.set(a.current_time()); player.duration.set(a.duration()); listened.update_value(|l| if let Some((_, secs, last)) = l { let pos = a.current_time(); let delta = pos - *last; if !a.paused() && delta > 0.0 && delta <= MAX_LISTEN_STEP_SECS { *secs += delta; } *last = pos; }); if !a.paused() { sync_playback(false); } maybe_prefetch(a.duration() - a.current_time()); } }
+                    on:pause=move |_| if active.get_value() == i { sync_playback(true) }
+                    on:waiting=move |_| if active.get_value() == i { buffering.set(true) }
+                    on:canplay=move |_| if active.get_value() == i { buffering.set(false) }
+                    on:playing=move |_| if active.get_value() == i { buffering.set(false); stream_retries.set_value(0); failed_tracks.set_value(0); }
+                    on:error=move |_| if active.get_value() == i { buffering.set(false); on_stream_error(); }
+                    on:ended=move |_| if active.get_value() == i {
+                        report_listen(true);
+                        // 「播完本曲」在曲目结束时停下，不再切到下一首
+                        if sleep_end_of_track.get_untracked() {
+                            pause_playback();
+                            cancel_sleep();
+                        } else {
+                            advance(false);
+                        }
+                    } />
+            }).collect_view()}
             <div class="flex items-center gap-3 md:gap-5 w-1/4">
                 {move || player.current_track.get().map(|track| {
-                    let cover_url = get_cover_url(track.album_id);
+                    let cover_url = get_cover_url(track.album_id, Some(200));
                     view! {
                         <>
                             <img src=cover_url class="w-12 h-12 md:w-16 md:h-16 rounded-xl object-cover border border-white/10" />
@@ -726,59 +2043,318 @@ fn PlayerBar() -> impl IntoView {
             </div>
             <div class="flex flex-col items-center gap-2 md:gap-3 flex-1 md:w-2/4">
                 <div class="flex items-center gap-6 md:gap-10">
-                    <button class="text-xl md:text-2xl text-white/40 hover:text-white transition-colors">"⏮"</button>
-                    <button class="w-10 h-10 md:w-14 md:h-14 rounded-full bg-white text-black flex items-center justify-center text-xl md:text-3xl shadow-xl hover:scale-105 active:scale-95 transition-all" on:click=toggle_play>{move || if player.is_playing.get() { "⏸" } else { "▶" }}</button>
+                    <button class="text-base md:text-lg transition-colors" class:text-papilio-cyan=move || player.shuffle.get() class:opacity-40=move || !player.shuffle.get() title="Shuffle" on:click=toggle_shuffle>"🔀"</button>
+                    <button class="text-xl md:text-2xl text-white/40 hover:text-white transition-colors" on:click=move |_| prev_track()>"⏮"</button>
+                    <button class="relative w-10 h-10 md:w-14 md:h-14 rounded-full bg-white text-papilio-bg flex items-center justify-center text-xl md:text-3xl shadow-xl hover:scale-105 active:scale-95 transition-all" class:opacity-60=move || buffering.get() aria-busy=move || buffering.get().to_string() title=move || if buffering.get() { "Buffering..." } else { "" } on:click=toggle_play>
+                        {move || if player.is_playing.get() { "⏸" } else { "▶" }}
+                        <Show when=move || buffering.get()>
+                            <span class="absolute -inset-1 rounded-full border-2 border-papilio-cyan border-t-transparent animate-spin"></span>
+                        </Show>
+                    </button>
                     <button class="text-xl md:text-2xl text-white/40 hover:text-white transition-colors" on:click=move |_| next_track()>"⏭"</button>
+                    <button class="text-base md:text-lg transition-colors" class:text-papilio-cyan=move || player.repeat.get() != RepeatMode::Off class:opacity-40=move || player.repeat.get() == RepeatMode::Off title="Repeat" on:click=toggle_repeat>{move || if player.repeat.get() == RepeatMode::One { "🔂" } else { "🔁" }}</button>
                 </div>
                 <div class="w-full max-w-2xl flex items-center gap-3 text-[9px] font-mono text-papilio-muted">
-                    <div class="flex-1 h-1 bg-white/5 rounded-full overflow-hidden relative">
-                        <div class="absolute top-0 left-0 h-full bg-papilio-accent shadow-[0_0_10px_#8B5CF6]" style:width=move || format!("{}%", (player.progress.get() / player.duration.get().max(1.0)) * 100.0)></div>
+                    // 拖动中显示目标位置
+                    <span class="w-10 text-right tabular-nums">{move || format_duration(drag_fraction.get().map(|f| f * total_secs()).unwrap_or_else(|| player.progress.get()))}</span>
+                    <div
+                        node_ref=progress_bar
+                        class="flex-1 py-2 cursor-pointer touch-none group"
+                        on:pointerdown=on_progress_down
+                        on:pointermove=on_progress_move
+                        on:pointerup=on_progress_up
+                        on:pointercancel=move |_| drag_fraction.set(None)
+                    >
+                        <div class="h-1 group-hover:h-1.5 bg-white/5 rounded-full overflow-hidden relative transition-all">
+                            <div class="absolute top-0 left-0 h-full bg-papilio-accent shadow-[0_0_10px_#8B5CF6]" style:width=move || {
+                                let fraction = drag_fraction.get().unwrap_or_else(|| player.progress.get() / total_secs().max(1.0));
+                                format!("{}%", fraction.min(1.0) * 100.0)
+                            }></div>
+                        </div>
                     </div>
+                    <span class="w-10 tabular-nums">{move || player.current_track.with(|t| t.is_some()).then(|| format_duration(total_secs()))}</span>
                 </div>
             </div>
-            <div class="flex items-center justify-end w-1/4">
+            <div class="flex items-center justify-end gap-3 md:gap-4 w-1/4">
+                <div class="hidden md:flex items-center gap-1 text-[10px] font-mono text-papilio-muted" title="Sleep Timer">
+                    <span class="text-base">"⏾"</span>
+                    <select class="bg-transparent focus:outline-none cursor-pointer" prop:value=move || sleep_choice.get() on:change=move |ev| {
+                        let value = event_target_value(&ev);
+                        if value.is_empty() { cancel_sleep() } else { start_sleep(value) }
+                    }>
+                        <option value="">"Off"</option>
+                        <option value="15">"15m"</option>
+                        <option value="30">"30m"</option>
+                        <option value="60">"60m"</option>
+                        <option value="end">"End of track"</option>
+                    </select>
+                    {move || sleep_remaining.get().filter(|_| !sleep_end_of_track.get()).map(|ms| {
+                        let secs = (ms / 1000.0).max(0.0) as u64;
+                        format!("{}:{:02}", secs / 60, secs % 60)
+                    })}
+                </div>
+                <button class="text-lg opacity-60 hover:opacity-100 transition-all" title="Mute" on:click=move |_| player.muted.set(!player.muted.get_untracked())>
+                    {move || if player.muted.get() || player.volume.get() == 0.0 { "🔇" } else if player.volume.get() < 0.5 { "🔉" } else { "🔊" }}
+                </button>
+                <input type="range" min="0" max="1" step="0.01" class="hidden md:block w-24 accent-papilio-accent cursor-pointer" prop:value=move || if player.muted.get() { 0.0 } else { player.volume.get() } on:input=on_volume />
+                <button class="text-xl opacity-60 hover:opacity-100 hover:scale-110 transition-all" class:text-papilio-cyan=move || player.is_queue_open.get() title="Queue" on:click=move |_| player.is_queue_open.set(!player.is_queue_open.get_untracked())>"☰"</button>
                 <button class="text-2xl opacity-60 hover:opacity-100 hover:scale-110 transition-all" on:click=move |_| player.is_fullscreen.set(!player.is_fullscreen.get())>"⛶"</button>
             </div>
         </footer>
+        // footer 的 backdrop-blur 会成为 fixed 元素的定位容器，提示条与帮助层放在 footer 之外
+        {move || toast.get().map(|message| view! {
+            <div class="fixed bottom-28 left-1/2 -translate-x-1/2 z-[70] px-5 py-3 rounded-2xl bg-papilio-surface border border-white/10 shadow-2xl text-sm">{message}</div>
+        })}
+        <Show when=move || show_shortcuts.get()>
+            <div class="fixed inset-0 z-[150] flex items-center justify-center bg-black/60 backdrop-blur-sm" on:click=move |_| show_shortcuts.set(false)>
+                <div class="bg-papilio-surface border border-white/10 rounded-3xl p-8 w-[min(90vw,420px)] shadow-2xl" on:click=|ev| ev.stop_propagation()>
+                    <h3 class="text-xl font-black tracking-tight mb-6">"键盘快捷键"</h3>
+                    <dl class="grid grid-cols-[auto_1fr] gap-x-6 gap-y-3 text-sm">
+                        {KEYBOARD_SHORTCUTS.iter().map(|(keys, action)| view! {
+                            <dt><kbd class="px-2 py-1 rounded-lg bg-white/10 font-mono text-xs">{*keys}</kbd></dt>
+                            <dd class="text-papilio-muted">{*action}</dd>
+                        }).collect_view()}
+                    </dl>
+                </div>
+            </div>
+        </Show>
+        </>
+    }
+}
+
+/// 流错误后的重试间隔 (毫秒)，次数即数组长度
+const STREAM_RETRY_DELAYS_MS: &[u32] = &[1000, 3000];
+
+/// ← / → 每次跳转的秒数
+const SEEK_STEP_SECS: f64 = 5.0;
+
+/// `?` 打开的快捷键帮助，与 `PlayerBar` 中的按键处理保持一致
+const KEYBOARD_SHORTCUTS: &[(&str, &str)] = &[
+    ("Space", "播放 / 暂停"),
+    ("← / →", "后退 / 快进 5 秒"),
+    ("Shift + ← / →", "上一首 / 下一首"),
+    ("F", "沉浸模式 (全屏歌词)"),
+    ("?", "显示 / 隐藏本帮助"),
+    ("Esc", "关闭帮助或退出沉浸模式"),
+];
+
+/// 插入到当前曲目之后；已在队列中的曲目先移除再插入
+fn queue_play_next(player: &PlayerContext, track: Track) {
+    let current_id = player.current_track.get_untracked().map(|t| t.id);
+    player.playlist.update(|list| {
+        list.retain(|t| t.id != track.id || Some(t.id) == current_id);
+        if Some(track.id) == current_id {
+            return;
+        }
+        let insert_at = current_id
+            .and_then(|id| list.iter().position(|t| t.id == id))
+            .map(|pos| pos + 1)
+            .unwrap_or(0);
+        list.insert(insert_at, track);
+    });
+}
+
+/// 播放队列：列出当前曲目之后的待播曲目，可移除、拖拽排序
+#[component]
+fn QueuePanel() -> impl IntoView {
+    let player = use_context::<PlayerContext>().expect("context not found");
+    let dragging = store_value(None::<usize>);
+
+    // 待播列表在整个 playlist 中的起始下标
+    let upcoming_start = move || {
+        let current_id = player.current_track.get().map(|t| t.id);
+        player.playlist.with(|list| {
+            current_id
+                .and_then(|id| list.iter().position(|t| t.id == id))
+                .map(|pos| pos + 1)
+                .unwrap_or(0)
+        })
+    };
+
+    let remove_at = move |index: usize| {
+        player.playlist.update(|list| {
+            if index < list.len() {
+                list.remove(index);
+            }
+        });
+    };
+
+    let move_to = move |from: usize, to: usize| {
+        if from == to {
+            return;
+        }
+        player.playlist.update(|list| {
+            if from < list.len() && to < list.len() {
+                let track = list.remove(from);
+                list.insert(to, track);
+            }
+        });
+    };
+
+    view! {
+        <aside class="fixed top-0 right-0 bottom-24 w-full md:w-96 bg-papilio-surface/95 backdrop-blur-[40px] border-l border-white/5 z-[55] flex flex-col transition-transform duration-500" class:translate-x-full=move || !player.is_queue_open.get() class:translate-x-0=move || player.is_queue_open.get()>
+            <header class="p-6 flex items-center justify-between border-b border-white/5">
+                <div class="text-xs uppercase tracking-[0.3em] font-bold text-papilio-cyan">"Up Next"</div>
+                <button class="w-8 h-8 rounded-full bg-white/5 hover:bg-white/10 flex items-center justify-center" on:click=move |_| player.is_queue_open.set(false)>"✕"</button>
+            </header>
+            <div class="flex-1 overflow-y-auto custom-scrollbar p-4 space-y-2">
+                {move || {
+                    let start = upcoming_start();
+                    let upcoming: Vec<(usize, Track)> = player
+                        .playlist
+                        .get()
+                        .into_iter()
+                        .enumerate()
+                        .skip(start)
+                        .collect();
+                    if upcoming.is_empty() {
+                        return view! { <div class="text-center text-papilio-muted text-sm py-10">"Queue is empty"</div> }.into_view();
+                    }
+                    upcoming
+                        .into_iter()
+                        .map(|(index, track)| {
+                            let cover_url = get_cover_url(track.album_id, Some(200));
+                            let play = {
+                                let track = track.clone();
+                                move |_| {
+                                    player.current_track.set(Some(track.clone()));
+                                    player.is_playing.set(true);
+                                }
+                            };
+                            view! {
+                                <div
+                                    class="group flex items-center gap-3 p-2 rounded-xl hover:bg-white/5 cursor-grab"
+                                    draggable="true"
+                                    on:dragstart=move |_| dragging.set_value(Some(index))
+                                    on:dragover=move |ev| ev.prevent_default()
+                                    on:drop=move |ev| {
+                                        ev.prevent_default();
+                                        if let Some(from) = dragging.get_value() {
+                                            move_to(from, index);
+                                        }
+                                        dragging.set_value(None);
+                                    }
+                                    on:dblclick=play
+                                >
+                                    <img src=cover_url class="w-10 h-10 rounded-lg object-cover border border-white/10" />
+                                    <div class="flex-1 overflow-hidden text-sm font-bold truncate">{track.title}</div>
+                                    <button class="opacity-0 group-hover:opacity-60 hover:!opacity-100 transition-all" title="Remove" on:click=move |_| remove_at(index)>"✕"</button>
+                                </div>
+                            }
+                        })
+                        .collect_view()
+                }}
+            </div>
+        </aside>
     }
 }
 
 #[component]
 fn FullscreenPlayer() -> impl IntoView {
     let player = use_context::<PlayerContext>().expect("context not found");
+    // 歌词时间轴 = 播放进度 - 用户设置的偏移
+    let lyric_clock = move || player.progress.get() - player.lyric_offset_ms.get() as f64 / 1000.0;
     let active_index = move || {
-        let current_time = player.progress.get();
+        let current_time = lyric_clock();
         let list = player.lyrics.get();
         list.iter()
             .rposition(|line| line.time <= current_time)
             .unwrap_or(0)
     };
+    let adjust_offset = move |delta: i32| {
+        let Some(track) = player.current_track.get_untracked() else { return };
+        let offset = player.lyric_offset_ms.get_untracked() + delta;
+        player.lyric_offset_ms.set(offset);
+        spawn_local(async move {
+            let _ = update_lyric_offset_api(track.id, offset).await;
+        });
+    };
     view! {
         <div class="fixed inset-0 z-[100] bg-papilio-bg transition-all duration-700 ease-[cubic-bezier(0.85,0,0.15,1)] flex flex-col" class:translate-y-full=move || !player.is_fullscreen.get() class:translate-y-0=move || player.is_fullscreen.get()>
             {move || player.current_track.get().map(|track| {
-                let cover_url = get_cover_url(track.album_id);
+                let cover_url = get_cover_url(track.album_id, Some(200));
                 view! { <div class="absolute inset-0 z-0"><img src=cover_url class="w-full h-full object-cover blur-[100px] opacity-40 scale-125" /><div class="absolute inset-0 bg-gradient-to-b from-black/40 via-papilio-bg/90 to-papilio-bg"></div></div> }
             })}
             <header class="p-6 md:p-10 flex justify-between items-center z-10"><button class="w-12 h-12 rounded-full bg-white/5 hover:bg-white/10 flex items-center justify-center text-3xl" on:click=move |_| player.is_fullscreen.set(false)>"↓"</button><div class="text-center"><div class="text-[10px] uppercase tracking-[0.4em] text-papilio-cyan font-bold opacity-80">"Immersion Mode"</div><div class="text-lg md:text-3xl font-black mt-2 tracking-tight">{move || player.current_track.get().map(|t| t.title).unwrap_or_default()}</div></div><div class="w-12"></div></header>
             <div class="flex-1 flex flex-col md:flex-row items-center justify-center gap-10 md:gap-32 p-6 md:p-20 z-10 overflow-hidden">
                 <div class="w-full max-w-[300px] md:max-w-[500px] aspect-square rounded-[3rem] md:rounded-[4rem] overflow-hidden shadow-[0_50px_100px_rgba(0,0,0,0.8)] border border-white/10">
-                    {move || player.current_track.get().map(|track| { let cover_url = get_cover_url(track.album_id); view! { <img src=cover_url class="w-full h-full object-cover" /> } })}
+                    {move || player.current_track.get().map(|track| { let cover_url = get_cover_url(track.album_id, None); view! { <img src=cover_url class="w-full h-full object-cover" /> } })}
                 </div>
+                <Show
+                    when=move || player.lyrics_synced.get()
+                    fallback=move || view! {
+                        <div class="flex-1 w-full max-w-3xl h-[400px] md:h-full overflow-y-auto py-10 text-center md:text-left">
+                            {move || match player.lyrics_raw.get() {
+                                Some(raw) => view! {
+                                    <p class="whitespace-pre-line text-lg md:text-2xl font-bold leading-relaxed text-white/80">{plain_lyrics_text(&raw)}</p>
+                                }.into_view(),
+                                None => view! {
+                                    <div class="h-full flex items-center justify-center md:justify-start text-2xl md:text-4xl font-black text-white/20">"暂无歌词"</div>
+                                }.into_view(),
+                            }}
+                        </div>
+                    }
+                >
                 <div class="flex-1 w-full max-w-3xl h-[400px] md:h-full flex flex-col justify-center relative overflow-hidden text-center md:text-left">
                     <div class="transition-all duration-700 ease-out" style:transform=move || format!("translateY(-{}px)", active_index() as f64 * (if window().inner_width().unwrap_or_default().as_f64().unwrap_or(0.0) < 768.0 { 60.0 } else { 90.0 }))>
                         {move || player.lyrics.get().into_iter().enumerate().map(|(i, line)| {
                             let is_active = i == active_index();
+                            let content = if is_active && !line.words.is_empty() {
+                                // 逐字高亮：已唱过的字保持白色，当前字使用强调色
+                                let words = line.words.clone();
+                                view! { <span class="whitespace-pre-wrap">
+                                    {move || {
+                                        let now = lyric_clock();
+                                        let current = words.iter().rposition(|(t, _)| *t <= now);
+                                        words.iter().enumerate().map(|(j, (_, word))| {
+                                            let class = match current {
+                                                Some(c) if j == c => "text-papilio-cyan transition-colors duration-150",
+                                                Some(c) if j < c => "text-white transition-colors duration-150",
+                                                _ => "text-white/40 transition-colors duration-150",
+                                            };
+                                            view! { <span class=class>{word.clone()}</span> }
+                                        }).collect_view()
+                                    }}
+                                </span> }.into_view()
+                            } else {
+                                line.text.into_view()
+                            };
+                            let translation = (!line.translations.is_empty()).then(|| line.translations.join(" / "));
                             view! { <div
-                                class="h-[60px] md:h-[90px] flex items-center text-2xl md:text-5xl font-black transition-all duration-700"
+                                class="h-[60px] md:h-[90px] flex flex-col justify-center text-2xl md:text-5xl font-black leading-tight transition-all duration-700"
                                 class:text-white=is_active
                                 class:opacity-100=is_active
                                 class:scale-105=is_active
                                 class:blur-sm=!is_active
                                 class=move || if !is_active { "text-white/10" } else { "" }
-                            >{line.text}</div> }
+                            >
+                                <div>{content}</div>
+                                {move || translation.clone().filter(|_| player.show_translation.get()).map(|t| view! {
+                                    <div class="text-sm md:text-xl font-bold opacity-50 truncate mt-1">{t}</div>
+                                })}
+                            </div> }
                         }).collect_view()}
                     </div>
                 </div>
+                </Show>
+            </div>
+            <div class="z-10 pb-10 flex items-center justify-center gap-2 text-xs font-mono">
+                <button class="px-3 py-1.5 rounded-full bg-white/5 hover:bg-white/10 transition-all" on:click=move |_| adjust_offset(-500)>"-0.5s"</button>
+                <button class="px-3 py-1.5 rounded-full bg-white/5 hover:bg-white/10 transition-all" on:click=move |_| adjust_offset(-100)>"-0.1s"</button>
+                <span class="w-24 text-center text-papilio-cyan" title="Lyric offset">{move || format!("{:+.1}s", player.lyric_offset_ms.get() as f64 / 1000.0)}</span>
+                <button class="px-3 py-1.5 rounded-full bg-white/5 hover:bg-white/10 transition-all" on:click=move |_| adjust_offset(100)>"+0.1s"</button>
+                <button class="px-3 py-1.5 rounded-full bg-white/5 hover:bg-white/10 transition-all" on:click=move |_| adjust_offset(500)>"+0.5s"</button>
+                <Show when=move || player.lyrics.with(|l| l.iter().any(|line| !line.translations.is_empty()))>
+                    <button
+                        class="ml-4 px-3 py-1.5 rounded-full bg-white/5 hover:bg-white/10 transition-all"
+                        class:text-papilio-cyan=move || player.show_translation.get()
+                        on:click=move |_| {
+                            let show = !player.show_translation.get_untracked();
+                            player.show_translation.set(show);
+                            save_pref("lyrics_translation", if show { "true" } else { "false" });
+                        }
+                    >"译"</button>
+                </Show>
             </div>
         </div>
     }
@@ -787,11 +2363,31 @@ fn FullscreenPlayer() -> impl IntoView {
 #[component]
 fn Profile() -> impl IntoView {
     let user_res = create_resource(|| (), |_| async move { fetch_me().await });
+    let theme_ctx = use_context::<ThemeContext>().expect("theme");
 
     let (nickname, set_nickname) = create_signal(String::new());
     let (email, set_email) = create_signal(String::new());
     let (password, set_password) = create_signal(String::new());
     let (message, set_message) = create_signal(Option::<(String, bool)>::None);
+    let share_now_playing = create_rw_signal(true);
+    spawn_local(async move {
+        if let Ok(share) = fetch_share_now_playing().await {
+            share_now_playing.set(share);
+        }
+    });
+
+    let toggle_share = move |ev: leptos::ev::Event| {
+        let share = event_target_checked(&ev);
+        spawn_local(async move {
+            match update_share_now_playing_api(share).await {
+                Ok(_) => share_now_playing.set(share),
+                Err(e) => {
+                    share_now_playing.set(!share);
+                    set_message.set(Some((format!("更新失败: {}", e), false)));
+                }
+            }
+        });
+    };
 
     create_effect(move |_| {
         if let Some(Ok(user)) = user_res.get() {
@@ -902,7 +2498,12 @@ fn Profile() -> impl IntoView {
                                 </div>
                             </div>
 
-                            <button on:click=save_profile class="mt-4 bg-white text-black font-black py-5 rounded-2xl hover:scale-[1.02] active:scale-95 transition-all shadow-xl shadow-white/5">
+                            <label class="flex items-center gap-3 px-2 text-sm cursor-pointer">
+                                <input type="checkbox" prop:checked=move || share_now_playing.get() on:change=toggle_share class="accent-papilio-cyan w-4 h-4" />
+                                <span>"向其他用户公开我正在收听的曲目"</span>
+                            </label>
+
+                            <button on:click=save_profile class="mt-4 bg-white text-papilio-bg font-black py-5 rounded-2xl hover:scale-[1.02] active:scale-95 transition-all shadow-xl shadow-white/5">
                                 "保存所有更改"
                             </button>
                         </div>
@@ -910,6 +2511,53 @@ fn Profile() -> impl IntoView {
                     Err(_) => view! { <div class="text-red-400">"获取用户信息失败"</div> }.into_view()
                 })}
             </Suspense>
+
+            <div class="mt-8 flex flex-col gap-6 bg-white/5 border border-white/10 rounded-[2.5rem] p-8 md:p-12 backdrop-blur-xl">
+                <h3 class="text-2xl font-black tracking-tight">"外观"</h3>
+                <div class="flex flex-col gap-2">
+                    <label class="text-[10px] uppercase tracking-widest text-papilio-muted px-2">"主题 (Theme)"</label>
+                    <div class="flex gap-3">
+                        {[(Theme::Dark, "深色"), (Theme::Light, "浅色")].into_iter().map(|(value, label)| view! {
+                            <button
+                                class="px-6 py-3 rounded-2xl border transition-all"
+                                class=("border-papilio-cyan", move || theme_ctx.theme.get() == value)
+                                class=("text-papilio-cyan", move || theme_ctx.theme.get() == value)
+                                class=("border-white/10", move || theme_ctx.theme.get() != value)
+                                on:click=move |_| theme_ctx.theme.set(value)
+                            >
+                                {label}
+                            </button>
+                        }).collect_view()}
+                    </div>
+                </div>
+                <div class="flex flex-col gap-2">
+                    <label class="text-[10px] uppercase tracking-widest text-papilio-muted px-2">"强调色 (Accent)"</label>
+                    <div class="flex items-center gap-3 flex-wrap">
+                        {ACCENT_PRESETS.iter().map(|&color| view! {
+                            <button
+                                class="w-9 h-9 rounded-full border-2 transition-all hover:scale-110"
+                                class=("border-white", move || theme_ctx.accent.get().as_deref() == Some(color))
+                                class=("border-transparent", move || theme_ctx.accent.get().as_deref() != Some(color))
+                                style=format!("background-color: {}", color)
+                                title=color
+                                on:click=move |_| theme_ctx.accent.set(Some(color.to_string()))
+                            ></button>
+                        }).collect_view()}
+                        <input
+                            type="color"
+                            class="w-9 h-9 rounded-full bg-transparent cursor-pointer"
+                            prop:value=move || theme_ctx.accent.get().unwrap_or_else(|| ACCENT_PRESETS[0].to_string())
+                            on:input=move |ev| theme_ctx.accent.set(Some(event_target_value(&ev)))
+                        />
+                        <button
+                            class="px-4 py-2 rounded-xl text-sm text-papilio-muted hover:bg-white/5 transition-all"
+                            on:click=move |_| theme_ctx.accent.set(None)
+                        >
+                            "恢复默认"
+                        </button>
+                    </div>
+                </div>
+            </div>
         </div>
     }
 }
@@ -927,9 +2575,9 @@ fn AuthScreen() -> impl IntoView {
         let reg = is_register.get();
         spawn_local(async move {
             let path = if reg {
-                "/api/auth/register"
+                "/api/v1/auth/register"
             } else {
-                "/api/auth/login"
+                "/api/v1/auth/login"
             };
             let body = serde_json::json!({ "username": u, "password": p });
             if let Ok(resp) = api_request("POST", path, Some(body)).await {
@@ -963,7 +2611,7 @@ fn AuthScreen() -> impl IntoView {
                     {move || error.get().map(|e| view! { <div class="bg-red-500/20 border border-red-500/20 text-red-400 p-4 rounded-2xl text-xs text-center font-bold tracking-wide">{e}</div> })}
                     <input type="text" placeholder="Username" class="bg-white/5 border border-white/10 rounded-2xl px-6 py-4 text-lg focus:outline-none focus:border-papilio-cyan transition-all placeholder:text-white/20" on:input=move |ev| set_username.set(event_target_value(&ev)) />
                     <input type="password" placeholder="Password" class="bg-white/5 border border-white/10 rounded-2xl px-6 py-4 text-lg focus:outline-none focus:border-papilio-cyan transition-all placeholder:text-white/20" on:input=move |ev| set_password.set(event_target_value(&ev)) />
-                    <button class="bg-white text-black font-black py-4 rounded-2xl shadow-[0_10px_30px_rgba(255,255,255,0.2)] hover:scale-[1.02] active:scale-95 transition-all mt-4 text-lg" on:click=handle_auth>{move || if is_register.get() { "SIGN UP" } else { "SIGN IN" }}</button>
+                    <button class="bg-white text-papilio-bg font-black py-4 rounded-2xl shadow-[0_10px_30px_rgba(255,255,255,0.2)] hover:scale-[1.02] active:scale-95 transition-all mt-4 text-lg" on:click=handle_auth>{move || if is_register.get() { "SIGN UP" } else { "SIGN IN" }}</button>
                 </div>
                 <button class="text-papilio-muted text-sm hover:text-white transition-colors font-medium tracking-wide" on:click=move |_| set_is_register.set(!is_register.get())>{move || if is_register.get() { "Already have an account? Login" } else { "Don't have an account? Register" }}</button>
             </div>
@@ -979,6 +2627,8 @@ fn Admin() -> impl IntoView {
     let organize_action =
         create_action(move |_: &()| async move { trigger_library_organize_api().await });
 
+    let cancel_sync_action = create_action(move |_: &()| async move { cancel_artist_sync_api().await });
+
     // 2. 定义状态轮询：通过一个自增的时间信号驱动 Resource
     let poll_tick = create_rw_signal(0);
 
@@ -997,6 +2647,20 @@ fn Admin() -> impl IntoView {
         |_| async move { fetch_artist_sync_status().await },
     );
 
+    // 同步进度变化时刷新失败列表，空闲时不会重复请求
+    let sync_failures_res = create_resource(
+        move || {
+            sync_status_res
+                .get()
+                .and_then(|r| r.ok())
+                .map(|s| (s.is_syncing, s.current_count))
+        },
+        |_| async move { fetch_artist_sync_failures().await },
+    );
+
+    // 统计查询不需要跟随轮询刷新，进入页面时加载一次
+    let stats_res = create_resource(|| (), |_| async move { fetch_library_stats().await });
+
     let start_sync = move |_| {
         sync_action.dispatch(());
     };
@@ -1029,6 +2693,7 @@ fn Admin() -> impl IntoView {
                                 {move || {
                                     if sync_action.pending().get() { "请求中..." }
                                     else if sync_status_res.get().and_then(|r| r.ok()).map(|s| s.is_syncing).unwrap_or(false) { "正在同步..." }
+                                    else if sync_status_res.get().and_then(|r| r.ok()).map(|s| s.resumable).unwrap_or(false) { "继续上次同步" }
                                     else { "开始批量同步" }
                                 }}
                             </button>
@@ -1057,9 +2722,20 @@ fn Admin() -> impl IntoView {
                                     let progress = (status.current_count as f32 / status.total_count.max(1) as f32) * 100.0;
                                     view! {
                                         <div class="bg-black/20 p-6 rounded-3xl border border-white/5 flex flex-col gap-4">
-                                            <div class="flex justify-between text-sm font-mono">
+                                            <div class="flex justify-between items-center text-sm font-mono">
                                                 <span class="text-papilio-cyan">"同步进度: " {status.current_count} " / " {status.total_count}</span>
-                                                <span>{format!("{:.1}%", progress)}</span>
+                                                <div class="flex items-center gap-4">
+                                                    <span>{format!("{:.1}%", progress)}</span>
+                                                    {status.is_syncing.then(|| view! {
+                                                        <button
+                                                            on:click=move |_| cancel_sync_action.dispatch(())
+                                                            disabled=move || cancel_sync_action.pending().get()
+                                                            class="text-xs font-sans font-bold px-4 py-1.5 rounded-xl bg-red-400/10 text-red-400 border border-red-400/20 hover:bg-red-400/20 transition-all disabled:opacity-50"
+                                                        >
+                                                            "取消"
+                                                        </button>
+                                                    })}
+                                                </div>
                                             </div>
                                             <div class="w-full h-2 bg-white/5 rounded-full overflow-hidden">
                                                 <div
@@ -1078,13 +2754,90 @@ fn Admin() -> impl IntoView {
                                 _ => view! {}.into_view()
                             })}
                         </Suspense>
+
+                        <Suspense fallback=move || view! { <div></div> }>
+                            {move || sync_failures_res.get().and_then(|r| r.ok()).filter(|f| !f.is_empty()).map(|failures| view! {
+                                <div class="bg-black/20 p-6 rounded-3xl border border-white/5 flex flex-col gap-3">
+                                    <div class="font-bold text-red-400">{format!("同步失败的歌手 ({})", failures.len())}</div>
+                                    <div class="flex flex-col gap-2 max-h-64 overflow-y-auto">
+                                        {failures.into_iter().map(|f| view! {
+                                            <div class="flex items-start justify-between gap-4 text-sm">
+                                                <A href=format!("/artist/{}", f.artist_id) class="font-bold hover:text-papilio-cyan transition-colors shrink-0">{f.artist_name}</A>
+                                                <span class="text-xs text-papilio-muted font-mono text-right break-all">
+                                                    {if f.status == "timeout" { "超时".to_string() } else { f.error.unwrap_or_default() }}
+                                                </span>
+                                            </div>
+                                        }).collect_view()}
+                                    </div>
+                                </div>
+                            })}
+                        </Suspense>
                     </div>
                 </section>
 
-                <section class="bg-white/5 border border-white/10 rounded-[2.5rem] p-8 md:p-12 backdrop-blur-xl opacity-50 grayscale pointer-events-none">
+                <section class="bg-white/5 border border-white/10 rounded-[2.5rem] p-8 md:p-12 backdrop-blur-xl shadow-2xl">
                     <h3 class="text-2xl font-bold mb-6 flex items-center gap-3">
-                        <span class="w-1.5 h-6 bg-papilio-muted rounded-full"></span>"系统监控 (开发中)"
+                        <span class="w-1.5 h-6 bg-papilio-cyan rounded-full"></span>"系统监控"
                     </h3>
+
+                    <Suspense fallback=move || view! { <div class="animate-pulse h-40 bg-white/5 rounded-3xl"></div> }>
+                        {move || stats_res.get().map(|res| match res {
+                            Ok(stats) => {
+                                let hours = stats.total_duration / 3600;
+                                let minutes = (stats.total_duration % 3600) / 60;
+                                let cards = vec![
+                                    ("曲目", stats.tracks.to_string()),
+                                    ("专辑", stats.albums.to_string()),
+                                    ("歌手", stats.artists.to_string()),
+                                    ("总容量", format_bytes(stats.total_size)),
+                                    ("总时长", format!("{}h {}m", hours, minutes)),
+                                ];
+                                let missing = vec![
+                                    ("缺少封面的曲目", stats.missing.track_covers),
+                                    ("缺少歌词的曲目", stats.missing.track_lyrics),
+                                    ("缺少头像的歌手", stats.missing.artist_images),
+                                ];
+                                view! {
+                                    <div class="flex flex-col gap-6">
+                                        <div class="grid grid-cols-2 md:grid-cols-5 gap-4">
+                                            {cards.into_iter().map(|(label, value)| view! {
+                                                <div class="bg-black/20 p-5 rounded-3xl border border-white/5">
+                                                    <div class="text-xs text-papilio-muted uppercase tracking-widest">{label}</div>
+                                                    <div class="text-2xl font-black mt-1">{value}</div>
+                                                </div>
+                                            }).collect_view()}
+                                        </div>
+                                        <div class="bg-black/20 p-6 rounded-3xl border border-white/5 flex flex-col gap-3">
+                                            <div class="font-bold">"格式分布"</div>
+                                            {stats.formats.into_iter().map(|f| {
+                                                let percent = f.count as f64 / stats.tracks.max(1) as f64 * 100.0;
+                                                view! {
+                                                    <div class="flex items-center gap-4 text-sm font-mono">
+                                                        <span class="w-16 uppercase">{f.format}</span>
+                                                        <div class="flex-1 h-2 bg-white/5 rounded-full overflow-hidden">
+                                                            <div class="h-full bg-papilio-cyan" style:width=format!("{}%", percent)></div>
+                                                        </div>
+                                                        <span class="w-32 text-right text-papilio-muted">{format!("{} · {}", f.count, format_bytes(f.size))}</span>
+                                                    </div>
+                                                }
+                                            }).collect_view()}
+                                        </div>
+                                        <div class="grid grid-cols-1 md:grid-cols-3 gap-4">
+                                            {missing.into_iter().map(|(label, count)| view! {
+                                                <div class="bg-black/20 p-5 rounded-3xl border border-white/5 flex items-center justify-between">
+                                                    <span class="text-sm text-papilio-muted">{label}</span>
+                                                    <span class="text-xl font-bold" class:text-amber-400=move || count > 0>{count}</span>
+                                                </div>
+                                            }).collect_view()}
+                                        </div>
+                                    </div>
+                                }.into_view()
+                            },
+                            Err(e) => view! {
+                                <div class="text-sm text-red-400 bg-red-400/10 p-4 rounded-2xl border border-red-400/20">"统计加载失败: " {e}</div>
+                            }.into_view(),
+                        })}
+                    </Suspense>
                 </section>
             </div>
         </div>
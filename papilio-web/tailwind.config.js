@@ -6,12 +6,14 @@ module.exports = {
   theme: {
     extend: {
       colors: {
+        // 主题色取自 index.html 中定义的 CSS 变量 (默认深色)，浅色主题与自定义强调色只改变量
+        white: "rgb(var(--papilio-fg) / <alpha-value>)",
         papilio: {
-          bg: "#05070A",
-          surface: "#0F172A",
-          accent: "#8B5CF6", // 紫罗兰
-          cyan: "#06B6D4",   // 极光青
-          muted: "#94A3B8",
+          bg: "rgb(var(--papilio-bg) / <alpha-value>)",
+          surface: "rgb(var(--papilio-surface) / <alpha-value>)",
+          accent: "rgb(var(--papilio-accent) / <alpha-value>)", // 紫罗兰 #8B5CF6
+          cyan: "rgb(var(--papilio-cyan) / <alpha-value>)",     // 极光青 #06B6D4
+          muted: "rgb(var(--papilio-muted) / <alpha-value>)",
         }
       },
       backdropBlur: {
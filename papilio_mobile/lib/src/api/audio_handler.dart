@@ -9,23 +9,66 @@ import '../models/track.dart';
 import '../config/env_config.dart';
 import 'music_repository.dart';
 
+class _PendingListen {
+  final String trackId;
+  final int playedMs;
+  final bool completed;
+
+  const _PendingListen(this.trackId, this.playedMs, this.completed);
+}
+
 class AudioPlayerHandler extends BaseAudioHandler with SeekHandler {
   final AudioPlayer _player = AudioPlayer();
   final _playlist = ConcatenatingAudioSource(children: []);
   MusicRepository? _repository;
-  final List<String> _pendingRecordTasks = [];
+  final List<_PendingListen> _pendingRecordTasks = [];
+
+  // Listening progress of the current track, reported when it is left.
+  // Only time actually played is summed up; position jumps from seeking are skipped
+  static const _maxListenStep = Duration(seconds: 2);
+  String? _listeningId;
+  Duration? _listeningDuration;
+  Duration _listenedFor = Duration.zero;
+  Duration _lastPosition = Duration.zero;
 
   void setRepository(MusicRepository repo) {
     _repository = repo;
     // Process any tasks that were queued before the repository was ready
     if (_pendingRecordTasks.isNotEmpty) {
-      for (var trackId in _pendingRecordTasks) {
-        _repository?.recordPlay(trackId);
+      for (var task in _pendingRecordTasks) {
+        _repository?.recordPlay(task.trackId, playedMs: task.playedMs, completed: task.completed);
       }
       _pendingRecordTasks.clear();
     }
   }
 
+  void _startListen(MediaItem item) {
+    _listeningId = item.id;
+    _listeningDuration = item.duration;
+    _listenedFor = Duration.zero;
+    _lastPosition = Duration.zero;
+  }
+
+  /// Report how long the current track was played; the server decides whether it counts as a play
+  void _reportListen({bool completed = false}) {
+    final trackId = _listeningId;
+    if (trackId == null) return;
+    final duration = _listeningDuration;
+    final reachedEnd = duration != null &&
+        duration > Duration.zero &&
+        _lastPosition >= duration - const Duration(seconds: 2);
+    final task = _PendingListen(trackId, _listenedFor.inMilliseconds, completed || reachedEnd);
+    _listeningId = null;
+    _listenedFor = Duration.zero;
+    _lastPosition = Duration.zero;
+
+    if (_repository != null) {
+      _repository!.recordPlay(task.trackId, playedMs: task.playedMs, completed: task.completed);
+    } else {
+      _pendingRecordTasks.add(task);
+    }
+  }
+
   AudioPlayerHandler() {
     _initSession();
     _player.setAudioSource(_playlist);
@@ -42,18 +85,24 @@ class AudioPlayerHandler extends BaseAudioHandler with SeekHandler {
         final item = source.tag as MediaItem;
         mediaItem.add(item);
         _persistLastTrack(item.id, index);
-        
-        // Auto-record play history with fallback queue
-        if (_repository != null) {
-          _repository!.recordPlay(item.id);
-        } else {
-          _pendingRecordTasks.add(item.id);
+
+        // Report the track we are leaving, then start tracking the new one
+        if (item.id != _listeningId) {
+          _reportListen();
+          _startListen(item);
         }
       }
     });
 
+    _player.processingStateStream.listen((state) {
+      if (state == ProcessingState.completed) _reportListen(completed: true);
+    });
+
     _player.positionStream.listen((pos) {
       if (_player.playing) {
+        final step = pos - _lastPosition;
+        if (step > Duration.zero && step <= _maxListenStep) _listenedFor += step;
+        _lastPosition = pos;
         _persistPosition(pos);
         // Only update if not already being updated by pipe
         if (!playbackState.hasListener || _player.processingState == ProcessingState.ready) {
@@ -151,7 +200,10 @@ class AudioPlayerHandler extends BaseAudioHandler with SeekHandler {
   @override
   Future<void> seek(Duration position) => _player.seek(position);
   @override
-  Future<void> stop() => _player.stop();
+  Future<void> stop() {
+    _reportListen();
+    return _player.stop();
+  }
   @override
   Future<void> skipToNext() async {
     final currentMode = _player.loopMode;
@@ -249,6 +301,7 @@ class AudioPlayerHandler extends BaseAudioHandler with SeekHandler {
       // Final version check before modifying the actual player state
       if (currentTag != _requestTag) return;
       
+      _reportListen();
       await _player.stop();
       await _playlist.clear();
       await _playlist.addAll(audioSources);
@@ -262,6 +315,7 @@ class AudioPlayerHandler extends BaseAudioHandler with SeekHandler {
         
         final source = audioSources[safeIndex] as IndexedAudioSource;
         mediaItem.add(source.tag as MediaItem);
+        _startListen(source.tag as MediaItem);
         
         _player.play();
       }
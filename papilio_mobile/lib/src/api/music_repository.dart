@@ -137,8 +137,9 @@ class MusicRepository {
     }
   }
 
-  Future<void> recordPlay(String trackId) async {
-    await _client.post('music/play/$trackId');
+  /// Report a listen; the server decides from played_ms / completed whether it counts as a play
+  Future<void> recordPlay(String trackId, {required int playedMs, bool completed = false}) async {
+    await _client.post('music/play/$trackId', data: {'played_ms': playedMs, 'completed': completed});
   }
 
   Future<void> updateCloudPlayback(String trackId, int positionMs) async {
@@ -143,7 +143,6 @@ class PlayerController {
     await _handler.playTrack(t, _config); 
     if (actionId == _currentActionId) {
       startCloudSync(); 
-      _ref.read(musicRepositoryProvider).recordPlay(t.id);
     }
   }
 
@@ -154,9 +153,6 @@ class PlayerController {
     await _handler.setQueue(l, i, _config); 
     if (actionId == _currentActionId) {
       startCloudSync(); 
-      if (l.isNotEmpty && i < l.length) {
-        _ref.read(musicRepositoryProvider).recordPlay(l[i].id);
-      }
     }
   }
 